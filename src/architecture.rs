@@ -0,0 +1,485 @@
+//! Architecture overview derived from codemaps.
+//!
+//! Files are grouped into top-level modules: the entries directly under the
+//! project root (the deepest directory that holds every file, or the one
+//! above it if that is `src`), looking through a `src` directory, so
+//! `src/codemap/rust.rs` belongs to `codemap`, `src/output.rs` to `output`
+//! and `tests/cli.rs` to `tests`. Imports are resolved against those module
+//! names to find which modules depend on which.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::codemap::{Codemap, Declaration};
+
+/// Prefixes that anchor a path-like import in the current crate or package.
+const ANCHORS: &[&str] = &["crate", "self", "super", "@", "~"];
+
+/// Directory holding a package's sources, whose entries are the modules.
+const SOURCE_DIR: &str = "src";
+
+/// A top-level module and its relationships.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Module {
+    pub name: String,
+    /// Files with codemaps in the module.
+    pub files: usize,
+    /// Top-level declarations across those files.
+    pub declarations: usize,
+    /// Other modules this one imports, sorted.
+    pub imports: Vec<String>,
+    /// Other modules that import this one, sorted.
+    pub imported_by: Vec<String>,
+}
+
+/// Modules, their imports and the project's entry points.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Architecture {
+    /// Modules sorted by name.
+    pub modules: Vec<Module>,
+    /// Files that start a program or library (see [`is_entry_point`]),
+    /// relative to the project root.
+    pub entry_points: Vec<PathBuf>,
+}
+
+impl Architecture {
+    /// Derive the overview from `codemaps`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::path::PathBuf;
+    /// use pith::architecture::Architecture;
+    /// use pith::codemap::{Codemap, Import};
+    /// use pith::filter::Language;
+    ///
+    /// let mut output = Codemap::empty(PathBuf::from("src/output.rs"), Language::Rust);
+    /// output.imports.push(Import {
+    ///     source: "crate::tree".into(),
+    ///     items: ["FileNode".to_string()].into_iter().collect(),
+    /// });
+    /// let tree = Codemap::empty(PathBuf::from("src/tree.rs"), Language::Rust);
+    ///
+    /// let architecture = Architecture::from_codemaps(&[output, tree]);
+    /// assert_eq!(architecture.modules[0].name, "output");
+    /// assert_eq!(architecture.modules[0].imports, ["tree"]);
+    /// assert_eq!(architecture.modules[1].imported_by, ["output"]);
+    /// ```
+    pub fn from_codemaps(codemaps: &[Codemap]) -> Self {
+        let mut root = common_directory(codemaps.iter().map(|c| c.path.as_path()));
+        if root.file_name().is_some_and(|name| name == SOURCE_DIR) {
+            root.pop();
+        }
+        let resolver = Resolver::new(&root, codemaps);
+
+        let mut modules: BTreeMap<&str, Module> = BTreeMap::new();
+        let mut edges: BTreeSet<(&str, &str)> = BTreeSet::new();
+        let mut entry_points = Vec::new();
+        for (codemap, name) in codemaps.iter().zip(&resolver.file_modules) {
+            let Some(name) = name.as_deref() else {
+                continue;
+            };
+            let module = modules.entry(name).or_insert_with(|| Module {
+                name: name.to_string(),
+                files: 0,
+                declarations: 0,
+                imports: Vec::new(),
+                imported_by: Vec::new(),
+            });
+            module.files += 1;
+            module.declarations += codemap.declarations.len();
+
+            for import in &codemap.imports {
+                if let Some(target) = resolver.resolve(&import.source, &import.items, &codemap.path)
+                {
+                    if target != name {
+                        edges.insert((name, target));
+                    }
+                }
+            }
+            if is_entry_point(codemap) {
+                let path = codemap.path.strip_prefix(&root).unwrap_or(&codemap.path);
+                entry_points.push(path.to_path_buf());
+            }
+        }
+
+        for (from, to) in edges {
+            if let Some(module) = modules.get_mut(from) {
+                module.imports.push(to.to_string());
+            }
+            if let Some(module) = modules.get_mut(to) {
+                module.imported_by.push(from.to_string());
+            }
+        }
+        entry_points.sort();
+
+        Self {
+            modules: modules.into_values().collect(),
+            entry_points,
+        }
+    }
+
+    /// Check if there is nothing to show.
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+}
+
+impl fmt::Display for Architecture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Modules:")?;
+        for module in &self.modules {
+            write!(
+                f,
+                "- {} ({} {}, {} {})",
+                module.name,
+                module.files,
+                if module.files == 1 { "file" } else { "files" },
+                module.declarations,
+                if module.declarations == 1 {
+                    "declaration"
+                } else {
+                    "declarations"
+                }
+            )?;
+            if !module.imports.is_empty() {
+                write!(f, "; imports {}", module.imports.join(", "))?;
+            }
+            if !module.imported_by.is_empty() {
+                write!(f, "; imported by {}", module.imported_by.join(", "))?;
+            }
+            writeln!(f)?;
+        }
+        if !self.entry_points.is_empty() {
+            writeln!(f, "\nEntry points:")?;
+            for path in &self.entry_points {
+                writeln!(f, "- {}", path.display())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Maps files and imports to module names.
+struct Resolver<'a> {
+    root: &'a Path,
+    /// Module of each codemap, in order.
+    file_modules: Vec<Option<String>>,
+    /// Names of all modules.
+    names: BTreeSet<String>,
+    /// Names of `root` and its ancestors, which package-qualified imports
+    /// (`myproj.walker`, `github.com/acme/app/internal/db`) mention before
+    /// the module.
+    root_names: Vec<String>,
+}
+
+impl<'a> Resolver<'a> {
+    fn new(root: &'a Path, codemaps: &[Codemap]) -> Self {
+        let file_modules: Vec<Option<String>> =
+            codemaps.iter().map(|c| module_of(root, &c.path)).collect();
+        let names = file_modules.iter().flatten().cloned().collect();
+        let root_names = root
+            .ancestors()
+            .filter_map(|dir| dir.file_name()?.to_str())
+            .map(str::to_string)
+            .collect();
+        Self {
+            root,
+            file_modules,
+            names,
+            root_names,
+        }
+    }
+
+    /// Module that an import of `source` (naming `items`) in `from` refers
+    /// to, if it is one of ours.
+    fn resolve(&self, source: &str, items: &[String], from: &Path) -> Option<&str> {
+        if let Some(module) = self.resolve_source(source, from) {
+            return Some(module);
+        }
+        // `use crate::{output, tree}` and `from . import walker` name modules
+        // as items.
+        if source.contains('/') {
+            return None;
+        }
+        items.iter().find_map(|item| {
+            let item = item.split(" as ").next().unwrap_or(item).trim();
+            let joined = if source.chars().all(|c| c == '.') {
+                format!("{source}{item}")
+            } else if source.contains("::") || ANCHORS.contains(&source) {
+                format!("{source}::{item}")
+            } else {
+                format!("{source}.{item}")
+            };
+            self.resolve_source(&joined, from)
+        })
+    }
+
+    fn resolve_source(&self, source: &str, from: &Path) -> Option<&str> {
+        if source.starts_with('.') {
+            let path = relative_import_path(source, from)?;
+            let module = module_of(self.root, &path)?;
+            return self.names.get(&module).map(String::as_str);
+        }
+
+        let segments: Vec<&str> = source
+            .split([':', '/', '.'])
+            .filter(|s| !s.is_empty())
+            .collect();
+        let start = segments.iter().take_while(|s| ANCHORS.contains(s)).count();
+        let after_root = segments
+            .iter()
+            .rposition(|s| self.root_names.iter().any(|name| name == s))
+            .map(|i| i + 1);
+        [Some(start), after_root]
+            .into_iter()
+            .flatten()
+            .filter_map(|i| segments.get(i))
+            .find_map(|segment| self.names.get(*segment))
+            .map(String::as_str)
+    }
+}
+
+/// The path a relative import points at: `./util/x` and `../x` for
+/// JavaScript, `.walker` and `..pkg.mod` for Python.
+fn relative_import_path(source: &str, from: &Path) -> Option<PathBuf> {
+    let mut path = from.parent()?.to_path_buf();
+    if source.contains('/') {
+        for component in Path::new(source).components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    path.pop();
+                }
+                other => path.push(other),
+            }
+        }
+    } else {
+        let rest = source.trim_start_matches('.');
+        for _ in 1..source.len() - rest.len() {
+            path.pop();
+        }
+        path.extend(rest.split('.').filter(|s| !s.is_empty()));
+    }
+    Some(path)
+}
+
+/// Module of `path`: its first component below `root` (or below `root/src`),
+/// or its file stem if it sits directly in that directory.
+fn module_of(root: &Path, path: &Path) -> Option<String> {
+    let mut relative = path.strip_prefix(root).ok()?;
+    if let Ok(source) = relative.strip_prefix(SOURCE_DIR) {
+        if source.components().next().is_some() {
+            relative = source;
+        }
+    }
+    let mut components = relative.components();
+    let first = components.next()?;
+    let name = if components.next().is_some() {
+        first.as_os_str().to_str()?
+    } else {
+        Path::new(first.as_os_str()).file_stem()?.to_str()?
+    };
+    Some(name.to_string())
+}
+
+/// Deepest directory containing every path.
+fn common_directory<'a>(mut paths: impl Iterator<Item = &'a Path>) -> PathBuf {
+    let Some(first) = paths.next() else {
+        return PathBuf::new();
+    };
+    let mut common = first.parent().unwrap_or(Path::new("")).to_path_buf();
+    for path in paths {
+        while !path.starts_with(&common) {
+            if !common.pop() {
+                break;
+            }
+        }
+    }
+    common
+}
+
+/// Whether `codemap` starts a program or library: `main`, `index` and
+/// `__main__` files, `lib.rs`, `app.py`, files under a `bin` directory, and
+/// files declaring a top-level `main` function.
+pub fn is_entry_point(codemap: &Codemap) -> bool {
+    let path = &codemap.path;
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    matches!(stem, "main" | "__main__" | "index")
+        || path
+            .file_name()
+            .is_some_and(|name| name == "lib.rs" || name == "app.py")
+        || path
+            .parent()
+            .and_then(Path::file_name)
+            .is_some_and(|dir| dir == "bin")
+        || codemap
+            .declarations
+            .iter()
+            .any(|d| matches!(d, Declaration::Function { .. }) && d.name() == "main")
+}
+
+/// Files the `package.json` at `path` starts: its `main` and the files its
+/// `scripts` run (`node server.js`, `tsx watch src/cli.ts`), resolved
+/// against its directory. Only words with a file extension are taken, and
+/// the paths are not checked for existence. Invalid JSON yields nothing.
+///
+/// # Examples
+///
+/// ```
+/// use std::path::{Path, PathBuf};
+/// use pith::architecture::package_entry_points;
+///
+/// let json = r#"{"main": "lib/index.js", "scripts": {"dev": "tsx watch ./src/server.ts"}}"#;
+/// assert_eq!(
+///     package_entry_points(Path::new("web/package.json"), json),
+///     [PathBuf::from("web/lib/index.js"), PathBuf::from("web/src/server.ts")]
+/// );
+/// ```
+pub fn package_entry_points(path: &Path, content: &str) -> Vec<PathBuf> {
+    let Ok(package) = serde_json::from_str::<serde_json::Value>(content) else {
+        return Vec::new();
+    };
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let scripts = package["scripts"].as_object().into_iter().flatten();
+    let words = package["main"].as_str().into_iter().chain(
+        scripts
+            .filter_map(|(_, script)| script.as_str())
+            .flat_map(str::split_whitespace),
+    );
+
+    let mut targets: Vec<PathBuf> = Vec::new();
+    for word in words {
+        let word = word.trim_matches(|c| c == '"' || c == '\'');
+        if word.starts_with('-') || Path::new(word).extension().is_none() {
+            continue;
+        }
+        let target: PathBuf = dir
+            .join(word)
+            .components()
+            .filter(|c| *c != Component::CurDir)
+            .collect();
+        if !targets.contains(&target) {
+            targets.push(target);
+        }
+    }
+    targets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codemap::Import;
+    use crate::filter::Language;
+
+    type File<'a> = (&'a str, &'a [(&'a str, &'a [&'a str])]);
+
+    /// Architecture of files given as paths and their `(source, items)`
+    /// imports. The language doesn't affect resolution.
+    fn architecture(files: &[File]) -> Architecture {
+        let codemaps: Vec<Codemap> = files
+            .iter()
+            .map(|(path, imports)| {
+                let mut codemap = Codemap::empty(PathBuf::from(path), Language::Rust);
+                codemap.imports = imports
+                    .iter()
+                    .map(|(source, items)| Import {
+                        source: (*source).to_string(),
+                        items: items.iter().map(|item| (*item).to_string()).collect(),
+                    })
+                    .collect();
+                codemap
+            })
+            .collect();
+        Architecture::from_codemaps(&codemaps)
+    }
+
+    #[test]
+    fn test_rust_architecture() {
+        let architecture = architecture(&[
+            ("app/src/lib.rs", &[]),
+            (
+                "app/src/output.rs",
+                &[("crate::codemap", &["Codemap"]), ("std::fmt", &[])],
+            ),
+            ("app/src/codemap/mod.rs", &[("crate", &["tree"])]),
+            ("app/src/codemap/rust.rs", &[("super", &["Codemap"])]),
+            ("app/src/tree.rs", &[]),
+            ("app/src/bin/app.rs", &[("app::output", &["run"])]),
+        ]);
+
+        let names: Vec<&str> = architecture
+            .modules
+            .iter()
+            .map(|m| m.name.as_str())
+            .collect();
+        assert_eq!(names, ["bin", "codemap", "lib", "output", "tree"]);
+        let codemap = &architecture.modules[1];
+        assert_eq!(codemap.files, 2);
+        assert_eq!(codemap.imports, ["tree"]);
+        assert_eq!(codemap.imported_by, ["output"]);
+        // `app` is the directory above the source root.
+        assert_eq!(architecture.modules[0].imports, ["output"]);
+        assert_eq!(
+            architecture.entry_points,
+            [PathBuf::from("src/bin/app.rs"), PathBuf::from("src/lib.rs")]
+        );
+
+        let text = architecture.to_string();
+        assert!(text
+            .contains("- codemap (2 files, 0 declarations); imports tree; imported by output\n"));
+        assert!(text.contains("\nEntry points:\n- src/bin/app.rs\n"));
+    }
+
+    #[test]
+    fn test_tests_directory_beside_src() {
+        let architecture = architecture(&[
+            ("/work/app/src/lib.rs", &[]),
+            ("/work/app/src/output.rs", &[("crate::tree", &["FileNode"])]),
+            ("/work/app/src/tree.rs", &[]),
+            ("/work/app/src/bin/app.rs", &[("app::output", &["run"])]),
+            ("/work/app/tests/cli.rs", &[("app::tree", &["FileNode"])]),
+        ]);
+
+        let names: Vec<&str> = architecture
+            .modules
+            .iter()
+            .map(|m| m.name.as_str())
+            .collect();
+        assert_eq!(names, ["bin", "lib", "output", "tests", "tree"]);
+        assert_eq!(architecture.modules[2].imports, ["tree"]);
+        assert_eq!(architecture.modules[3].imports, ["tree"]);
+        assert_eq!(architecture.modules[4].imported_by, ["output", "tests"]);
+        assert_eq!(
+            architecture.entry_points,
+            [PathBuf::from("src/bin/app.rs"), PathBuf::from("src/lib.rs")]
+        );
+    }
+
+    #[test]
+    fn test_relative_imports() {
+        let web = architecture(&[
+            ("web/components/button.ts", &[("../utils/css", &["cx"])]),
+            ("web/utils/css.ts", &[("react", &[])]),
+            ("web/index.ts", &[("./components/button", &[])]),
+        ]);
+        assert_eq!(web.modules[0].name, "components");
+        assert_eq!(web.modules[0].imports, ["utils"]);
+        assert_eq!(web.modules[1].name, "index");
+        assert_eq!(web.modules[1].imports, ["components"]);
+        assert!(web.modules[2].imports.is_empty());
+        assert_eq!(web.entry_points, [PathBuf::from("index.ts")]);
+
+        let python = architecture(&[
+            ("pkg/mypkg/cli.py", &[(".", &["walker"])]),
+            ("pkg/mypkg/walker.py", &[("mypkg.cli", &[])]),
+        ]);
+        assert_eq!(python.modules[0].imports, ["walker"]);
+        assert_eq!(python.modules[1].imports, ["cli"]);
+
+        assert!(architecture(&[]).is_empty());
+    }
+}
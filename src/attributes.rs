@@ -0,0 +1,188 @@
+//! `.gitattributes` support for GitHub Linguist overrides.
+//!
+//! Reads `linguist-generated` and `linguist-vendored` from the root
+//! `.gitattributes` so paths GitHub treats as generated or vendored can be
+//! skipped, or kept out of full-content selection.
+
+use std::path::Path;
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+/// How files marked `linguist-generated` or `linguist-vendored` are treated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LinguistMode {
+    /// Leave marked files out entirely (default).
+    #[default]
+    Skip,
+    /// Extract codemaps for marked files, but never include their full content.
+    CodemapOnly,
+    /// Ignore the attributes.
+    Include,
+}
+
+/// Linguist attributes that apply to a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Linguist {
+    pub generated: bool,
+    pub vendored: bool,
+}
+
+impl Linguist {
+    /// Check if either attribute is set.
+    pub fn is_marked(self) -> bool {
+        self.generated || self.vendored
+    }
+}
+
+/// One `.gitattributes` line that sets or unsets a linguist attribute.
+#[derive(Debug)]
+struct Rule {
+    pattern: Gitignore,
+    generated: Option<bool>,
+    vendored: Option<bool>,
+}
+
+/// Parsed linguist rules from a `.gitattributes` file.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::path::Path;
+/// use pith::attributes::GitAttributes;
+///
+/// let attributes = GitAttributes::load(Path::new("./project"));
+/// if attributes.linguist(Path::new("./project/api/schema.pb.go")).generated {
+///     println!("generated");
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct GitAttributes {
+    rules: Vec<Rule>,
+}
+
+impl GitAttributes {
+    /// Read `.gitattributes` in `root`. A missing or unreadable file yields
+    /// no rules.
+    pub fn load(root: &Path) -> Self {
+        std::fs::read_to_string(root.join(".gitattributes"))
+            .map(|content| Self::parse(root, &content))
+            .unwrap_or_default()
+    }
+
+    /// Parse `.gitattributes` content whose patterns are relative to `root`.
+    /// Lines without a linguist attribute and invalid patterns are ignored.
+    pub fn parse(root: &Path, content: &str) -> Self {
+        let rules = content
+            .lines()
+            .filter_map(|line| {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    return None;
+                }
+
+                let mut tokens = line.split_whitespace();
+                let pattern = tokens.next()?;
+                // Negative patterns are not allowed in .gitattributes.
+                if pattern.starts_with('!') {
+                    return None;
+                }
+
+                let mut rule = Rule {
+                    pattern: Gitignore::empty(),
+                    generated: None,
+                    vendored: None,
+                };
+                for token in tokens {
+                    let Some((name, value)) = parse_attribute(token) else {
+                        continue;
+                    };
+                    match name {
+                        "linguist-generated" => rule.generated = Some(value),
+                        "linguist-vendored" => rule.vendored = Some(value),
+                        _ => {}
+                    }
+                }
+                if rule.generated.is_none() && rule.vendored.is_none() {
+                    return None;
+                }
+
+                let mut builder = GitignoreBuilder::new(root);
+                builder.add_line(None, pattern).ok()?;
+                rule.pattern = builder.build().ok()?;
+                Some(rule)
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// Linguist attributes of the file at `path`. When several lines match,
+    /// the last one to mention an attribute wins, as in git.
+    pub fn linguist(&self, path: &Path) -> Linguist {
+        let mut linguist = Linguist::default();
+        for rule in &self.rules {
+            if !rule.pattern.matched(path, false).is_ignore() {
+                continue;
+            }
+            if let Some(generated) = rule.generated {
+                linguist.generated = generated;
+            }
+            if let Some(vendored) = rule.vendored {
+                linguist.vendored = vendored;
+            }
+        }
+        linguist
+    }
+
+    /// Check if no linguist rules were found.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+/// Parse `attr`, `attr=true`, `attr=false`, `-attr` or `!attr` into a name
+/// and whether it is set.
+fn parse_attribute(token: &str) -> Option<(&str, bool)> {
+    if let Some(name) = token.strip_prefix('-').or_else(|| token.strip_prefix('!')) {
+        return Some((name, false));
+    }
+    match token.split_once('=') {
+        Some((name, "true")) => Some((name, true)),
+        Some((name, "false")) => Some((name, false)),
+        Some(_) => None,
+        None => Some((token, true)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_linguist_attributes() {
+        let root = Path::new("/repo");
+        let attributes = GitAttributes::parse(
+            root,
+            "# Generated code\n\
+             *.pb.go linguist-generated\n\
+             vendor/** linguist-vendored\n\
+             vendor/ours/** -linguist-vendored\n\
+             docs/** linguist-documentation\n\
+             api/*.ts linguist-generated=true text eol=lf\n",
+        );
+
+        let linguist = |path: &str| attributes.linguist(&root.join(path));
+        assert!(linguist("api/user.pb.go").generated);
+        assert!(linguist("api/client.ts").generated);
+        assert!(!linguist("api/nested/client.ts").generated);
+        assert!(linguist("vendor/lib/a.go").vendored);
+        assert!(!linguist("vendor/ours/a.go").vendored);
+        assert!(!linguist("src/main.go").is_marked());
+        assert!(!linguist("docs/guide.md").is_marked());
+    }
+
+    #[test]
+    fn test_missing_gitattributes() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(GitAttributes::load(dir.path()).is_empty());
+    }
+}
@@ -1,22 +1,49 @@
 //! Pith CLI - Generate optimized codebase context for LLMs.
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
-use std::io::Read;
-use std::path::PathBuf;
-
-use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use std::io::{IsTerminal, Read};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use clap::{ArgAction, CommandFactory, Parser, Subcommand, ValueEnum};
 use clap_complete::{generate, Shell};
 use glob::Pattern;
-use pith::codemap::{extract_codemap, ExtractOptions};
+use pith::architecture::{is_entry_point, package_entry_points};
+use pith::attributes::{GitAttributes, LinguistMode};
+use pith::budget::{fit_output, BudgetCheck, BudgetExceeded};
+use pith::builder::Pith;
+use pith::codemap::{
+    extract_codemap, Codemap, Declaration, DocSummary, ExtractOptions, KindFilter,
+    VisibilityThreshold,
+};
+use pith::decode::decode;
 use pith::errors::{exit_code, PithError};
-use pith::filter::{detect_language, should_process, FilterResult, Language};
-use pith::output::{format_output, OutputFormat, OutputOptions, SelectedFile};
-use pith::tokens::{count_tokens_with_encoding, Encoding};
-use pith::tree::{render_tree, RenderOptions};
-use pith::walker::{build_tree_with_options, walk, WalkOptions};
+use pith::filter::{
+    is_candidate, should_process, should_process_with, FilterOptions, FilterResult, Language,
+    CONFIG_FILE,
+};
+use pith::init::{config_template, detect_project, pithignore_template};
+use pith::minify::Minify;
+use pith::output::{
+    format_api_diff, serialize, write_atomic, xml_attribute, xml_cdata, DeclarationOrder,
+    OutputError, OutputFormat, OutputOptions, SelectedFile, SkippedFile, SymbolFilter,
+    XML_DECLARATION,
+};
+use pith::progress::{Phase, Progress};
+use pith::records::to_jsonl;
+use pith::tokens::{
+    count_tokens_multi, count_tokens_with_encoding, preload, Encoding, EncodingTotal,
+};
+use pith::tools::{format_tools, ToolFormat};
+use pith::tree::{format_number, render_tree, FileNode, RenderOptions};
+use pith::walker::{build_tree_with_options, walk_with_options, WalkOptions};
 use rayon::prelude::*;
 use serde::Serialize;
+use tracing_subscriber::EnvFilter;
 
 #[derive(Parser)]
 #[command(name = "pith")]
@@ -25,6 +52,39 @@ use serde::Serialize;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Write output to this file instead of stdout, atomically
+    #[arg(short, long, global = true, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Overwrite existing files (the --output file, `export --records`, or the files `init` writes)
+    #[arg(long, global = true)]
+    force: bool,
+
+    /// Log skipped files and why (-v), parse timings and cache hits (-vv) to stderr
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    verbose: u8,
+
+    /// Write progress events to stderr as they happen
+    #[arg(long, global = true, value_name = "FORMAT")]
+    progress: Option<ProgressFormat>,
+
+    /// Use at most N threads for extraction and token counting (default: one per CPU)
+    #[arg(long, global = true, value_name = "N")]
+    threads: Option<NonZeroUsize>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ProgressFormat {
+    /// One JSON object per line (NDJSON)
+    Json,
+}
+
+/// Progress reporter for this run, set once from `--progress`.
+static PROGRESS: OnceLock<Progress> = OnceLock::new();
+
+fn progress() -> &'static Progress {
+    PROGRESS.get_or_init(Progress::disabled)
 }
 
 #[derive(Subcommand)]
@@ -35,11 +95,15 @@ enum Commands {
         #[arg(default_value = ".")]
         path: PathBuf,
 
-        /// Output as JSON
-        #[arg(long)]
+        /// Output format (default: plain text)
+        #[arg(long, value_name = "FORMAT")]
+        format: Option<FormatArg>,
+
+        /// Same as --format json (deprecated)
+        #[arg(long, conflicts_with = "format")]
         json: bool,
 
-        /// Hide file sizes and line counts
+        /// Hide file sizes and line counts, and skip counting lines
         #[arg(long)]
         no_metadata: bool,
 
@@ -47,9 +111,41 @@ enum Commands {
         #[arg(long)]
         include_hidden: bool,
 
+        /// Don't skip node_modules, vendor, dist, .next, target, lock files and minified files
+        #[arg(long)]
+        no_default_ignores: bool,
+
+        /// Don't apply .gitignore, global gitignore or .git/info/exclude rules
+        #[arg(long)]
+        no_gitignore: bool,
+
+        /// Also skip paths matching this ignore file, anchored at the root (repeatable)
+        #[arg(long, value_name = "FILE", value_parser = parse_ignore_file)]
+        ignore_file: Vec<PathBuf>,
+
         /// Maximum directory depth
         #[arg(long)]
         max_depth: Option<usize>,
+
+        /// Show at most N entries per directory
+        #[arg(long, value_name = "N")]
+        max_children: Option<usize>,
+
+        /// Summarize directories deeper than N levels instead of listing them
+        #[arg(long, value_name = "N")]
+        tree_depth: Option<usize>,
+
+        /// Color the tree (auto: only when stdout is a terminal and NO_COLOR is unset)
+        #[arg(long, value_name = "WHEN", default_value = "auto")]
+        color: ColorArg,
+
+        /// Show per-file and per-directory token counts
+        #[arg(long)]
+        tokens: bool,
+
+        /// Token encoding for --tokens
+        #[arg(long, default_value = "cl100k")]
+        encoding: EncodingArg,
     },
 
     /// Extract API signatures from source files
@@ -58,25 +154,129 @@ enum Commands {
         #[arg(default_value = ".")]
         path: PathBuf,
 
-        /// Output as JSON
-        #[arg(long)]
+        /// Output format (default: xml)
+        #[arg(long, value_name = "FORMAT")]
+        format: Option<FormatArg>,
+
+        /// Same as --format json (deprecated)
+        #[arg(long, conflicts_with = "format")]
         json: bool,
 
         /// Include doc comments
         #[arg(long)]
         include_docs: bool,
 
-        /// Include private items
-        #[arg(long)]
+        /// Include private items (same as --visibility all)
+        #[arg(long, conflicts_with = "visibility")]
         include_private: bool,
 
-        /// Token encoding for token summary
-        #[arg(long, default_value = "cl100k")]
-        encoding: EncodingArg,
+        /// Minimum visibility of shown items
+        #[arg(long, value_name = "LEVEL", default_value = "public")]
+        visibility: VisibilityArg,
+
+        /// Skip test modules, test functions and Go test files
+        #[arg(long)]
+        skip_tests: bool,
+
+        /// List TODO, FIXME and HACK comments
+        #[arg(long)]
+        include_todos: bool,
+
+        /// List test names per file
+        #[arg(long)]
+        list_tests: bool,
+
+        /// Show up to N lines of source under each declaration
+        #[arg(long, value_name = "N")]
+        snippet: Option<usize>,
+
+        /// Inline full source of functions at most N lines long
+        #[arg(long, value_name = "N")]
+        inline_bodies: Option<usize>,
+
+        /// Drop doc comments that are copyright or license notices
+        #[arg(long)]
+        strip_license: bool,
+
+        /// Rewrite Python docstrings and JSDoc as summary, parameter names and return (implies --include-docs)
+        #[arg(long)]
+        normalize_docs: bool,
+
+        /// Shorten doc comments to their first sentence or first N tokens (implies --include-docs)
+        #[arg(long, value_name = "sentence|N")]
+        doc_summary: Option<DocSummary>,
+
+        /// Don't parse files larger than N bytes; list them with a parse error instead
+        #[arg(long, value_name = "N")]
+        max_parse_bytes: Option<usize>,
+
+        /// Give up parsing a file after MS milliseconds; list it with a parse error instead
+        #[arg(long, value_name = "MS")]
+        parse_timeout: Option<u64>,
+
+        /// Token encoding for token summary; list several (cl100k,o200k) to report the total in each
+        #[arg(long, value_delimiter = ',', default_value = "cl100k")]
+        encoding: Vec<EncodingArg>,
+
+        /// Prune output to fit this many tokens; exit with status 6 if it can't
+        #[arg(long, value_name = "N")]
+        max_tokens: Option<usize>,
+
+        /// Under --max-tokens, drop the least important declarations before whole files
+        #[arg(long)]
+        trim_declarations: bool,
+
+        /// Only include these declaration kinds
+        #[arg(long, value_delimiter = ',')]
+        only: Vec<KindArg>,
+
+        /// Only show declarations whose name matches this regex
+        #[arg(long, value_name = "REGEX", value_parser = parse_symbol_regex)]
+        symbol_filter: Option<SymbolFilter>,
+
+        /// Only show declarations whose name matches this glob
+        #[arg(long, value_name = "GLOB", value_parser = parse_symbol_glob, conflicts_with = "symbol_filter")]
+        symbol_glob: Option<SymbolFilter>,
+
+        /// Order of declarations within each file
+        #[arg(long, value_name = "ORDER", default_value = "source")]
+        sort: SortArg,
 
         /// Filter to specific language(s)
         #[arg(long, value_delimiter = ',')]
         lang: Vec<LanguageArg>,
+
+        /// Treat files with extension EXT as LANG, or skip them with EXT=skip (repeatable)
+        #[arg(long, value_name = "EXT=LANG", value_parser = parse_extension_mapping)]
+        map_ext: Vec<(String, Option<Language>)>,
+
+        /// Detect the language of files without a known extension from Vim/Emacs modelines
+        #[arg(long)]
+        modelines: bool,
+
+        /// Read files that aren't UTF-8 instead of skipping them: transcode UTF-16 with a BOM, replace invalid bytes
+        #[arg(long)]
+        lossy_decoding: bool,
+
+        /// Load extension mappings and heuristic thresholds from a TOML or JSON file (default: pith.toml in PATH)
+        #[arg(long, value_name = "FILE")]
+        filter_config: Option<PathBuf>,
+
+        /// Handling of files marked linguist-generated or linguist-vendored in .gitattributes
+        #[arg(long, value_name = "MODE", default_value = "skip")]
+        linguist: LinguistArg,
+
+        /// Don't skip node_modules, vendor, dist, .next, target, lock files and minified files
+        #[arg(long)]
+        no_default_ignores: bool,
+
+        /// Don't apply .gitignore, global gitignore or .git/info/exclude rules
+        #[arg(long)]
+        no_gitignore: bool,
+
+        /// Also skip paths matching this ignore file, anchored at the root (repeatable)
+        #[arg(long, value_name = "FILE", value_parser = parse_ignore_file)]
+        ignore_file: Vec<PathBuf>,
     },
 
     /// Generate full context (tree + codemaps)
@@ -85,29 +285,175 @@ enum Commands {
         #[arg(default_value = ".")]
         path: PathBuf,
 
-        /// Output as JSON
-        #[arg(long)]
+        /// Output format (default: xml)
+        #[arg(long, value_name = "FORMAT")]
+        format: Option<FormatArg>,
+
+        /// Same as --format json (deprecated)
+        #[arg(long, conflicts_with = "format")]
         json: bool,
 
         /// Include doc comments
         #[arg(long)]
         include_docs: bool,
 
-        /// Include private items
-        #[arg(long)]
+        /// Include private items (same as --visibility all)
+        #[arg(long, conflicts_with = "visibility")]
         include_private: bool,
 
-        /// Token encoding for token summary
-        #[arg(long, default_value = "cl100k")]
-        encoding: EncodingArg,
+        /// Minimum visibility of shown items
+        #[arg(long, value_name = "LEVEL", default_value = "public")]
+        visibility: VisibilityArg,
+
+        /// Skip test modules, test functions and Go test files
+        #[arg(long)]
+        skip_tests: bool,
+
+        /// List TODO, FIXME and HACK comments
+        #[arg(long)]
+        include_todos: bool,
+
+        /// List test names per file
+        #[arg(long)]
+        list_tests: bool,
+
+        /// Show up to N lines of source under each declaration
+        #[arg(long, value_name = "N")]
+        snippet: Option<usize>,
+
+        /// Inline full source of functions at most N lines long
+        #[arg(long, value_name = "N")]
+        inline_bodies: Option<usize>,
+
+        /// Drop license headers from selected files and license doc comments
+        #[arg(long)]
+        strip_license: bool,
+
+        /// Rewrite Python docstrings and JSDoc as summary, parameter names and return (implies --include-docs)
+        #[arg(long)]
+        normalize_docs: bool,
+
+        /// Shorten doc comments to their first sentence or first N tokens (implies --include-docs)
+        #[arg(long, value_name = "sentence|N")]
+        doc_summary: Option<DocSummary>,
+
+        /// Don't parse files larger than N bytes; list them with a parse error instead
+        #[arg(long, value_name = "N")]
+        max_parse_bytes: Option<usize>,
+
+        /// Give up parsing a file after MS milliseconds; list it with a parse error instead
+        #[arg(long, value_name = "MS")]
+        parse_timeout: Option<u64>,
+
+        /// Token encoding for token summary; list several (cl100k,o200k) to report the total in each
+        #[arg(long, value_delimiter = ',', default_value = "cl100k")]
+        encoding: Vec<EncodingArg>,
+
+        /// Prune output to fit this many tokens; exit with status 6 if it can't
+        #[arg(long, value_name = "N")]
+        max_tokens: Option<usize>,
+
+        /// Under --max-tokens, drop the least important declarations before whole files
+        #[arg(long)]
+        trim_declarations: bool,
 
         /// Select files for full content inclusion
         #[arg(long)]
         select: Vec<String>,
 
+        /// Select every file in these languages for full content inclusion
+        #[arg(long, value_delimiter = ',', value_name = "LANG")]
+        select_lang: Vec<LanguageArg>,
+
+        /// Select entry points (main.rs, lib.rs, index.ts, app.py, main.go, package.json scripts) for full content inclusion
+        #[arg(long)]
+        select_entrypoints: bool,
+
+        /// Prefix each line of selected files with its line number
+        #[arg(long)]
+        line_numbers: bool,
+
+        /// Open with an overview of modules, their imports and entry points
+        #[arg(long)]
+        architecture: bool,
+
+        /// Remove redundant whitespace from selected files
+        #[arg(
+            long,
+            value_name = "MODE",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "whitespace"
+        )]
+        minify: Option<MinifyArg>,
+
+        /// Show at most N entries per directory in the file tree
+        #[arg(long, value_name = "N")]
+        max_children: Option<usize>,
+
+        /// Show each file's token cost in the file tree
+        #[arg(long)]
+        tokens: bool,
+
+        /// Show only selected files and files with codemaps in the file tree
+        #[arg(long)]
+        prune_tree: bool,
+
+        /// Summarize directories deeper than N levels in the file tree
+        #[arg(long, value_name = "N")]
+        tree_depth: Option<usize>,
+
+        /// Only include these declaration kinds
+        #[arg(long, value_delimiter = ',')]
+        only: Vec<KindArg>,
+
+        /// Only show declarations whose name matches this regex
+        #[arg(long, value_name = "REGEX", value_parser = parse_symbol_regex)]
+        symbol_filter: Option<SymbolFilter>,
+
+        /// Only show declarations whose name matches this glob
+        #[arg(long, value_name = "GLOB", value_parser = parse_symbol_glob, conflicts_with = "symbol_filter")]
+        symbol_glob: Option<SymbolFilter>,
+
+        /// Order of declarations within each file
+        #[arg(long, value_name = "ORDER", default_value = "source")]
+        sort: SortArg,
+
         /// Filter to specific language(s)
         #[arg(long, value_delimiter = ',')]
         lang: Vec<LanguageArg>,
+
+        /// Treat files with extension EXT as LANG, or skip them with EXT=skip (repeatable)
+        #[arg(long, value_name = "EXT=LANG", value_parser = parse_extension_mapping)]
+        map_ext: Vec<(String, Option<Language>)>,
+
+        /// Detect the language of files without a known extension from Vim/Emacs modelines
+        #[arg(long)]
+        modelines: bool,
+
+        /// Read files that aren't UTF-8 instead of skipping them: transcode UTF-16 with a BOM, replace invalid bytes
+        #[arg(long)]
+        lossy_decoding: bool,
+
+        /// Load extension mappings and heuristic thresholds from a TOML or JSON file (default: pith.toml in PATH)
+        #[arg(long, value_name = "FILE")]
+        filter_config: Option<PathBuf>,
+
+        /// Handling of files marked linguist-generated or linguist-vendored in .gitattributes
+        #[arg(long, value_name = "MODE", default_value = "skip")]
+        linguist: LinguistArg,
+
+        /// Don't skip node_modules, vendor, dist, .next, target, lock files and minified files
+        #[arg(long)]
+        no_default_ignores: bool,
+
+        /// Don't apply .gitignore, global gitignore or .git/info/exclude rules
+        #[arg(long)]
+        no_gitignore: bool,
+
+        /// Also skip paths matching this ignore file, anchored at the root (repeatable)
+        #[arg(long, value_name = "FILE", value_parser = parse_ignore_file)]
+        ignore_file: Vec<PathBuf>,
     },
 
     /// Count tokens for files
@@ -116,6 +462,49 @@ enum Commands {
         #[arg(default_value = ".")]
         path: PathBuf,
 
+        /// Output format (default: plain text)
+        #[arg(long, value_name = "FORMAT")]
+        format: Option<FormatArg>,
+
+        /// Same as --format json (deprecated)
+        #[arg(long, conflicts_with = "format")]
+        json: bool,
+
+        /// Token encoding; list several (cl100k,o200k) to report the total in each
+        #[arg(long, value_delimiter = ',', default_value = "cl100k")]
+        encoding: Vec<EncodingArg>,
+
+        /// Show per-file breakdown
+        #[arg(long)]
+        per_file: bool,
+
+        /// Show the file tree with token totals per file and directory
+        #[arg(long, conflicts_with = "per_file")]
+        tree: bool,
+
+        /// Exit with status 6 and list the largest files if the total is over N tokens
+        #[arg(long, value_name = "N")]
+        check: Option<usize>,
+
+        /// Don't skip node_modules, vendor, dist, .next, target, lock files and minified files
+        #[arg(long)]
+        no_default_ignores: bool,
+
+        /// Don't apply .gitignore, global gitignore or .git/info/exclude rules
+        #[arg(long)]
+        no_gitignore: bool,
+
+        /// Also skip paths matching this ignore file, anchored at the root (repeatable)
+        #[arg(long, value_name = "FILE", value_parser = parse_ignore_file)]
+        ignore_file: Vec<PathBuf>,
+    },
+
+    /// Show aggregate statistics for a codebase
+    Stats {
+        /// Root directory or file to analyze
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
         /// Output as JSON
         #[arg(long)]
         json: bool,
@@ -124,9 +513,143 @@ enum Commands {
         #[arg(long, default_value = "cl100k")]
         encoding: EncodingArg,
 
-        /// Show per-file breakdown
+        /// Number of largest files to list
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+
+        /// Don't skip node_modules, vendor, dist, .next, target, lock files and minified files
         #[arg(long)]
-        per_file: bool,
+        no_default_ignores: bool,
+
+        /// Don't apply .gitignore, global gitignore or .git/info/exclude rules
+        #[arg(long)]
+        no_gitignore: bool,
+
+        /// Also skip paths matching this ignore file, anchored at the root (repeatable)
+        #[arg(long, value_name = "FILE", value_parser = parse_ignore_file)]
+        ignore_file: Vec<PathBuf>,
+    },
+
+    /// Time the walk, read, parse and tokenize phases separately and list the slowest files
+    Bench {
+        /// Root directory or file to benchmark
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Token encoding for the tokenize phase
+        #[arg(long, default_value = "cl100k")]
+        encoding: EncodingArg,
+
+        /// Number of slowest files to list
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+
+        /// Don't skip node_modules, vendor, dist, .next, target, lock files and minified files
+        #[arg(long)]
+        no_default_ignores: bool,
+
+        /// Don't apply .gitignore, global gitignore or .git/info/exclude rules
+        #[arg(long)]
+        no_gitignore: bool,
+
+        /// Also skip paths matching this ignore file, anchored at the root (repeatable)
+        #[arg(long, value_name = "FILE", value_parser = parse_ignore_file)]
+        ignore_file: Vec<PathBuf>,
+    },
+
+    /// Classify public Rust API changes between two snapshots as breaking, additive or patch
+    ApiDiff {
+        /// Root of the old snapshot (e.g. a worktree of the last release)
+        old: PathBuf,
+
+        /// Root of the new snapshot
+        new: PathBuf,
+
+        /// Output format (default: xml)
+        #[arg(long, value_name = "FORMAT")]
+        format: Option<FormatArg>,
+
+        /// Same as --format json (deprecated)
+        #[arg(long, conflicts_with = "format")]
+        json: bool,
+
+        /// Don't skip node_modules, vendor, dist, .next, target, lock files and minified files
+        #[arg(long)]
+        no_default_ignores: bool,
+
+        /// Don't apply .gitignore, global gitignore or .git/info/exclude rules
+        #[arg(long)]
+        no_gitignore: bool,
+
+        /// Also skip paths matching this ignore file, anchored at the root (repeatable)
+        #[arg(long, value_name = "FILE", value_parser = parse_ignore_file)]
+        ignore_file: Vec<PathBuf>,
+    },
+
+    /// Write declaration records for vector databases, or a tool manifest for agents
+    Export {
+        /// Root directory or file to export
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// Write one record per declaration to FILE as NDJSON
+        #[arg(long, value_name = "FILE", required_unless_present = "tools")]
+        records: Option<PathBuf>,
+
+        /// Write public functions as a tool manifest to FILE
+        #[arg(long, value_name = "FILE")]
+        tools: Option<PathBuf>,
+
+        /// Layout of the --tools manifest
+        #[arg(long, value_name = "FORMAT", default_value = "openai")]
+        tool_format: ToolFormatArg,
+
+        /// Include private items (same as --visibility all)
+        #[arg(long, conflicts_with = "visibility")]
+        include_private: bool,
+
+        /// Minimum visibility of exported items
+        #[arg(long, value_name = "LEVEL", default_value = "public")]
+        visibility: VisibilityArg,
+
+        /// Skip test modules, test functions and Go test files
+        #[arg(long)]
+        skip_tests: bool,
+
+        /// Include up to N lines of source in each record
+        #[arg(long, value_name = "N")]
+        snippet: Option<usize>,
+
+        /// Filter to specific language(s)
+        #[arg(long, value_delimiter = ',')]
+        lang: Vec<LanguageArg>,
+
+        /// Token encoding for record token counts
+        #[arg(long, default_value = "cl100k")]
+        encoding: EncodingArg,
+
+        /// Don't skip node_modules, vendor, dist, .next, target, lock files and minified files
+        #[arg(long)]
+        no_default_ignores: bool,
+
+        /// Don't apply .gitignore, global gitignore or .git/info/exclude rules
+        #[arg(long)]
+        no_gitignore: bool,
+
+        /// Also skip paths matching this ignore file, anchored at the root (repeatable)
+        #[arg(long, value_name = "FILE", value_parser = parse_ignore_file)]
+        ignore_file: Vec<PathBuf>,
+    },
+
+    /// Write a starter .pithignore and pith.toml for the detected project type
+    Init {
+        /// Project root
+        #[arg(default_value = ".")]
+        path: PathBuf,
     },
 
     /// Show supported languages
@@ -156,6 +679,127 @@ enum LanguageArg {
 }
 
 #[derive(Clone, ValueEnum)]
+enum KindArg {
+    Functions,
+    Types,
+    Structs,
+    Enums,
+    Traits,
+    TypeAliases,
+    Constants,
+    Interfaces,
+    Classes,
+    ReExports,
+}
+
+#[derive(Clone, ValueEnum)]
+enum VisibilityArg {
+    Public,
+    Crate,
+    All,
+}
+
+#[derive(Clone, ValueEnum)]
+enum ColorArg {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorArg {
+    fn enabled(&self) -> bool {
+        match self {
+            ColorArg::Always => true,
+            ColorArg::Never => false,
+            ColorArg::Auto => {
+                std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+            }
+        }
+    }
+}
+
+#[derive(Clone, ValueEnum)]
+enum LinguistArg {
+    Skip,
+    CodemapOnly,
+    Include,
+}
+
+impl From<LinguistArg> for LinguistMode {
+    fn from(arg: LinguistArg) -> Self {
+        match arg {
+            LinguistArg::Skip => LinguistMode::Skip,
+            LinguistArg::CodemapOnly => LinguistMode::CodemapOnly,
+            LinguistArg::Include => LinguistMode::Include,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ToolFormatArg {
+    Openai,
+    Mcp,
+}
+
+impl From<ToolFormatArg> for ToolFormat {
+    fn from(arg: ToolFormatArg) -> Self {
+        match arg {
+            ToolFormatArg::Openai => ToolFormat::OpenAi,
+            ToolFormatArg::Mcp => ToolFormat::Mcp,
+        }
+    }
+}
+
+#[derive(Clone, ValueEnum)]
+enum SortArg {
+    Source,
+    Name,
+    Kind,
+    Visibility,
+    Importance,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum FormatArg {
+    Xml,
+    Json,
+    Md,
+    Yaml,
+    Compact,
+    StrictXml,
+}
+
+impl From<FormatArg> for OutputFormat {
+    fn from(arg: FormatArg) -> Self {
+        match arg {
+            FormatArg::Xml => OutputFormat::Xml,
+            FormatArg::Json => OutputFormat::Json,
+            FormatArg::Md => OutputFormat::Markdown,
+            FormatArg::Yaml => OutputFormat::Yaml,
+            FormatArg::Compact => OutputFormat::Compact,
+            FormatArg::StrictXml => OutputFormat::StrictXml,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum MinifyArg {
+    /// Strip trailing whitespace and collapse runs of blank lines
+    Whitespace,
+    /// Also remove indentation shared by every line
+    Dedent,
+}
+
+impl From<MinifyArg> for Minify {
+    fn from(arg: MinifyArg) -> Self {
+        match arg {
+            MinifyArg::Whitespace => Minify::Whitespace,
+            MinifyArg::Dedent => Minify::Dedent,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
 enum EncodingArg {
     Cl100k,
     O200k,
@@ -170,10 +814,234 @@ impl From<EncodingArg> for Encoding {
     }
 }
 
-impl From<LanguageArg> for Language {
-    fn from(arg: LanguageArg) -> Self {
+/// The first encoding counts tokens and enforces budgets; the others, minus
+/// repeats, only get their totals reported.
+fn split_encodings(args: &[EncodingArg]) -> (Encoding, Vec<Encoding>) {
+    let mut encodings: Vec<Encoding> = Vec::new();
+    for &arg in args {
+        let encoding = arg.into();
+        if !encodings.contains(&encoding) {
+            encodings.push(encoding);
+        }
+    }
+    let primary = encodings.first().copied().unwrap_or_default();
+    (primary, encodings.into_iter().skip(1).collect())
+}
+
+impl From<KindArg> for KindFilter {
+    fn from(arg: KindArg) -> Self {
         match arg {
-            LanguageArg::Rust => Language::Rust,
+            KindArg::Functions => KindFilter::Functions,
+            KindArg::Types => KindFilter::Types,
+            KindArg::Structs => KindFilter::Structs,
+            KindArg::Enums => KindFilter::Enums,
+            KindArg::Traits => KindFilter::Traits,
+            KindArg::TypeAliases => KindFilter::TypeAliases,
+            KindArg::Constants => KindFilter::Constants,
+            KindArg::Interfaces => KindFilter::Interfaces,
+            KindArg::Classes => KindFilter::Classes,
+            KindArg::ReExports => KindFilter::ReExports,
+        }
+    }
+}
+
+impl From<VisibilityArg> for VisibilityThreshold {
+    fn from(arg: VisibilityArg) -> Self {
+        match arg {
+            VisibilityArg::Public => VisibilityThreshold::Public,
+            VisibilityArg::Crate => VisibilityThreshold::Crate,
+            VisibilityArg::All => VisibilityThreshold::All,
+        }
+    }
+}
+
+impl From<SortArg> for DeclarationOrder {
+    fn from(arg: SortArg) -> Self {
+        match arg {
+            SortArg::Source => DeclarationOrder::Source,
+            SortArg::Name => DeclarationOrder::Name,
+            SortArg::Kind => DeclarationOrder::Kind,
+            SortArg::Visibility => DeclarationOrder::Visibility,
+            SortArg::Importance => DeclarationOrder::Importance,
+        }
+    }
+}
+
+fn parse_symbol_regex(s: &str) -> Result<SymbolFilter, String> {
+    SymbolFilter::regex(s).map_err(|e| e.to_string())
+}
+
+fn parse_symbol_glob(s: &str) -> Result<SymbolFilter, String> {
+    SymbolFilter::glob(s).map_err(|e| e.to_string())
+}
+
+fn parse_ignore_file(s: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(s);
+    if path.is_file() {
+        Ok(path)
+    } else {
+        Err(format!("no such file: {s}"))
+    }
+}
+
+/// Parse `EXT=LANG` or `EXT=skip`.
+fn parse_extension_mapping(s: &str) -> Result<(String, Option<Language>), String> {
+    let (ext, lang) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected EXT=LANG or EXT=skip, got '{s}'"))?;
+    if ext.trim_start_matches('.').is_empty() {
+        return Err(format!("missing extension in '{s}'"));
+    }
+    if lang.eq_ignore_ascii_case("skip") {
+        return Ok((ext.to_string(), None));
+    }
+    Ok((ext.to_string(), Some(lang.parse()?)))
+}
+
+/// Walk options, filter options and `.gitattributes` linguist rules for
+/// `codemap` and `context`.
+struct SourceFilter {
+    walk_options: WalkOptions,
+    options: FilterOptions,
+    attributes: GitAttributes,
+    linguist: LinguistMode,
+}
+
+impl SourceFilter {
+    /// Layer `--map-ext`, `--modelines` and `--lossy-decoding` over the
+    /// `--filter-config` file, or the root's `pith.toml` if there is one.
+    fn new(
+        root: &Path,
+        config: Option<&Path>,
+        mappings: Vec<(String, Option<Language>)>,
+        modelines: bool,
+        lossy_decoding: bool,
+        linguist: LinguistMode,
+        walk_options: WalkOptions,
+    ) -> Result<Self, PithError> {
+        let project_config = root.join(CONFIG_FILE);
+        let base = match config {
+            Some(path) => FilterOptions::load(path)?,
+            None if project_config.is_file() => FilterOptions::load(&project_config)?,
+            None => FilterOptions::default(),
+        };
+        let detect_modelines = base.detect_modelines || modelines;
+        let lossy_decoding = base.lossy_decoding || lossy_decoding;
+        let options = mappings
+            .into_iter()
+            .fold(base, |options, (ext, lang)| match lang {
+                Some(lang) => options.map_extension(&ext, lang),
+                None => options.skip_extension(&ext),
+            })
+            .detect_modelines(detect_modelines)
+            .lossy_decoding(lossy_decoding);
+        let attributes = match linguist {
+            LinguistMode::Skip | LinguistMode::CodemapOnly => GitAttributes::load(root),
+            LinguistMode::Include => GitAttributes::default(),
+        };
+        Ok(Self {
+            walk_options,
+            options,
+            attributes,
+            linguist,
+        })
+    }
+
+    /// Whether `path` is worth reading, judged by its name alone.
+    fn is_candidate(&self, path: &Path) -> bool {
+        if self.is_linguist_skipped(path) {
+            return false;
+        }
+        if is_candidate(path, &self.options) {
+            return true;
+        }
+        if let FilterResult::Reject(reason) = should_process_with(path, None, &self.options) {
+            report_skipped(path, &reason);
+        }
+        false
+    }
+
+    /// Whether `path` is marked generated or vendored and such files are skipped.
+    fn is_linguist_skipped(&self, path: &Path) -> bool {
+        if self.linguist != LinguistMode::Skip {
+            return false;
+        }
+        let linguist = self.attributes.linguist(path);
+        if linguist.generated {
+            report_skipped(path, &"linguist-generated");
+        } else if linguist.vendored {
+            report_skipped(path, &"linguist-vendored");
+        }
+        linguist.is_marked()
+    }
+
+    /// Whether `path` may get a codemap but never full content.
+    fn is_codemap_only(&self, path: &Path) -> bool {
+        self.linguist == LinguistMode::CodemapOnly && self.attributes.linguist(path).is_marked()
+    }
+
+    /// Language of `path` given its first 1KB, or `None` if it is rejected.
+    fn check(&self, path: &Path, first_kb: &[u8]) -> Option<Language> {
+        match should_process_with(path, Some(first_kb), &self.options) {
+            FilterResult::Accept(lang) => Some(lang),
+            FilterResult::Reject(reason) => {
+                report_skipped(path, &reason);
+                None
+            }
+        }
+    }
+
+    /// Language and content of `path`, or `None` if the filter rejects it or
+    /// `wanted` turns its language down. A file that can't be read, or isn't
+    /// UTF-8 and isn't decoded lossily, is an error with the reason.
+    fn read(
+        &self,
+        path: &Path,
+        wanted: impl FnOnce(Language) -> bool,
+    ) -> Result<Option<(Language, String)>, SkippedFile> {
+        let skipped = |reason: String| {
+            report_skipped(path, &reason);
+            SkippedFile {
+                path: path.to_path_buf(),
+                reason,
+            }
+        };
+
+        // Check heuristics on first 1KB (binary/minified/generated)
+        let mut file = std::fs::File::open(path).map_err(|e| skipped(e.to_string()))?;
+        let mut first_kb = [0u8; 1024];
+        let n = file
+            .read(&mut first_kb)
+            .map_err(|e| skipped(e.to_string()))?;
+        let Some(lang) = self.check(path, &first_kb[..n]) else {
+            return Ok(None);
+        };
+        if !wanted(lang) {
+            return Ok(None);
+        }
+
+        // Read full content (reuse already-read prefix)
+        let mut bytes = first_kb[..n].to_vec();
+        file.read_to_end(&mut bytes)
+            .map_err(|e| skipped(e.to_string()))?;
+        let decoded = decode(bytes, self.options.lossy_decoding)
+            .ok_or_else(|| skipped("invalid UTF-8".to_string()))?;
+        if let Some(transcoding) = decoded.transcoding {
+            tracing::warn!("{}: {}", path.display(), transcoding);
+        }
+        Ok(Some((lang, decoded.text)))
+    }
+}
+
+/// Log a file left out of `codemap` or `context` and the rule that rejected it.
+fn report_skipped(path: &Path, reason: &dyn std::fmt::Display) {
+    tracing::info!("skipped {}: {}", path.display(), reason);
+}
+
+impl From<LanguageArg> for Language {
+    fn from(arg: LanguageArg) -> Self {
+        match arg {
+            LanguageArg::Rust => Language::Rust,
             LanguageArg::Typescript => Language::TypeScript,
             LanguageArg::Tsx => Language::Tsx,
             LanguageArg::Javascript => Language::JavaScript,
@@ -186,272 +1054,1489 @@ impl From<LanguageArg> for Language {
 
 fn main() {
     let cli = Cli::parse();
+    init_logging(cli.verbose);
+    if let Some(ProgressFormat::Json) = cli.progress {
+        PROGRESS.get_or_init(|| Progress::ndjson(std::io::stderr()));
+    }
     let json_output = json_flag(&cli.command);
+    // Load the tokenizer while files are walked; the join handle is dropped
+    // because counting waits for the load anyway.
+    if let Some(encoding) = token_encoding(&cli.command) {
+        preload(encoding);
+    }
+
+    let (output, force) = (cli.output.as_deref(), cli.force);
+    let result = match cli.threads {
+        // A pool of our own rather than the global one, installed around the
+        // whole command so the cap covers everything it runs in parallel.
+        Some(threads) => rayon::ThreadPoolBuilder::new()
+            .num_threads(threads.get())
+            .build()
+            .map_err(|e| PithError::Io(std::io::Error::other(e)))
+            .and_then(|pool| pool.install(|| run(cli.command, output, force))),
+        None => run(cli.command, output, force),
+    }
+    .and_then(|text| emit(&text, output, force));
+    if result.is_ok() {
+        progress().complete();
+    }
+
+    if let Err(e) = result {
+        if json_output {
+            #[derive(Serialize)]
+            struct ErrorOutput {
+                error: String,
+            }
+
+            let payload = ErrorOutput {
+                error: e.to_string(),
+            };
+
+            let json = serde_json::to_string(&payload)
+                .unwrap_or_else(|_| "{\"error\":\"serialization failed\"}".to_string());
+            eprintln!("{json}");
+        } else {
+            eprintln!("error: {}", e);
+        }
+        std::process::exit(exit_code(&e));
+    }
+}
 
-    let result = match cli.command {
+/// Run `command`, returning what to print.
+fn run(command: Commands, output: Option<&Path>, force: bool) -> Result<String, PithError> {
+    match command {
         Commands::Tree {
             path,
+            format,
             json,
             no_metadata,
             include_hidden,
+            no_default_ignores,
+            no_gitignore,
+            ignore_file,
             max_depth,
-        } => run_tree(path, json, no_metadata, include_hidden, max_depth),
+            max_children,
+            tree_depth,
+            color,
+            tokens,
+            encoding,
+        } => run_tree(
+            path,
+            output_format(format, json),
+            WalkOptions {
+                max_depth,
+                include_hidden,
+                default_ignores: !no_default_ignores,
+                respect_gitignore: !no_gitignore,
+                custom_ignores: ignore_file,
+                ..Default::default()
+            }
+            .metadata(!no_metadata),
+            RenderOptions {
+                show_size: !no_metadata,
+                show_lines: !no_metadata,
+                show_language: !no_metadata,
+                show_totals: !no_metadata,
+                max_children,
+                max_depth: tree_depth,
+                color: color.enabled(),
+                ..Default::default()
+            },
+            tokens.then(|| encoding.into()),
+        ),
         Commands::Codemap {
             path,
+            format,
             json,
             include_docs,
             include_private,
+            visibility,
+            skip_tests,
+            include_todos,
+            list_tests,
+            snippet,
+            inline_bodies,
+            strip_license,
+            normalize_docs,
+            doc_summary,
+            max_parse_bytes,
+            parse_timeout,
+            only,
+            symbol_filter,
+            symbol_glob,
+            sort,
             encoding,
+            max_tokens,
+            trim_declarations,
             lang,
-        } => run_codemap(
-            path,
-            json,
-            include_docs,
-            include_private,
-            encoding.into(),
-            lang,
-        ),
+            map_ext,
+            modelines,
+            lossy_decoding,
+            filter_config,
+            linguist,
+            no_default_ignores,
+            no_gitignore,
+            ignore_file,
+        } => {
+            let extract_opts = ExtractOptions {
+                include_docs: include_docs || normalize_docs || doc_summary.is_some(),
+                visibility: if include_private {
+                    VisibilityThreshold::All
+                } else {
+                    visibility.into()
+                },
+                skip_tests,
+                include_todos,
+                list_tests,
+                include_snippet: snippet,
+                inline_bodies,
+                only: only.into_iter().map(Into::into).collect(),
+                strip_license_docs: strip_license,
+                normalize_docs,
+                doc_summary: doc_summary.unwrap_or_default(),
+                max_parse_bytes,
+                parse_timeout: parse_timeout.map(Duration::from_millis),
+            };
+            let (encoding, extra_encodings) = split_encodings(&encoding);
+            let output_opts = OutputOptions {
+                format: output_format(format, json).unwrap_or_default(),
+                visibility: extract_opts.visibility,
+                symbol_filter: symbol_filter.or(symbol_glob),
+                sort: sort.into(),
+                max_tokens,
+                trim_declarations,
+                extra_encodings,
+                ..OutputOptions::codemap_only()
+            };
+            SourceFilter::new(
+                &path,
+                filter_config.as_deref(),
+                map_ext,
+                modelines,
+                lossy_decoding,
+                linguist.into(),
+                WalkOptions {
+                    default_ignores: !no_default_ignores,
+                    respect_gitignore: !no_gitignore,
+                    custom_ignores: ignore_file,
+                    ..Default::default()
+                },
+            )
+            .and_then(|filter| {
+                run_codemap(path, extract_opts, output_opts, encoding, lang, &filter)
+            })
+        }
         Commands::Context {
             path,
+            format,
             json,
             include_docs,
             include_private,
+            visibility,
+            skip_tests,
+            include_todos,
+            list_tests,
+            snippet,
+            inline_bodies,
+            strip_license,
+            normalize_docs,
+            doc_summary,
+            max_parse_bytes,
+            parse_timeout,
+            only,
+            symbol_filter,
+            symbol_glob,
+            sort,
             encoding,
+            max_tokens,
+            trim_declarations,
             select,
+            select_lang,
+            select_entrypoints,
+            line_numbers,
+            minify,
+            architecture,
+            max_children,
+            tokens,
+            prune_tree,
+            tree_depth,
             lang,
-        } => run_context(
+            map_ext,
+            modelines,
+            lossy_decoding,
+            filter_config,
+            linguist,
+            no_default_ignores,
+            no_gitignore,
+            ignore_file,
+        } => {
+            let extract_opts = ExtractOptions {
+                include_docs: include_docs || normalize_docs || doc_summary.is_some(),
+                visibility: if include_private {
+                    VisibilityThreshold::All
+                } else {
+                    visibility.into()
+                },
+                skip_tests,
+                include_todos,
+                list_tests,
+                include_snippet: snippet,
+                inline_bodies,
+                only: only.into_iter().map(Into::into).collect(),
+                strip_license_docs: strip_license,
+                normalize_docs,
+                doc_summary: doc_summary.unwrap_or_default(),
+                max_parse_bytes,
+                parse_timeout: parse_timeout.map(Duration::from_millis),
+            };
+            let (encoding, extra_encodings) = split_encodings(&encoding);
+            let output_opts = OutputOptions {
+                format: output_format(format, json).unwrap_or_default(),
+                visibility: extract_opts.visibility,
+                symbol_filter: symbol_filter.or(symbol_glob),
+                sort: sort.into(),
+                tree_max_children: max_children,
+                tree_tokens: tokens,
+                prune_tree,
+                tree_max_depth: tree_depth,
+                line_numbers,
+                strip_license_headers: strip_license,
+                minify: minify.map_or(Minify::Off, Into::into),
+                include_architecture: architecture,
+                max_tokens,
+                trim_declarations,
+                extra_encodings,
+                ..OutputOptions::default()
+            };
+            SourceFilter::new(
+                &path,
+                filter_config.as_deref(),
+                map_ext,
+                modelines,
+                lossy_decoding,
+                linguist.into(),
+                WalkOptions {
+                    default_ignores: !no_default_ignores,
+                    respect_gitignore: !no_gitignore,
+                    custom_ignores: ignore_file,
+                    ..Default::default()
+                },
+            )
+            .and_then(|filter| {
+                run_context(
+                    path,
+                    extract_opts,
+                    output_opts,
+                    encoding,
+                    Selection::new(&select, select_lang, select_entrypoints),
+                    lang,
+                    &filter,
+                )
+            })
+        }
+        Commands::Tokens {
+            path,
+            format,
+            json,
+            encoding,
+            per_file,
+            tree,
+            check,
+            no_default_ignores,
+            no_gitignore,
+            ignore_file,
+        } => run_tokens(
+            path,
+            output_format(format, json),
+            &split_encodings(&encoding),
+            (per_file, tree),
+            check,
+            &WalkOptions {
+                default_ignores: !no_default_ignores,
+                respect_gitignore: !no_gitignore,
+                custom_ignores: ignore_file,
+                ..Default::default()
+            },
+        )
+        .and_then(|(report, exceeded)| match exceeded {
+            // Write the report before failing so CI logs show what went over.
+            Some(exceeded) => emit(&report, output, force).and(Err(exceeded.into())),
+            None => Ok(report),
+        }),
+        Commands::Stats {
+            path,
+            json,
+            encoding,
+            top,
+            no_default_ignores,
+            no_gitignore,
+            ignore_file,
+        } => run_stats(
             path,
             json,
-            include_docs,
-            include_private,
             encoding.into(),
-            select,
-            lang,
+            top,
+            &WalkOptions {
+                default_ignores: !no_default_ignores,
+                respect_gitignore: !no_gitignore,
+                custom_ignores: ignore_file,
+                ..Default::default()
+            },
         ),
-        Commands::Tokens {
+        Commands::Bench {
             path,
             json,
             encoding,
-            per_file,
-        } => run_tokens(path, json, encoding.into(), per_file),
+            top,
+            no_default_ignores,
+            no_gitignore,
+            ignore_file,
+        } => run_bench(
+            path,
+            json,
+            encoding.into(),
+            top,
+            &WalkOptions {
+                default_ignores: !no_default_ignores,
+                respect_gitignore: !no_gitignore,
+                custom_ignores: ignore_file,
+                ..Default::default()
+            },
+        ),
+        Commands::ApiDiff {
+            old,
+            new,
+            format,
+            json,
+            no_default_ignores,
+            no_gitignore,
+            ignore_file,
+        } => run_api_diff(
+            &old,
+            &new,
+            output_format(format, json).unwrap_or_default(),
+            &WalkOptions {
+                default_ignores: !no_default_ignores,
+                respect_gitignore: !no_gitignore,
+                custom_ignores: ignore_file,
+                ..Default::default()
+            },
+        ),
+        Commands::Export {
+            path,
+            records,
+            tools,
+            tool_format,
+            include_private,
+            visibility,
+            skip_tests,
+            snippet,
+            lang,
+            encoding,
+            no_default_ignores,
+            no_gitignore,
+            ignore_file,
+        } => {
+            let mut pith = Pith::new(&path)
+                .include_docs(true)
+                .visibility(if include_private {
+                    VisibilityThreshold::All
+                } else {
+                    visibility.into()
+                })
+                .skip_tests(skip_tests)
+                .encoding(encoding.into())
+                .default_ignores(!no_default_ignores)
+                .respect_gitignore(!no_gitignore);
+            if let Some(lines) = snippet {
+                pith = pith.include_snippet(lines);
+            }
+            if !lang.is_empty() {
+                let languages: Vec<Language> = lang.into_iter().map(Into::into).collect();
+                pith = pith.languages(&languages);
+            }
+            for file in ignore_file {
+                pith = pith.ignore_file(file);
+            }
+            let tools = tools.map(|file| (file, tool_format.into()));
+            run_export(pith, records.as_deref(), tools, force)
+        }
+        Commands::Init { path } => run_init(&path, force),
         Commands::Languages { json } => run_languages(json),
         Commands::Completions { shell } => {
-            generate(shell, &mut Cli::command(), "pith", &mut std::io::stdout());
+            let mut script = Vec::new();
+            generate(shell, &mut Cli::command(), "pith", &mut script);
+            Ok(String::from_utf8_lossy(&script).into_owned())
+        }
+    }
+}
+
+/// Send logs to stderr. `PITH_LOG` takes `RUST_LOG`-style directives
+/// (`debug`, `pith::codemap=trace`) and overrides `-v`.
+fn init_logging(verbose: u8) {
+    let level = match verbose {
+        0 => "warn",
+        1 => "info",
+        2 => "debug",
+        _ => "trace",
+    };
+    let filter = EnvFilter::try_from_env("PITH_LOG").unwrap_or_else(|_| EnvFilter::new(level));
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal())
+        .without_time()
+        .with_target(false)
+        .init();
+}
+
+/// Print `output`, or write it to `path` through a temporary file and a
+/// rename, so a failed run never leaves a truncated file behind.
+fn emit(output: &str, path: Option<&Path>, force: bool) -> Result<(), PithError> {
+    match path {
+        Some(path) => Ok(write_atomic(path, output.as_bytes(), force)?),
+        None => {
+            print!("{}", output);
             Ok(())
         }
+    }
+}
+
+/// `--format`, with the deprecated `--json` standing for `--format json`.
+fn output_format(format: Option<FormatArg>, json: bool) -> Option<OutputFormat> {
+    if json {
+        Some(OutputFormat::Json)
+    } else {
+        format.map(Into::into)
+    }
+}
+
+fn json_flag(cmd: &Commands) -> bool {
+    match cmd {
+        Commands::Tree { json, format, .. }
+        | Commands::Codemap { json, format, .. }
+        | Commands::Context { json, format, .. }
+        | Commands::ApiDiff { json, format, .. }
+        | Commands::Tokens { json, format, .. } => *json || *format == Some(FormatArg::Json),
+        Commands::Stats { json, .. } | Commands::Bench { json, .. } => *json,
+        Commands::Languages { json } => *json,
+        Commands::Export { .. } | Commands::Init { .. } | Commands::Completions { .. } => false,
+    }
+}
+
+/// Encoding the command counts tokens with, if it counts any.
+fn token_encoding(cmd: &Commands) -> Option<Encoding> {
+    match cmd {
+        Commands::Tree {
+            tokens, encoding, ..
+        } => tokens.then(|| (*encoding).into()),
+        Commands::Codemap { encoding, .. }
+        | Commands::Context { encoding, .. }
+        | Commands::Tokens { encoding, .. } => Some(split_encodings(encoding).0),
+        Commands::Stats { encoding, .. } | Commands::Export { encoding, .. } => {
+            Some((*encoding).into())
+        }
+        // Bench loads the tokenizer itself, outside the timed phases.
+        Commands::ApiDiff { .. }
+        | Commands::Bench { .. }
+        | Commands::Init { .. }
+        | Commands::Languages { .. }
+        | Commands::Completions { .. } => None,
+    }
+}
+
+// --- API diff command ---
+
+fn run_api_diff(
+    old: &Path,
+    new: &Path,
+    format: OutputFormat,
+    walk_opts: &WalkOptions,
+) -> Result<String, PithError> {
+    let snapshot = |root: &Path| {
+        let mut pith = Pith::new(root)
+            .languages(&[Language::Rust])
+            .include_docs(true)
+            .visibility(VisibilityThreshold::All)
+            .default_ignores(walk_opts.default_ignores)
+            .respect_gitignore(walk_opts.respect_gitignore);
+        for file in &walk_opts.custom_ignores {
+            pith = pith.ignore_file(file);
+        }
+        pith.build()
     };
+    let diff = snapshot(old)?.api_diff(&snapshot(new)?);
+    Ok(format_api_diff(&diff, format)?)
+}
 
-    if let Err(e) = result {
-        if json_output {
-            #[derive(Serialize)]
-            struct ErrorOutput {
-                error: String,
+// --- Export command ---
+
+/// Write the records and/or tool manifest of `pith`'s codemaps, reporting
+/// what was written.
+fn run_export(
+    pith: Pith,
+    records: Option<&Path>,
+    tools: Option<(PathBuf, ToolFormat)>,
+    force: bool,
+) -> Result<String, PithError> {
+    let result = pith.build()?;
+    let mut out = String::new();
+    if let Some(path) = records {
+        let records = result.records();
+        let jsonl = to_jsonl(&records).map_err(OutputError::from)?;
+        write_atomic(path, jsonl.as_bytes(), force)?;
+        let tokens: usize = records.iter().map(|record| record.tokens).sum();
+        out.push_str(&format!(
+            "Wrote {} records ({} tokens) to {}\n",
+            format_number(records.len()),
+            format_number(tokens),
+            path.display()
+        ));
+    }
+    if let Some((path, format)) = tools {
+        let tools = result.tools();
+        let manifest = format_tools(&tools, format).map_err(OutputError::from)?;
+        write_atomic(&path, format!("{}\n", manifest).as_bytes(), force)?;
+        out.push_str(&format!(
+            "Wrote {} tools to {}\n",
+            format_number(tools.len()),
+            path.display()
+        ));
+    }
+    Ok(out)
+}
+
+// --- Init command ---
+
+fn run_init(path: &Path, force: bool) -> Result<String, PithError> {
+    if !path.is_dir() {
+        return Err(PithError::PathNotFound(path.to_path_buf()));
+    }
+
+    let kinds = detect_project(path);
+    let files = [
+        (path.join(".pithignore"), pithignore_template(&kinds)),
+        (path.join(CONFIG_FILE), config_template(&kinds)),
+    ];
+    // Check both up front so a refusal never leaves just one file written.
+    if !force {
+        if let Some((existing, _)) = files.iter().find(|(file, _)| file.exists()) {
+            return Err(OutputError::FileExists {
+                path: existing.clone(),
+            }
+            .into());
+        }
+    }
+
+    let mut out = String::new();
+    if kinds.is_empty() {
+        out.push_str("No Rust, Node, Python or Go project detected; writing generic defaults\n");
+    } else {
+        let names: Vec<String> = kinds.iter().map(ToString::to_string).collect();
+        out.push_str(&format!("Detected: {}\n", names.join(", ")));
+    }
+    for (file, content) in &files {
+        write_atomic(file, content.as_bytes(), force)?;
+        out.push_str(&format!("Wrote {}\n", file.display()));
+    }
+    Ok(out)
+}
+
+// --- Languages command ---
+
+#[derive(Serialize)]
+struct LanguageInfo {
+    name: String,
+    extensions: Vec<String>,
+}
+
+fn run_languages(json: bool) -> Result<String, PithError> {
+    let languages: Vec<LanguageInfo> = Language::all()
+        .iter()
+        .map(|lang| LanguageInfo {
+            name: lang.to_string(),
+            extensions: lang
+                .extensions()
+                .iter()
+                .map(|e| format!(".{}", e))
+                .collect(),
+        })
+        .collect();
+
+    if json {
+        #[derive(Serialize)]
+        struct Output {
+            languages: Vec<LanguageInfo>,
+        }
+        let output = Output { languages };
+        let json = serde_json::to_string_pretty(&output)
+            .map_err(|e| PithError::Io(std::io::Error::other(e.to_string())))?;
+        Ok(format!("{json}\n"))
+    } else {
+        let mut out = String::from("Supported languages:\n");
+        for lang in &languages {
+            out.push_str(&format!(
+                "  {:12} {}\n",
+                lang.name,
+                lang.extensions.join(", ")
+            ));
+        }
+        Ok(out)
+    }
+}
+
+// --- Tokens command ---
+
+fn run_tokens(
+    path: PathBuf,
+    format: Option<OutputFormat>,
+    (encoding, extra_encodings): &(Encoding, Vec<Encoding>),
+    (per_file, tree): (bool, bool),
+    budget: Option<usize>,
+    walk_opts: &WalkOptions,
+) -> Result<(String, Option<BudgetExceeded>), PithError> {
+    if !path.exists() {
+        return Err(PithError::PathNotFound(path));
+    }
+
+    let encoding = *encoding;
+    let encodings: Vec<Encoding> = std::iter::once(encoding)
+        .chain(extra_encodings.iter().copied())
+        .collect();
+    // Counts under every encoding, the first being `encoding`.
+    let mut file_counts: BTreeMap<PathBuf, Vec<EncodingTotal>> = BTreeMap::new();
+
+    if path.is_file() {
+        let content = fs::read_to_string(&path)?;
+        file_counts.insert(path.clone(), count_tokens_multi(&content, &encodings));
+    } else {
+        // Collect file paths for parallel processing
+        let paths = walk_files(&path, walk_opts);
+        let done = AtomicUsize::new(0);
+
+        file_counts = paths
+            .par_iter()
+            .map(|entry_path| {
+                use std::io::Read;
+
+                let mut file = std::fs::File::open(entry_path).ok()?;
+
+                let mut first_kb = [0u8; 1024];
+                let n = file.read(&mut first_kb).ok()?;
+
+                match should_process(entry_path, Some(&first_kb[..n])) {
+                    FilterResult::Accept(_) => {}
+                    FilterResult::Reject(_) => return None,
+                }
+
+                let mut content = String::new();
+                content.push_str(std::str::from_utf8(&first_kb[..n]).ok()?);
+                file.read_to_string(&mut content).ok()?;
+
+                let counts = count_tokens_multi(&content, &encodings);
+
+                let relative = entry_path
+                    .strip_prefix(&path)
+                    .unwrap_or(entry_path)
+                    .to_path_buf();
+                Some((relative, counts))
+            })
+            .inspect(|_| {
+                let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+                progress().update(Phase::Extract, done, Some(paths.len()));
+            })
+            .flatten()
+            .collect();
+        progress().finish(Phase::Extract, paths.len(), Some(paths.len()));
+    }
+
+    let file_tokens: BTreeMap<PathBuf, usize> = file_counts
+        .iter()
+        .map(|(path, counts)| (path.clone(), counts[0].total))
+        .collect();
+    let total: usize = file_tokens.values().sum();
+    let extra_totals: Vec<EncodingTotal> = extra_encodings
+        .iter()
+        .enumerate()
+        .map(|(i, &encoding)| EncodingTotal {
+            encoding,
+            total: file_counts.values().map(|counts| counts[i + 1].total).sum(),
+        })
+        .collect();
+    let check = budget.map(|budget| BudgetCheck::new(&file_tokens, budget, CHECK_TOP_FILES));
+    let exceeded = check.as_ref().and_then(BudgetCheck::error);
+
+    // The counted files nested by directory. `file_tokens` is keyed by
+    // paths relative to the root, the tree by full paths.
+    let count_of = |p: &Path| {
+        let relative = p
+            .strip_prefix(&path)
+            .ok()
+            .filter(|r| !r.as_os_str().is_empty())
+            .unwrap_or(p);
+        file_tokens.get(relative).copied()
+    };
+    let tree = if tree {
+        let tree = build_tree_with_options(&path, walk_opts)
+            .map_err(|e| PithError::Io(std::io::Error::other(e.to_string())))?;
+        Some(tree.filter(|file| count_of(&file.path).is_some()))
+    } else {
+        None
+    };
+    let tree_tokens: HashMap<&PathBuf, usize> = tree
+        .iter()
+        .flat_map(FileNode::iter_files)
+        .filter_map(|file| Some((&file.path, count_of(&file.path)?)))
+        .collect();
+    let rendered_tree = tree.as_ref().map(|tree| {
+        render_tree(
+            tree,
+            &RenderOptions {
+                show_totals: true,
+                tokens: tree_tokens.clone(),
+                ..RenderOptions::minimal()
+            },
+        )
+    });
+
+    if let Some(format) = format.filter(|f| f.is_structured()) {
+        #[derive(Serialize)]
+        struct Output {
+            total: usize,
+            encoding: String,
+            #[serde(skip_serializing_if = "Vec::is_empty")]
+            extra_totals: Vec<EncodingTotal>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            files: Option<BTreeMap<String, usize>>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            tree: Option<JsonTreeNode>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            check: Option<BudgetCheck>,
+        }
+
+        let files = if per_file {
+            Some(
+                file_tokens
+                    .into_iter()
+                    .map(|(k, v)| (k.display().to_string(), v))
+                    .collect(),
+            )
+        } else {
+            None
+        };
+
+        let output = Output {
+            total,
+            encoding: encoding.to_string(),
+            extra_totals,
+            files,
+            tree: tree.as_ref().map(|tree| tree_to_json(tree, &tree_tokens)),
+            check,
+        };
+        return Ok((structured(&output, format)?, exceeded));
+    }
+
+    if format == Some(OutputFormat::StrictXml) {
+        let files = if per_file { Some(&file_tokens) } else { None };
+        let mut out = tokens_strict_xml(total, encoding, &extra_totals, files, check.as_ref());
+        if let Some(rendered) = &rendered_tree {
+            let file_map = format!("<file_map>\n{}\n</file_map>\n", xml_cdata(rendered));
+            out.insert_str(out.len() - "</token_summary>\n".len(), &file_map);
+        }
+        return Ok((out, exceeded));
+    }
+
+    use std::fmt::Write;
+    let mut out = String::new();
+    match format {
+        Some(OutputFormat::Markdown) => {
+            if let Some(rendered) = &rendered_tree {
+                writeln!(out, "```\n{}```\n", rendered).ok();
+            }
+            if per_file {
+                out.push_str("| File | Tokens |\n|------|-------:|\n");
+                for (file, count) in &file_tokens {
+                    writeln!(out, "| {} | {} |", file.display(), format_number(*count)).ok();
+                }
+                out.push('\n');
+            }
+            writeln!(out, "**Total: {} tokens**", format_number(total)).ok();
+            for extra in &extra_totals {
+                writeln!(
+                    out,
+                    "**Total ({}): {} tokens**",
+                    extra.encoding,
+                    format_number(extra.total)
+                )
+                .ok();
+            }
+        }
+        Some(OutputFormat::Compact) => {
+            out.push_str(rendered_tree.as_deref().unwrap_or_default());
+            if per_file {
+                for (file, count) in &file_tokens {
+                    writeln!(out, "{} {}", file.display(), count).ok();
+                }
+            }
+            writeln!(out, "{}", total).ok();
+            for extra in &extra_totals {
+                writeln!(out, "{} {}", extra.total, extra.encoding).ok();
+            }
+        }
+        _ => {
+            if let Some(rendered) = &rendered_tree {
+                writeln!(out, "{}", rendered).ok();
+            }
+            if per_file {
+                for (file, count) in &file_tokens {
+                    writeln!(out, "{}: {} tokens", file.display(), count).ok();
+                }
+            }
+            writeln!(out, "Total: {} tokens", total).ok();
+            for extra in &extra_totals {
+                writeln!(out, "Total ({}): {} tokens", extra.encoding, extra.total).ok();
+            }
+        }
+    }
+    if let Some(check) = &check {
+        push_budget_check(&mut out, check, format);
+    }
+    if format == Some(OutputFormat::Xml) {
+        out = format!("<token_summary>\n{}</token_summary>\n", out);
+    }
+    Ok((out, exceeded))
+}
+
+/// The tokens report as a well-formed XML document.
+fn tokens_strict_xml(
+    total: usize,
+    encoding: Encoding,
+    extra_totals: &[EncodingTotal],
+    files: Option<&BTreeMap<PathBuf, usize>>,
+    check: Option<&BudgetCheck>,
+) -> String {
+    use std::fmt::Write;
+    let mut out = format!(
+        "{}<token_summary total=\"{}\" encoding=\"{}\">\n",
+        XML_DECLARATION, total, encoding
+    );
+    for extra in extra_totals {
+        writeln!(
+            out,
+            "<encoding name=\"{}\" total=\"{}\"/>",
+            extra.encoding, extra.total
+        )
+        .ok();
+    }
+    for (file, count) in files.into_iter().flatten() {
+        let path = xml_attribute(&file.display().to_string());
+        writeln!(out, "<file path=\"{}\" tokens=\"{}\"/>", path, count).ok();
+    }
+    if let Some(check) = check {
+        writeln!(
+            out,
+            "<budget tokens=\"{}\" passed=\"{}\" excess=\"{}\">",
+            check.budget, check.passed, check.excess
+        )
+        .ok();
+        for file in &check.largest {
+            let path = xml_attribute(&file.path.display().to_string());
+            writeln!(out, "<file path=\"{}\" tokens=\"{}\"/>", path, file.tokens).ok();
+        }
+        out.push_str("</budget>\n");
+    }
+    out.push_str("</token_summary>\n");
+    out
+}
+
+/// Files listed when `tokens --check` fails.
+const CHECK_TOP_FILES: usize = 10;
+
+/// Append the budget line, and the largest files if the check failed.
+fn push_budget_check(out: &mut String, check: &BudgetCheck, format: Option<OutputFormat>) {
+    use std::fmt::Write;
+    let verdict = if check.passed {
+        format!("{} to spare", format_number(check.budget - check.total))
+    } else {
+        format!("over by {}", format_number(check.excess))
+    };
+    match format {
+        Some(OutputFormat::Compact) => {
+            let status = if check.passed { "ok" } else { "over" };
+            writeln!(out, "budget {} {} {}", check.budget, status, check.excess).ok();
+        }
+        Some(OutputFormat::Markdown) => {
+            writeln!(
+                out,
+                "**Budget: {} tokens ({})**",
+                format_number(check.budget),
+                verdict
+            )
+            .ok();
+        }
+        _ => {
+            writeln!(
+                out,
+                "Budget: {} tokens ({})",
+                format_number(check.budget),
+                verdict
+            )
+            .ok();
+        }
+    }
+    if check.passed {
+        return;
+    }
+
+    let markdown = format == Some(OutputFormat::Markdown);
+    if format != Some(OutputFormat::Compact) {
+        out.push_str(if markdown {
+            "\nLargest files:\n\n"
+        } else {
+            "Largest files:\n"
+        });
+    }
+    for file in &check.largest {
+        match format {
+            Some(OutputFormat::Compact) => writeln!(out, "{} {}", file.path.display(), file.tokens),
+            _ if markdown => writeln!(
+                out,
+                "- `{}`: {} tokens",
+                file.path.display(),
+                format_number(file.tokens)
+            ),
+            _ => writeln!(
+                out,
+                "  {}: {} tokens",
+                file.path.display(),
+                format_number(file.tokens)
+            ),
+        }
+        .ok();
+    }
+}
+
+/// Serialize `value` as JSON or YAML, ending with a newline.
+fn structured<T: Serialize>(value: &T, format: OutputFormat) -> Result<String, PithError> {
+    let mut text = serialize(value, format)?;
+    if !text.ends_with('\n') {
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+// --- Stats command ---
+
+#[derive(Serialize, Default)]
+struct LanguageStats {
+    files: usize,
+    lines: usize,
+    tokens: usize,
+}
+
+#[derive(Serialize)]
+struct FileStats {
+    path: String,
+    language: String,
+    lines: usize,
+    tokens: usize,
+}
+
+#[derive(Serialize)]
+struct StatsOutput {
+    files: usize,
+    lines: usize,
+    tokens: usize,
+    encoding: String,
+    languages: BTreeMap<String, LanguageStats>,
+    declarations: BTreeMap<String, usize>,
+    largest_files: Vec<FileStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    average_function_lines: Option<f64>,
+}
+
+fn run_stats(
+    path: PathBuf,
+    json: bool,
+    encoding: Encoding,
+    top: usize,
+    walk_opts: &WalkOptions,
+) -> Result<String, PithError> {
+    if !path.exists() {
+        return Err(PithError::PathNotFound(path));
+    }
+
+    let paths: Vec<PathBuf> = if path.is_file() {
+        vec![path.clone()]
+    } else {
+        walk_with_options(&path, walk_opts)
+            .filter_map(|e| e.ok())
+            .filter(|e| e.is_file)
+            .map(|e| e.path)
+            .collect()
+    };
+
+    // Count every declaration, private ones included.
+    let extract_opts = ExtractOptions {
+        visibility: VisibilityThreshold::All,
+        ..Default::default()
+    };
+
+    let mut files: Vec<(FileStats, Vec<Declaration>)> = paths
+        .par_iter()
+        .filter_map(|entry_path| {
+            let (lang, content) = read_source_file(entry_path)?;
+            let codemap = extract_codemap(entry_path, &content, lang, &extract_opts);
+
+            let relative = entry_path
+                .strip_prefix(&path)
+                .ok()
+                .filter(|p| !p.as_os_str().is_empty())
+                .unwrap_or(entry_path);
+            let stats = FileStats {
+                path: relative.display().to_string(),
+                language: lang.to_string(),
+                lines: content.lines().count(),
+                tokens: count_tokens_with_encoding(&content, encoding),
+            };
+            Some((stats, codemap.declarations.into_vec()))
+        })
+        .collect();
+
+    if files.is_empty() {
+        return Err(PithError::NoFilesFound(path));
+    }
+
+    let mut languages: BTreeMap<String, LanguageStats> = BTreeMap::new();
+    let mut declarations: BTreeMap<String, usize> = BTreeMap::new();
+    let mut function_lines = Vec::new();
+
+    for (stats, decls) in &files {
+        let entry = languages.entry(stats.language.clone()).or_default();
+        entry.files += 1;
+        entry.lines += stats.lines;
+        entry.tokens += stats.tokens;
+
+        for decl in decls {
+            count_declaration(decl, false, &mut declarations, &mut function_lines);
+        }
+    }
+
+    let average_function_lines = (!function_lines.is_empty())
+        .then(|| function_lines.iter().sum::<usize>() as f64 / function_lines.len() as f64);
+
+    files.sort_by(|(a, _), (b, _)| b.tokens.cmp(&a.tokens).then_with(|| a.path.cmp(&b.path)));
+    let output = StatsOutput {
+        files: files.len(),
+        lines: languages.values().map(|l| l.lines).sum(),
+        tokens: languages.values().map(|l| l.tokens).sum(),
+        encoding: encoding.to_string(),
+        languages,
+        declarations,
+        largest_files: files
+            .into_iter()
+            .take(top)
+            .map(|(stats, _)| stats)
+            .collect(),
+        average_function_lines,
+    };
+
+    if json {
+        let json = serde_json::to_string_pretty(&output)
+            .map_err(|e| PithError::Io(std::io::Error::other(e.to_string())))?;
+        Ok(format!("{json}\n"))
+    } else {
+        Ok(format_stats(&output))
+    }
+}
+
+/// Read a file the same way `codemap` does: skip anything the filter rejects
+/// (binary, minified, generated, unsupported) or that is not UTF-8.
+fn read_source_file(path: &Path) -> Option<(Language, String)> {
+    let mut file = std::fs::File::open(path).ok()?;
+
+    let mut first_kb = [0u8; 1024];
+    let n = file.read(&mut first_kb).ok()?;
+
+    let lang = match should_process(path, Some(&first_kb[..n])) {
+        FilterResult::Accept(lang) => lang,
+        FilterResult::Reject(_) => return None,
+    };
+
+    let mut content = String::new();
+    content.push_str(std::str::from_utf8(&first_kb[..n]).ok()?);
+    file.read_to_string(&mut content).ok()?;
+    Some((lang, content))
+}
+
+/// Tally a declaration by kind; functions nested in structs and classes count as methods.
+fn count_declaration(
+    decl: &Declaration,
+    nested: bool,
+    counts: &mut BTreeMap<String, usize>,
+    function_lines: &mut Vec<usize>,
+) {
+    let kind = if nested && decl.kind() == "function" {
+        "method"
+    } else {
+        decl.kind()
+    };
+    *counts.entry(kind.to_string()).or_default() += 1;
+
+    match decl {
+        Declaration::Function {
+            metrics: Some(metrics),
+            ..
+        } => function_lines.push(metrics.lines),
+        Declaration::Struct { methods, .. } | Declaration::Enum { methods, .. } => {
+            for method in methods {
+                count_declaration(method, true, counts, function_lines);
+            }
+        }
+        Declaration::Class { members, .. } => {
+            for member in members {
+                count_declaration(member, true, counts, function_lines);
             }
+        }
+        _ => {}
+    }
+}
 
-            let payload = ErrorOutput {
-                error: e.to_string(),
-            };
+fn format_stats(stats: &StatsOutput) -> String {
+    use std::fmt::Write;
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        "Files: {}  Lines: {}  Tokens: {}",
+        format_number(stats.files),
+        format_number(stats.lines),
+        format_number(stats.tokens)
+    )
+    .ok();
+
+    out.push_str("\nLanguages:\n");
+    for (name, lang) in &stats.languages {
+        writeln!(
+            out,
+            "  {:12} {:>6} files {:>10} lines {:>12} tokens",
+            name,
+            format_number(lang.files),
+            format_number(lang.lines),
+            format_number(lang.tokens)
+        )
+        .ok();
+    }
 
-            let json = serde_json::to_string(&payload)
-                .unwrap_or_else(|_| "{\"error\":\"serialization failed\"}".to_string());
-            eprintln!("{json}");
-        } else {
-            eprintln!("error: {}", e);
+    if !stats.declarations.is_empty() {
+        out.push_str("\nDeclarations:\n");
+        for (kind, count) in &stats.declarations {
+            writeln!(out, "  {:12} {:>6}", kind, format_number(*count)).ok();
         }
-        std::process::exit(exit_code(&e));
     }
-}
 
-fn json_flag(cmd: &Commands) -> bool {
-    match cmd {
-        Commands::Tree { json, .. } => *json,
-        Commands::Codemap { json, .. } => *json,
-        Commands::Context { json, .. } => *json,
-        Commands::Tokens { json, .. } => *json,
-        Commands::Languages { json } => *json,
-        Commands::Completions { .. } => false,
+    out.push_str("\nLargest files:\n");
+    for file in &stats.largest_files {
+        writeln!(
+            out,
+            "  {}: {} lines, {} tokens",
+            file.path,
+            format_number(file.lines),
+            format_number(file.tokens)
+        )
+        .ok();
+    }
+
+    if let Some(avg) = stats.average_function_lines {
+        writeln!(out, "\nAverage function length: {:.1} lines", avg).ok();
     }
+
+    out
 }
 
-// --- Languages command ---
+// --- Bench command ---
 
 #[derive(Serialize)]
-struct LanguageInfo {
-    name: String,
-    extensions: Vec<String>,
+struct PhaseTiming {
+    phase: &'static str,
+    files: usize,
+    wall_ms: f64,
+    files_per_sec: f64,
 }
 
-fn run_languages(json: bool) -> Result<(), PithError> {
-    let languages: Vec<LanguageInfo> = Language::all()
-        .iter()
-        .map(|lang| LanguageInfo {
-            name: lang.to_string(),
-            extensions: lang
-                .extensions()
-                .iter()
-                .map(|e| format!(".{}", e))
-                .collect(),
-        })
-        .collect();
-
-    if json {
-        #[derive(Serialize)]
-        struct Output {
-            languages: Vec<LanguageInfo>,
-        }
-        let output = Output { languages };
-        let json = serde_json::to_string_pretty(&output)
-            .map_err(|e| PithError::Io(std::io::Error::other(e.to_string())))?;
-        println!("{json}");
-    } else {
-        println!("Supported languages:");
-        for lang in &languages {
-            println!("  {:12} {}", lang.name, lang.extensions.join(", "));
+impl PhaseTiming {
+    fn new(phase: &'static str, files: usize, wall: Duration) -> Self {
+        let seconds = wall.as_secs_f64();
+        Self {
+            phase,
+            files,
+            wall_ms: seconds * 1000.0,
+            files_per_sec: if seconds > 0.0 {
+                files as f64 / seconds
+            } else {
+                0.0
+            },
         }
     }
+}
 
-    Ok(())
+#[derive(Serialize)]
+struct FileTiming {
+    path: String,
+    language: String,
+    bytes: usize,
+    read_ms: f64,
+    parse_ms: f64,
+    tokenize_ms: f64,
+    total_ms: f64,
 }
 
-// --- Tokens command ---
+#[derive(Serialize)]
+struct BenchOutput {
+    files: usize,
+    bytes: usize,
+    threads: usize,
+    encoding: String,
+    phases: Vec<PhaseTiming>,
+    slowest_files: Vec<FileTiming>,
+}
 
-fn run_tokens(
+/// Run each phase over every file before starting the next, so each phase's
+/// wall time is its own. Reading and parsing use the thread pool like
+/// `codemap` does; the tokenizer is loaded before tokenizing starts.
+fn run_bench(
     path: PathBuf,
     json: bool,
     encoding: Encoding,
-    per_file: bool,
-) -> Result<(), PithError> {
+    top: usize,
+    walk_opts: &WalkOptions,
+) -> Result<String, PithError> {
     if !path.exists() {
         return Err(PithError::PathNotFound(path));
     }
+    let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    let timed = |f: &dyn Fn() -> Option<(Language, String)>| {
+        let start = Instant::now();
+        f().map(|source| (source, start.elapsed()))
+    };
 
-    let mut file_tokens: BTreeMap<PathBuf, usize> = BTreeMap::new();
-
-    if path.is_file() {
-        let content = fs::read_to_string(&path)?;
-        let count = count_tokens_with_encoding(&content, encoding);
-        file_tokens.insert(path.clone(), count);
+    let start = Instant::now();
+    let paths: Vec<PathBuf> = if path.is_file() {
+        vec![path.clone()]
     } else {
-        // Collect file paths for parallel processing
-        let paths: Vec<PathBuf> = walk(&path)
+        walk_with_options(&path, walk_opts)
             .filter_map(|e| e.ok())
             .filter(|e| e.is_file)
             .map(|e| e.path)
-            .collect();
-
-        file_tokens = paths
-            .par_iter()
-            .filter_map(|entry_path| {
-                use std::io::Read;
-
-                let mut file = std::fs::File::open(entry_path).ok()?;
-
-                let mut first_kb = [0u8; 1024];
-                let n = file.read(&mut first_kb).ok()?;
-
-                match should_process(entry_path, Some(&first_kb[..n])) {
-                    FilterResult::Accept(_) => {}
-                    FilterResult::Reject(_) => return None,
-                }
-
-                let mut content = String::new();
-                content.push_str(std::str::from_utf8(&first_kb[..n]).ok()?);
-                file.read_to_string(&mut content).ok()?;
-
-                let count = count_tokens_with_encoding(&content, encoding);
-
-                let relative = entry_path
-                    .strip_prefix(&path)
-                    .unwrap_or(entry_path)
-                    .to_path_buf();
-                Some((relative, count))
-            })
-            .collect();
+            .collect()
+    };
+    let walk = PhaseTiming::new("walk", paths.len(), start.elapsed());
+
+    let start = Instant::now();
+    let sources: Vec<(&PathBuf, Language, String, Duration)> = paths
+        .par_iter()
+        .filter_map(|entry_path| {
+            let ((lang, content), elapsed) = timed(&|| read_source_file(entry_path))?;
+            Some((entry_path, lang, content, elapsed))
+        })
+        .collect();
+    let read = PhaseTiming::new("read", sources.len(), start.elapsed());
+    if sources.is_empty() {
+        return Err(PithError::NoFilesFound(path));
     }
 
-    let total: usize = file_tokens.values().sum();
-
-    if json {
-        #[derive(Serialize)]
-        struct Output {
-            total: usize,
-            encoding: String,
-            #[serde(skip_serializing_if = "Option::is_none")]
-            files: Option<BTreeMap<String, usize>>,
-        }
+    let extract_opts = ExtractOptions {
+        visibility: VisibilityThreshold::All,
+        ..Default::default()
+    };
+    let start = Instant::now();
+    let parse_times: Vec<Duration> = sources
+        .par_iter()
+        .map(|(entry_path, lang, content, _)| {
+            let start = Instant::now();
+            extract_codemap(entry_path, content, *lang, &extract_opts);
+            start.elapsed()
+        })
+        .collect();
+    let parse = PhaseTiming::new("parse", sources.len(), start.elapsed());
+
+    preload(encoding).join().ok();
+    let start = Instant::now();
+    let tokenize_times: Vec<Duration> = sources
+        .par_iter()
+        .map(|(_, _, content, _)| {
+            let start = Instant::now();
+            count_tokens_with_encoding(content, encoding);
+            start.elapsed()
+        })
+        .collect();
+    let tokenize = PhaseTiming::new("tokenize", sources.len(), start.elapsed());
 
-        let files = if per_file {
-            Some(
-                file_tokens
-                    .into_iter()
-                    .map(|(k, v)| (k.display().to_string(), v))
-                    .collect(),
-            )
-        } else {
-            None
-        };
+    let mut files: Vec<FileTiming> = sources
+        .iter()
+        .zip(parse_times.iter().zip(&tokenize_times))
+        .map(|((entry_path, lang, content, read), (parse, tokenize))| {
+            let relative = entry_path
+                .strip_prefix(&path)
+                .ok()
+                .filter(|p| !p.as_os_str().is_empty())
+                .unwrap_or(entry_path);
+            FileTiming {
+                path: relative.display().to_string(),
+                language: lang.to_string(),
+                bytes: content.len(),
+                read_ms: millis(*read),
+                parse_ms: millis(*parse),
+                tokenize_ms: millis(*tokenize),
+                total_ms: millis(*read + *parse + *tokenize),
+            }
+        })
+        .collect();
+    files.sort_by(|a, b| b.total_ms.total_cmp(&a.total_ms));
+    files.truncate(top);
+
+    let output = BenchOutput {
+        files: sources.len(),
+        bytes: sources.iter().map(|(_, _, content, _)| content.len()).sum(),
+        threads: rayon::current_num_threads(),
+        encoding: encoding.to_string(),
+        phases: vec![walk, read, parse, tokenize],
+        slowest_files: files,
+    };
 
-        let output = Output {
-            total,
-            encoding: encoding.to_string(),
-            files,
-        };
+    if json {
         let json = serde_json::to_string_pretty(&output)
             .map_err(|e| PithError::Io(std::io::Error::other(e.to_string())))?;
-        println!("{json}");
+        Ok(format!("{json}\n"))
     } else {
-        use std::io::{BufWriter, Write};
-        let stdout = std::io::stdout();
-        let mut out = BufWriter::new(stdout.lock());
-        if per_file {
-            for (file, count) in &file_tokens {
-                writeln!(out, "{}: {} tokens", file.display(), count).ok();
-            }
-        }
-        writeln!(out, "Total: {} tokens", total).ok();
+        Ok(format_bench(&output))
+    }
+}
+
+fn format_bench(bench: &BenchOutput) -> String {
+    use std::fmt::Write;
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        "Files: {}  Bytes: {}  Threads: {}",
+        format_number(bench.files),
+        format_number(bench.bytes),
+        bench.threads
+    )
+    .ok();
+
+    out.push_str("\nPhases:\n");
+    for phase in &bench.phases {
+        writeln!(
+            out,
+            "  {:10} {:>10.1} ms {:>12} files/sec",
+            phase.phase,
+            phase.wall_ms,
+            // Rates are whole files per second; fractions are noise.
+            format_number(phase.files_per_sec as usize)
+        )
+        .ok();
+    }
+    let total: f64 = bench.phases.iter().map(|p| p.wall_ms).sum();
+    writeln!(out, "  {:10} {:>10.1} ms", "total", total).ok();
+
+    out.push_str("\nSlowest files:\n");
+    for file in &bench.slowest_files {
+        writeln!(
+            out,
+            "  {}: {:.1} ms (read {:.1}, parse {:.1}, tokenize {:.1})",
+            file.path, file.total_ms, file.read_ms, file.parse_ms, file.tokenize_ms
+        )
+        .ok();
     }
 
-    Ok(())
+    out
 }
 
 // --- Tree command ---
 
 fn run_tree(
     path: PathBuf,
-    json: bool,
-    no_metadata: bool,
-    include_hidden: bool,
-    max_depth: Option<usize>,
-) -> Result<(), PithError> {
+    format: Option<OutputFormat>,
+    walk_opts: WalkOptions,
+    render_opts: RenderOptions<'static>,
+    token_encoding: Option<Encoding>,
+) -> Result<String, PithError> {
     if !path.exists() {
         return Err(PithError::PathNotFound(path));
     }
 
-    let walk_opts = WalkOptions {
-        max_depth,
-        include_hidden,
-        ..Default::default()
-    };
-
-    let tree = build_tree_with_options(&path, &walk_opts)
+    let mut tree = build_tree_with_options(&path, &walk_opts)
         .map_err(|e| PithError::Io(std::io::Error::other(e.to_string())))?;
+    // --no-metadata only hides metadata in the rendered tree; structured
+    // output always carries sizes and line counts.
+    let structured_format = format.filter(|f| f.is_structured());
+    if structured_format.is_some() && !(walk_opts.file_sizes && walk_opts.count_lines) {
+        tree.load_metadata();
+    }
 
-    if json {
-        // Use serde to serialize the tree
-        let json = serde_json::to_string_pretty(&tree_to_json(&tree))
-            .map_err(|e| PithError::Io(std::io::Error::other(e.to_string())))?;
-        println!("{json}");
-    } else {
-        let render_opts = RenderOptions {
-            show_size: !no_metadata,
-            show_lines: !no_metadata,
-            show_language: !no_metadata,
-            ..Default::default()
-        };
-        print!("{}", render_tree(&tree, &render_opts));
+    // Token counts for source files, as counted by the tokens command
+    let token_counts: HashMap<PathBuf, usize> = match token_encoding {
+        Some(encoding) => {
+            let mut paths = Vec::new();
+            collect_file_paths(&tree, &mut paths);
+            paths
+                .par_iter()
+                .filter_map(|p| {
+                    let (_, content) = read_source_file(p)?;
+                    Some(((*p).clone(), count_tokens_with_encoding(&content, encoding)))
+                })
+                .collect()
+        }
+        None => HashMap::new(),
+    };
+    let tokens: HashMap<&PathBuf, usize> = token_counts.iter().map(|(p, n)| (p, *n)).collect();
+
+    if let Some(format) = structured_format {
+        return structured(&tree_to_json(&tree, &tokens), format);
     }
 
-    Ok(())
+    let render_opts = RenderOptions {
+        tokens,
+        ..render_opts
+    };
+    Ok(match format {
+        Some(OutputFormat::Xml) => {
+            format!(
+                "<file_map>\n{}</file_map>\n",
+                render_tree(&tree, &render_opts)
+            )
+        }
+        Some(OutputFormat::Markdown) => {
+            format!("```\n{}```\n", render_tree(&tree, &render_opts))
+        }
+        Some(OutputFormat::StrictXml) => format!(
+            "{}<file_map>\n{}\n</file_map>\n",
+            XML_DECLARATION,
+            xml_cdata(&render_tree(&tree, &render_opts))
+        ),
+        Some(OutputFormat::Compact) => render_tree(
+            &tree,
+            &RenderOptions {
+                show_size: false,
+                show_lines: false,
+                show_language: false,
+                show_totals: false,
+                ..render_opts
+            },
+        ),
+        _ => render_tree(&tree, &render_opts),
+    })
+}
+
+fn collect_file_paths<'a>(node: &'a pith::tree::FileNode, paths: &mut Vec<&'a PathBuf>) {
+    if node.is_file() {
+        paths.push(&node.path);
+    }
+    for child in node.children() {
+        collect_file_paths(child, paths);
+    }
 }
 
 #[derive(Serialize)]
@@ -462,23 +2547,58 @@ struct JsonTreeNode {
     #[serde(skip_serializing_if = "Option::is_none")]
     extension: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    file_count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     size: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     lines: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tokens: Option<usize>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     children: Vec<JsonTreeNode>,
 }
 
-fn tree_to_json(node: &pith::tree::FileNode) -> JsonTreeNode {
+fn tree_to_json(node: &pith::tree::FileNode, tokens: &HashMap<&PathBuf, usize>) -> JsonTreeNode {
     use pith::tree::NodeKind;
 
-    let (kind, extension, size, lines) = match &node.kind {
-        NodeKind::Directory => ("directory".to_string(), None, None, None),
+    let (kind, extension, file_count, size, lines) = match &node.kind {
+        NodeKind::Directory => {
+            let totals = node.totals();
+            (
+                "directory".to_string(),
+                None,
+                Some(totals.files),
+                Some(totals.size),
+                Some(totals.lines),
+            )
+        }
         NodeKind::File {
             extension,
             size,
             lines,
-        } => ("file".to_string(), extension.clone(), Some(*size), *lines),
+        } => (
+            "file".to_string(),
+            extension.clone(),
+            None,
+            Some(*size),
+            *lines,
+        ),
+    };
+
+    let children: Vec<JsonTreeNode> = node
+        .children()
+        .iter()
+        .map(|c| tree_to_json(c, tokens))
+        .collect();
+
+    // Directories sum the counts of their children, if any have one
+    let tokens = if node.is_directory() {
+        children
+            .iter()
+            .filter_map(|c| c.tokens)
+            .reduce(|a, b| a + b)
+    } else {
+        tokens.get(&node.path).copied()
     };
 
     JsonTreeNode {
@@ -486,9 +2606,11 @@ fn tree_to_json(node: &pith::tree::FileNode) -> JsonTreeNode {
         path: node.path.display().to_string(),
         kind,
         extension,
+        file_count,
         size,
         lines,
-        children: node.children().iter().map(tree_to_json).collect(),
+        tokens,
+        children,
     }
 }
 
@@ -496,74 +2618,63 @@ fn tree_to_json(node: &pith::tree::FileNode) -> JsonTreeNode {
 
 fn run_codemap(
     path: PathBuf,
-    json: bool,
-    include_docs: bool,
-    include_private: bool,
+    extract_opts: ExtractOptions,
+    output_opts: OutputOptions,
     encoding: Encoding,
     lang_filter: Vec<LanguageArg>,
-) -> Result<(), PithError> {
+    filter: &SourceFilter,
+) -> Result<String, PithError> {
     if !path.exists() {
         return Err(PithError::PathNotFound(path));
     }
 
     let lang_set: Vec<Language> = lang_filter.into_iter().map(|l| l.into()).collect();
 
-    let extract_opts = ExtractOptions {
-        include_docs,
-        include_private,
-    };
-
-    let mut codemaps = Vec::new();
-
-    for entry in walk(&path).flatten() {
-        if !entry.is_file {
-            continue;
-        }
-
-        let entry_path = entry.path.as_path();
-
-        let lang = match detect_language(entry_path) {
-            Some(l) => l,
-            None => continue,
-        };
+    let files = walk_files(&path, &filter.walk_options);
+    let done = AtomicUsize::new(0);
 
-        // Apply language filter if specified
-        if !lang_set.is_empty() && !lang_set.contains(&lang) {
-            continue;
-        }
+    let results: Vec<Result<Codemap, SkippedFile>> = files
+        .par_iter()
+        .map(|entry_path| {
+            let entry_path = entry_path.as_path();
 
-        // Check heuristics on first 1KB
-        let mut file = match std::fs::File::open(entry_path) {
-            Ok(f) => f,
-            Err(_) => continue,
-        };
+            if !filter.is_candidate(entry_path) {
+                return None;
+            }
 
-        let mut first_kb = [0u8; 1024];
-        let n = {
-            use std::io::Read;
-            match file.read(&mut first_kb) {
-                Ok(n) => n,
-                Err(_) => continue,
+            // Apply language filter if specified
+            let wanted = |lang| lang_set.is_empty() || lang_set.contains(&lang);
+            let (lang, content) = match filter.read(entry_path, wanted) {
+                Ok(Some(source)) => source,
+                Ok(None) => return None,
+                Err(file) => return Some(Err(file)),
+            };
+            if extract_opts.skips_file(entry_path, lang) {
+                return None;
             }
-        };
 
-        match should_process(entry_path, Some(&first_kb[..n])) {
-            FilterResult::Accept(_) => {}
-            FilterResult::Reject(_) => continue,
-        }
+            Some(Ok(extract_codemap(
+                entry_path,
+                &content,
+                lang,
+                &extract_opts,
+            )))
+        })
+        .inspect(|_| {
+            let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+            progress().update(Phase::Extract, done, Some(files.len()));
+        })
+        .flatten()
+        .collect();
+    progress().finish(Phase::Extract, files.len(), Some(files.len()));
 
-        let mut content = String::new();
-        let prefix = match std::str::from_utf8(&first_kb[..n]) {
-            Ok(s) => s,
-            Err(_) => continue,
-        };
-        content.push_str(prefix);
-        if file.read_to_string(&mut content).is_err() {
-            continue;
+    let mut codemaps = Vec::new();
+    let mut skipped = Vec::new();
+    for result in results {
+        match result {
+            Ok(codemap) => codemaps.push(codemap),
+            Err(file) => skipped.push(file),
         }
-
-        let codemap = extract_codemap(entry_path, &content, lang, &extract_opts);
-        codemaps.push(codemap);
     }
 
     if codemaps.is_empty() {
@@ -571,35 +2682,72 @@ fn run_codemap(
     }
 
     let output_opts = OutputOptions {
-        format: if json {
-            OutputFormat::Json
-        } else {
-            OutputFormat::Xml
-        },
-        include_tree: false,
-        include_codemaps: true,
-        include_selected_files: false,
-        include_summary: true,
-        public_only: !include_private,
+        skipped,
+        root: path,
+        ..output_opts
     };
 
-    let output = format_output(None, &codemaps, &[], &output_opts, encoding);
-    print!("{}", output);
-
-    Ok(())
+    render_within_budget(None, codemaps, Vec::new(), output_opts, encoding)
 }
 
 // --- Context command ---
 
+/// Files whose full content `context` includes.
+struct Selection {
+    patterns: Vec<Pattern>,
+    languages: Vec<Language>,
+    entry_points: bool,
+    /// Files started by `package.json` files, see `add_package_entry_points`.
+    package_entry_points: HashSet<PathBuf>,
+}
+
+impl Selection {
+    /// Invalid glob patterns are ignored.
+    fn new(patterns: &[String], languages: Vec<LanguageArg>, entry_points: bool) -> Self {
+        Self {
+            patterns: patterns
+                .iter()
+                .filter_map(|p| Pattern::new(p).ok())
+                .collect(),
+            languages: languages.into_iter().map(Into::into).collect(),
+            entry_points,
+            package_entry_points: HashSet::new(),
+        }
+    }
+
+    /// With entry points selected, read the `package.json` files among
+    /// `files` for the files they start.
+    fn add_package_entry_points(&mut self, files: &[PathBuf]) {
+        if !self.entry_points {
+            return;
+        }
+        for file in files {
+            if file.file_name().is_some_and(|name| name == "package.json") {
+                if let Ok(content) = fs::read_to_string(file) {
+                    self.package_entry_points
+                        .extend(package_entry_points(file, &content));
+                }
+            }
+        }
+    }
+
+    /// Whether a file is selected before looking at its codemap.
+    fn matches(&self, path: &Path, relative: &str, lang: Language) -> bool {
+        self.languages.contains(&lang)
+            || self.package_entry_points.contains(path)
+            || self.patterns.iter().any(|p| p.matches(relative))
+    }
+}
+
 fn run_context(
     path: PathBuf,
-    json: bool,
-    include_docs: bool,
-    include_private: bool,
+    extract_opts: ExtractOptions,
+    output_opts: OutputOptions,
     encoding: Encoding,
-    select_patterns: Vec<String>,
+    mut selection: Selection,
     lang_filter: Vec<LanguageArg>,
-) -> Result<(), PithError> {
+    filter: &SourceFilter,
+) -> Result<String, PithError> {
     if !path.exists() {
         return Err(PithError::PathNotFound(path));
     }
@@ -607,89 +2755,79 @@ fn run_context(
     let lang_set: Vec<Language> = lang_filter.into_iter().map(|l| l.into()).collect();
 
     // Build the file tree
-    let tree = build_tree_with_options(&path, &WalkOptions::default())
+    let tree = build_tree_with_options(&path, &filter.walk_options)
         .map_err(|e| PithError::Io(std::io::Error::other(e.to_string())))?;
 
-    let extract_opts = ExtractOptions {
-        include_docs,
-        include_private,
-    };
-
-    // Compile glob patterns
-    let patterns: Vec<Pattern> = select_patterns
-        .iter()
-        .filter_map(|p| Pattern::new(p).ok())
-        .collect();
-
-    let mut codemaps = Vec::new();
-    let mut selected_files = Vec::new();
-
-    for entry in walk(&path).flatten() {
-        if !entry.is_file {
-            continue;
-        }
-
-        let entry_path = entry.path.as_path();
+    let files = walk_files(&path, &filter.walk_options);
+    selection.add_package_entry_points(&files);
+    let done = AtomicUsize::new(0);
 
-        let relative = entry_path.strip_prefix(&path).unwrap_or(entry_path);
-        let relative_str = relative.to_string_lossy();
+    type Extracted = (Option<Codemap>, Option<SelectedFile>);
+    let results: Vec<Result<Extracted, SkippedFile>> = files
+        .par_iter()
+        .map(|entry_path| {
+            let entry_path = entry_path.as_path();
 
-        // Check if file matches any select pattern
-        let is_selected = patterns.iter().any(|p| p.matches(&relative_str));
-
-        // Check language
-        let lang = detect_language(entry_path);
-
-        // Check heuristics on first 1KB (binary/minified/generated)
-        let mut file = match std::fs::File::open(entry_path) {
-            Ok(f) => f,
-            Err(_) => continue,
-        };
-
-        let mut first_kb = [0u8; 1024];
-        let n = {
-            use std::io::Read;
-            match file.read(&mut first_kb) {
-                Ok(n) => n,
-                Err(_) => continue,
+            if filter.is_linguist_skipped(entry_path) {
+                return None;
             }
-        };
-
-        match should_process(entry_path, Some(&first_kb[..n])) {
-            FilterResult::Accept(_) => {}
-            FilterResult::Reject(_) => continue,
-        }
 
-        // Read full content (reuse already-read prefix)
-        let mut content = String::new();
-        let prefix = match std::str::from_utf8(&first_kb[..n]) {
-            Ok(s) => s,
-            Err(_) => continue,
-        };
-        content.push_str(prefix);
-        if file.read_to_string(&mut content).is_err() {
-            continue;
-        }
+            let relative = entry_path.strip_prefix(&path).unwrap_or(entry_path);
+            let relative_str = relative.to_string_lossy();
+
+            // Check if file is selected by pattern or language; generated and
+            // vendored files never contribute full content in codemap-only mode
+            let codemap_only = filter.is_codemap_only(entry_path);
+            let is_selected =
+                |lang| !codemap_only && selection.matches(entry_path, &relative_str, lang);
+
+            let in_lang_set = |lang| lang_set.is_empty() || lang_set.contains(&lang);
+            let (lang, content) =
+                match filter.read(entry_path, |lang| is_selected(lang) || in_lang_set(lang)) {
+                    Ok(Some(source)) => source,
+                    Ok(None) => return None,
+                    Err(file) => return Some(Err(file)),
+                };
+
+            // Extract codemap, applying the language filter if specified
+            let codemap = (in_lang_set(lang) && !extract_opts.skips_file(entry_path, lang))
+                .then(|| extract_codemap(entry_path, &content, lang, &extract_opts));
+            let entry_point = selection.entry_points
+                && !codemap_only
+                && codemap.as_ref().is_some_and(is_entry_point);
+
+            // Add to selected files if it matches patterns or is an entry point
+            let selected = (is_selected(lang) || entry_point).then(|| {
+                let lines = content.lines().count();
+                let tokens = count_tokens_with_encoding(&content, encoding);
+                SelectedFile {
+                    path: entry_path.to_path_buf(),
+                    language: Some(lang),
+                    content,
+                    lines,
+                    tokens,
+                }
+            });
+            Some(Ok((codemap, selected)))
+        })
+        .inspect(|_| {
+            let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+            progress().update(Phase::Extract, done, Some(files.len()));
+        })
+        .flatten()
+        .collect();
+    progress().finish(Phase::Extract, files.len(), Some(files.len()));
 
-        // Extract codemap if it's a supported language
-        if let Some(lang) = lang {
-            // Apply language filter if specified
-            if lang_set.is_empty() || lang_set.contains(&lang) {
-                let codemap = extract_codemap(entry_path, &content, lang, &extract_opts);
-                codemaps.push(codemap);
+    let mut codemaps = Vec::new();
+    let mut selected_files = Vec::new();
+    let mut skipped = Vec::new();
+    for result in results {
+        match result {
+            Ok((codemap, selected)) => {
+                codemaps.extend(codemap);
+                selected_files.extend(selected);
             }
-        }
-
-        // Add to selected files if it matches patterns
-        if is_selected {
-            let lines = content.lines().count();
-            let tokens = count_tokens_with_encoding(&content, encoding);
-            selected_files.push(SelectedFile {
-                path: entry_path.to_path_buf(),
-                content,
-                lines,
-                tokens,
-            });
+            Err(file) => skipped.push(file),
         }
     }
 
@@ -698,26 +2836,50 @@ fn run_context(
     }
 
     let output_opts = OutputOptions {
-        format: if json {
-            OutputFormat::Json
-        } else {
-            OutputFormat::Xml
-        },
-        include_tree: true,
-        include_codemaps: true,
         include_selected_files: !selected_files.is_empty(),
-        include_summary: true,
-        public_only: !include_private,
+        skipped,
+        root: path,
+        ..output_opts
     };
 
-    let output = format_output(
-        Some(&tree),
-        &codemaps,
-        &selected_files,
-        &output_opts,
-        encoding,
-    );
-    print!("{}", output);
+    render_within_budget(Some(&tree), codemaps, selected_files, output_opts, encoding)
+}
+
+/// Files under `root`, in walk order, reporting the walk phase.
+fn walk_files(root: &Path, options: &WalkOptions) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for entry in walk_with_options(root, options).flatten() {
+        if entry.is_file {
+            files.push(entry.path);
+            progress().update(Phase::Walk, files.len(), None);
+        }
+    }
+    progress().finish(Phase::Walk, files.len(), Some(files.len()));
+    files
+}
 
-    Ok(())
+/// Render output, pruning to `--max-tokens` if given and noting on stderr
+/// what was cut.
+fn render_within_budget(
+    tree: Option<&FileNode>,
+    codemaps: Vec<Codemap>,
+    selected_files: Vec<SelectedFile>,
+    output_opts: OutputOptions,
+    encoding: Encoding,
+) -> Result<String, PithError> {
+    let budget = output_opts.max_tokens;
+    let files = codemaps.len() + selected_files.len();
+    progress().update(Phase::Render, 0, Some(files));
+    let fitted = fit_output(tree, codemaps, selected_files, output_opts, encoding)?;
+    progress().finish(Phase::Render, files, Some(files));
+    if let (Some(budget), false) = (budget, fitted.steps.is_empty()) {
+        let steps: Vec<String> = fitted.steps.iter().map(ToString::to_string).collect();
+        eprintln!(
+            "pruned to {} tokens (budget {}): {}",
+            format_number(fitted.tokens),
+            format_number(budget),
+            steps.join(", ")
+        );
+    }
+    Ok(fitted.output)
 }
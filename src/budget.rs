@@ -0,0 +1,496 @@
+//! Fitting output into a token budget.
+//!
+//! [`fit_output`] renders with [`format_output`] and, while the result is
+//! over `OutputOptions::max_tokens`, applies the cheapest remaining pruning
+//! step and renders again. Steps go from least to most information lost:
+//!
+//! 1. Prune the file tree to selected files and files with codemaps.
+//! 2. Drop selected files' full content, largest first.
+//! 3. Show public declarations only.
+//! 4. Drop the file tree.
+//! 5. With `OutputOptions::trim_declarations`, drop declarations, least
+//!    important first (see [`crate::importance`]).
+//! 6. Drop codemaps, largest first.
+//!
+//! [`BudgetCheck`] compares a plain token count against a budget, for
+//! `pith tokens --check` in CI.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::codemap::{Codemap, VisibilityThreshold};
+use crate::importance::Importance;
+use crate::output::{
+    format_codemap_block, format_output, is_visible, DeclarationOrder, OutputOptions, SelectedFile,
+};
+use crate::tokens::{Encoding, TokenCounter};
+use crate::tree::FileNode;
+
+/// Output is over its token budget: after every pruning step for
+/// [`fit_output`], or as counted for [`BudgetCheck`].
+#[derive(Debug, Error)]
+#[error("output needs {needed} tokens, over the budget of {budget}")]
+pub struct BudgetExceeded {
+    /// Tokens in the (most-pruned) output.
+    pub needed: usize,
+    /// The requested budget.
+    pub budget: usize,
+}
+
+/// One pruning step applied by [`fit_output`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PruneStep {
+    /// Limited the tree to selected files and files with codemaps.
+    PruneTree,
+    /// Dropped a selected file's full content.
+    DropSelected(PathBuf),
+    /// Hid non-public declarations.
+    PublicOnly,
+    /// Left out the file tree.
+    DropTree,
+    /// Left out this many of the least important declarations.
+    DropDeclarations(usize),
+    /// Left out a file's codemap.
+    DropCodemap(PathBuf),
+}
+
+impl fmt::Display for PruneStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PruneStep::PruneTree => write!(f, "pruned tree"),
+            PruneStep::DropSelected(path) => write!(f, "dropped content of {}", path.display()),
+            PruneStep::PublicOnly => write!(f, "public items only"),
+            PruneStep::DropTree => write!(f, "dropped tree"),
+            PruneStep::DropDeclarations(count) => {
+                write!(f, "dropped {} low-importance declarations", count)
+            }
+            PruneStep::DropCodemap(path) => write!(f, "dropped codemap of {}", path.display()),
+        }
+    }
+}
+
+/// Rendered output that fits the budget.
+#[derive(Debug, Clone)]
+pub struct FittedOutput {
+    /// The rendered output.
+    pub output: String,
+    /// Tokens in `output`.
+    pub tokens: usize,
+    /// Pruning steps applied, in order. Empty if the output fit as is.
+    pub steps: Vec<PruneStep>,
+}
+
+/// Render output, pruning until it fits `options.max_tokens`. Without a
+/// budget this is [`format_output`].
+///
+/// # Examples
+///
+/// ```
+/// use pith::budget::fit_output;
+/// use pith::output::OutputOptions;
+/// use pith::tokens::Encoding;
+///
+/// let options = OutputOptions {
+///     max_tokens: Some(1_000),
+///     ..OutputOptions::codemap_only()
+/// };
+/// let fitted = fit_output(None, Vec::new(), Vec::new(), options, Encoding::default()).unwrap();
+/// assert!(fitted.tokens <= 1_000);
+/// ```
+pub fn fit_output(
+    tree: Option<&FileNode>,
+    mut codemaps: Vec<Codemap>,
+    mut selected_files: Vec<SelectedFile>,
+    mut options: OutputOptions,
+    encoding: Encoding,
+) -> Result<FittedOutput, BudgetExceeded> {
+    let counter = TokenCounter::new(encoding);
+    let mut steps = Vec::new();
+    // Score once, so trimming doesn't change what the rest is scored against.
+    let ranks = options.trim_declarations || options.sort == DeclarationOrder::Importance;
+    if ranks && options.importance.is_none() {
+        options.importance = Some(Arc::new(Importance::from_codemaps(&codemaps)));
+    }
+
+    loop {
+        let output = format_output(tree, &codemaps, &selected_files, &options, encoding);
+        let tokens = counter.count(&output);
+        let Some(budget) = options.max_tokens.filter(|&budget| tokens > budget) else {
+            return Ok(FittedOutput {
+                output,
+                tokens,
+                steps,
+            });
+        };
+        let excess = tokens - budget;
+        tracing::debug!("output is {} tokens, {} over the budget", tokens, excess);
+        let shows_tree = options.include_tree && tree.is_some();
+
+        if shows_tree && !options.prune_tree {
+            options.prune_tree = true;
+            steps.push(PruneStep::PruneTree);
+        } else if options.include_selected_files && !selected_files.is_empty() {
+            let sizes: Vec<usize> = selected_files.iter().map(|file| file.tokens).collect();
+            for index in largest_covering(&sizes, excess) {
+                let file = selected_files.remove(index);
+                steps.push(PruneStep::DropSelected(file.path));
+            }
+        } else if options.include_codemaps
+            && options.visibility != VisibilityThreshold::Public
+            && !codemaps.is_empty()
+        {
+            options.visibility = VisibilityThreshold::Public;
+            steps.push(PruneStep::PublicOnly);
+        } else if shows_tree {
+            options.include_tree = false;
+            steps.push(PruneStep::DropTree);
+        } else if let Some(count) = trim_declarations(&mut codemaps, &options, excess, &counter) {
+            steps.push(PruneStep::DropDeclarations(count));
+        } else if options.include_codemaps && !codemaps.is_empty() {
+            let sizes: Vec<usize> = codemaps
+                .iter()
+                .map(|codemap| counter.count(&format_codemap_block(codemap, &options)))
+                .collect();
+            for index in largest_covering(&sizes, excess) {
+                let codemap = codemaps.remove(index);
+                steps.push(PruneStep::DropCodemap(codemap.path));
+            }
+        } else {
+            return Err(BudgetExceeded {
+                needed: tokens,
+                budget,
+            });
+        }
+    }
+}
+
+/// Remove the least important visible declarations, about enough to cover
+/// `excess` tokens at their average size. Returns how many were removed, or
+/// `None` if trimming is off or nothing is left to remove.
+fn trim_declarations(
+    codemaps: &mut [Codemap],
+    options: &OutputOptions,
+    excess: usize,
+    counter: &TokenCounter,
+) -> Option<usize> {
+    if !options.trim_declarations || !options.include_codemaps {
+        return None;
+    }
+    let importance = options.importance.as_deref()?;
+
+    // (score, codemap, declaration) of every visible declaration.
+    let mut candidates: Vec<(usize, usize, usize)> = Vec::new();
+    for (file, codemap) in codemaps.iter().enumerate() {
+        for (index, decl) in codemap.declarations.iter().enumerate() {
+            if is_visible(decl, options) {
+                let score = importance.score(&codemap.path, decl).total();
+                candidates.push((score, file, index));
+            }
+        }
+    }
+    if candidates.is_empty() {
+        return None;
+    }
+
+    let tokens: usize = codemaps
+        .iter()
+        .map(|codemap| counter.count(&format_codemap_block(codemap, options)))
+        .sum();
+    let average = (tokens / candidates.len()).max(1);
+    let count = excess.div_ceil(average).clamp(1, candidates.len());
+
+    // Lowest scores first; among equals, later declarations go first.
+    candidates.sort_by_key(|&(score, file, index)| {
+        (score, std::cmp::Reverse(file), std::cmp::Reverse(index))
+    });
+    let mut doomed: Vec<(usize, usize)> = candidates[..count]
+        .iter()
+        .map(|&(_, file, index)| (file, index))
+        .collect();
+    // Remove from the back so earlier indices stay valid.
+    doomed.sort_unstable_by(|a, b| b.cmp(a));
+    for (file, index) in doomed {
+        codemaps[file].declarations.remove(index);
+    }
+    Some(count)
+}
+
+/// A token total checked against a budget, with the files that weigh most.
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetCheck {
+    pub budget: usize,
+    pub total: usize,
+    pub passed: bool,
+    /// Tokens over the budget; 0 if the check passed.
+    pub excess: usize,
+    /// The largest files, most tokens first.
+    pub largest: Vec<FileTokens>,
+}
+
+/// A file and its token count.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileTokens {
+    pub path: PathBuf,
+    pub tokens: usize,
+}
+
+impl BudgetCheck {
+    /// Check the summed token counts of `files` against `budget`, keeping the
+    /// `top` largest files for the report.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use std::path::PathBuf;
+    /// use pith::budget::BudgetCheck;
+    ///
+    /// let files = BTreeMap::from([(PathBuf::from("src/lib.rs"), 900)]);
+    /// let check = BudgetCheck::new(&files, 500, 10);
+    /// assert!(!check.passed);
+    /// assert_eq!(check.excess, 400);
+    /// ```
+    pub fn new(files: &BTreeMap<PathBuf, usize>, budget: usize, top: usize) -> Self {
+        let total: usize = files.values().sum();
+        let mut largest: Vec<FileTokens> = files
+            .iter()
+            .map(|(path, &tokens)| FileTokens {
+                path: path.clone(),
+                tokens,
+            })
+            .collect();
+        // Stable sort keeps ties in path order.
+        largest.sort_by_key(|file| std::cmp::Reverse(file.tokens));
+        largest.truncate(top);
+
+        Self {
+            budget,
+            total,
+            passed: total <= budget,
+            excess: total.saturating_sub(budget),
+            largest,
+        }
+    }
+
+    /// The failure to report, if the total is over the budget.
+    pub fn error(&self) -> Option<BudgetExceeded> {
+        (!self.passed).then_some(BudgetExceeded {
+            needed: self.total,
+            budget: self.budget,
+        })
+    }
+}
+
+/// Indices of the largest `sizes` whose sum reaches `target` (at least one),
+/// in descending index order so they can be removed one by one.
+fn largest_covering(sizes: &[usize], target: usize) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(sizes[i]));
+
+    let mut covered = 0;
+    let mut picked = Vec::new();
+    for i in order {
+        picked.push(i);
+        covered += sizes[i];
+        if covered >= target {
+            break;
+        }
+    }
+    picked.sort_unstable_by(|a, b| b.cmp(a));
+    picked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codemap::{Declaration, Location, Visibility};
+    use crate::filter::Language;
+
+    fn codemap(name: &str, functions: usize) -> Codemap {
+        let mut codemap = Codemap::empty(PathBuf::from(format!("src/{name}.rs")), Language::Rust);
+        for i in 0..functions {
+            codemap.declarations.push(Declaration::Function {
+                name: format!("{name}_{i}"),
+                scope: None,
+                signature: format!("pub fn {name}_{i}(input: &str) -> Result<String, Error>"),
+                visibility: Visibility::Public,
+                location: Location::single_line(i + 1),
+                is_async: false,
+                overloads: Vec::new(),
+                component: None,
+                metrics: None,
+                attributes: Vec::new(),
+                doc: None,
+                snippet: None,
+            });
+        }
+        codemap
+    }
+
+    fn selected(name: &str, lines: usize) -> SelectedFile {
+        let content = "let value = compute(input);\n".repeat(lines);
+        SelectedFile {
+            path: PathBuf::from(format!("src/{name}.rs")),
+            language: Some(Language::Rust),
+            tokens: TokenCounter::new(Encoding::default()).count(&content),
+            content,
+            lines,
+        }
+    }
+
+    #[test]
+    fn test_fits_without_pruning() {
+        let options = OutputOptions {
+            max_tokens: Some(10_000),
+            ..OutputOptions::codemap_only()
+        };
+        let fitted = fit_output(
+            None,
+            vec![codemap("a", 2)],
+            Vec::new(),
+            options,
+            Encoding::default(),
+        )
+        .unwrap();
+        assert!(fitted.steps.is_empty());
+        assert!(fitted.tokens <= 10_000);
+    }
+
+    #[test]
+    fn test_drops_selected_files_before_codemaps() {
+        let codemaps = vec![codemap("a", 2), codemap("b", 2)];
+        let files = vec![selected("a", 200), selected("b", 5)];
+        let unbudgeted = fit_output(
+            None,
+            codemaps.clone(),
+            Vec::new(),
+            OutputOptions {
+                include_tree: false,
+                ..OutputOptions::full_context()
+            },
+            Encoding::default(),
+        )
+        .unwrap();
+
+        let options = OutputOptions {
+            include_tree: false,
+            max_tokens: Some(unbudgeted.tokens + 200),
+            ..OutputOptions::full_context()
+        };
+        let fitted = fit_output(None, codemaps, files, options, Encoding::default()).unwrap();
+        assert_eq!(
+            fitted.steps,
+            vec![PruneStep::DropSelected(PathBuf::from("src/a.rs"))]
+        );
+        assert!(fitted.output.contains("src/b.rs ("));
+        assert!(fitted.tokens <= unbudgeted.tokens + 200);
+    }
+
+    #[test]
+    fn test_drops_largest_codemaps_then_fails() {
+        let codemaps = vec![codemap("small", 1), codemap("large", 40)];
+        let options = OutputOptions {
+            max_tokens: Some(150),
+            ..OutputOptions::codemap_only()
+        };
+        let fitted = fit_output(
+            None,
+            codemaps.clone(),
+            Vec::new(),
+            options,
+            Encoding::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            fitted.steps,
+            vec![PruneStep::DropCodemap(PathBuf::from("src/large.rs"))]
+        );
+        assert!(fitted.output.contains("small_0"));
+
+        let options = OutputOptions {
+            max_tokens: Some(5),
+            ..OutputOptions::codemap_only()
+        };
+        let err = fit_output(None, codemaps, Vec::new(), options, Encoding::default()).unwrap_err();
+        assert_eq!(err.budget, 5);
+        assert!(err.needed > 5);
+    }
+
+    #[test]
+    fn test_trims_least_important_declarations() {
+        let mut user = codemap("user", 1);
+        if let Declaration::Function {
+            name, signature, ..
+        } = &mut user.declarations[0]
+        {
+            *name = "load_user".to_string();
+            *signature = "pub fn load_user(id: u64) -> User".to_string();
+        }
+        let mut caller = codemap("caller", 0);
+        caller.imports.push(crate::codemap::Import {
+            source: "crate::user".into(),
+            items: smallvec::smallvec!["load_user".into()],
+        });
+        let codemaps = vec![user, codemap("other", 6), caller];
+        let options = OutputOptions {
+            max_tokens: Some(200),
+            trim_declarations: true,
+            ..OutputOptions::codemap_only()
+        };
+        let fitted = fit_output(None, codemaps, Vec::new(), options, Encoding::default()).unwrap();
+        assert!(matches!(
+            fitted.steps[..],
+            [PruneStep::DropDeclarations(_), ..]
+        ));
+        assert!(fitted
+            .steps
+            .iter()
+            .all(|step| matches!(step, PruneStep::DropDeclarations(_))));
+        assert!(fitted.output.contains("load_user"));
+        assert!(!fitted.output.contains("other_5"));
+    }
+
+    #[test]
+    fn test_budget_check() {
+        let files = BTreeMap::from([
+            (PathBuf::from("a.rs"), 100),
+            (PathBuf::from("b.rs"), 300),
+            (PathBuf::from("c.rs"), 100),
+        ]);
+
+        let check = BudgetCheck::new(&files, 400, 2);
+        assert!(!check.passed);
+        assert_eq!((check.total, check.excess), (500, 100));
+        assert_eq!(
+            check.largest,
+            vec![
+                FileTokens {
+                    path: PathBuf::from("b.rs"),
+                    tokens: 300
+                },
+                FileTokens {
+                    path: PathBuf::from("a.rs"),
+                    tokens: 100
+                },
+            ]
+        );
+        assert_eq!(check.error().unwrap().needed, 500);
+
+        let check = BudgetCheck::new(&files, 500, 2);
+        assert!(check.passed);
+        assert_eq!(check.excess, 0);
+        assert!(check.error().is_none());
+    }
+
+    #[test]
+    fn test_largest_covering() {
+        assert_eq!(largest_covering(&[5, 50, 20], 30), vec![1]);
+        assert_eq!(largest_covering(&[5, 50, 20], 60), vec![2, 1]);
+        assert_eq!(largest_covering(&[5, 50, 20], 0), vec![1]);
+    }
+}
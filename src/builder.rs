@@ -4,14 +4,27 @@
 //! for extracting codemaps from codebases.
 
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+use glob::Pattern;
 use rayon::prelude::*;
 
-use crate::codemap::{extract_codemap, Codemap, ExtractOptions};
-use crate::errors::PithError;
-use crate::filter::{passes_extension_filter, should_process, FilterResult, Language};
+use crate::attributes::{GitAttributes, LinguistMode};
+use crate::budget::fit_output;
+use crate::cancel::CancellationToken;
+use crate::codemap::{
+    extract_codemap, Codemap, DocSummary, ExtractOptions, KindFilter, VisibilityThreshold,
+};
+use crate::decode::decode;
+use crate::errors::{Diagnostic, DiagnosticKind, PithError};
+use crate::filter::{is_candidate, should_process_with, FilterOptions, FilterResult, Language};
+use crate::output::{format_codemap_block, OutputOptions, SelectedFile, SkippedFile};
+use crate::records::{records, Record};
+use crate::semver::ApiDiff;
+use crate::tokens::{Encoding, TokenCounter};
+use crate::tools::{tools, Tool};
 use crate::tree::{FileNode, RenderOptions};
-use crate::walker::{build_tree_with_options, walk_with_options, WalkOptions};
+use crate::walker::{build_tree_with_options, walk_with_options, WalkError, WalkOptions};
 
 /// Builder for extracting codemaps from a codebase.
 ///
@@ -29,8 +42,27 @@ pub struct Pith {
     root: PathBuf,
     languages: Option<Vec<Language>>,
     include_docs: bool,
-    include_private: bool,
+    visibility: VisibilityThreshold,
+    skip_tests: bool,
+    include_todos: bool,
+    list_tests: bool,
+    include_snippet: Option<usize>,
+    inline_bodies: Option<usize>,
+    only: Vec<KindFilter>,
+    strip_license_docs: bool,
+    normalize_docs: bool,
+    doc_summary: DocSummary,
+    max_parse_bytes: Option<usize>,
+    parse_timeout: Option<Duration>,
+    filter_options: FilterOptions,
+    linguist: LinguistMode,
     walk_options: WalkOptions,
+    encoding: Encoding,
+    select: Vec<String>,
+    select_languages: Vec<Language>,
+    output_options: OutputOptions,
+    threads: Option<usize>,
+    strict: bool,
 }
 
 impl Pith {
@@ -40,8 +72,27 @@ impl Pith {
             root: root.into(),
             languages: None,
             include_docs: false,
-            include_private: true,
+            visibility: VisibilityThreshold::All,
+            skip_tests: false,
+            include_todos: false,
+            list_tests: false,
+            include_snippet: None,
+            inline_bodies: None,
+            only: Vec::new(),
+            strip_license_docs: false,
+            normalize_docs: false,
+            doc_summary: DocSummary::Full,
+            max_parse_bytes: None,
+            parse_timeout: None,
+            filter_options: FilterOptions::default(),
+            linguist: LinguistMode::default(),
             walk_options: WalkOptions::default(),
+            encoding: Encoding::default(),
+            select: Vec::new(),
+            select_languages: Vec::new(),
+            output_options: OutputOptions::default(),
+            threads: None,
+            strict: false,
         }
     }
 
@@ -59,7 +110,100 @@ impl Pith {
 
     /// Include private items (default: true, capture all).
     pub fn include_private(mut self, include: bool) -> Self {
-        self.include_private = include;
+        self.visibility = if include {
+            VisibilityThreshold::All
+        } else {
+            VisibilityThreshold::Public
+        };
+        self
+    }
+
+    /// Set the minimum visibility of extracted items.
+    pub fn visibility(mut self, threshold: VisibilityThreshold) -> Self {
+        self.visibility = threshold;
+        self
+    }
+
+    /// Drop test modules, test functions and Go test files from codemaps.
+    pub fn skip_tests(mut self, skip: bool) -> Self {
+        self.skip_tests = skip;
+        self
+    }
+
+    /// Collect `TODO`, `FIXME` and `HACK` comments into codemaps.
+    pub fn include_todos(mut self, include: bool) -> Self {
+        self.include_todos = include;
+        self
+    }
+
+    /// List test names per file into codemaps.
+    pub fn list_tests(mut self, list: bool) -> Self {
+        self.list_tests = list;
+        self
+    }
+
+    /// Attach up to `max_lines` lines of source to each declaration.
+    pub fn include_snippet(mut self, max_lines: usize) -> Self {
+        self.include_snippet = Some(max_lines);
+        self
+    }
+
+    /// Inline the full source of functions spanning at most `max_lines` lines.
+    pub fn inline_bodies(mut self, max_lines: usize) -> Self {
+        self.inline_bodies = Some(max_lines);
+        self
+    }
+
+    /// Keep only declarations of the given kinds.
+    pub fn only(mut self, kinds: impl IntoIterator<Item = KindFilter>) -> Self {
+        self.only = kinds.into_iter().collect();
+        self
+    }
+
+    /// Drop doc comments that are copyright or license notices.
+    pub fn strip_license_docs(mut self, strip: bool) -> Self {
+        self.strip_license_docs = strip;
+        self
+    }
+
+    /// Rewrite Python docstrings and JSDoc comments as a summary, parameter
+    /// names and return value.
+    pub fn normalize_docs(mut self, normalize: bool) -> Self {
+        self.normalize_docs = normalize;
+        self
+    }
+
+    /// Shorten doc comments to their first sentence or first few tokens.
+    /// Only has an effect with [`Pith::include_docs`].
+    pub fn doc_summary(mut self, summary: DocSummary) -> Self {
+        self.doc_summary = summary;
+        self
+    }
+
+    /// Don't parse files larger than `bytes`; they are kept with a parse
+    /// error instead of declarations.
+    pub fn max_parse_bytes(mut self, bytes: usize) -> Self {
+        self.max_parse_bytes = Some(bytes);
+        self
+    }
+
+    /// Give up parsing a file after `timeout`; it is kept with a parse error
+    /// instead of declarations.
+    pub fn parse_timeout(mut self, timeout: Duration) -> Self {
+        self.parse_timeout = Some(timeout);
+        self
+    }
+
+    /// Map custom extensions to languages or skip them.
+    pub fn filter_options(mut self, options: FilterOptions) -> Self {
+        self.filter_options = options;
+        self
+    }
+
+    /// Set how files marked `linguist-generated` or `linguist-vendored` in
+    /// `.gitattributes` are treated (default: skipped).
+    pub fn linguist(mut self, mode: LinguistMode) -> Self {
+        self.linguist = mode;
         self
     }
 
@@ -75,47 +219,225 @@ impl Pith {
         self
     }
 
+    /// Skip dependency directories, build output and lock files that are not
+    /// gitignored (default: true). See [`crate::walker::DEFAULT_IGNORES`].
+    pub fn default_ignores(mut self, enabled: bool) -> Self {
+        self.walk_options.default_ignores = enabled;
+        self
+    }
+
+    /// Apply `.gitignore`, the global gitignore and `.git/info/exclude`
+    /// (default: true). `.pithignore` is always applied.
+    pub fn respect_gitignore(mut self, respect: bool) -> Self {
+        self.walk_options.respect_gitignore = respect;
+        self
+    }
+
+    /// Also skip paths matching the ignore file at `path`, with patterns
+    /// anchored at the root. Can be called more than once.
+    pub fn ignore_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.walk_options.custom_ignores.push(path.into());
+        self
+    }
+
+    /// Set the encoding codemap token counts use (default: cl100k).
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Include the full content of files whose path relative to the root
+    /// matches any of the glob `patterns`. Invalid patterns are ignored.
+    pub fn select<S: Into<String>>(mut self, patterns: impl IntoIterator<Item = S>) -> Self {
+        self.select = patterns.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Include the full content of every file in `language`, alongside any
+    /// [`Pith::select`] matches. Can be called more than once.
+    pub fn select_language(mut self, language: Language) -> Self {
+        self.select_languages.push(language);
+        self
+    }
+
+    /// Set the options [`PithResult::render`] uses.
+    pub fn output_options(mut self, options: OutputOptions) -> Self {
+        self.output_options = options;
+        self
+    }
+
+    /// Stop early with [`PithError::Cancelled`] once `token` is cancelled.
+    /// Walking stops and files not yet parsed are skipped.
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.walk_options.cancel = Some(token);
+        self
+    }
+
+    /// Fail on the first path that can't be read, instead of recording it
+    /// in [`PithResult::diagnostics`] (default: false). Permission errors
+    /// become [`PithError::PermissionDenied`].
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Run extraction and token counting on a pool of `threads` threads
+    /// instead of rayon's global pool. `0` uses one thread per CPU.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
     /// Build and return extraction results.
     pub fn build(self) -> Result<PithResult, PithError> {
+        in_pool(self.threads, move || self.build_inner())
+    }
+
+    /// Extract codemaps only (no tree).
+    pub fn extract(self) -> Result<Vec<Codemap>, PithError> {
+        in_pool(self.threads, move || self.extract_inner())
+    }
+
+    /// Build tree only (no codemaps).
+    pub fn tree(self) -> Result<FileNode, PithError> {
+        in_pool(self.threads, move || {
+            let tree =
+                build_tree_with_options(&self.root, &self.walk_options).map_err(PithError::Walk)?;
+            self.check_cancelled()?;
+            Ok(tree)
+        })
+    }
+
+    fn build_inner(self) -> Result<PithResult, PithError> {
         // Build tree
         let tree =
             build_tree_with_options(&self.root, &self.walk_options).map_err(PithError::Walk)?;
+        self.check_cancelled()?;
 
         // Extract codemaps in parallel
         let extract_options = ExtractOptions {
             include_docs: self.include_docs,
-            include_private: self.include_private,
+            visibility: self.visibility,
+            skip_tests: self.skip_tests,
+            include_todos: self.include_todos,
+            list_tests: self.list_tests,
+            include_snippet: self.include_snippet,
+            inline_bodies: self.inline_bodies,
+            only: self.only.clone(),
+            strip_license_docs: self.strip_license_docs,
+            normalize_docs: self.normalize_docs,
+            doc_summary: self.doc_summary,
+            max_parse_bytes: self.max_parse_bytes,
+            parse_timeout: self.parse_timeout,
         };
 
-        let codemaps = extract_codemaps_parallel(
+        let (mut codemaps, diagnostics) = extract_codemaps_parallel(
             &self.root,
             &self.walk_options,
             &extract_options,
+            &self.filter_options,
+            self.linguist,
             self.languages.as_deref(),
         )?;
+        self.check_cancelled()?;
+        self.check_strict(&diagnostics)?;
+        count_codemap_tokens(&mut codemaps, self.encoding);
+
+        let selected_files = read_selected_files(
+            &self.root,
+            &self.walk_options,
+            &self.filter_options,
+            self.linguist,
+            &self.select,
+            &self.select_languages,
+            self.encoding,
+        );
+        self.check_cancelled()?;
+        let output_options = OutputOptions {
+            include_selected_files: self.output_options.include_selected_files
+                || !selected_files.is_empty(),
+            skipped: diagnostics
+                .iter()
+                .filter(|d| d.kind.is_skipped())
+                .map(|d| SkippedFile {
+                    path: d.path.clone(),
+                    reason: d.message.clone(),
+                })
+                .collect(),
+            root: self.root.clone(),
+            ..self.output_options
+        };
 
-        Ok(PithResult { tree, codemaps })
+        Ok(PithResult {
+            tree,
+            codemaps,
+            selected_files,
+            diagnostics,
+            encoding: self.encoding,
+            output_options,
+        })
     }
 
-    /// Extract codemaps only (no tree).
-    pub fn extract(self) -> Result<Vec<Codemap>, PithError> {
+    fn extract_inner(self) -> Result<Vec<Codemap>, PithError> {
         let extract_options = ExtractOptions {
             include_docs: self.include_docs,
-            include_private: self.include_private,
+            visibility: self.visibility,
+            skip_tests: self.skip_tests,
+            include_todos: self.include_todos,
+            list_tests: self.list_tests,
+            include_snippet: self.include_snippet,
+            inline_bodies: self.inline_bodies,
+            only: self.only.clone(),
+            strip_license_docs: self.strip_license_docs,
+            normalize_docs: self.normalize_docs,
+            doc_summary: self.doc_summary,
+            max_parse_bytes: self.max_parse_bytes,
+            parse_timeout: self.parse_timeout,
         };
 
-        extract_codemaps_parallel(
+        let (mut codemaps, diagnostics) = extract_codemaps_parallel(
             &self.root,
             &self.walk_options,
             &extract_options,
+            &self.filter_options,
+            self.linguist,
             self.languages.as_deref(),
-        )
+        )?;
+        self.check_cancelled()?;
+        self.check_strict(&diagnostics)?;
+        count_codemap_tokens(&mut codemaps, self.encoding);
+        Ok(codemaps)
     }
 
-    /// Build tree only (no codemaps).
-    pub fn tree(self) -> Result<FileNode, PithError> {
-        build_tree_with_options(&self.root, &self.walk_options).map_err(PithError::Walk)
+    fn check_strict(&self, diagnostics: &[Diagnostic]) -> Result<(), PithError> {
+        match diagnostics.iter().find(|d| d.kind.is_skipped()) {
+            Some(diagnostic) if self.strict => Err(diagnostic.clone().into()),
+            _ => Ok(()),
+        }
     }
+
+    fn check_cancelled(&self) -> Result<(), PithError> {
+        match &self.walk_options.cancel {
+            Some(token) if token.is_cancelled() => Err(PithError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Run `op` on a dedicated pool of `threads` threads, or on the current
+/// pool if `threads` is `None`.
+fn in_pool<T: Send>(
+    threads: Option<usize>,
+    op: impl FnOnce() -> Result<T, PithError> + Send,
+) -> Result<T, PithError> {
+    let Some(threads) = threads else {
+        return op();
+    };
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(|e| PithError::Io(std::io::Error::other(e)))?
+        .install(op)
 }
 
 /// Result of a pith extraction.
@@ -123,8 +445,19 @@ impl Pith {
 pub struct PithResult {
     /// File tree of the codebase.
     pub tree: FileNode,
-    /// Extracted codemaps.
+    /// Extracted codemaps, with token counts.
     pub codemaps: Vec<Codemap>,
+    /// Files matching [`Pith::select`], with their content.
+    pub selected_files: Vec<SelectedFile>,
+    /// Paths left out because they could not be read, and files decoded
+    /// lossily, in path order.
+    pub diagnostics: Vec<Diagnostic>,
+    /// Encoding the token counts use.
+    pub encoding: Encoding,
+    /// Options set with [`Pith::output_options`]. Selected files are
+    /// included whenever there are any, and skipped
+    /// [`PithResult::diagnostics`] are listed as skipped files.
+    pub output_options: OutputOptions,
 }
 
 impl PithResult {
@@ -138,6 +471,74 @@ impl PithResult {
         self.codemaps.iter().find(|c| c.path == path)
     }
 
+    /// Tokens across all codemaps.
+    pub fn total_tokens(&self) -> usize {
+        self.codemaps.iter().map(|c| c.token_count).sum()
+    }
+
+    /// Compare the public Rust API in this result (the old snapshot)
+    /// against `new`. Files are matched by their path relative to each root,
+    /// as in [`FileNode::diff`]. Build both with private items and docs for
+    /// a complete report.
+    pub fn api_diff(&self, new: &PithResult) -> ApiDiff {
+        ApiDiff::between(
+            &self.codemaps,
+            &self.tree.path,
+            &new.codemaps,
+            &new.tree.path,
+        )
+    }
+
+    /// One record per declaration, with paths relative to the root, for
+    /// embedding (see [`crate::records`]). Build with docs, and snippets if
+    /// the bodies should be embedded too.
+    pub fn records(&self) -> Vec<Record> {
+        records(&self.codemaps, &self.tree.path, self.encoding)
+    }
+
+    /// One tool definition per public function (see [`crate::tools`]).
+    /// Build with docs for tool descriptions.
+    pub fn tools(&self) -> Vec<Tool> {
+        tools(&self.codemaps)
+    }
+
+    /// Render tree, codemaps, selected files and summary as the CLI would,
+    /// pruning to fit `options.max_tokens` if set.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use pith::builder::Pith;
+    /// use pith::output::{OutputFormat, OutputOptions};
+    /// use pith::tokens::Encoding;
+    ///
+    /// let result = Pith::new("./project").select(["src/main.rs"]).build().unwrap();
+    /// let options = OutputOptions {
+    ///     format: OutputFormat::Markdown,
+    ///     ..OutputOptions::full_context()
+    /// };
+    /// println!("{}", result.to_string(&options, Encoding::default()).unwrap());
+    /// ```
+    pub fn to_string(
+        &self,
+        options: &OutputOptions,
+        encoding: Encoding,
+    ) -> Result<String, PithError> {
+        let fitted = fit_output(
+            Some(&self.tree),
+            self.codemaps.clone(),
+            self.selected_files.clone(),
+            options.clone(),
+            encoding,
+        )?;
+        Ok(fitted.output)
+    }
+
+    /// Render with the builder's output options and encoding.
+    pub fn render(&self) -> Result<String, PithError> {
+        self.to_string(&self.output_options, self.encoding)
+    }
+
     /// Build render options with codemap markers.
     pub fn render_options(&self) -> RenderOptions<'_> {
         RenderOptions {
@@ -147,83 +548,233 @@ impl PithResult {
     }
 }
 
-/// Extract codemaps from a directory in parallel.
-fn extract_codemaps_parallel(
+/// Set each codemap's `token_count` to the tokens of its block in codemap
+/// output.
+fn count_codemap_tokens(codemaps: &mut [Codemap], encoding: Encoding) {
+    let counter = TokenCounter::new(encoding);
+    // Extraction already applied the visibility threshold.
+    let options = OutputOptions {
+        visibility: VisibilityThreshold::All,
+        ..OutputOptions::codemap_only()
+    };
+    codemaps.par_iter_mut().for_each(|codemap| {
+        codemap.token_count = counter.count(&format_codemap_block(codemap, &options));
+    });
+}
+
+/// Read files under `root` whose relative path matches any of `patterns`,
+/// or whose language is one of `languages`.
+fn read_selected_files(
     root: &Path,
     walk_options: &WalkOptions,
-    extract_options: &ExtractOptions,
-    language_filter: Option<&[Language]>,
-) -> Result<Vec<Codemap>, PithError> {
-    // Threshold for memory-mapping large files (5MB)
-    const MMAP_THRESHOLD: u64 = 5_000_000;
+    filter_options: &FilterOptions,
+    linguist: LinguistMode,
+    patterns: &[String],
+    languages: &[Language],
+    encoding: Encoding,
+) -> Vec<SelectedFile> {
+    let patterns: Vec<Pattern> = patterns
+        .iter()
+        .filter_map(|p| Pattern::new(p).ok())
+        .collect();
+    if patterns.is_empty() && languages.is_empty() {
+        return Vec::new();
+    }
 
-    // Collect files that pass filtering
-    let files: Vec<(PathBuf, Language)> = walk_with_options(root, walk_options)
+    // Generated and vendored files never contribute full content unless
+    // explicitly included
+    let attributes = match linguist {
+        LinguistMode::Skip | LinguistMode::CodemapOnly => GitAttributes::load(root),
+        LinguistMode::Include => GitAttributes::default(),
+    };
+    let counter = TokenCounter::new(encoding);
+
+    walk_with_options(root, walk_options)
         .filter_map(|entry| entry.ok())
-        .filter(|entry| entry.is_file)
+        .filter(|entry| entry.is_file && !attributes.linguist(&entry.path).is_marked())
         .filter_map(|entry| {
-            // Check extension filter
-            let lang = passes_extension_filter(&entry.path)?;
+            let relative = entry.path.strip_prefix(root).unwrap_or(&entry.path);
+            let matched = patterns
+                .iter()
+                .any(|p| p.matches(&relative.to_string_lossy()));
+            if !matched && languages.is_empty() {
+                return None;
+            }
+            let bytes = std::fs::read(&entry.path).ok()?;
+            let content = decode(bytes, filter_options.lossy_decoding)?.text;
+            let lang =
+                match should_process_with(&entry.path, Some(content.as_bytes()), filter_options) {
+                    FilterResult::Accept(lang) => lang,
+                    FilterResult::Reject(_) => return None,
+                };
+            if !matched && !languages.contains(&lang) {
+                return None;
+            }
+            Some(SelectedFile {
+                lines: content.lines().count(),
+                tokens: counter.count(&content),
+                path: entry.path,
+                language: Some(lang),
+                content,
+            })
+        })
+        .collect()
+}
 
-            // Apply language filter if specified
-            if let Some(langs) = language_filter {
-                if !langs.contains(&lang) {
-                    return None;
+/// Codemaps of the files under `root`, and diagnostics for the paths that
+/// could not be read.
+fn extract_codemaps_parallel(
+    root: &Path,
+    walk_options: &WalkOptions,
+    extract_options: &ExtractOptions,
+    filter_options: &FilterOptions,
+    linguist: LinguistMode,
+    language_filter: Option<&[Language]>,
+) -> Result<(Vec<Codemap>, Vec<Diagnostic>), PithError> {
+    // Without selected files, codemap-only marked files are simply included
+    let attributes = match linguist {
+        LinguistMode::Skip => GitAttributes::load(root),
+        LinguistMode::CodemapOnly | LinguistMode::Include => GitAttributes::default(),
+    };
+
+    // Collect files that pass extension filtering (or may be identified by
+    // their modeline)
+    let mut files = Vec::new();
+    let mut diagnostics = Vec::new();
+    for entry in walk_with_options(root, walk_options) {
+        match entry {
+            Ok(entry) => {
+                if entry.is_file
+                    && is_candidate(&entry.path, filter_options)
+                    && !attributes.linguist(&entry.path).is_marked()
+                {
+                    files.push(entry.path);
                 }
             }
+            Err(WalkError::PermissionDenied { path }) => diagnostics.push(Diagnostic {
+                path,
+                kind: DiagnosticKind::PermissionDenied,
+                message: "permission denied".to_string(),
+            }),
+            Err(WalkError::Io { path, source }) => diagnostics.push(Diagnostic::io(path, &source)),
+            Err(e) => return Err(e.into()),
+        }
+    }
 
-            Some((entry.path, lang))
+    // Extract codemaps in parallel
+    let cancel = walk_options.cancel.as_ref();
+    let results: Vec<Result<(Codemap, Option<Diagnostic>), Diagnostic>> = files
+        .into_par_iter()
+        .filter_map(|path| {
+            if cancel.is_some_and(CancellationToken::is_cancelled) {
+                return None;
+            }
+            extract_file(&path, extract_options, filter_options, language_filter).transpose()
         })
         .collect();
 
-    // Extract codemaps in parallel
-    let codemaps: Vec<Codemap> = files
-        .into_par_iter()
-        .filter_map(|(path, lang)| {
-            use std::io::Read;
+    let mut codemaps = Vec::with_capacity(results.len());
+    for result in results {
+        match result {
+            Ok((codemap, transcoded)) => {
+                codemaps.push(codemap);
+                diagnostics.extend(transcoded);
+            }
+            Err(diagnostic) => diagnostics.push(diagnostic),
+        }
+    }
+    diagnostics.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok((codemaps, diagnostics))
+}
 
-            // Get file metadata for size-based optimizations
-            let metadata = std::fs::metadata(&path).ok()?;
-            let file_size = metadata.len();
+/// Codemap of the file at `path`, with a diagnostic if it had to be decoded
+/// lossily, or `None` if it is filtered out.
+fn extract_file(
+    path: &Path,
+    extract_options: &ExtractOptions,
+    filter_options: &FilterOptions,
+    language_filter: Option<&[Language]>,
+) -> Result<Option<(Codemap, Option<Diagnostic>)>, Diagnostic> {
+    use std::io::Read;
 
-            // Open file once so we can reuse the handle
-            let mut file = std::fs::File::open(&path).ok()?;
+    // Threshold for memory-mapping large files (5MB)
+    const MMAP_THRESHOLD: u64 = 5_000_000;
 
-            // Read first 1KB for heuristics check
-            let mut first_kb = [0u8; 1024];
-            let n = file.read(&mut first_kb).ok()?;
+    let io_error = |e: std::io::Error| Diagnostic::io(path.to_path_buf(), &e);
 
-            // Apply content heuristics on first 1KB only
-            match should_process(&path, Some(&first_kb[..n])) {
-                FilterResult::Accept(_) => {}
-                FilterResult::Reject(_) => return None,
-            }
+    // Get file metadata for size-based optimizations
+    let metadata = std::fs::metadata(path).map_err(io_error)?;
+    let file_size = metadata.len();
 
-            // Read file content with size-based optimization
-            let content = if usize::try_from(file_size).ok().is_some_and(|sz| sz <= n) {
-                // Small file: we already have it in the buffer
-                String::from_utf8(first_kb[..n].to_vec()).ok()?
-            } else if file_size > MMAP_THRESHOLD {
-                // Large file: use memory mapping to avoid heap allocation.
-                // Avoid immediately allocating a full String; parse from &str.
-                use memmap2::Mmap;
-                let mmap = unsafe { Mmap::map(&file).ok()? };
-                let text = std::str::from_utf8(&mmap).ok()?;
-                return Some(extract_codemap(&path, text, lang, extract_options));
-            } else {
-                // Medium file: reuse the already-read prefix and continue reading.
-                let mut content = String::new();
-                content.push_str(std::str::from_utf8(&first_kb[..n]).ok()?);
-                file.read_to_string(&mut content).ok()?;
-                content
-            };
-
-            // Extract codemap
-            Some(extract_codemap(&path, &content, lang, extract_options))
-        })
-        .collect();
+    // Open file once so we can reuse the handle
+    let mut file = std::fs::File::open(path).map_err(io_error)?;
+
+    // Read first 1KB for heuristics check
+    let mut first_kb = [0u8; 1024];
+    let n = file.read(&mut first_kb).map_err(io_error)?;
+
+    // Apply content heuristics on first 1KB only
+    let lang = match should_process_with(path, Some(&first_kb[..n]), filter_options) {
+        FilterResult::Accept(lang) => lang,
+        FilterResult::Reject(_) => return Ok(None),
+    };
+
+    // Apply language filter if specified
+    if language_filter.is_some_and(|langs| !langs.contains(&lang))
+        || extract_options.skips_file(path, lang)
+    {
+        return Ok(None);
+    }
+
+    // Files too large to parse aren't read in full.
+    let len = usize::try_from(file_size).unwrap_or(usize::MAX);
+    if let Some(issue) = extract_options.size_limit_error(len) {
+        return Ok(Some((
+            Codemap::with_error(path.to_path_buf(), lang, issue),
+            None,
+        )));
+    }
 
-    Ok(codemaps)
+    let read_text = |bytes: Vec<u8>| {
+        decode(bytes, filter_options.lossy_decoding).ok_or_else(|| Diagnostic {
+            path: path.to_path_buf(),
+            kind: DiagnosticKind::InvalidUtf8,
+            message: "invalid UTF-8".to_string(),
+        })
+    };
+
+    // Read file content with size-based optimization
+    let decoded = if usize::try_from(file_size).ok().is_some_and(|sz| sz <= n) {
+        // Small file: we already have it in the buffer
+        read_text(first_kb[..n].to_vec())?
+    } else if file_size > MMAP_THRESHOLD {
+        // Large file: use memory mapping to avoid heap allocation.
+        // Avoid immediately allocating a full String; parse from &str.
+        use memmap2::Mmap;
+        let mmap = unsafe { Mmap::map(&file).map_err(io_error)? };
+        match std::str::from_utf8(&mmap) {
+            Ok(text) => {
+                return Ok(Some((
+                    extract_codemap(path, text, lang, extract_options),
+                    None,
+                )))
+            }
+            Err(_) => read_text(mmap.to_vec())?,
+        }
+    } else {
+        // Medium file: reuse the already-read prefix and continue reading.
+        let mut bytes = first_kb[..n].to_vec();
+        file.read_to_end(&mut bytes).map_err(io_error)?;
+        read_text(bytes)?
+    };
+
+    let transcoded = decoded.transcoding.map(|message| Diagnostic {
+        path: path.to_path_buf(),
+        kind: DiagnosticKind::Transcoded,
+        message,
+    });
+    let codemap = extract_codemap(path, &decoded.text, lang, extract_options);
+    Ok(Some((codemap, transcoded)))
 }
 
 // ============================================================================
@@ -247,7 +798,15 @@ pub fn extract_from_path(
     root: impl AsRef<Path>,
     options: &ExtractOptions,
 ) -> Result<Vec<Codemap>, PithError> {
-    extract_codemaps_parallel(root.as_ref(), &WalkOptions::default(), options, None)
+    extract_codemaps_parallel(
+        root.as_ref(),
+        &WalkOptions::default(),
+        options,
+        &FilterOptions::default(),
+        LinguistMode::default(),
+        None,
+    )
+    .map(|(codemaps, _)| codemaps)
 }
 
 /// Extract codemaps for specific languages.
@@ -260,8 +819,11 @@ pub fn extract_languages(
         root.as_ref(),
         &WalkOptions::default(),
         options,
+        &FilterOptions::default(),
+        LinguistMode::default(),
         Some(languages),
     )
+    .map(|(codemaps, _)| codemaps)
 }
 
 /// Build a file tree from a path.
@@ -319,6 +881,184 @@ pub fn process(config: &Config) -> String {
         assert!(!result.codemaps.is_empty());
     }
 
+    #[test]
+    fn test_codemap_token_counts() {
+        let dir = create_test_project();
+
+        let result = Pith::new(dir.path()).build().unwrap();
+        assert!(result.codemaps.iter().all(|c| c.token_count > 0));
+        assert_eq!(
+            result.total_tokens(),
+            result.codemaps.iter().map(|c| c.token_count).sum::<usize>()
+        );
+
+        let o200k = Pith::new(dir.path())
+            .encoding(Encoding::O200kBase)
+            .build()
+            .unwrap();
+        assert_eq!(o200k.encoding, Encoding::O200kBase);
+        assert!(o200k.total_tokens() > 0);
+    }
+
+    #[test]
+    fn test_render_with_selected_files() {
+        let dir = create_test_project();
+
+        let result = Pith::new(dir.path())
+            .select(["src/lib.rs"])
+            .output_options(OutputOptions {
+                format: crate::output::OutputFormat::Markdown,
+                ..OutputOptions::default()
+            })
+            .build()
+            .unwrap();
+        assert_eq!(result.selected_files.len(), 1);
+        assert!(result.output_options.include_selected_files);
+
+        let rendered = result.render().unwrap();
+        assert!(rendered.contains("main.rs"));
+        assert!(rendered.contains("config.name.clone()"));
+
+        let codemap_only = result
+            .to_string(&OutputOptions::codemap_only(), Encoding::default())
+            .unwrap();
+        assert!(!codemap_only.contains("config.name.clone()"));
+
+        let over_budget = OutputOptions {
+            max_tokens: Some(1),
+            ..OutputOptions::default()
+        };
+        assert!(result.to_string(&over_budget, Encoding::default()).is_err());
+    }
+
+    #[test]
+    fn test_select_language() {
+        let dir = create_test_project();
+        fs::write(dir.path().join("tool.py"), "def run():\n    pass\n").unwrap();
+
+        let result = Pith::new(dir.path())
+            .select_language(Language::Python)
+            .build()
+            .unwrap();
+        let selected: Vec<_> = result.selected_files.iter().map(|f| &f.path).collect();
+        assert_eq!(selected, [&dir.path().join("tool.py")]);
+        assert_eq!(result.codemaps.len(), 3);
+    }
+
+    #[test]
+    fn test_trait_impl_in_other_file() {
+        let dir = create_test_project();
+        fs::write(
+            dir.path().join("src/display.rs"),
+            "use crate::Config;\n\nimpl std::fmt::Display for Config {\n    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {\n        f.write_str(&self.name)\n    }\n}\n",
+        )
+        .unwrap();
+
+        let result = Pith::new(dir.path()).build().unwrap();
+        let display = result
+            .codemap_for(&dir.path().join("src/display.rs"))
+            .unwrap();
+        let names: Vec<_> = display.declarations.iter().map(|d| d.name()).collect();
+        assert_eq!(names, ["Config::fmt"]);
+    }
+
+    #[test]
+    fn test_threads() {
+        let dir = create_test_project();
+
+        let single = Pith::new(dir.path()).threads(1).build().unwrap();
+        let default = Pith::new(dir.path()).build().unwrap();
+        assert_eq!(single.codemaps.len(), default.codemaps.len());
+        assert_eq!(single.total_tokens(), default.total_tokens());
+
+        let codemaps = Pith::new(dir.path()).threads(2).extract().unwrap();
+        assert_eq!(codemaps.len(), 2);
+
+        assert_eq!(
+            in_pool(Some(3), || Ok(rayon::current_num_threads())).unwrap(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_cancellation() {
+        let dir = create_test_project();
+
+        let token = CancellationToken::new();
+        let pith = || Pith::new(dir.path()).cancellation(token.clone());
+        assert_eq!(pith().extract().unwrap().len(), 2);
+
+        token.cancel();
+        assert!(matches!(pith().build(), Err(PithError::Cancelled)));
+        assert!(matches!(pith().extract(), Err(PithError::Cancelled)));
+        assert!(matches!(pith().tree(), Err(PithError::Cancelled)));
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let dir = create_test_project();
+        fs::write(
+            dir.path().join("src/legacy.rs"),
+            b"// caf\xe9\nfn old() {}\n",
+        )
+        .unwrap();
+
+        let result = Pith::new(dir.path()).build().unwrap();
+        assert_eq!(result.codemaps.len(), 2);
+        assert_eq!(result.diagnostics.len(), 1);
+        assert!(result.diagnostics[0].path.ends_with("legacy.rs"));
+        assert_eq!(result.diagnostics[0].kind, DiagnosticKind::InvalidUtf8);
+        assert!(result
+            .render()
+            .unwrap()
+            .contains("legacy.rs: invalid UTF-8"));
+
+        let strict = Pith::new(dir.path()).strict(true).extract();
+        assert!(matches!(strict, Err(PithError::Unreadable(_))));
+
+        // Decoded lossily, the file is included and the transcoding recorded;
+        // strict mode doesn't fail on it.
+        let result = Pith::new(dir.path())
+            .filter_options(FilterOptions::default().lossy_decoding(true))
+            .strict(true)
+            .build()
+            .unwrap();
+        assert_eq!(result.codemaps.len(), 3);
+        assert_eq!(result.diagnostics[0].kind, DiagnosticKind::Transcoded);
+        assert_eq!(
+            result.diagnostics[0].message,
+            "replaced 1 invalid UTF-8 sequence(s)"
+        );
+        assert!(result.output_options.skipped.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_permission_denied_diagnostics() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = create_test_project();
+        let locked = dir.path().join("locked");
+        fs::create_dir(&locked).unwrap();
+        fs::write(locked.join("hidden.rs"), "fn hidden() {}\n").unwrap();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+        // Permissions don't apply to root.
+        let enforced = fs::read_dir(&locked).is_err();
+
+        let result = Pith::new(dir.path()).build();
+        let strict = Pith::new(dir.path()).strict(true).build();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+        if !enforced {
+            return;
+        }
+
+        let diagnostics = result.unwrap().diagnostics;
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].path, locked);
+        assert_eq!(diagnostics[0].kind, DiagnosticKind::PermissionDenied);
+        assert!(matches!(strict, Err(PithError::PermissionDenied(path)) if path == locked));
+    }
+
     #[test]
     fn test_extract_only() {
         let dir = create_test_project();
@@ -357,6 +1097,22 @@ pub fn process(config: &Config) -> String {
         assert!(rendered.contains(".hidden.rs"));
     }
 
+    #[test]
+    fn test_respect_gitignore() {
+        let dir = create_test_project();
+        fs::create_dir(dir.path().join(".git")).unwrap();
+        fs::write(dir.path().join(".gitignore"), "src/lib.rs\n").unwrap();
+
+        let has_lib = |codemaps: &[Codemap]| codemaps.iter().any(|c| c.path.ends_with("lib.rs"));
+
+        assert!(!has_lib(&Pith::new(dir.path()).extract().unwrap()));
+        let codemaps = Pith::new(dir.path())
+            .respect_gitignore(false)
+            .extract()
+            .unwrap();
+        assert!(has_lib(&codemaps));
+    }
+
     #[test]
     fn test_tree_max_depth() {
         let dir = TempDir::new().unwrap();
@@ -400,4 +1156,28 @@ pub fn process(config: &Config) -> String {
         assert_eq!(rust_only.len(), 1);
         assert!(rust_only[0].path.ends_with("main.rs"));
     }
+
+    #[test]
+    fn test_linguist_generated_files_skipped() {
+        let dir = create_test_project();
+        fs::write(dir.path().join("src/schema.rs"), "pub struct Schema;\n").unwrap();
+        fs::write(
+            dir.path().join(".gitattributes"),
+            "src/schema.rs linguist-generated\n",
+        )
+        .unwrap();
+
+        let has_schema =
+            |codemaps: &[Codemap]| codemaps.iter().any(|c| c.path.ends_with("schema.rs"));
+
+        let codemaps = Pith::new(dir.path()).extract().unwrap();
+        assert_eq!(codemaps.len(), 2);
+        assert!(!has_schema(&codemaps));
+
+        let codemaps = Pith::new(dir.path())
+            .linguist(LinguistMode::Include)
+            .extract()
+            .unwrap();
+        assert!(has_schema(&codemaps));
+    }
 }
@@ -0,0 +1,51 @@
+//! Cooperative cancellation.
+//!
+//! Editors and servers often start an extraction and then lose interest in
+//! it, because the user typed again or the request was dropped. A
+//! [`CancellationToken`] is shared between the caller and the run; walks stop
+//! yielding entries and extraction skips the remaining files once it is
+//! cancelled.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Flag shared between a run and whoever may want to stop it. Clones share
+/// the same flag.
+///
+/// # Examples
+///
+/// ```
+/// use pith::cancel::CancellationToken;
+///
+/// let token = CancellationToken::new();
+/// let handle = token.clone();
+/// handle.cancel();
+/// assert!(token.is_cancelled());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// A token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask every run holding this token to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Check if [`CancellationToken::cancel`] was called on this token or a
+    /// clone of it.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl From<Arc<AtomicBool>> for CancellationToken {
+    /// Share an existing flag; storing `true` in it cancels.
+    fn from(flag: Arc<AtomicBool>) -> Self {
+        Self(flag)
+    }
+}
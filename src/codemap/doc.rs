@@ -0,0 +1,519 @@
+//! Shortening doc comments.
+//!
+//! Heavily documented crates spend most of their codemap on prose. A summary
+//! keeps the gist of each doc comment (its first sentence, or its first few
+//! tokens) and drops the rest. Normalizing rewrites structured docstrings
+//! (Google, NumPy and reST style Python docstrings, JSDoc tags) into the same
+//! compact shape: a summary line, then parameter names and what is returned.
+
+use std::str::FromStr;
+
+use crate::tokens::TokenCounter;
+
+/// Abbreviations whose trailing period doesn't end a sentence.
+const ABBREVIATIONS: &[&str] = &["e.g.", "i.e.", "etc.", "vs.", "cf."];
+
+/// Google and NumPy section headers listing parameters.
+const PARAM_SECTIONS: &[&str] = &[
+    "args",
+    "arguments",
+    "parameters",
+    "params",
+    "keyword args",
+    "keyword arguments",
+    "other parameters",
+];
+
+/// Google and NumPy section headers describing the result.
+const RETURN_SECTIONS: &[&str] = &["returns", "return", "yields", "yield"];
+
+/// Other Google and NumPy section headers; their content is dropped.
+const OTHER_SECTIONS: &[&str] = &[
+    "raises",
+    "raise",
+    "exceptions",
+    "warns",
+    "attributes",
+    "methods",
+    "examples",
+    "example",
+    "notes",
+    "note",
+    "see also",
+    "references",
+    "warning",
+    "warnings",
+    "todo",
+];
+
+/// How much of each doc comment to keep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DocSummary {
+    /// Keep doc comments as written.
+    #[default]
+    Full,
+    /// Keep the first sentence of the first paragraph, on one line.
+    FirstSentence,
+    /// Keep this many tokens (cl100k_base), marking the cut with `...`.
+    Tokens(usize),
+}
+
+impl FromStr for DocSummary {
+    type Err = String;
+
+    /// Parse `full`, `sentence`, or a token count.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "full" => Ok(DocSummary::Full),
+            "sentence" => Ok(DocSummary::FirstSentence),
+            other => match other.parse::<usize>() {
+                Ok(0) => Err("token count must be at least 1".to_string()),
+                Ok(tokens) => Ok(DocSummary::Tokens(tokens)),
+                Err(_) => Err(format!(
+                    "unknown doc summary: {} (expected full, sentence or a token count)",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+/// Shorten `doc` as `mode` asks.
+///
+/// # Examples
+///
+/// ```
+/// use pith::codemap::{summarize_doc, DocSummary};
+///
+/// let doc = "Parse a config file.\n\nReads TOML, e.g. `pith.toml`, and\nvalidates it.";
+/// assert_eq!(summarize_doc(doc, DocSummary::FirstSentence), "Parse a config file.");
+/// assert_eq!(summarize_doc(doc, DocSummary::Tokens(3)), "Parse a config...");
+/// assert_eq!(summarize_doc(doc, DocSummary::Full), doc);
+/// ```
+pub fn summarize_doc(doc: &str, mode: DocSummary) -> String {
+    match mode {
+        DocSummary::Full => doc.to_string(),
+        DocSummary::FirstSentence => first_sentence(doc),
+        DocSummary::Tokens(max) => first_tokens(doc, max),
+    }
+}
+
+/// Part of a docstring being read by [`normalize_doc`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Summary,
+    /// Prose after the summary, and sections that are dropped.
+    Skipped,
+    Params,
+    Returns,
+}
+
+/// Rewrite a structured docstring as its summary (first paragraph, on one
+/// line), a `Params:` line of parameter names and a `Returns:` line.
+///
+/// Understands Google (`Args:`), NumPy (`Parameters` over a `----` rule) and
+/// reST (`:param x:`) sections and JSDoc tags (`@param`, `@returns`). The
+/// return description is kept, falling back to the return type. A doc with
+/// no summary, parameters or return is returned as-is.
+///
+/// # Examples
+///
+/// ```
+/// use pith::codemap::normalize_doc;
+///
+/// let doc = "Fetch a user.\n\n    Looks in the cache first.\n\n    Args:\n        user_id (int): Who to fetch.\n        timeout: Seconds to wait.\n\n    Returns:\n        User: The user, or None.\n    ";
+/// assert_eq!(
+///     normalize_doc(doc),
+///     "Fetch a user.\nParams: user_id, timeout\nReturns: The user, or None."
+/// );
+///
+/// let jsdoc = "Fetch a user.\n@param {number} id - Who to fetch.\n@param {Object} [opts]\n@returns {Promise<User>}";
+/// assert_eq!(
+///     normalize_doc(jsdoc),
+///     "Fetch a user.\nParams: id, opts\nReturns: Promise<User>"
+/// );
+/// ```
+pub fn normalize_doc(doc: &str) -> String {
+    let lines = clean_lines(doc);
+    let mut summary: Vec<&str> = Vec::new();
+    let mut params: Vec<String> = Vec::new();
+    let mut returns: Vec<&str> = Vec::new();
+    let mut return_type: Option<&str> = None;
+    let mut section = Section::Summary;
+    // Indentation of entries in the current Google or NumPy section; deeper
+    // lines continue an entry.
+    let mut entry_indent: Option<usize> = None;
+
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let text = line.trim();
+        let indent = line.len() - line.trim_start().len();
+        i += 1;
+
+        if text.is_empty() {
+            if section == Section::Summary && !summary.is_empty() {
+                section = Section::Skipped;
+            }
+            continue;
+        }
+
+        // NumPy headers are underlined; Google headers end with a colon.
+        let underlined = lines
+            .get(i)
+            .is_some_and(|next| next.trim().len() >= 3 && next.trim().chars().all(|c| c == '-'));
+        let header = if underlined {
+            Some(text)
+        } else {
+            text.strip_suffix(':')
+                .filter(|name| known_section(name).is_some())
+        };
+        if let Some(header) = header {
+            if underlined {
+                i += 1;
+            }
+            section = known_section(header).unwrap_or(Section::Skipped);
+            entry_indent = None;
+            continue;
+        }
+
+        if let Some(tag) = text.strip_prefix('@') {
+            let (tag, rest) = split_first_word(tag);
+            section = Section::Skipped;
+            match tag {
+                "param" | "arg" | "argument" => params.extend(jsdoc_param(rest)),
+                "returns" | "return" => {
+                    let (ty, description) = jsdoc_type(rest);
+                    return_type = ty;
+                    returns = vec![description.trim_start_matches("- ")];
+                    section = Section::Returns;
+                    entry_indent = Some(indent);
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        if let Some((field, rest)) = rest_field(text) {
+            let (kind, name) = field.split_once(' ').unwrap_or((field, ""));
+            section = Section::Skipped;
+            match kind {
+                "param" | "parameter" | "arg" | "argument" | "key" | "keyword" => {
+                    if let Some(name) = name.split_whitespace().last() {
+                        params.push(name.to_string());
+                    }
+                }
+                "returns" | "return" => {
+                    returns = vec![rest];
+                    section = Section::Returns;
+                    entry_indent = Some(indent);
+                }
+                "rtype" => return_type = Some(rest),
+                _ => {}
+            }
+            continue;
+        }
+
+        match section {
+            Section::Summary => summary.push(text),
+            Section::Skipped => {}
+            Section::Params => {
+                let entry = *entry_indent.get_or_insert(indent);
+                if indent == entry {
+                    params.extend(param_names(text));
+                }
+            }
+            Section::Returns => {
+                let entry = *entry_indent.get_or_insert(indent);
+                if indent < entry {
+                    section = Section::Skipped;
+                } else if returns.is_empty() && return_type.is_none() {
+                    // `int: The count` (Google), or a NumPy type line with
+                    // its description indented below it.
+                    let next_deeper = lines.get(i).is_some_and(|next| {
+                        !next.trim().is_empty() && next.len() - next.trim_start().len() > indent
+                    });
+                    if let Some((ty, description)) = text.split_once(':') {
+                        return_type = Some(ty.trim());
+                        returns.push(description.trim());
+                    } else if next_deeper {
+                        return_type = Some(text);
+                    } else {
+                        returns.push(text);
+                    }
+                } else {
+                    returns.push(text);
+                }
+            }
+        }
+    }
+
+    let returns = returns
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let returns = Some(returns)
+        .filter(|returns| !returns.is_empty())
+        .or(return_type.filter(|ty| !ty.is_empty()).map(str::to_string));
+    if summary.is_empty() && params.is_empty() && returns.is_none() {
+        return doc.to_string();
+    }
+
+    let mut out = summary.join(" ");
+    let mut push_line = |line: String| {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&line);
+    };
+    if !params.is_empty() {
+        push_line(format!("Params: {}", params.join(", ")));
+    }
+    if let Some(returns) = returns {
+        push_line(format!("Returns: {}", returns));
+    }
+    out
+}
+
+/// Lines of `doc` with the indentation shared by all but the first removed,
+/// as Python's `inspect.cleandoc` does; the first line follows the quotes.
+fn clean_lines(doc: &str) -> Vec<&str> {
+    let mut lines: Vec<&str> = doc.trim().lines().collect();
+    let indent = lines
+        .iter()
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    for line in lines.iter_mut().skip(1) {
+        *line = line.get(indent..).unwrap_or_else(|| line.trim_start());
+    }
+    lines
+}
+
+/// The section a Google or NumPy header opens, if it is a known header.
+fn known_section(header: &str) -> Option<Section> {
+    let header = header.trim().to_lowercase();
+    if PARAM_SECTIONS.contains(&header.as_str()) {
+        Some(Section::Params)
+    } else if RETURN_SECTIONS.contains(&header.as_str()) {
+        Some(Section::Returns)
+    } else if OTHER_SECTIONS.contains(&header.as_str()) {
+        Some(Section::Skipped)
+    } else {
+        None
+    }
+}
+
+/// Names from a Google (`name (int): ...`) or NumPy (`x, y : float`)
+/// parameter entry. Lines that don't start with names yield nothing.
+fn param_names(entry: &str) -> Vec<String> {
+    let head = entry.split(':').next().unwrap_or(entry);
+    let head = head.split('(').next().unwrap_or(head);
+    let names: Vec<&str> = head.split(',').map(str::trim).collect();
+    if names.iter().all(|name| is_param_name(name)) {
+        names.into_iter().map(str::to_string).collect()
+    } else {
+        Vec::new()
+    }
+}
+
+fn is_param_name(name: &str) -> bool {
+    let name = name.trim_start_matches('*');
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Name of a JSDoc `@param` (`{type} name`, `[name]` or `[name=default]`).
+/// Properties of another parameter (`options.verbose`) are skipped.
+fn jsdoc_param(rest: &str) -> Option<String> {
+    let (_, rest) = jsdoc_type(rest);
+    let (name, _) = split_first_word(rest);
+    let name = name.trim_start_matches('[').trim_end_matches(']');
+    let name = name.split('=').next().unwrap_or(name);
+    is_param_name(name).then(|| name.to_string())
+}
+
+/// Split a leading `{type}` off a JSDoc tag's text.
+fn jsdoc_type(rest: &str) -> (Option<&str>, &str) {
+    let rest = rest.trim();
+    if !rest.starts_with('{') {
+        return (None, rest);
+    }
+    let mut depth = 0;
+    for (i, c) in rest.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return (Some(rest[1..i].trim()), rest[i + 1..].trim());
+                }
+            }
+            _ => {}
+        }
+    }
+    (None, rest)
+}
+
+/// Field name and text of a reST field line (`:param x: text`).
+fn rest_field(text: &str) -> Option<(&str, &str)> {
+    let (field, rest) = text.strip_prefix(':')?.split_once(':')?;
+    Some((field.trim(), rest.trim()))
+}
+
+fn split_first_word(text: &str) -> (&str, &str) {
+    let text = text.trim();
+    text.split_once(char::is_whitespace)
+        .map_or((text, ""), |(word, rest)| (word, rest.trim()))
+}
+
+/// The first paragraph joined into one line, cut after the first `.`, `!` or
+/// `?` that is followed by whitespace and doesn't end an abbreviation.
+fn first_sentence(doc: &str) -> String {
+    let paragraph = doc
+        .trim()
+        .lines()
+        .map(str::trim)
+        .take_while(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let mut words_end = 0;
+    for word in paragraph.split(' ') {
+        words_end += word.len() + 1;
+        let ends_sentence = word.ends_with(['.', '!', '?'])
+            && !ABBREVIATIONS
+                .iter()
+                .any(|abbr| word.eq_ignore_ascii_case(abbr));
+        if ends_sentence {
+            return paragraph[..words_end - 1].to_string();
+        }
+    }
+    paragraph
+}
+
+/// The first `max` tokens of `doc`. Without a tokenizer, whitespace-separated
+/// words stand in for tokens.
+fn first_tokens(doc: &str, max: usize) -> String {
+    let doc = doc.trim();
+    let cut = match TokenCounter::default().token_boundaries(doc) {
+        Some(boundaries) => boundaries.get(max).copied().filter(|&cut| cut < doc.len()),
+        None => word_start(doc, max),
+    };
+    let Some(mut cut) = cut else {
+        return doc.to_string();
+    };
+    // Tokens can split a multi-byte character.
+    while !doc.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    format!("{}...", doc[..cut].trim_end())
+}
+
+/// Byte offset of the `n`th (0-indexed) whitespace-separated word.
+fn word_start(text: &str, n: usize) -> Option<usize> {
+    let mut words = 0;
+    let mut after_space = true;
+    for (i, c) in text.char_indices() {
+        if !c.is_whitespace() && after_space {
+            if words == n {
+                return Some(i);
+            }
+            words += 1;
+        }
+        after_space = c.is_whitespace();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize_doc() {
+        let sentence = |doc| summarize_doc(doc, DocSummary::FirstSentence);
+        assert_eq!(
+            sentence("Walk the tree,\nskipping ignored paths. Symlinks are followed."),
+            "Walk the tree, skipping ignored paths."
+        );
+        assert_eq!(
+            sentence("Returns early, i.e. before reading. Then stops."),
+            "Returns early, i.e. before reading."
+        );
+        // Docstrings often start with a newline; a version number has no
+        // space after its periods.
+        assert_eq!(
+            sentence("\n    Needs v1.2 or later\n\n    Details."),
+            "Needs v1.2 or later"
+        );
+        assert_eq!(sentence("Is it? Yes."), "Is it?");
+
+        let tokens = |doc, max| summarize_doc(doc, DocSummary::Tokens(max));
+        assert_eq!(tokens("Short doc.", 10), "Short doc.");
+        assert_eq!(tokens("one two three four five", 2), "one two...");
+        assert_eq!(word_start("  one two\nthree", 2), Some(10));
+        assert_eq!(word_start("one", 1), None);
+    }
+
+    #[test]
+    fn test_normalize_doc() {
+        let numpy = r"Compute the mean.
+
+        Parameters
+        ----------
+        values : array_like
+            Numbers to average.
+        axis, dtype : int, optional
+            Where and how.
+
+        Returns
+        -------
+        float
+            The mean of `values`.
+
+        See Also
+        --------
+        median : The middle value.
+        ";
+        assert_eq!(
+            normalize_doc(numpy),
+            "Compute the mean.\nParams: values, axis, dtype\nReturns: The mean of `values`."
+        );
+
+        let rest = "Open a file.\n\n    :param str path: Where it is.\n    :param mode: How to open it.\n    :type mode: str\n    :returns: A handle,\n        ready to read.\n    :rtype: File\n    :raises OSError: If it can't.";
+        assert_eq!(
+            normalize_doc(rest),
+            "Open a file.\nParams: path, mode\nReturns: A handle, ready to read."
+        );
+
+        // The return type stands in for a missing description; `*args`
+        // are parameters too.
+        let google = "Run it.\n\nArgs:\n    *args: Passed on.\n        More about them.\n\nReturns:\n    bool\n\nRaises:\n    ValueError: Never.";
+        assert_eq!(
+            normalize_doc(google),
+            "Run it.\nParams: *args\nReturns: bool"
+        );
+
+        let jsdoc = "Render a button.\nUsed everywhere.\n@param {Object} props\n@param {string} props.label - Ignored as a property.\n@param {number} [size=2]\n@returns {JSX.Element} The button.\n@example\nrender()";
+        assert_eq!(
+            normalize_doc(jsdoc),
+            "Render a button. Used everywhere.\nParams: props, size\nReturns: The button."
+        );
+
+        // Plain prose keeps only its first paragraph.
+        assert_eq!(normalize_doc("One line.\n\nMore detail."), "One line.");
+        assert_eq!(normalize_doc("Args:\n    --"), "Args:\n    --");
+    }
+
+    #[test]
+    fn test_parse_doc_summary() {
+        assert_eq!("sentence".parse(), Ok(DocSummary::FirstSentence));
+        assert_eq!("Full".parse(), Ok(DocSummary::Full));
+        assert_eq!("40".parse(), Ok(DocSummary::Tokens(40)));
+        assert!("0".parse::<DocSummary>().is_err());
+        assert!("short".parse::<DocSummary>().is_err());
+    }
+}
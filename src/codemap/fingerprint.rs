@@ -0,0 +1,225 @@
+//! Stable API fingerprints for change detection.
+//!
+//! Fingerprints are 64-bit FNV-1a hashes over whitespace-normalized
+//! signature data. They ignore locations and docs, so moving or documenting
+//! a declaration keeps its fingerprint, and they do not depend on the Rust
+//! version or process, so they can be compared across runs.
+
+use super::{Codemap, Declaration, Field};
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+struct Fingerprinter(u64);
+
+impl Fingerprinter {
+    fn new() -> Self {
+        Self(FNV_OFFSET)
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= u64::from(b);
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    /// Hash a string with runs of whitespace collapsed, followed by a separator.
+    fn text(&mut self, s: &str) {
+        for (i, word) in s.split_whitespace().enumerate() {
+            if i > 0 {
+                self.bytes(b" ");
+            }
+            self.bytes(word.as_bytes());
+        }
+        self.bytes(&[0]);
+    }
+
+    fn list<S: AsRef<str>>(&mut self, items: &[S]) {
+        self.bytes(&(items.len() as u64).to_le_bytes());
+        for item in items {
+            self.text(item.as_ref());
+        }
+    }
+
+    fn fields(&mut self, fields: &[Field]) {
+        self.bytes(&(fields.len() as u64).to_le_bytes());
+        for field in fields {
+            self.text(&field.name);
+            self.text(&field.ty);
+            self.text(&field.visibility.to_string());
+            self.list(&field.modifiers);
+            self.text(field.tag.as_deref().unwrap_or_default());
+        }
+    }
+
+    /// Nested declarations, in any order.
+    fn nested(&mut self, decls: &[Declaration]) {
+        self.bytes(&combined_fingerprint(decls.iter()).to_le_bytes());
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Order-independent fingerprint of a set of declarations.
+pub(crate) fn combined_fingerprint<'a>(decls: impl Iterator<Item = &'a Declaration>) -> u64 {
+    let mut fingerprints: Vec<u64> = decls.map(Declaration::fingerprint).collect();
+    fingerprints.sort_unstable();
+
+    let mut hasher = Fingerprinter::new();
+    for fingerprint in fingerprints {
+        hasher.bytes(&fingerprint.to_le_bytes());
+    }
+    hasher.finish()
+}
+
+impl Declaration {
+    /// Stable hash of this declaration's API: kind, name, visibility and
+    /// signature-level details (fields, variants, members). Locations,
+    /// docs and body metrics are ignored.
+    pub fn fingerprint(&self) -> u64 {
+        let mut h = Fingerprinter::new();
+        h.text(self.kind());
+        h.text(self.name());
+        h.text(&self.visibility().to_string());
+
+        match self {
+            Declaration::Function {
+                signature,
+                is_async,
+                overloads,
+                attributes,
+                ..
+            } => {
+                h.text(signature);
+                h.bytes(&[u8::from(*is_async)]);
+                h.list(overloads);
+                h.list(attributes);
+            }
+            Declaration::Struct {
+                generics,
+                kind,
+                fields,
+                methods,
+                implements,
+                attributes,
+                ..
+            } => {
+                h.text(generics);
+                h.text(&kind.to_string());
+                h.fields(fields);
+                h.nested(methods);
+                h.list(implements);
+                h.list(attributes);
+            }
+            Declaration::Enum {
+                generics,
+                variants,
+                methods,
+                implements,
+                attributes,
+                ..
+            } => {
+                h.text(generics);
+                h.bytes(&(variants.len() as u64).to_le_bytes());
+                for variant in variants {
+                    h.text(&variant.name);
+                }
+                h.nested(methods);
+                h.list(implements);
+                h.list(attributes);
+            }
+            Declaration::Trait {
+                generics,
+                methods,
+                attributes,
+                ..
+            } => {
+                h.text(generics);
+                h.list(methods);
+                h.list(attributes);
+            }
+            Declaration::TypeAlias {
+                generics, target, ..
+            } => {
+                h.text(generics);
+                h.text(target);
+            }
+            Declaration::Const { ty, .. } => h.text(ty),
+            Declaration::Interface { members, .. } => h.list(members),
+            Declaration::Class {
+                generics,
+                extends,
+                implements,
+                is_abstract,
+                fields,
+                members,
+                ..
+            } => {
+                h.text(generics);
+                h.list(extends);
+                h.list(implements);
+                h.bytes(&[u8::from(*is_abstract)]);
+                h.fields(fields);
+                h.nested(members);
+            }
+            Declaration::ReExport { items, .. } => h.list(items),
+        }
+
+        h.finish()
+    }
+}
+
+impl Codemap {
+    /// Stable hash of all declarations in the file, independent of their order.
+    pub fn fingerprint(&self) -> u64 {
+        combined_fingerprint(self.declarations.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codemap::{Location, Visibility};
+
+    fn function(signature: &str, start_line: usize, doc: Option<&str>) -> Declaration {
+        Declaration::Function {
+            name: "run".into(),
+            scope: None,
+            signature: signature.into(),
+            visibility: Visibility::Public,
+            location: Location::single_line(start_line),
+            is_async: false,
+            overloads: Vec::new(),
+            component: None,
+            metrics: None,
+            attributes: Vec::new(),
+            doc: doc.map(Into::into),
+            snippet: None,
+        }
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_location_docs_and_whitespace() {
+        let a = function("pub fn run(x: i32) -> i32", 1, None);
+        let b = function("pub fn run(x: i32)\n    -> i32", 40, Some("Runs."));
+        assert_eq!(a.fingerprint(), b.fingerprint());
+    }
+
+    #[test]
+    fn test_fingerprint_changes_with_signature() {
+        let a = function("pub fn run(x: i32) -> i32", 1, None);
+        let b = function("pub fn run(x: i64) -> i32", 1, None);
+        assert_ne!(a.fingerprint(), b.fingerprint());
+    }
+
+    #[test]
+    fn test_fingerprint_is_stable() {
+        // Guards against accidental changes to the hashing scheme, which
+        // would invalidate fingerprints saved by earlier runs.
+        let decl = function("pub fn run()", 1, None);
+        assert_eq!(format!("{:016x}", decl.fingerprint()), "61cc9b6a828aaffe");
+    }
+}
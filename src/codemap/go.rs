@@ -1,22 +1,22 @@
 //! Go codemap extraction using tree-sitter.
 
+use std::collections::{HashMap, HashSet};
+
 use super::{
-    find_child_by_kind, node_text, with_go_parser, Declaration, ExtractOptions, Field, Import,
-    Location, Visibility,
+    collect_syntax_errors, find_child_by_kind, function_metrics, node_text, parse_source,
+    with_go_parser, Declaration, ExtractOptions, Extracted, Field, Import, Location, StructKind,
+    Variant, Visibility,
 };
 
 /// Extract imports and declarations from Go source code.
-pub fn extract(
-    content: &str,
-    options: &ExtractOptions,
-) -> Result<(Vec<Import>, Vec<Declaration>), String> {
+pub fn extract(content: &str, options: &ExtractOptions) -> Result<Extracted, String> {
     with_go_parser(|parser| {
-        let tree = parser
-            .parse(content, None)
-            .ok_or_else(|| "failed to parse".to_string())?;
+        let tree = parse_source(parser, content, options)?;
 
         let mut imports = Vec::new();
         let mut declarations = Vec::new();
+        let mut methods = Vec::new();
+        let syntax_errors = collect_syntax_errors(tree.root_node(), content);
 
         extract_from_node(
             tree.root_node(),
@@ -24,9 +24,35 @@ pub fn extract(
             options,
             &mut imports,
             &mut declarations,
+            &mut methods,
         );
+        replace_iota_aliases(&mut declarations);
+
+        // Attach methods to their receiver struct, like Rust impl blocks.
+        let struct_indices: HashMap<String, usize> = declarations
+            .iter()
+            .enumerate()
+            .filter_map(|(i, d)| match d {
+                Declaration::Struct { name, .. } => Some((name.clone(), i)),
+                _ => None,
+            })
+            .collect();
+        for (receiver, method) in methods {
+            match struct_indices
+                .get(&receiver)
+                .map(|&idx| &mut declarations[idx])
+            {
+                Some(Declaration::Struct { methods, .. }) => methods.push(method),
+                // Receiver declared in another file, or not a struct.
+                _ => declarations.push(method),
+            }
+        }
+
+        if options.skip_tests {
+            declarations.retain(|decl| !is_test_function(decl));
+        }
 
-        Ok((imports, declarations))
+        Ok((imports, declarations, syntax_errors))
     })?
 }
 
@@ -36,6 +62,7 @@ fn extract_from_node(
     options: &ExtractOptions,
     imports: &mut Vec<Import>,
     declarations: &mut Vec<Declaration>,
+    methods: &mut Vec<(String, Declaration)>,
 ) {
     let mut cursor = node.walk();
 
@@ -46,15 +73,15 @@ fn extract_from_node(
             }
             "function_declaration" => {
                 if let Some(func) = extract_function(child, content, options) {
-                    if options.include_private || func.visibility() == Visibility::Public {
+                    if options.visibility.allows(func.visibility()) {
                         declarations.push(func);
                     }
                 }
             }
             "method_declaration" => {
                 if let Some(method) = extract_method(child, content, options) {
-                    if options.include_private || method.visibility() == Visibility::Public {
-                        declarations.push(method);
+                    if options.visibility.allows(method.visibility()) {
+                        methods.push((receiver_type(child, content), method));
                     }
                 }
             }
@@ -64,11 +91,50 @@ fn extract_from_node(
             "const_declaration" | "var_declaration" => {
                 extract_const_var(child, content, options, declarations);
             }
+            "ERROR" => extract_from_node(child, content, options, imports, declarations, methods),
             _ => {}
         }
     }
 }
 
+/// `go test` entry points: `TestXxx`, `BenchmarkXxx` and `FuzzXxx` taking a `*testing` parameter.
+fn is_test_function(decl: &Declaration) -> bool {
+    match decl {
+        Declaration::Function {
+            name, signature, ..
+        } => is_test_signature(name, signature),
+        _ => false,
+    }
+}
+
+fn is_test_signature(name: &str, signature: &str) -> bool {
+    ["Test", "Benchmark", "Fuzz"]
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+        && signature.contains("*testing.")
+}
+
+/// Names of `go test` entry points.
+pub fn extract_test_names(content: &str) -> Result<Vec<String>, String> {
+    with_go_parser(|parser| {
+        let tree = parser
+            .parse(content, None)
+            .ok_or_else(|| "failed to parse".to_string())?;
+
+        let root = tree.root_node();
+        let names = root
+            .children(&mut root.walk())
+            .filter(|child| child.kind() == "function_declaration")
+            .filter_map(|func| {
+                let name = node_text(func.child_by_field_name("name")?, content);
+                let params = node_text(func.child_by_field_name("parameters")?, content);
+                is_test_signature(&name, &params).then_some(name)
+            })
+            .collect();
+        Ok(names)
+    })?
+}
+
 fn extract_imports(node: tree_sitter::Node, content: &str, imports: &mut Vec<Import>) {
     for child in node.children(&mut node.walk()) {
         if child.kind() == "import_spec_list" {
@@ -134,11 +200,17 @@ fn extract_function(
 
     Some(Declaration::Function {
         name,
+        scope: None,
         signature,
         visibility,
         location,
         is_async: false, // Go doesn't have async keyword
+        overloads: Vec::new(),
+        component: None,
+        metrics: function_metrics(node),
+        attributes: Vec::new(),
         doc,
+        snippet: None,
     })
 }
 
@@ -189,14 +261,36 @@ fn extract_method(
 
     Some(Declaration::Function {
         name,
+        scope: None,
         signature,
         visibility,
         location,
         is_async: false,
+        overloads: Vec::new(),
+        component: None,
+        metrics: function_metrics(node),
+        attributes: Vec::new(),
         doc,
+        snippet: None,
     })
 }
 
+/// Base type name of a method receiver: `Stack` for `(s *Stack[T])`.
+fn receiver_type(node: tree_sitter::Node, content: &str) -> String {
+    let mut ty = node
+        .child_by_field_name("receiver")
+        .and_then(|r| find_child_by_kind(r, "parameter_declaration"))
+        .and_then(|p| p.child_by_field_name("type"));
+    while let Some(t) = ty {
+        match t.kind() {
+            "pointer_type" => ty = t.named_child(0),
+            "generic_type" => ty = t.child_by_field_name("type"),
+            _ => return node_text(t, content),
+        }
+    }
+    String::new()
+}
+
 fn extract_type_decl(
     node: tree_sitter::Node,
     content: &str,
@@ -206,7 +300,7 @@ fn extract_type_decl(
     for child in node.children(&mut node.walk()) {
         if child.kind() == "type_spec" {
             if let Some(decl) = extract_type_spec(child, content, options) {
-                if options.include_private || decl.visibility() == Visibility::Public {
+                if options.visibility.allows(decl.visibility()) {
                     declarations.push(decl);
                 }
             }
@@ -223,6 +317,11 @@ fn extract_type_spec(
 
     let visibility = go_visibility(&name);
 
+    let generics = node
+        .child_by_field_name("type_parameters")
+        .map(|n| node_text(n, content))
+        .unwrap_or_default();
+
     // Check what kind of type it is
     if let Some(struct_type) = find_child_by_kind(node, "struct_type") {
         let mut fields = Vec::new();
@@ -230,9 +329,7 @@ fn extract_type_spec(
         if let Some(field_list) = find_child_by_kind(struct_type, "field_declaration_list") {
             for field in field_list.children(&mut field_list.walk()) {
                 if field.kind() == "field_declaration" {
-                    if let Some(f) = extract_struct_field(field, content) {
-                        fields.push(f);
-                    }
+                    fields.extend(extract_struct_fields(field, content));
                 }
             }
         }
@@ -247,11 +344,16 @@ fn extract_type_spec(
 
         return Some(Declaration::Struct {
             name,
+            generics,
+            kind: StructKind::Named,
             fields: fields.into(),
             visibility,
             location,
             methods: Vec::new(),
+            implements: Vec::new(),
+            attributes: Vec::new(),
             doc,
+            snippet: None,
         });
     }
 
@@ -259,8 +361,12 @@ fn extract_type_spec(
         let mut members = Vec::new();
 
         for child in interface_type.children(&mut interface_type.walk()) {
-            // Handle different tree-sitter-go versions
-            if child.kind() == "method_spec" || child.kind() == "method_elem" {
+            // Handle different tree-sitter-go versions. `type_elem` is an
+            // embedded interface (`io.Reader`) or a constraint (`~int | ~string`).
+            if matches!(
+                child.kind(),
+                "method_spec" | "method_elem" | "type_elem" | "interface_type_name"
+            ) {
                 members.push(node_text(child, content));
             }
         }
@@ -279,81 +385,241 @@ fn extract_type_spec(
             visibility,
             location,
             doc,
+            snippet: None,
         });
     }
 
     // Type alias
-    let full_text = node_text(node, content);
-    let target = full_text
-        .split_once(&name)
-        .map(|(_, s)| s.trim().to_string())
+    let target = node
+        .child_by_field_name("type")
+        .map(|n| node_text(n, content))
         .unwrap_or_default();
 
     let location = Location::new(node.start_position().row + 1, node.end_position().row + 1);
 
     Some(Declaration::TypeAlias {
         name,
+        generics,
         target,
         visibility,
         location,
+        snippet: None,
     })
 }
 
-fn extract_struct_field(node: tree_sitter::Node, content: &str) -> Option<Field> {
-    let name = find_child_by_kind(node, "field_identifier").map(|n| node_text(n, content))?;
+/// One field per name in a `field_declaration` (`Name, Alias string`). An
+/// embedded field (`*http.Server`) is named after its type, as in Go, and
+/// marked with the `embedded` modifier.
+fn extract_struct_fields(node: tree_sitter::Node, content: &str) -> Vec<Field> {
+    let Some(type_node) = node.child_by_field_name("type") else {
+        return Vec::new();
+    };
+    let ty = node_text(type_node, content);
+    let tag = node
+        .child_by_field_name("tag")
+        .map(|t| node_text(t, content));
+
+    let names: Vec<String> = node
+        .children_by_field_name("name", &mut node.walk())
+        .filter(|n| n.kind() == "field_identifier")
+        .map(|n| node_text(n, content))
+        .collect();
 
-    let visibility = go_visibility(&name);
+    if names.is_empty() {
+        let base = match type_node.kind() {
+            "qualified_type" => type_node.child_by_field_name("name"),
+            "generic_type" => type_node.child_by_field_name("type"),
+            _ => Some(type_node),
+        };
+        let name = base.map(|n| node_text(n, content)).unwrap_or_default();
+        // The pointer star is a sibling of the type node.
+        let ty = if find_child_by_kind(node, "*").is_some() {
+            format!("*{}", ty)
+        } else {
+            ty
+        };
+        return vec![Field {
+            visibility: go_visibility(&name),
+            name,
+            ty,
+            modifiers: vec!["embedded".to_string()],
+            tag,
+            doc: None,
+        }];
+    }
 
-    // Get type - could be various type nodes
-    let ty = node
-        .children(&mut node.walk())
-        .find(|c| {
-            c.kind().contains("type") || c.kind() == "qualified_type" || c.kind() == "pointer_type"
+    names
+        .into_iter()
+        .map(|name| Field {
+            visibility: go_visibility(&name),
+            name,
+            ty: ty.clone(),
+            modifiers: Vec::new(),
+            tag: tag.clone(),
+            doc: None,
         })
-        .map(|n| node_text(n, content))
-        .unwrap_or_default();
+        .collect()
+}
 
-    Some(Field {
-        name,
-        ty,
-        visibility,
-    })
+/// Put each iota enum in place of the bare `type Color int` declaration of
+/// the same name, whether the type comes before or after the `const` block.
+fn replace_iota_aliases(declarations: &mut Vec<Declaration>) {
+    let aliases: HashSet<String> = declarations
+        .iter()
+        .filter_map(|d| match d {
+            Declaration::TypeAlias { name, .. } => Some(name.clone()),
+            _ => None,
+        })
+        .collect();
+    let (enums, rest): (Vec<_>, Vec<_>) = declarations
+        .drain(..)
+        .partition(|d| matches!(d, Declaration::Enum { name, .. } if aliases.contains(name)));
+    let mut enums: HashMap<String, Declaration> = enums
+        .into_iter()
+        .map(|d| (d.name().to_string(), d))
+        .collect();
+    *declarations = rest
+        .into_iter()
+        .map(|d| match &d {
+            Declaration::TypeAlias { name, .. } => enums.remove(name).unwrap_or(d),
+            _ => d,
+        })
+        .collect();
 }
 
+/// One constant per name in each spec. A `const` block whose first spec is
+/// typed and uses `iota` becomes an enum of that type, which later replaces
+/// the bare `type Color int` declaration when it is in the same file.
 fn extract_const_var(
     node: tree_sitter::Node,
     content: &str,
     options: &ExtractOptions,
     declarations: &mut Vec<Declaration>,
 ) {
-    for child in node.children(&mut node.walk()) {
-        if child.kind() == "const_spec" || child.kind() == "var_spec" {
-            if let Some(name_node) = find_child_by_kind(child, "identifier") {
-                let name = node_text(name_node, content);
-                let visibility = go_visibility(&name);
-
-                if !options.include_private && visibility != Visibility::Public {
-                    continue;
-                }
-
-                let ty = find_child_by_kind(child, "type_identifier")
-                    .map(|n| node_text(n, content))
-                    .unwrap_or_default();
+    if node.kind() == "const_declaration" {
+        if let Some(decl) = extract_iota_enum(node, content, options) {
+            if options.visibility.allows(decl.visibility()) {
+                declarations.push(decl);
+            }
+            return;
+        }
+    }
 
-                let location =
-                    Location::new(child.start_position().row + 1, child.end_position().row + 1);
+    // Specs without a type or value repeat the previous ones (`iota` blocks).
+    // The grammar also tags the commas between names as `name`, hence the kind filters.
+    let mut last_type = String::new();
+    for spec in node.children(&mut node.walk()) {
+        if spec.kind() != "const_spec" && spec.kind() != "var_spec" {
+            continue;
+        }
+        let values: Vec<_> = spec
+            .child_by_field_name("value")
+            .map(|list| list.named_children(&mut list.walk()).collect())
+            .unwrap_or_default();
+        if let Some(ty) = spec.child_by_field_name("type") {
+            last_type = node_text(ty, content);
+        } else if !values.is_empty() || spec.kind() == "var_spec" {
+            last_type.clear();
+        }
 
-                declarations.push(Declaration::Const {
-                    name,
-                    ty,
-                    visibility,
-                    location,
-                });
+        let location = Location::new(spec.start_position().row + 1, spec.end_position().row + 1);
+        let names: Vec<_> = spec
+            .children_by_field_name("name", &mut spec.walk())
+            .filter(|n| n.kind() == "identifier")
+            .collect();
+        for (i, name_node) in names.into_iter().enumerate() {
+            let name = node_text(name_node, content);
+            let visibility = go_visibility(&name);
+            if !options.visibility.allows(visibility) {
+                continue;
             }
+
+            let ty = if last_type.is_empty() {
+                values
+                    .get(i)
+                    .and_then(|v| infer_literal_type(*v))
+                    .unwrap_or_default()
+                    .to_string()
+            } else {
+                last_type.clone()
+            };
+
+            declarations.push(Declaration::Const {
+                name,
+                ty,
+                visibility,
+                location,
+                is_static: false,
+                snippet: None,
+            });
         }
     }
 }
 
+/// `const ( Red Color = iota; Green; Blue )` as an enum named `Color`.
+fn extract_iota_enum(
+    node: tree_sitter::Node,
+    content: &str,
+    options: &ExtractOptions,
+) -> Option<Declaration> {
+    let specs: Vec<_> = node
+        .children(&mut node.walk())
+        .filter(|c| c.kind() == "const_spec")
+        .collect();
+    let first = specs.first()?;
+    let ty = first.child_by_field_name("type")?;
+    let value = first.child_by_field_name("value")?;
+    if !node_text(value, content).contains("iota") {
+        return None;
+    }
+    let name = node_text(ty, content);
+
+    let variants = specs
+        .iter()
+        .flat_map(|spec| {
+            spec.children_by_field_name("name", &mut spec.walk())
+                .filter(|n| n.kind() == "identifier")
+                .collect::<Vec<_>>()
+        })
+        .map(|n| node_text(n, content))
+        .filter(|n| options.visibility.allows(go_visibility(n)))
+        .map(|name| Variant { name, doc: None })
+        .collect();
+
+    let doc = if options.include_docs {
+        extract_comment(node, content)
+    } else {
+        None
+    };
+
+    Some(Declaration::Enum {
+        visibility: go_visibility(&name),
+        name,
+        generics: String::new(),
+        variants,
+        location: Location::new(node.start_position().row + 1, node.end_position().row + 1),
+        methods: Vec::new(),
+        implements: Vec::new(),
+        attributes: Vec::new(),
+        doc,
+        snippet: None,
+    })
+}
+
+/// Default type of an untyped constant's literal value.
+fn infer_literal_type(value: tree_sitter::Node) -> Option<&'static str> {
+    let ty = match value.kind() {
+        "int_literal" => "int",
+        "float_literal" => "float64",
+        "imaginary_literal" => "complex128",
+        "rune_literal" => "rune",
+        "interpreted_string_literal" | "raw_string_literal" => "string",
+        "true" | "false" => "bool",
+        _ => return None,
+    };
+    Some(ty)
+}
+
 fn extract_comment(node: tree_sitter::Node, content: &str) -> Option<String> {
     let mut prev = node.prev_sibling();
     let mut doc_lines = Vec::new();
@@ -389,6 +655,7 @@ fn go_visibility(name: &str) -> Visibility {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::codemap::VisibilityThreshold;
 
     #[test]
     fn test_extract_function() {
@@ -404,7 +671,7 @@ func Process(input string) (string, error) {
             include_docs: true,
             ..Default::default()
         };
-        let (_, decls) = extract(code, &opts).unwrap();
+        let (_, decls, _) = extract(code, &opts).unwrap();
         assert_eq!(decls.len(), 1);
 
         match &decls[0] {
@@ -432,7 +699,7 @@ type Config struct {
     Timeout int
 }
 "#;
-        let (_, decls) = extract(code, &ExtractOptions::default()).unwrap();
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
         assert_eq!(decls.len(), 1);
 
         match &decls[0] {
@@ -454,7 +721,7 @@ type Handler interface {
     Name() string
 }
 "#;
-        let (_, decls) = extract(code, &ExtractOptions::default()).unwrap();
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
         assert_eq!(decls.len(), 1);
 
         match &decls[0] {
@@ -482,7 +749,7 @@ import (
     "net/http"
 )
 "#;
-        let (imports, _) = extract(code, &ExtractOptions::default()).unwrap();
+        let (imports, _, _) = extract(code, &ExtractOptions::default()).unwrap();
         assert_eq!(imports.len(), 2);
         assert_eq!(imports[0].source, "fmt");
         assert_eq!(imports[1].source, "net/http");
@@ -497,10 +764,10 @@ func PublicFunc() {}
 func privateFunc() {}
 "#;
         let opts = ExtractOptions {
-            include_private: true,
+            visibility: VisibilityThreshold::All,
             ..Default::default()
         };
-        let (_, decls) = extract(code, &opts).unwrap();
+        let (_, decls, _) = extract(code, &opts).unwrap();
         assert_eq!(decls.len(), 2);
 
         assert_eq!(decls[0].visibility(), Visibility::Public);
@@ -516,7 +783,7 @@ func (h *Handler) Handle(req Request) Response {
     return Response{}
 }
 "#;
-        let (_, decls) = extract(code, &ExtractOptions::default()).unwrap();
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
         assert_eq!(decls.len(), 1);
 
         match &decls[0] {
@@ -529,4 +796,233 @@ func (h *Handler) Handle(req Request) Response {
             _ => panic!("expected function"),
         }
     }
+
+    #[test]
+    fn test_methods_merged_into_receiver_struct() {
+        let code = r"
+package main
+
+type Stack[T any] struct {
+    items []T
+}
+
+func (s *Stack[T]) Push(v T) {}
+
+func (s Stack[T]) Len() int { return 0 }
+
+func (c Celsius) String() string { return strconv.Itoa(int(c)) }
+";
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
+        assert_eq!(decls.len(), 2);
+
+        match &decls[0] {
+            Declaration::Struct { name, methods, .. } => {
+                assert_eq!(name, "Stack");
+                let names: Vec<&str> = methods.iter().map(Declaration::name).collect();
+                assert_eq!(names, ["Push", "Len"]);
+            }
+            _ => panic!("expected struct"),
+        }
+        assert_eq!(decls[1].name(), "String");
+    }
+
+    #[test]
+    fn test_generic_type_parameters() {
+        let code = r"
+package main
+
+type Stack[T any] struct {
+    items []T
+}
+
+type List[T comparable] []T
+";
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
+        assert_eq!(decls.len(), 2);
+
+        match &decls[0] {
+            Declaration::Struct { name, generics, .. } => {
+                assert_eq!(name, "Stack");
+                assert_eq!(generics, "[T any]");
+            }
+            _ => panic!("expected struct"),
+        }
+        match &decls[1] {
+            Declaration::TypeAlias {
+                generics, target, ..
+            } => {
+                assert_eq!(generics, "[T comparable]");
+                assert_eq!(target, "[]T");
+            }
+            _ => panic!("expected type alias"),
+        }
+    }
+
+    #[test]
+    fn test_skip_tests() {
+        let code = r"
+package main
+
+func Testify() {}
+
+func TestHandle(t *testing.T) {}
+
+func BenchmarkHandle(b *testing.B) {}
+";
+        let opts = ExtractOptions {
+            skip_tests: true,
+            ..Default::default()
+        };
+        let (_, decls, _) = extract(code, &opts).unwrap();
+        let names: Vec<&str> = decls.iter().map(Declaration::name).collect();
+        assert_eq!(names, ["Testify"]);
+    }
+
+    #[test]
+    fn test_embedded_fields_and_interfaces() {
+        let code = r"
+package main
+
+type Server struct {
+	*http.Server
+	sync.Mutex
+	Base[T]
+	Name, Alias string
+}
+
+type ReadCloser interface {
+	io.Reader
+	Close() error
+}
+";
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
+        match &decls[0] {
+            Declaration::Struct { fields, .. } => {
+                let fields: Vec<_> = fields
+                    .iter()
+                    .map(|f| (f.name.as_str(), f.ty.as_str(), f.modifiers.is_empty()))
+                    .collect();
+                assert_eq!(
+                    fields,
+                    [
+                        ("Server", "*http.Server", false),
+                        ("Mutex", "sync.Mutex", false),
+                        ("Base", "Base[T]", false),
+                        ("Name", "string", true),
+                        ("Alias", "string", true),
+                    ]
+                );
+            }
+            _ => panic!("expected struct"),
+        }
+        match &decls[1] {
+            Declaration::Interface { members, .. } => {
+                assert_eq!(members.as_slice(), ["io.Reader", "Close() error"]);
+            }
+            _ => panic!("expected interface"),
+        }
+    }
+
+    #[test]
+    fn test_struct_tags() {
+        let code = r#"
+package main
+
+type User struct {
+    ID        int64  `json:"id" gorm:"primaryKey"`
+    Name      string `json:"name,omitempty"`
+    CreatedAt time.Time
+}
+"#;
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
+        match &decls[0] {
+            Declaration::Struct { fields, .. } => {
+                let tags: Vec<Option<&str>> = fields.iter().map(|f| f.tag.as_deref()).collect();
+                assert_eq!(
+                    tags,
+                    [
+                        Some(r#"`json:"id" gorm:"primaryKey"`"#),
+                        Some(r#"`json:"name,omitempty"`"#),
+                        None,
+                    ]
+                );
+            }
+            _ => panic!("expected struct"),
+        }
+    }
+
+    #[test]
+    fn test_const_specs_and_iota_enums() {
+        let code = r#"
+package main
+
+const (
+    A, B = 1, 2.5
+    C int64 = 3
+    Name = "pith"
+)
+
+type Color int
+
+const (
+    Red Color = iota
+    Green
+    Blue
+)
+
+var X, Y float64
+"#;
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
+        let consts: Vec<(&str, &str)> = decls
+            .iter()
+            .filter_map(|d| match d {
+                Declaration::Const { name, ty, .. } => Some((name.as_str(), ty.as_str())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            consts,
+            [
+                ("A", "int"),
+                ("B", "float64"),
+                ("C", "int64"),
+                ("Name", "string"),
+                ("X", "float64"),
+                ("Y", "float64"),
+            ]
+        );
+
+        let enums: Vec<_> = decls.iter().filter(|d| d.name() == "Color").collect();
+        assert_eq!(enums.len(), 1);
+        match enums[0] {
+            Declaration::Enum { variants, .. } => {
+                let names: Vec<&str> = variants.iter().map(|v| v.name.as_str()).collect();
+                assert_eq!(names, ["Red", "Green", "Blue"]);
+            }
+            _ => panic!("expected enum"),
+        }
+    }
+
+    #[test]
+    fn test_iota_enum_before_its_type() {
+        let code = r"
+package main
+
+const (
+    Red Color = iota
+    Green
+)
+
+type Color int
+";
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
+        assert_eq!(decls.len(), 1);
+        match &decls[0] {
+            Declaration::Enum { name, variants, .. } => {
+                assert_eq!(name, "Color");
+                assert_eq!(variants.len(), 2);
+            }
+            _ => panic!("expected enum"),
+        }
+    }
 }
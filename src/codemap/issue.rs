@@ -0,0 +1,137 @@
+//! Parse issues: where a file could not be parsed, and why.
+//!
+//! tree-sitter recovers from syntax errors by wrapping the text it could not
+//! parse in `ERROR` nodes and inserting zero-width `MISSING` nodes for tokens
+//! it expected. Declarations outside those nodes are still extracted; the
+//! ranges are recorded so readers know which parts of the file may be
+//! missing declarations.
+
+use serde::{Deserialize, Serialize};
+use tree_sitter::Node;
+
+use super::Location;
+
+/// Longest stretch of unexpected source quoted in a message.
+const MAX_QUOTED: usize = 20;
+
+/// A spot in a file that could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseIssue {
+    /// 1-indexed line where the issue starts.
+    pub line: usize,
+    /// 1-indexed byte column where the issue starts.
+    pub column: usize,
+    /// 1-indexed last line of the unparsed range (inclusive).
+    pub end_line: usize,
+    /// 1-indexed notebook cell; lines are then relative to the cell.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cell: Option<usize>,
+    /// What went wrong (e.g., "missing `)`" or "unexpected `=> {`").
+    pub message: String,
+}
+
+impl ParseIssue {
+    /// An issue at a single point.
+    pub fn new(line: usize, column: usize, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            column,
+            end_line: line,
+            cell: None,
+            message: message.into(),
+        }
+    }
+
+    /// An issue with no better location than the whole file, reported at its
+    /// start.
+    pub fn whole_file(message: impl Into<String>) -> Self {
+        Self::new(1, 1, message)
+    }
+
+    /// Lines covered by the issue.
+    pub fn location(&self) -> Location {
+        Location::new(self.line, self.end_line).in_cell(self.cell)
+    }
+}
+
+impl std::fmt::Display for ParseIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(cell) = self.cell {
+            write!(f, "cell {}, ", cell)?;
+        }
+        write!(f, "line {}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+/// Collect the outermost `ERROR` nodes and every `MISSING` node under `root`,
+/// in source order.
+///
+/// When nothing at the top level parses, tree-sitter may make the root itself
+/// an `ERROR` node; its children are then searched instead, so the reported
+/// ranges stay small.
+pub(crate) fn collect_syntax_errors(root: Node, content: &str) -> Vec<ParseIssue> {
+    let mut errors = Vec::new();
+    if !root.has_error() {
+        return errors;
+    }
+    if root.is_error() {
+        let mut cursor = root.walk();
+        for child in root.children(&mut cursor) {
+            collect(child, content, &mut errors);
+        }
+        if errors.is_empty() {
+            errors.push(syntax_error(root, content));
+        }
+    } else {
+        collect(root, content, &mut errors);
+    }
+    errors
+}
+
+fn collect(node: Node, content: &str, errors: &mut Vec<ParseIssue>) {
+    if node.is_error() || node.is_missing() {
+        errors.push(syntax_error(node, content));
+        return;
+    }
+    if !node.has_error() {
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect(child, content, errors);
+    }
+}
+
+fn syntax_error(node: Node, content: &str) -> ParseIssue {
+    let start = node.start_position();
+    let end = node.end_position();
+    // An error ending at column 0 stops at the previous line's newline.
+    let end_line = if end.column == 0 && end.row > start.row {
+        end.row
+    } else {
+        end.row + 1
+    };
+
+    let message = if node.is_missing() {
+        format!("missing `{}`", node.kind())
+    } else {
+        let text = content[node.byte_range()].trim();
+        let first_line = text.lines().next().unwrap_or_default();
+        let quoted: String = first_line.chars().take(MAX_QUOTED).collect();
+        if quoted.is_empty() {
+            "unexpected input".to_string()
+        } else if quoted.len() < text.len() {
+            format!("unexpected `{}...`", quoted)
+        } else {
+            format!("unexpected `{}`", quoted)
+        }
+    };
+
+    ParseIssue {
+        line: start.row + 1,
+        column: start.column + 1,
+        end_line,
+        cell: None,
+        message,
+    }
+}
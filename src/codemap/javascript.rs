@@ -2,7 +2,7 @@
 //!
 //! Uses the TypeScript parser which handles JavaScript as well.
 
-use super::{Declaration, ExtractOptions, Import};
+use super::{ExtractOptions, Extracted};
 use crate::filter::Language;
 
 /// Extract imports and declarations from JavaScript/JSX source code.
@@ -13,7 +13,7 @@ pub fn extract(
     content: &str,
     language: Language,
     options: &ExtractOptions,
-) -> Result<(Vec<Import>, Vec<Declaration>), String> {
+) -> Result<Extracted, String> {
     // Use TypeScript extractor - it handles JS fine
     // Map JSX to TSX for proper JSX handling
     let ts_lang = match language {
@@ -24,9 +24,20 @@ pub fn extract(
     super::typescript::extract(content, ts_lang, options)
 }
 
+/// Test names from `describe`/`it`/`test` calls, via the TypeScript extractor.
+pub fn extract_test_names(content: &str, language: Language) -> Result<Vec<String>, String> {
+    let ts_lang = match language {
+        Language::Jsx => Language::Tsx,
+        _ => Language::TypeScript,
+    };
+
+    super::typescript::extract_test_names(content, ts_lang)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::codemap::Declaration;
 
     #[test]
     fn test_extract_js_function() {
@@ -35,7 +46,8 @@ export function greet(name) {
     return `Hello, ${name}`;
 }
 "#;
-        let (_, decls) = extract(code, Language::JavaScript, &ExtractOptions::default()).unwrap();
+        let (_, decls, _) =
+            extract(code, Language::JavaScript, &ExtractOptions::default()).unwrap();
         assert_eq!(decls.len(), 1);
         assert_eq!(decls[0].name(), "greet");
     }
@@ -49,7 +61,8 @@ export class Handler {
     }
 }
 "#;
-        let (_, decls) = extract(code, Language::JavaScript, &ExtractOptions::default()).unwrap();
+        let (_, decls, _) =
+            extract(code, Language::JavaScript, &ExtractOptions::default()).unwrap();
         assert_eq!(decls.len(), 1);
 
         match &decls[0] {
@@ -65,8 +78,37 @@ export class Handler {
         let code = r#"
 import { useState } from 'react';
 "#;
-        let (imports, _) = extract(code, Language::JavaScript, &ExtractOptions::default()).unwrap();
+        let (imports, _, _) =
+            extract(code, Language::JavaScript, &ExtractOptions::default()).unwrap();
         assert_eq!(imports.len(), 1);
         assert_eq!(imports[0].source, "react");
     }
+
+    #[test]
+    fn test_commonjs_require_and_exports() {
+        let code = r"
+const fs = require('fs');
+const { join, resolve: r } = require('path');
+function helper(a) {}
+module.exports = { helper, run: () => 1, version: '1.0' };
+exports.foo = async (a, b) => a;
+";
+        let (imports, decls, _) =
+            extract(code, Language::JavaScript, &ExtractOptions::default()).unwrap();
+        assert_eq!(imports.len(), 2);
+        assert_eq!(imports[0].source, "fs");
+        assert!(imports[0].items.is_empty());
+        assert_eq!(imports[1].source, "path");
+        assert_eq!(imports[1].items.as_slice(), ["join", "resolve"]);
+
+        let names: Vec<_> = decls.iter().map(Declaration::name).collect();
+        assert_eq!(names, vec!["helper", "run", "version", "foo"]);
+        match &decls[3] {
+            Declaration::Function { signature, .. } => {
+                assert_eq!(signature, "exports.foo = async (a, b) =>");
+            }
+            _ => panic!("expected function"),
+        }
+        assert!(matches!(decls[2], Declaration::Const { .. }));
+    }
 }
@@ -3,17 +3,25 @@
 //! Extracts API signatures (functions, structs, types) from source files
 //! without implementation bodies.
 
+mod doc;
+mod fingerprint;
 mod go;
+mod issue;
 mod javascript;
+mod notebook;
 mod python;
 mod rust;
+mod todo;
 mod typescript;
 
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
+use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
-use tree_sitter::{Node, Parser};
+use tree_sitter::{Node, Parser, Tree};
 
 // Thread-local parser caching to avoid re-initialization overhead.
 //
@@ -65,6 +73,7 @@ fn init_go_parser() -> Result<Parser, ()> {
 fn with_cached_parser<F, R>(
     cell: &'static std::thread::LocalKey<RefCell<Option<Parser>>>,
     init: fn() -> Result<Parser, ()>,
+    language: Language,
     f: F,
 ) -> Result<R, String>
 where
@@ -72,7 +81,10 @@ where
 {
     cell.with(|cell| {
         let mut slot = cell.borrow_mut();
-        if slot.is_none() {
+        if slot.is_some() {
+            tracing::debug!("parser cache hit: {}", language);
+        } else {
+            tracing::debug!("parser cache miss: initializing {} parser", language);
             *slot = Some(init().map_err(|()| "failed to initialize parser".to_string())?);
         }
 
@@ -88,7 +100,7 @@ pub(crate) fn with_rust_parser<F, R>(f: F) -> Result<R, String>
 where
     F: FnOnce(&mut Parser) -> R,
 {
-    with_cached_parser(&RUST_PARSER, init_rust_parser, f)
+    with_cached_parser(&RUST_PARSER, init_rust_parser, Language::Rust, f)
 }
 
 /// Execute a function with a cached TypeScript parser.
@@ -96,7 +108,7 @@ pub(crate) fn with_ts_parser<F, R>(f: F) -> Result<R, String>
 where
     F: FnOnce(&mut Parser) -> R,
 {
-    with_cached_parser(&TS_PARSER, init_ts_parser, f)
+    with_cached_parser(&TS_PARSER, init_ts_parser, Language::TypeScript, f)
 }
 
 /// Execute a function with a cached TSX parser.
@@ -104,7 +116,7 @@ pub(crate) fn with_tsx_parser<F, R>(f: F) -> Result<R, String>
 where
     F: FnOnce(&mut Parser) -> R,
 {
-    with_cached_parser(&TSX_PARSER, init_tsx_parser, f)
+    with_cached_parser(&TSX_PARSER, init_tsx_parser, Language::Tsx, f)
 }
 
 /// Execute a function with a cached Python parser.
@@ -112,7 +124,7 @@ pub(crate) fn with_python_parser<F, R>(f: F) -> Result<R, String>
 where
     F: FnOnce(&mut Parser) -> R,
 {
-    with_cached_parser(&PYTHON_PARSER, init_python_parser, f)
+    with_cached_parser(&PYTHON_PARSER, init_python_parser, Language::Python, f)
 }
 
 /// Execute a function with a cached Go parser.
@@ -120,7 +132,33 @@ pub(crate) fn with_go_parser<F, R>(f: F) -> Result<R, String>
 where
     F: FnOnce(&mut Parser) -> R,
 {
-    with_cached_parser(&GO_PARSER, init_go_parser, f)
+    with_cached_parser(&GO_PARSER, init_go_parser, Language::Go, f)
+}
+
+/// Parse `content`, giving up after `options.parse_timeout`.
+pub(crate) fn parse_source(
+    parser: &mut Parser,
+    content: &str,
+    options: &ExtractOptions,
+) -> Result<Tree, String> {
+    // 0 disables the timeout; the parser is cached, so always set it.
+    let micros = options.parse_timeout.map_or(0, |timeout| {
+        u64::try_from(timeout.as_micros())
+            .unwrap_or(u64::MAX)
+            .max(1)
+    });
+    parser.set_timeout_micros(micros);
+    let tree = parser.parse(content, None);
+    parser.set_timeout_micros(0);
+
+    tree.ok_or_else(|| {
+        // Without a reset, the next parse would resume this one.
+        parser.reset();
+        match options.parse_timeout {
+            Some(timeout) => format!("parse timed out after {:?}", timeout),
+            None => "failed to parse".to_string(),
+        }
+    })
 }
 
 /// Find a child node by kind.
@@ -135,10 +173,23 @@ pub(crate) fn node_text(node: Node, content: &str) -> String {
 
 use thiserror::Error;
 
-use crate::filter::Language;
+use crate::filter::{is_notebook, Language};
+use crate::license::is_license_text;
+use crate::tree::format_number;
+
+pub use doc::{normalize_doc, summarize_doc, DocSummary};
+pub(crate) use fingerprint::combined_fingerprint;
+// Each extractor records syntax errors with this, then recurses into the
+// `ERROR` nodes tree-sitter wraps unparsable text in, so complete
+// declarations inside them are still extracted.
+use issue::collect_syntax_errors;
+pub use issue::ParseIssue;
+pub use notebook::NotebookSource;
+pub use todo::TodoComment;
 
 /// Visibility of a declaration.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Visibility {
     Public,
     #[default]
@@ -160,13 +211,43 @@ impl std::fmt::Display for Visibility {
     }
 }
 
+/// Minimum visibility an item needs to appear in a codemap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VisibilityThreshold {
+    /// Public items only.
+    #[default]
+    Public,
+    /// Public and Rust `pub(crate)` items.
+    Crate,
+    /// Everything, including private items.
+    All,
+}
+
+impl VisibilityThreshold {
+    /// Check if an item with this visibility is shown.
+    pub fn allows(self, visibility: Visibility) -> bool {
+        match self {
+            VisibilityThreshold::Public => visibility == Visibility::Public,
+            VisibilityThreshold::Crate => {
+                matches!(visibility, Visibility::Public | Visibility::Crate)
+            }
+            VisibilityThreshold::All => true,
+        }
+    }
+}
+
 /// Source location of a declaration.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Location {
     /// 1-indexed start line.
     pub start_line: usize,
     /// 1-indexed end line (inclusive).
     pub end_line: usize,
+    /// 1-indexed notebook cell the lines belong to. Lines are then relative
+    /// to the cell.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cell: Option<usize>,
 }
 
 impl Location {
@@ -174,6 +255,7 @@ impl Location {
         Self {
             start_line,
             end_line,
+            cell: None,
         }
     }
 
@@ -181,68 +263,319 @@ impl Location {
         Self {
             start_line: line,
             end_line: line,
+            cell: None,
+        }
+    }
+
+    /// Attach a notebook cell.
+    pub fn in_cell(self, cell: Option<usize>) -> Self {
+        Self { cell, ..self }
+    }
+}
+
+/// Unique, stable IDs for the declarations of one codemap.
+///
+/// Each is [`Declaration::id`] with the path relative to the extraction
+/// root. Declarations that would share an ID are numbered in source order:
+/// the first keeps the plain ID and later ones get `:2`, `:3`, ..., so adding
+/// a duplicate below never renames the ones above it.
+///
+/// # Examples
+///
+/// ```
+/// use std::path::Path;
+/// use pith::codemap::{extract_codemap, DeclarationIds, ExtractOptions, VisibilityThreshold};
+/// use pith::filter::Language;
+///
+/// let options = ExtractOptions {
+///     visibility: VisibilityThreshold::All,
+///     ..ExtractOptions::default()
+/// };
+/// let codemap = extract_codemap(
+///     Path::new("project/main.go"),
+///     "package main\n\nfunc init() {}\n\nfunc init() {}\n",
+///     Language::Go,
+///     &options,
+/// );
+/// let ids = DeclarationIds::new(&codemap, Path::new("project"));
+/// assert_eq!(ids.id(&codemap.declarations[0], None), "main.go#init@function");
+/// assert_eq!(ids.id(&codemap.declarations[1], None), "main.go#init@function:2");
+/// ```
+#[derive(Debug, Clone)]
+pub struct DeclarationIds {
+    path: PathBuf,
+    /// Locations of the declarations sharing each ID, in source order.
+    shared: HashMap<String, Vec<Location>>,
+}
+
+impl DeclarationIds {
+    /// IDs for `codemap`, extracted from under `root`. An empty `root`
+    /// keeps paths as given.
+    pub fn new(codemap: &Codemap, root: &Path) -> Self {
+        let path = codemap
+            .path
+            .strip_prefix(root)
+            .ok()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(&codemap.path)
+            .components()
+            .filter(|c| *c != std::path::Component::CurDir)
+            .collect();
+        let mut ids = Self {
+            path,
+            shared: HashMap::new(),
+        };
+        for decl in &codemap.declarations {
+            ids.add(decl, None);
+        }
+        for locations in ids.shared.values_mut() {
+            locations.sort_by_key(|l| (l.cell, l.start_line, l.end_line));
+        }
+        ids.shared.retain(|_, locations| locations.len() > 1);
+        ids
+    }
+
+    fn add(&mut self, decl: &Declaration, container: Option<&str>) {
+        let id = decl.id(&self.path, container);
+        self.shared.entry(id).or_default().push(decl.location());
+        for child in decl.nested() {
+            self.add(child, Some(decl.name()));
+        }
+    }
+
+    /// File path the IDs use.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// ID of `decl`, nested in `container` if it is a method or member.
+    pub fn id(&self, decl: &Declaration, container: Option<&str>) -> String {
+        let id = decl.id(&self.path, container);
+        let ordinal = self
+            .shared
+            .get(&id)
+            .and_then(|locations| locations.iter().position(|l| *l == decl.location()));
+        match ordinal {
+            Some(i) if i > 0 => format!("{}:{}", id, i + 1),
+            _ => id,
         }
     }
 }
 
 /// A field in a struct or class.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Field {
     pub name: String,
     pub ty: String,
     pub visibility: Visibility,
+    /// Modifiers as written, in source order (TS `private`, `static`, `readonly`).
+    pub modifiers: Vec<String>,
+    /// Go struct tag as written, including backticks (`` `json:"name"` ``).
+    pub tag: Option<String>,
+    pub doc: Option<String>,
+}
+
+/// React component details for a function or class declaration.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Component {
+    /// Props type as written (e.g., "ButtonProps"), if annotated.
+    pub props: Option<String>,
+    /// Members of the props type when it is an inline object type or an
+    /// interface declared in the same file.
+    pub props_members: Vec<String>,
+}
+
+/// Size and complexity of a function body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Metrics {
+    /// Lines spanned by the body, including braces.
+    pub lines: usize,
+    /// Cyclomatic complexity estimate: 1 plus the number of branch points
+    /// (conditionals, loops, match/switch arms, catch clauses, `&&`/`||`).
+    pub complexity: usize,
+}
+
+/// Node kinds that add a branch, across all supported grammars.
+const BRANCH_KINDS: &[&str] = &[
+    // Rust
+    "if_expression",
+    "while_expression",
+    "for_expression",
+    "match_arm",
+    // TypeScript/JavaScript, Go and Python
+    "if_statement",
+    "for_statement",
+    "for_in_statement",
+    "while_statement",
+    "do_statement",
+    "switch_case",
+    "catch_clause",
+    "ternary_expression",
+    "expression_case",
+    "type_case",
+    "communication_case",
+    "elif_clause",
+    "except_clause",
+    "case_clause",
+    "conditional_expression",
+    "boolean_operator",
+    "if_clause",
+];
+
+/// Metrics for a function node's `body` field, if it has one.
+pub(crate) fn function_metrics(node: Node) -> Option<Metrics> {
+    let body = node.child_by_field_name("body")?;
+    Some(Metrics {
+        lines: body.end_position().row - body.start_position().row + 1,
+        complexity: 1 + count_branches(body),
+    })
+}
+
+fn count_branches(node: Node) -> usize {
+    let is_branch = BRANCH_KINDS.contains(&node.kind())
+        || (node.kind() == "binary_expression"
+            && node
+                .child_by_field_name("operator")
+                .is_some_and(|op| matches!(op.kind(), "&&" | "||" | "??")));
+
+    usize::from(is_branch)
+        + node
+            .children(&mut node.walk())
+            .map(count_branches)
+            .sum::<usize>()
+}
+
+/// A variant of an enum.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Variant {
+    /// Variant as written, including any payload (e.g., "Error(String)").
+    pub name: String,
+    pub doc: Option<String>,
+}
+
+/// How a struct declares its fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StructKind {
+    /// Named fields (`struct Point { x: i32 }`).
+    #[default]
+    Named,
+    /// Positional fields (`struct Meters(f64)`), named `0`, `1`, ...
+    Tuple,
+    /// No fields (`struct Marker;`).
+    Unit,
+    /// Named fields sharing storage (Rust `union`).
+    Union,
+}
+
+impl std::fmt::Display for StructKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StructKind::Named => write!(f, "named"),
+            StructKind::Tuple => write!(f, "tuple"),
+            StructKind::Unit => write!(f, "unit"),
+            StructKind::Union => write!(f, "union"),
+        }
+    }
 }
 
 /// A declaration extracted from source code.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Declaration {
     /// A function or method declaration (Rust fn, Python def, Go func, JS/TS function).
     Function {
         name: String,
+        /// Enclosing module, namespace or impl the name is qualified with
+        /// (Rust `net::http`, TS `Geometry.Shapes`); `None` at the top level.
+        scope: Option<String>,
         signature: String,
         visibility: Visibility,
         location: Location,
         is_async: bool,
+        /// Overload signatures preceding the implementation (TS), in source order.
+        overloads: Vec<String>,
+        /// Set for React function components.
+        component: Option<Component>,
+        /// Body size and complexity; `None` for bodiless signatures.
+        metrics: Option<Metrics>,
+        /// Rust attributes (`#[cfg(...)]`) or Python decorators (`@property`), as written.
+        attributes: Vec<String>,
         doc: Option<String>,
+        /// Source excerpt, when requested via `ExtractOptions::include_snippet`.
+        snippet: Option<String>,
     },
     /// A struct declaration with fields and optional methods (Rust struct, Go struct).
     Struct {
         name: String,
-        fields: SmallVec<[Field; 6]>,
+        /// Generic parameters, bounds and where clause as written (`<T: Clone> where T: Debug`).
+        generics: String,
+        kind: StructKind,
+        fields: SmallVec<[Field; 4]>,
         visibility: Visibility,
         location: Location,
         methods: Vec<Declaration>, // Vec needed for recursive type
+        /// Traits implemented for this type (Rust `impl Trait for Type`).
+        implements: Vec<String>,
+        /// Attributes such as `#[derive(...)]` and `#[cfg(...)]`, as written.
+        attributes: Vec<String>,
         doc: Option<String>,
+        /// Source excerpt, when requested via `ExtractOptions::include_snippet`.
+        snippet: Option<String>,
     },
     /// An enum declaration with variants (Rust enum).
     Enum {
         name: String,
-        variants: SmallVec<[String; 6]>,
+        /// Generic parameters, bounds and where clause as written (`<T: Clone> where T: Debug`).
+        generics: String,
+        variants: SmallVec<[Variant; 6]>,
         visibility: Visibility,
         location: Location,
+        methods: Vec<Declaration>, // Vec needed for recursive type
+        /// Traits implemented for this type (Rust `impl Trait for Type`).
+        implements: Vec<String>,
+        /// Attributes such as `#[derive(...)]` and `#[cfg(...)]`, as written.
+        attributes: Vec<String>,
         doc: Option<String>,
+        /// Source excerpt, when requested via `ExtractOptions::include_snippet`.
+        snippet: Option<String>,
     },
     /// A trait declaration with method signatures (Rust trait).
     Trait {
         name: String,
+        /// Generic parameters, bounds and where clause as written (`<T: Clone> where T: Debug`).
+        generics: String,
+        /// Method signatures (required and default), associated types and consts.
         methods: SmallVec<[String; 8]>,
         visibility: Visibility,
         location: Location,
+        /// Attributes such as `#[derive(...)]` and `#[cfg(...)]`, as written.
+        attributes: Vec<String>,
         doc: Option<String>,
+        /// Source excerpt, when requested via `ExtractOptions::include_snippet`.
+        snippet: Option<String>,
     },
     /// A type alias (Rust type, Go type, TS type).
     TypeAlias {
         name: String,
+        /// Generic parameters, bounds and where clause as written (`<T: Clone> where T: Debug`).
+        generics: String,
         target: String,
         visibility: Visibility,
         location: Location,
+        /// Source excerpt, when requested via `ExtractOptions::include_snippet`.
+        snippet: Option<String>,
     },
-    /// A constant declaration (Rust const, Go const).
+    /// A constant declaration (Rust const/static, Go const).
     Const {
         name: String,
         ty: String,
         visibility: Visibility,
         location: Location,
+        /// Rust `static` rather than `const`.
+        is_static: bool,
+        /// Source excerpt, when requested via `ExtractOptions::include_snippet`.
+        snippet: Option<String>,
     },
     /// An interface declaration (Go interface, TS interface).
     Interface {
@@ -251,14 +584,41 @@ pub enum Declaration {
         visibility: Visibility,
         location: Location,
         doc: Option<String>,
+        /// Source excerpt, when requested via `ExtractOptions::include_snippet`.
+        snippet: Option<String>,
     },
     /// A class declaration with members (Python class, JS/TS class).
     Class {
         name: String,
+        /// Type parameters as written (e.g., "<T extends object>"), or empty.
+        generics: String,
+        /// Base class expressions (TS `extends Base<T>`).
+        extends: Vec<String>,
+        /// Implemented interfaces (TS `implements Store<T>`).
+        implements: Vec<String>,
+        /// Declared `abstract`.
+        is_abstract: bool,
+        /// Declared attributes (Python dataclass/attrs fields).
+        fields: SmallVec<[Field; 4]>,
         members: Vec<Declaration>, // Vec needed for recursive type
+        /// Set for React class components.
+        component: Option<Component>,
         visibility: Visibility,
         location: Location,
         doc: Option<String>,
+        /// Source excerpt, when requested via `ExtractOptions::include_snippet`.
+        snippet: Option<String>,
+    },
+    /// A re-export of items from another module (Rust `pub use`).
+    ReExport {
+        /// Module path the items come from (e.g., "crate::config").
+        name: String,
+        /// Re-exported items as written. Empty for `pub use foo::*` style globs.
+        items: SmallVec<[String; 4]>,
+        visibility: Visibility,
+        location: Location,
+        /// Source excerpt, when requested via `ExtractOptions::include_snippet`.
+        snippet: Option<String>,
     },
 }
 
@@ -274,6 +634,142 @@ impl Declaration {
             Declaration::Const { name, .. } => name,
             Declaration::Interface { name, .. } => name,
             Declaration::Class { name, .. } => name,
+            Declaration::ReExport { name, .. } => name,
+        }
+    }
+
+    /// Kind name as used in JSON output ("function", "struct", "static", ...).
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Declaration::Function { .. } => "function",
+            Declaration::Struct { .. } => "struct",
+            Declaration::Enum { .. } => "enum",
+            Declaration::Trait { .. } => "trait",
+            Declaration::TypeAlias { .. } => "type_alias",
+            Declaration::Const { is_static, .. } => {
+                if *is_static {
+                    "static"
+                } else {
+                    "const"
+                }
+            }
+            Declaration::Interface { .. } => "interface",
+            Declaration::Class { .. } => "class",
+            Declaration::ReExport { .. } => "re_export",
+        }
+    }
+
+    /// Stable identifier, `path#container::name@kind` (or `path#name@kind` at
+    /// the top level). Unlike line numbers, it survives edits elsewhere in the
+    /// file, so tools and follow-up prompts can refer to a symbol across runs.
+    /// A leading `./` is dropped from `path`. Re-exports are named after the
+    /// items they export (`Config,ConfigError` for
+    /// `pub use crate::config::{Config, Error as ConfigError}`), globs after
+    /// their module.
+    ///
+    /// Two declarations of one file can share this ID (two Go `init`
+    /// functions, a Python property and its setter); [`DeclarationIds`] tells
+    /// them apart.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::path::Path;
+    /// use pith::codemap::{extract_codemap, ExtractOptions};
+    /// use pith::filter::Language;
+    ///
+    /// let codemap = extract_codemap(
+    ///     Path::new("src/lib.rs"),
+    ///     "pub fn run() {}\n",
+    ///     Language::Rust,
+    ///     &ExtractOptions::default(),
+    /// );
+    /// let run = &codemap.declarations[0];
+    /// assert_eq!(run.id(Path::new("./src/lib.rs"), None), "src/lib.rs#run@function");
+    /// assert_eq!(run.id(Path::new("src/lib.rs"), Some("App")), "src/lib.rs#App::run@function");
+    /// ```
+    pub fn id(&self, path: &Path, container: Option<&str>) -> String {
+        let path: PathBuf = path
+            .components()
+            .filter(|c| *c != std::path::Component::CurDir)
+            .collect();
+        let name = match self {
+            Declaration::ReExport { items, .. } if !items.is_empty() => {
+                // `Error as ConfigError` and TypeScript's `type A` export
+                // their last word; a glob has only its module to go by.
+                let exported: Vec<&str> = items
+                    .iter()
+                    .filter_map(|item| item.split_whitespace().last())
+                    .map(|word| if word == "*" { self.name() } else { word })
+                    .collect();
+                exported.join(",")
+            }
+            _ => self.name().to_string(),
+        };
+        match container {
+            Some(container) => {
+                format!("{}#{}::{}@{}", path.display(), container, name, self.kind())
+            }
+            None => format!("{}#{}@{}", path.display(), name, self.kind()),
+        }
+    }
+
+    /// Methods and members nested in this declaration, which are named by it
+    /// as their container.
+    pub(crate) fn nested(&self) -> &[Declaration] {
+        match self {
+            Declaration::Struct { methods, .. } | Declaration::Enum { methods, .. } => methods,
+            Declaration::Class { members, .. } => members,
+            _ => &[],
+        }
+    }
+
+    /// Get a mutable reference to the name, used to qualify nested declarations.
+    pub(crate) fn name_mut(&mut self) -> &mut String {
+        match self {
+            Declaration::Function { name, .. } => name,
+            Declaration::Struct { name, .. } => name,
+            Declaration::Enum { name, .. } => name,
+            Declaration::Trait { name, .. } => name,
+            Declaration::TypeAlias { name, .. } => name,
+            Declaration::Const { name, .. } => name,
+            Declaration::Interface { name, .. } => name,
+            Declaration::Class { name, .. } => name,
+            Declaration::ReExport { name, .. } => name,
+        }
+    }
+
+    /// Qualify the name with an enclosing scope (`net::http` + `::` + `get`).
+    /// Functions also record the scope, so output can name it without
+    /// splitting the qualified name.
+    pub(crate) fn qualify(&mut self, scope: &str, separator: &str) {
+        let name = self.name_mut();
+        *name = format!("{}{}{}", scope, separator, name);
+        if let Declaration::Function {
+            scope: function_scope,
+            ..
+        } = self
+        {
+            *function_scope = Some(match function_scope.take() {
+                Some(inner) => format!("{}{}{}", scope, separator, inner),
+                None => scope.to_string(),
+            });
+        }
+    }
+
+    /// Get a mutable reference to the visibility, used to cap items nested
+    /// in a less visible module.
+    pub(crate) fn visibility_mut(&mut self) -> &mut Visibility {
+        match self {
+            Declaration::Function { visibility, .. } => visibility,
+            Declaration::Struct { visibility, .. } => visibility,
+            Declaration::Enum { visibility, .. } => visibility,
+            Declaration::Trait { visibility, .. } => visibility,
+            Declaration::TypeAlias { visibility, .. } => visibility,
+            Declaration::Const { visibility, .. } => visibility,
+            Declaration::Interface { visibility, .. } => visibility,
+            Declaration::Class { visibility, .. } => visibility,
+            Declaration::ReExport { visibility, .. } => visibility,
         }
     }
 
@@ -288,6 +784,7 @@ impl Declaration {
             Declaration::Const { visibility, .. } => *visibility,
             Declaration::Interface { visibility, .. } => *visibility,
             Declaration::Class { visibility, .. } => *visibility,
+            Declaration::ReExport { visibility, .. } => *visibility,
         }
     }
 
@@ -302,6 +799,7 @@ impl Declaration {
             Declaration::Const { location, .. } => *location,
             Declaration::Interface { location, .. } => *location,
             Declaration::Class { location, .. } => *location,
+            Declaration::ReExport { location, .. } => *location,
         }
     }
 
@@ -309,10 +807,82 @@ impl Declaration {
     pub fn is_public(&self) -> bool {
         matches!(self.visibility(), Visibility::Public)
     }
+
+    /// Source excerpt attached by `ExtractOptions::include_snippet`.
+    pub fn snippet(&self) -> Option<&str> {
+        match self {
+            Declaration::Function { snippet, .. }
+            | Declaration::Struct { snippet, .. }
+            | Declaration::Enum { snippet, .. }
+            | Declaration::Trait { snippet, .. }
+            | Declaration::TypeAlias { snippet, .. }
+            | Declaration::Const { snippet, .. }
+            | Declaration::Interface { snippet, .. }
+            | Declaration::Class { snippet, .. }
+            | Declaration::ReExport { snippet, .. } => snippet.as_deref(),
+        }
+    }
+
+    /// Doc comment, when extracted with `ExtractOptions::include_docs`.
+    pub fn doc(&self) -> Option<&str> {
+        match self {
+            Declaration::Function { doc, .. }
+            | Declaration::Struct { doc, .. }
+            | Declaration::Enum { doc, .. }
+            | Declaration::Trait { doc, .. }
+            | Declaration::Interface { doc, .. }
+            | Declaration::Class { doc, .. } => doc.as_deref(),
+            Declaration::TypeAlias { .. }
+            | Declaration::Const { .. }
+            | Declaration::ReExport { .. } => None,
+        }
+    }
+
+    fn location_mut(&mut self) -> &mut Location {
+        match self {
+            Declaration::Function { location, .. }
+            | Declaration::Struct { location, .. }
+            | Declaration::Enum { location, .. }
+            | Declaration::Trait { location, .. }
+            | Declaration::TypeAlias { location, .. }
+            | Declaration::Const { location, .. }
+            | Declaration::Interface { location, .. }
+            | Declaration::Class { location, .. }
+            | Declaration::ReExport { location, .. } => location,
+        }
+    }
+
+    fn doc_mut(&mut self) -> Option<&mut Option<String>> {
+        match self {
+            Declaration::Function { doc, .. }
+            | Declaration::Struct { doc, .. }
+            | Declaration::Enum { doc, .. }
+            | Declaration::Trait { doc, .. }
+            | Declaration::Interface { doc, .. }
+            | Declaration::Class { doc, .. } => Some(doc),
+            Declaration::TypeAlias { .. }
+            | Declaration::Const { .. }
+            | Declaration::ReExport { .. } => None,
+        }
+    }
+
+    fn snippet_mut(&mut self) -> &mut Option<String> {
+        match self {
+            Declaration::Function { snippet, .. }
+            | Declaration::Struct { snippet, .. }
+            | Declaration::Enum { snippet, .. }
+            | Declaration::Trait { snippet, .. }
+            | Declaration::TypeAlias { snippet, .. }
+            | Declaration::Const { snippet, .. }
+            | Declaration::Interface { snippet, .. }
+            | Declaration::Class { snippet, .. }
+            | Declaration::ReExport { snippet, .. } => snippet,
+        }
+    }
 }
 
 /// An import statement.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Import {
     /// Module path (e.g., "std::collections" or "react").
     pub source: String,
@@ -320,8 +890,12 @@ pub struct Import {
     pub items: SmallVec<[String; 4]>,
 }
 
+/// Imports, declarations and syntax errors a language extractor found in
+/// one source.
+pub(crate) type Extracted = (Vec<Import>, Vec<Declaration>, Vec<ParseIssue>);
+
 /// Extracted codemap from a source file.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Codemap {
     /// Path to the source file.
     pub path: PathBuf,
@@ -331,8 +905,21 @@ pub struct Codemap {
     pub imports: SmallVec<[Import; 8]>,
     /// Extracted declarations.
     pub declarations: SmallVec<[Declaration; 16]>,
-    /// Parse error if extraction failed.
-    pub parse_error: Option<String>,
+    /// Where the file could not be parsed, in source order. Declarations
+    /// around syntax errors are still extracted; ones inside them may be
+    /// missing. A file that could not be parsed at all has no declarations.
+    #[serde(default)]
+    pub parse_errors: Vec<ParseIssue>,
+    /// Declaration-only TypeScript file (`.d.ts`).
+    pub is_type_definition: bool,
+    /// `TODO`/`FIXME`/`HACK` comments, when requested.
+    pub todos: Vec<TodoComment>,
+    /// Test names (`tests::it_works`, `TestApi.test_get`, `parser > parses`), when requested.
+    pub tests: Vec<String>,
+    /// Tokens in the codemap as rendered in output. Counted by
+    /// [`crate::builder::Pith`]; 0 otherwise.
+    #[serde(default)]
+    pub token_count: usize,
 }
 
 impl Codemap {
@@ -343,18 +930,26 @@ impl Codemap {
             language,
             imports: SmallVec::new(),
             declarations: SmallVec::new(),
-            parse_error: None,
+            parse_errors: Vec::new(),
+            is_type_definition: false,
+            todos: Vec::new(),
+            tests: Vec::new(),
+            token_count: 0,
         }
     }
 
-    /// Create a codemap with a parse error.
-    pub fn with_error(path: PathBuf, language: Language, error: String) -> Self {
+    /// Create a codemap for a file that could not be parsed.
+    pub fn with_error(path: PathBuf, language: Language, error: ParseIssue) -> Self {
         Self {
             path,
             language,
             imports: SmallVec::new(),
             declarations: SmallVec::new(),
-            parse_error: Some(error),
+            parse_errors: vec![error],
+            is_type_definition: false,
+            todos: Vec::new(),
+            tests: Vec::new(),
+            token_count: 0,
         }
     }
 
@@ -367,7 +962,9 @@ impl Codemap {
     pub fn declaration_count(&self) -> usize {
         fn count_nested(decl: &Declaration) -> usize {
             let nested = match decl {
-                Declaration::Struct { methods, .. } => methods.iter().map(count_nested).sum(),
+                Declaration::Struct { methods, .. } | Declaration::Enum { methods, .. } => {
+                    methods.iter().map(count_nested).sum()
+                }
                 Declaration::Class { members, .. } => members.iter().map(count_nested).sum(),
                 _ => 0,
             };
@@ -377,27 +974,139 @@ impl Codemap {
     }
 }
 
+/// A declaration kind, or group of kinds, to keep with `ExtractOptions::only`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KindFilter {
+    /// Free functions (methods stay with their struct or class).
+    Functions,
+    /// All type definitions: structs, enums, traits, type aliases, interfaces and classes.
+    Types,
+    Structs,
+    Enums,
+    Traits,
+    TypeAliases,
+    /// Constants and statics.
+    Constants,
+    Interfaces,
+    Classes,
+    ReExports,
+}
+
+impl KindFilter {
+    /// Whether `decl` is of this kind.
+    pub fn matches(self, decl: &Declaration) -> bool {
+        match self {
+            KindFilter::Functions => matches!(decl, Declaration::Function { .. }),
+            KindFilter::Types => matches!(
+                decl,
+                Declaration::Struct { .. }
+                    | Declaration::Enum { .. }
+                    | Declaration::Trait { .. }
+                    | Declaration::TypeAlias { .. }
+                    | Declaration::Interface { .. }
+                    | Declaration::Class { .. }
+            ),
+            KindFilter::Structs => matches!(decl, Declaration::Struct { .. }),
+            KindFilter::Enums => matches!(decl, Declaration::Enum { .. }),
+            KindFilter::Traits => matches!(decl, Declaration::Trait { .. }),
+            KindFilter::TypeAliases => matches!(decl, Declaration::TypeAlias { .. }),
+            KindFilter::Constants => matches!(decl, Declaration::Const { .. }),
+            KindFilter::Interfaces => matches!(decl, Declaration::Interface { .. }),
+            KindFilter::Classes => matches!(decl, Declaration::Class { .. }),
+            KindFilter::ReExports => matches!(decl, Declaration::ReExport { .. }),
+        }
+    }
+}
+
 /// Options for codemap extraction.
 #[derive(Debug, Clone, Default)]
 pub struct ExtractOptions {
     /// Include doc comments.
     pub include_docs: bool,
-    /// Include private items.
-    pub include_private: bool,
+    /// Minimum visibility of extracted items.
+    pub visibility: VisibilityThreshold,
+    /// Drop test code: Rust `#[cfg(test)]` items and `#[test]` functions,
+    /// Python `test_*` functions and `Test*` classes, Go `TestXxx(t *testing.T)` functions
+    /// and whole `*_test.go` files.
+    pub skip_tests: bool,
+    /// Collect `TODO`, `FIXME` and `HACK` comments into `Codemap::todos`.
+    pub include_todos: bool,
+    /// List test names per file into `Codemap::tests`.
+    pub list_tests: bool,
+    /// Attach up to this many lines of each declaration's source (body
+    /// included) as its `snippet`.
+    pub include_snippet: Option<usize>,
+    /// Attach the full source of functions spanning at most this many lines
+    /// as their `snippet`, regardless of `include_snippet`.
+    pub inline_bodies: Option<usize>,
+    /// Keep only top-level declarations matching one of these kinds; empty keeps all.
+    pub only: Vec<KindFilter>,
+    /// Drop doc comments that are copyright or license notices.
+    pub strip_license_docs: bool,
+    /// Rewrite Python docstrings and JSDoc comments as a summary line plus
+    /// parameter names and return value (see [`normalize_doc`]).
+    pub normalize_docs: bool,
+    /// How much of each doc comment to keep.
+    pub doc_summary: DocSummary,
+    /// Don't parse sources larger than this many bytes; their codemap only
+    /// carries a parse error.
+    pub max_parse_bytes: Option<usize>,
+    /// Give up parsing a source after this long; its codemap only carries a
+    /// parse error.
+    pub parse_timeout: Option<Duration>,
 }
 
 impl ExtractOptions {
     pub fn with_docs() -> Self {
         Self {
             include_docs: true,
-            include_private: true,
+            visibility: VisibilityThreshold::All,
+            skip_tests: false,
+            include_todos: false,
+            list_tests: false,
+            include_snippet: None,
+            inline_bodies: None,
+            only: Vec::new(),
+            strip_license_docs: false,
+            normalize_docs: false,
+            doc_summary: DocSummary::Full,
+            max_parse_bytes: None,
+            parse_timeout: None,
         }
     }
 
+    /// Whether `skip_tests` drops the file at `path` whole: Go `*_test.go`
+    /// files only hold tests and their helpers.
+    pub fn skips_file(&self, path: &Path, language: Language) -> bool {
+        self.skip_tests && language == Language::Go && is_test_file(path, language)
+    }
+
+    /// The parse error for a `len`-byte source over `max_parse_bytes`.
+    pub(crate) fn size_limit_error(&self, len: usize) -> Option<ParseIssue> {
+        let limit = self.max_parse_bytes.filter(|&limit| len > limit)?;
+        Some(ParseIssue::whole_file(format!(
+            "{} bytes is over the {}-byte parse limit",
+            format_number(len),
+            format_number(limit)
+        )))
+    }
+
     pub fn public_only() -> Self {
         Self {
             include_docs: false,
-            include_private: false,
+            visibility: VisibilityThreshold::Public,
+            skip_tests: false,
+            include_todos: false,
+            list_tests: false,
+            include_snippet: None,
+            inline_bodies: None,
+            only: Vec::new(),
+            strip_license_docs: false,
+            normalize_docs: false,
+            doc_summary: DocSummary::Full,
+            max_parse_bytes: None,
+            parse_timeout: None,
         }
     }
 }
@@ -433,17 +1142,55 @@ pub enum CodemapError {
 ///
 /// # Returns
 ///
-/// A `Codemap` with extracted declarations. If parsing fails,
-/// the codemap will have `parse_error` set but may still contain
-/// partial results.
+/// A `Codemap` with extracted declarations. Syntax errors don't fail
+/// extraction: declarations around them are extracted and the unparsed
+/// ranges are listed in `parse_errors`. If the file cannot be parsed at all,
+/// `parse_errors` holds the reason and there are no declarations.
+///
+/// Jupyter notebooks (`.ipynb` as Python) are parsed as notebook JSON; their
+/// code cells are extracted and locations carry the cell they came from.
 pub fn extract_codemap(
     path: &Path,
     content: &str,
     language: Language,
     options: &ExtractOptions,
 ) -> Codemap {
-    let mut codemap = Codemap::empty(path.to_path_buf(), language);
+    let start = Instant::now();
+    let codemap = if let Some(issue) = options.size_limit_error(content.len()) {
+        Codemap::with_error(path.to_path_buf(), language, issue)
+    } else if language == Language::Python && is_notebook(path) {
+        notebook::extract(path, content, options)
+    } else {
+        extract_source(path, content, language, options)
+    };
+    match codemap.parse_errors.first() {
+        Some(first) => tracing::warn!(
+            "{} parse error(s) in {}, first at {}",
+            codemap.parse_errors.len(),
+            path.display(),
+            first
+        ),
+        None => tracing::debug!(
+            "parsed {} ({}) in {:.2?}",
+            path.display(),
+            language,
+            start.elapsed()
+        ),
+    }
+    codemap
+}
 
+fn extract_source(
+    path: &Path,
+    content: &str,
+    language: Language,
+    options: &ExtractOptions,
+) -> Codemap {
+    let mut codemap = Codemap::empty(path.to_path_buf(), language);
+    codemap.is_type_definition = typescript::is_type_definition_file(path);
+    if options.include_todos {
+        codemap.todos = todo::collect_todos(content, language);
+    }
     let result = match language {
         Language::Rust => rust::extract(content, options),
         Language::TypeScript | Language::Tsx => typescript::extract(content, language, options),
@@ -453,18 +1200,195 @@ pub fn extract_codemap(
     };
 
     match result {
-        Ok((imports, declarations)) => {
+        Ok((imports, declarations, parse_errors)) => {
+            // Test names need a second parse; skip it for sources that
+            // failed, or timed out, the first time.
+            if options.list_tests {
+                codemap.tests = extract_test_names(path, content, language).unwrap_or_default();
+            }
+            codemap.parse_errors = parse_errors;
             codemap.imports = imports.into();
             codemap.declarations = declarations.into();
+            if !options.only.is_empty() {
+                codemap
+                    .declarations
+                    .retain(|decl| options.only.iter().any(|kind| kind.matches(decl)));
+            }
+            if options.include_snippet.is_some() || options.inline_bodies.is_some() {
+                let lines: Vec<&str> = content.lines().collect();
+                attach_snippets(&mut codemap.declarations, &lines, options);
+            }
+            if options.strip_license_docs {
+                for_each_doc(&mut codemap.declarations, &mut |doc| {
+                    if doc.as_deref().is_some_and(is_license_text) {
+                        *doc = None;
+                    }
+                });
+            }
+            // Rust and Go docs are Markdown and plain prose, without these
+            // conventions.
+            let docstrings = matches!(
+                language,
+                Language::Python
+                    | Language::TypeScript
+                    | Language::Tsx
+                    | Language::JavaScript
+                    | Language::Jsx
+            );
+            if options.normalize_docs && docstrings {
+                for_each_doc(&mut codemap.declarations, &mut |doc| {
+                    if let Some(text) = doc {
+                        *text = normalize_doc(text);
+                    }
+                });
+            }
+            if options.doc_summary != DocSummary::Full {
+                for_each_doc(&mut codemap.declarations, &mut |doc| {
+                    if let Some(text) = doc {
+                        *text = summarize_doc(text, options.doc_summary);
+                    }
+                });
+            }
         }
         Err(e) => {
-            codemap.parse_error = Some(e);
+            codemap.parse_errors = vec![ParseIssue::whole_file(e)];
         }
     }
 
     codemap
 }
 
+/// Call `f` on the doc comment of each declaration, field and variant,
+/// recursing into methods and class members.
+fn for_each_doc(declarations: &mut [Declaration], f: &mut impl FnMut(&mut Option<String>)) {
+    for decl in declarations {
+        if let Some(doc) = decl.doc_mut() {
+            f(doc);
+        }
+        match decl {
+            Declaration::Struct {
+                fields, methods, ..
+            } => {
+                fields.iter_mut().for_each(|field| f(&mut field.doc));
+                for_each_doc(methods, f);
+            }
+            Declaration::Enum {
+                variants, methods, ..
+            } => {
+                variants.iter_mut().for_each(|variant| f(&mut variant.doc));
+                for_each_doc(methods, f);
+            }
+            Declaration::Class {
+                fields, members, ..
+            } => {
+                fields.iter_mut().for_each(|field| f(&mut field.doc));
+                for_each_doc(members, f);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Set each declaration's snippet, recursing into methods and class members.
+/// Small functions get their whole source; everything else is cut at
+/// `include_snippet` lines, if set.
+fn attach_snippets(declarations: &mut [Declaration], lines: &[&str], options: &ExtractOptions) {
+    for decl in declarations {
+        let location = decl.location();
+        let span = location.end_line - location.start_line + 1;
+        let is_small_function = matches!(
+            decl,
+            Declaration::Function {
+                metrics: Some(_),
+                ..
+            }
+        ) && options.inline_bodies.is_some_and(|limit| span <= limit);
+
+        let max_lines = if is_small_function {
+            Some(span)
+        } else {
+            options.include_snippet
+        };
+        if let Some(max_lines) = max_lines {
+            *decl.snippet_mut() = source_excerpt(lines, location, max_lines);
+        }
+
+        match decl {
+            Declaration::Struct { methods, .. } | Declaration::Enum { methods, .. } => {
+                attach_snippets(methods, lines, options);
+            }
+            Declaration::Class { members, .. } => attach_snippets(members, lines, options),
+            _ => {}
+        }
+    }
+}
+
+/// Up to `max_lines` lines of `location`, dedented, with a trailing `...`
+/// line when cut short.
+fn source_excerpt(lines: &[&str], location: Location, max_lines: usize) -> Option<String> {
+    let start = location.start_line.checked_sub(1)?;
+    let end = location.end_line.min(lines.len());
+    let taken = lines.get(start..end.min(start + max_lines))?;
+    if taken.is_empty() {
+        return None;
+    }
+
+    let indent = taken
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let mut excerpt: Vec<&str> = taken
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or_else(|| line.trim_start()))
+        .collect();
+    if start + taken.len() < end {
+        excerpt.push("...");
+    }
+    Some(excerpt.join("\n"))
+}
+
+/// Files that only hold tests, by each ecosystem's naming convention:
+/// `*_test.go`, `test_*.py` / `*_test.py`, and `*.test.ts` / `*.spec.ts`
+/// (any JS/TS extension).
+pub fn is_test_file(path: &Path, language: Language) -> bool {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+    let stem = name.split('.').next().unwrap_or(name);
+
+    match language {
+        Language::Go => name.ends_with("_test.go"),
+        Language::Python => stem.starts_with("test_") || stem.ends_with("_test"),
+        Language::TypeScript | Language::Tsx | Language::JavaScript | Language::Jsx => name
+            .split('.')
+            .skip(1)
+            .any(|part| part == "test" || part == "spec"),
+        Language::Rust => false,
+    }
+}
+
+fn extract_test_names(
+    path: &Path,
+    content: &str,
+    language: Language,
+) -> Result<Vec<String>, String> {
+    match language {
+        Language::Rust => rust::extract_test_names(content),
+        // `it`/`test` calls only mean tests inside test files.
+        Language::TypeScript | Language::Tsx | Language::JavaScript | Language::Jsx
+            if !is_test_file(path, language) =>
+        {
+            Ok(Vec::new())
+        }
+        Language::TypeScript | Language::Tsx => typescript::extract_test_names(content, language),
+        Language::JavaScript | Language::Jsx => javascript::extract_test_names(content, language),
+        Language::Python => python::extract_test_names(content),
+        Language::Go => go::extract_test_names(content),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -474,7 +1398,7 @@ mod tests {
         let codemap = Codemap::empty(PathBuf::from("test.rs"), Language::Rust);
         assert!(codemap.imports.is_empty());
         assert!(codemap.declarations.is_empty());
-        assert!(codemap.parse_error.is_none());
+        assert!(codemap.parse_errors.is_empty());
     }
 
     #[test]
@@ -482,24 +1406,496 @@ mod tests {
         let codemap = Codemap::with_error(
             PathBuf::from("test.rs"),
             Language::Rust,
-            "parse error".into(),
+            ParseIssue::whole_file("failed to parse"),
+        );
+        assert_eq!(codemap.parse_errors.len(), 1);
+        // Display keeps the message, prefixed with where it happened.
+        assert_eq!(
+            codemap.parse_errors[0].to_string(),
+            "line 1:1: failed to parse"
+        );
+    }
+
+    #[test]
+    fn test_partial_extraction_around_syntax_errors() {
+        let options = ExtractOptions::default();
+        let cases = [
+            (
+                "lib.rs",
+                Language::Rust,
+                "pub fn before() {}\n\npub fn broken(x: i32 {\n    x\n}\n\npub struct After;\n",
+            ),
+            (
+                "app.py",
+                Language::Python,
+                "def before():\n    pass\n\ndef broken(:\n    pass\n\nclass After:\n    pass\n",
+            ),
+            (
+                "main.go",
+                Language::Go,
+                "package main\n\nfunc Before() {}\n\nfunc Broken( {\n}\n\ntype After struct{}\n",
+            ),
+            (
+                "app.ts",
+                Language::TypeScript,
+                "export function before() {}\n\nconst x = ;\n\nexport class After {}\n",
+            ),
+        ];
+        for (path, language, content) in cases {
+            let codemap = extract_codemap(Path::new(path), content, language, &options);
+            let names: Vec<&str> = codemap.declarations.iter().map(|d| d.name()).collect();
+            assert!(
+                names.iter().any(|n| n.eq_ignore_ascii_case("before")),
+                "{}: {:?}",
+                path,
+                names
+            );
+            assert!(names.contains(&"After"), "{}: {:?}", path, names);
+            assert_eq!(codemap.parse_errors.len(), 1, "{}", path);
+        }
+
+        let clean = extract_codemap(
+            Path::new("ok.rs"),
+            "pub fn ok() {}\n",
+            Language::Rust,
+            &options,
+        );
+        assert!(clean.parse_errors.is_empty());
+
+        // Notebook errors point into their cell.
+        let notebook = r#"{"cells": [
+            {"cell_type": "code", "source": "import os\n"},
+            {"cell_type": "code", "source": "def a():\n    pass\n\ndef b(:\n    pass\n"}
+        ]}"#;
+        let codemap = extract_codemap(Path::new("nb.ipynb"), notebook, Language::Python, &options);
+        assert_eq!(codemap.declarations.len(), 2);
+        assert_eq!(
+            codemap.parse_errors[0].location(),
+            Location::single_line(4).in_cell(Some(2))
+        );
+        assert_eq!(
+            codemap.parse_errors[0].to_string(),
+            "cell 2, line 4:7: missing `)`"
+        );
+    }
+
+    #[test]
+    fn test_parse_limits() {
+        let content = "pub fn f() {}\n".repeat(20_000);
+
+        let limited = ExtractOptions {
+            max_parse_bytes: Some(1000),
+            ..Default::default()
+        };
+        let codemap = extract_codemap(Path::new("big.rs"), &content, Language::Rust, &limited);
+        assert!(codemap.declarations.is_empty());
+        assert_eq!(
+            codemap.parse_errors[0].message,
+            "280,000 bytes is over the 1,000-byte parse limit"
+        );
+
+        let hurried = ExtractOptions {
+            parse_timeout: Some(Duration::from_micros(1)),
+            ..Default::default()
+        };
+        let codemap = extract_codemap(Path::new("big.rs"), &content, Language::Rust, &hurried);
+        assert!(codemap.declarations.is_empty());
+        assert_eq!(codemap.parse_errors[0].message, "parse timed out after 1µs");
+
+        // The cached parser starts afresh after a timeout.
+        let codemap = extract_codemap(
+            Path::new("small.rs"),
+            "pub fn g() {}\n",
+            Language::Rust,
+            &ExtractOptions::default(),
+        );
+        assert_eq!(codemap.declarations[0].name(), "g");
+        assert!(codemap.parse_errors.is_empty());
+    }
+
+    #[test]
+    fn test_codemap_serde_round_trip() {
+        let content = "use std::io::{Read, Write};\n\n/// A point.\npub struct Point {\n    pub x: i32,\n}\n\nimpl Point {\n    pub fn norm(&self) -> f64 { 0.0 }\n}\n";
+        let codemap = extract_codemap(
+            Path::new("src/point.rs"),
+            content,
+            Language::Rust,
+            &ExtractOptions::default(),
+        );
+
+        let json = serde_json::to_string(&codemap).unwrap();
+        let restored: Codemap = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.path, codemap.path);
+        assert_eq!(restored.language, codemap.language);
+        assert_eq!(restored.imports, codemap.imports);
+        assert_eq!(restored.declarations, codemap.declarations);
+        assert_eq!(restored.fingerprint(), codemap.fingerprint());
+    }
+
+    #[test]
+    fn test_type_definition_files_marked() {
+        let content =
+            "declare const VERSION: string;\nexport declare function f(x: number): void;\n";
+        let options = ExtractOptions::default();
+
+        let codemap = extract_codemap(
+            Path::new("types/index.d.ts"),
+            content,
+            Language::TypeScript,
+            &options,
+        );
+        assert!(codemap.is_type_definition);
+        let names: Vec<_> = codemap.declarations.iter().map(Declaration::name).collect();
+        assert_eq!(names, vec!["VERSION", "f"]);
+
+        let codemap = extract_codemap(
+            Path::new("index.ts"),
+            content,
+            Language::TypeScript,
+            &options,
+        );
+        assert!(!codemap.is_type_definition);
+    }
+
+    #[test]
+    fn test_list_tests() {
+        let options = ExtractOptions {
+            list_tests: true,
+            ..Default::default()
+        };
+        let cases: [(&str, Language, &str, &[&str]); 4] = [
+            (
+                "lib.rs",
+                Language::Rust,
+                "pub fn run() {}\n\n#[cfg(test)]\nmod tests {\n    #[test]\n    fn it_runs() {}\n\n    #[tokio::test]\n    async fn it_awaits() {}\n\n    fn helper() {}\n}\n",
+                &["tests::it_runs", "tests::it_awaits"],
+            ),
+            (
+                "test_api.py",
+                Language::Python,
+                "def test_get():\n    pass\n\nclass TestApi:\n    @pytest.mark.slow\n    def test_post(self):\n        pass\n\n    def setup(self):\n        pass\n",
+                &["test_get", "TestApi.test_post"],
+            ),
+            (
+                "api_test.go",
+                Language::Go,
+                "package api\n\nfunc TestGet(t *testing.T) {}\n\nfunc BenchmarkGet(b *testing.B) {}\n\nfunc TestHelper() {}\n",
+                &["TestGet", "BenchmarkGet"],
+            ),
+            (
+                "parser.spec.ts",
+                Language::TypeScript,
+                "describe('parser', () => {\n  it('parses numbers', () => {});\n  it.skip(`parses dates`, () => {});\n});\ntest(\"standalone\", () => {});\n",
+                &["parser > parses numbers", "parser > parses dates", "standalone"],
+            ),
+        ];
+
+        for (path, language, content, expected) in cases {
+            let codemap = extract_codemap(Path::new(path), content, language, &options);
+            assert_eq!(codemap.tests, expected, "{path}");
+        }
+    }
+
+    #[test]
+    fn test_test_calls_outside_test_files_ignored() {
+        let options = ExtractOptions {
+            list_tests: true,
+            ..Default::default()
+        };
+        let codemap = extract_codemap(
+            Path::new("app.ts"),
+            "test('not a test file', () => {});\n",
+            Language::TypeScript,
+            &options,
+        );
+        assert!(codemap.tests.is_empty());
+    }
+
+    #[test]
+    fn test_list_tests_with_skip_tests() {
+        let options = ExtractOptions {
+            skip_tests: true,
+            list_tests: true,
+            ..Default::default()
+        };
+        let codemap = extract_codemap(
+            Path::new("api_test.go"),
+            "package api\n\nfunc TestGet(t *testing.T) {}\n\nfunc NewFixture() int { return 1 }\n",
+            Language::Go,
+            &options,
+        );
+        assert_eq!(codemap.declarations.len(), 1);
+        assert_eq!(codemap.declarations[0].name(), "NewFixture");
+        assert_eq!(codemap.tests, ["TestGet"]);
+    }
+
+    #[test]
+    fn test_is_test_file() {
+        assert!(is_test_file(Path::new("a/api_test.go"), Language::Go));
+        assert!(!is_test_file(Path::new("a/api.go"), Language::Go));
+        assert!(is_test_file(Path::new("test_api.py"), Language::Python));
+        assert!(is_test_file(Path::new("api_test.py"), Language::Python));
+        assert!(!is_test_file(Path::new("testing.py"), Language::Python));
+        assert!(is_test_file(Path::new("Button.test.tsx"), Language::Tsx));
+        assert!(is_test_file(Path::new("api.spec.js"), Language::JavaScript));
+        assert!(!is_test_file(Path::new("spec.ts"), Language::TypeScript));
+        assert!(!is_test_file(Path::new("lib.rs"), Language::Rust));
+    }
+
+    #[test]
+    fn test_skip_tests_skips_go_test_files() {
+        let skip = ExtractOptions {
+            skip_tests: true,
+            ..Default::default()
+        };
+        assert!(skip.skips_file(Path::new("a/api_test.go"), Language::Go));
+        assert!(!skip.skips_file(Path::new("a/api.go"), Language::Go));
+        assert!(!skip.skips_file(Path::new("test_api.py"), Language::Python));
+        assert!(!ExtractOptions::default().skips_file(Path::new("a/api_test.go"), Language::Go));
+    }
+
+    #[test]
+    fn test_function_metrics() {
+        let cases = [
+            (
+                "lib.rs",
+                Language::Rust,
+                "pub fn pick(x: Option<i32>, y: bool) -> i32 {\n    match x {\n        Some(v) if v > 0 && y => v,\n        Some(_) => 0,\n        None => -1,\n    }\n}\n",
+                Metrics {
+                    lines: 7,
+                    complexity: 5,
+                },
+            ),
+            (
+                "app.py",
+                Language::Python,
+                "def check(x):\n    if x > 1 and x < 5:\n        return 1\n    elif x:\n        return 2\n    return 0\n",
+                Metrics {
+                    lines: 5,
+                    complexity: 4,
+                },
+            ),
+            (
+                "app.ts",
+                Language::TypeScript,
+                "export function total(xs: number[]): number {\n  let t = 0;\n  for (const x of xs) t += x > 0 ? x : 0;\n  return t;\n}\n",
+                Metrics {
+                    lines: 5,
+                    complexity: 3,
+                },
+            ),
+            (
+                "main.go",
+                Language::Go,
+                "package main\n\nfunc Sign(x int) int {\n\tswitch {\n\tcase x > 0:\n\t\treturn 1\n\tcase x < 0:\n\t\treturn -1\n\t}\n\treturn 0\n}\n",
+                Metrics {
+                    lines: 9,
+                    complexity: 3,
+                },
+            ),
+        ];
+
+        for (path, language, content, expected) in cases {
+            let codemap = extract_codemap(
+                Path::new(path),
+                content,
+                language,
+                &ExtractOptions::default(),
+            );
+            match &codemap.declarations[0] {
+                Declaration::Function { metrics, .. } => {
+                    assert_eq!(*metrics, Some(expected), "{path}");
+                }
+                other => panic!("expected function in {path}, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_include_snippet() {
+        let content = "pub struct Counter {\n    n: u32,\n}\n\nimpl Counter {\n    pub fn bump(&mut self) -> u32 {\n        self.n += 1;\n        self.n\n    }\n}\n";
+        let options = ExtractOptions {
+            visibility: VisibilityThreshold::All,
+            include_snippet: Some(2),
+            ..Default::default()
+        };
+
+        let codemap = extract_codemap(Path::new("lib.rs"), content, Language::Rust, &options);
+        let Declaration::Struct { methods, .. } = &codemap.declarations[0] else {
+            panic!("expected struct");
+        };
+        assert_eq!(
+            codemap.declarations[0].snippet(),
+            Some("pub struct Counter {\n    n: u32,\n...")
+        );
+        // Method bodies are dedented out of the impl block.
+        assert_eq!(
+            methods[0].snippet(),
+            Some("pub fn bump(&mut self) -> u32 {\n    self.n += 1;\n...")
+        );
+
+        let codemap = extract_codemap(
+            Path::new("lib.rs"),
+            content,
+            Language::Rust,
+            &ExtractOptions::default(),
+        );
+        assert_eq!(codemap.declarations[0].snippet(), None);
+    }
+
+    #[test]
+    fn test_inline_bodies() {
+        let content = "def short(x):\n    return x + 1\n\ndef long(x):\n    y = x * 2\n    z = y + 1\n    return z\n\nclass Box:\n    def get(self):\n        return self.v\n";
+        let options = ExtractOptions {
+            inline_bodies: Some(3),
+            ..Default::default()
+        };
+
+        let codemap = extract_codemap(Path::new("app.py"), content, Language::Python, &options);
+        let snippets: Vec<(&str, Option<&str>)> = codemap
+            .declarations
+            .iter()
+            .map(|d| (d.name(), d.snippet()))
+            .collect();
+        assert_eq!(
+            snippets,
+            [
+                ("short", Some("def short(x):\n    return x + 1")),
+                ("long", None),
+                ("Box", None),
+            ]
+        );
+        let Declaration::Class { members, .. } = &codemap.declarations[2] else {
+            panic!("expected class");
+        };
+        assert_eq!(
+            members[0].snippet(),
+            Some("def get(self):\n    return self.v")
         );
-        assert!(codemap.parse_error.is_some());
+    }
+
+    #[test]
+    fn test_only_kinds() {
+        let content = "pub struct A;\npub enum B { X }\npub trait C {}\npub fn d() {}\npub const E: u8 = 1;\n";
+        let kinds = |only: Vec<KindFilter>| {
+            let options = ExtractOptions {
+                only,
+                ..Default::default()
+            };
+            let codemap = extract_codemap(Path::new("lib.rs"), content, Language::Rust, &options);
+            codemap
+                .declarations
+                .iter()
+                .map(|d| d.name().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(kinds(Vec::new()), ["A", "B", "C", "d", "E"]);
+        assert_eq!(kinds(vec![KindFilter::Types]), ["A", "B", "C"]);
+        assert_eq!(
+            kinds(vec![KindFilter::Functions, KindFilter::Traits]),
+            ["C", "d"]
+        );
+    }
+
+    #[test]
+    fn test_strip_license_docs() {
+        let content = "/// Copyright 2024 Acme. Licensed under MIT.\npub fn a() {}\n\n/// Adds two numbers.\npub fn b() {}\n";
+        let docs = |strip_license_docs| {
+            let options = ExtractOptions {
+                include_docs: true,
+                strip_license_docs,
+                ..Default::default()
+            };
+            let codemap = extract_codemap(Path::new("lib.rs"), content, Language::Rust, &options);
+            codemap
+                .declarations
+                .iter()
+                .map(|d| match d {
+                    Declaration::Function { doc, .. } => doc.clone(),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert!(docs(false)[0].is_some());
+        assert_eq!(docs(true), [None, Some("Adds two numbers.".to_string())]);
     }
 
     #[test]
     fn test_declaration_name() {
         let func = Declaration::Function {
             name: "test".into(),
+            scope: None,
             signature: "fn test()".into(),
             visibility: Visibility::Public,
             location: Location::single_line(1),
             is_async: false,
+            overloads: Vec::new(),
+            component: None,
+            metrics: None,
+            attributes: Vec::new(),
             doc: None,
+            snippet: None,
         };
         assert_eq!(func.name(), "test");
     }
 
+    #[test]
+    fn test_declaration_ids_are_unique() {
+        let options = ExtractOptions {
+            visibility: VisibilityThreshold::All,
+            ..ExtractOptions::default()
+        };
+        let ids = |path: &str, content: &str, language| {
+            let codemap = extract_codemap(Path::new(path), content, language, &options);
+            let ids = DeclarationIds::new(&codemap, Path::new("."));
+            let mut all = Vec::new();
+            for decl in &codemap.declarations {
+                all.push(ids.id(decl, None));
+                for child in decl.nested() {
+                    all.push(ids.id(child, Some(decl.name())));
+                }
+            }
+            all
+        };
+
+        assert_eq!(
+            ids(
+                "./main.go",
+                "package main\n\nfunc init() {}\n\nfunc init() {}\n",
+                Language::Go
+            ),
+            ["main.go#init@function", "main.go#init@function:2"]
+        );
+        assert_eq!(
+            ids(
+                "shape.py",
+                "class Shape:\n    @property\n    def size(self):\n        return 1\n\n    @size.setter\n    def size(self, value):\n        pass\n",
+                Language::Python
+            ),
+            [
+                "shape.py#Shape@class",
+                "shape.py#Shape::size@function",
+                "shape.py#Shape::size@function:2"
+            ]
+        );
+        // Re-exports are named after what they export, not where from.
+        assert_eq!(
+            ids(
+                "lib.rs",
+                "pub use crate::a::X;\npub use crate::a::Y;\npub use crate::b::{Error as BError, Z};\npub use crate::c::*;\n",
+                Language::Rust
+            ),
+            [
+                "lib.rs#X@re_export",
+                "lib.rs#Y@re_export",
+                "lib.rs#BError,Z@re_export",
+                "lib.rs#crate::c@re_export"
+            ]
+        );
+    }
+
     #[test]
     fn test_visibility_display() {
         assert_eq!(Visibility::Public.to_string(), "pub");
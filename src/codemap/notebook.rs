@@ -0,0 +1,303 @@
+//! Jupyter notebook (`.ipynb`) extraction.
+//!
+//! Code cells are joined into one Python source and run through the Python
+//! extractor. Locations are then mapped back to the cell they came from, so
+//! `cell 3, line 2` is the second line of the notebook's third cell.
+
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::{extract_source, Codemap, Declaration, ExtractOptions, Location, ParseIssue};
+use crate::filter::Language;
+
+#[derive(Deserialize)]
+struct Notebook {
+    #[serde(default)]
+    cells: Vec<Cell>,
+    #[serde(default)]
+    metadata: Metadata,
+}
+
+#[derive(Deserialize)]
+struct Cell {
+    cell_type: String,
+    #[serde(default)]
+    source: CellSource,
+}
+
+/// Cell source: a list of lines, each keeping its `\n`, or a single string.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CellSource {
+    Lines(Vec<String>),
+    Text(String),
+}
+
+impl Default for CellSource {
+    fn default() -> Self {
+        CellSource::Text(String::new())
+    }
+}
+
+impl CellSource {
+    fn text(&self) -> String {
+        match self {
+            CellSource::Lines(lines) => lines.concat(),
+            CellSource::Text(text) => text.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct Metadata {
+    kernelspec: Option<KernelSpec>,
+    language_info: Option<LanguageInfo>,
+}
+
+#[derive(Deserialize)]
+struct KernelSpec {
+    language: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LanguageInfo {
+    name: Option<String>,
+}
+
+impl Metadata {
+    /// Kernel language, if the notebook records one.
+    fn language(&self) -> Option<&str> {
+        self.language_info
+            .as_ref()
+            .and_then(|info| info.name.as_deref())
+            .or_else(|| self.kernelspec.as_ref()?.language.as_deref())
+    }
+}
+
+/// Where a code cell's lines sit in the joined source.
+#[derive(Debug, Clone, Copy)]
+struct CellSpan {
+    /// 1-indexed position of the cell among all cells, markdown included.
+    cell: usize,
+    /// 1-indexed line of the cell's first line in the joined source.
+    first_line: usize,
+    /// Number of lines in the cell.
+    lines: usize,
+}
+
+/// The code cells of a notebook joined into one Python source.
+#[derive(Debug)]
+pub struct NotebookSource {
+    /// Code cells separated by a blank line. IPython magics (`%time`,
+    /// `%%bash` cells) and shell escapes (`!pip install`) are commented out.
+    pub source: String,
+    cells: Vec<CellSpan>,
+}
+
+impl NotebookSource {
+    /// Parse notebook JSON. Fails on invalid JSON, pointing at the broken
+    /// spot in the JSON, or on a non-Python kernel.
+    pub fn parse(content: &str) -> Result<Self, ParseIssue> {
+        let notebook: Notebook = serde_json::from_str(content).map_err(json_issue)?;
+        if let Some(language) = notebook.metadata.language() {
+            if !language.eq_ignore_ascii_case("python") {
+                return Err(ParseIssue::whole_file(format!(
+                    "unsupported notebook language: {}",
+                    language
+                )));
+            }
+        }
+
+        let mut source = String::new();
+        let mut cells = Vec::new();
+        let mut next_line = 1;
+        for (index, cell) in notebook.cells.iter().enumerate() {
+            if cell.cell_type != "code" {
+                continue;
+            }
+
+            let text = cell.source.text();
+            let cell_magic = text.trim_start().starts_with("%%");
+            let mut lines = 0;
+            for line in text.lines() {
+                if cell_magic || is_ipython_syntax(line) {
+                    source.push('#');
+                }
+                source.push_str(line);
+                source.push('\n');
+                lines += 1;
+            }
+            // Keep a dedented cell from continuing the previous cell's block.
+            source.push('\n');
+
+            cells.push(CellSpan {
+                cell: index + 1,
+                first_line: next_line,
+                lines,
+            });
+            next_line += lines + 1;
+        }
+
+        Ok(Self { source, cells })
+    }
+
+    /// Map a location in `source` to lines within its cell.
+    pub fn locate(&self, location: Location) -> Location {
+        let Some(span) = self
+            .cells
+            .iter()
+            .rev()
+            .find(|span| span.first_line <= location.start_line)
+        else {
+            return location;
+        };
+
+        let start_line = location.start_line - span.first_line + 1;
+        let end_line = (location.end_line.saturating_sub(span.first_line) + 1)
+            .min(span.lines)
+            .max(start_line);
+        Location {
+            start_line,
+            end_line,
+            cell: Some(span.cell),
+        }
+    }
+}
+
+/// Invalid notebook JSON, located where serde gave up. The location is moved
+/// out of the message so it isn't repeated when displayed.
+fn json_issue(e: serde_json::Error) -> ParseIssue {
+    let text = e.to_string();
+    let message = text
+        .rsplit_once(" at line ")
+        .map_or(text.as_str(), |(m, _)| m);
+    ParseIssue::new(
+        e.line().max(1),
+        e.column().max(1),
+        format!("invalid notebook: {}", message),
+    )
+}
+
+/// Line magics (`%matplotlib inline`) and shell escapes (`!ls`).
+fn is_ipython_syntax(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with('%') || line.starts_with('!')
+}
+
+/// Extract a codemap from notebook JSON, with cell-relative locations.
+pub(super) fn extract(path: &Path, content: &str, options: &ExtractOptions) -> Codemap {
+    let notebook = match NotebookSource::parse(content) {
+        Ok(notebook) => notebook,
+        Err(e) => return Codemap::with_error(path.to_path_buf(), Language::Python, e),
+    };
+
+    let mut codemap = extract_source(path, &notebook.source, Language::Python, options);
+    relocate(&mut codemap.declarations, &notebook);
+    for todo in &mut codemap.todos {
+        let location = notebook.locate(Location::single_line(todo.line));
+        todo.line = location.start_line;
+        todo.cell = location.cell;
+    }
+    for issue in &mut codemap.parse_errors {
+        let location = notebook.locate(issue.location());
+        issue.line = location.start_line;
+        issue.end_line = location.end_line;
+        issue.cell = location.cell;
+    }
+    codemap
+}
+
+fn relocate(declarations: &mut [Declaration], notebook: &NotebookSource) {
+    for decl in declarations {
+        let location = decl.location_mut();
+        *location = notebook.locate(*location);
+        match decl {
+            Declaration::Struct { methods, .. } | Declaration::Enum { methods, .. } => {
+                relocate(methods, notebook);
+            }
+            Declaration::Class { members, .. } => relocate(members, notebook),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTEBOOK: &str = r##"{
+ "cells": [
+  {"cell_type": "markdown", "metadata": {}, "source": ["# Training\n"]},
+  {"cell_type": "code", "metadata": {}, "outputs": [], "execution_count": 1,
+   "source": ["%matplotlib inline\n", "import numpy as np\n", "!pip install torch\n"]},
+  {"cell_type": "code", "metadata": {}, "outputs": [], "execution_count": 2,
+   "source": ["# TODO: tune\n", "def train(model, epochs=3):\n", "    return model\n"]},
+  {"cell_type": "code", "metadata": {}, "outputs": [], "execution_count": 3,
+   "source": "%%bash\necho hi\n"},
+  {"cell_type": "code", "metadata": {}, "outputs": [], "execution_count": 4,
+   "source": ["class Model:\n", "    def fit(self, x):\n", "        pass\n"]}
+ ],
+ "metadata": {"kernelspec": {"language": "python", "name": "python3"}},
+ "nbformat": 4,
+ "nbformat_minor": 5
+}"##;
+
+    #[test]
+    fn test_notebook_cells_and_locations() {
+        let options = ExtractOptions {
+            include_todos: true,
+            ..Default::default()
+        };
+        let codemap = extract(Path::new("train.ipynb"), NOTEBOOK, &options);
+
+        assert!(codemap.parse_errors.is_empty());
+        assert_eq!(codemap.language, Language::Python);
+        assert_eq!(codemap.imports.len(), 1);
+
+        let train = &codemap.declarations[0];
+        assert_eq!(train.name(), "train");
+        assert_eq!(
+            train.location(),
+            Location {
+                start_line: 2,
+                end_line: 3,
+                cell: Some(3),
+            }
+        );
+
+        let Declaration::Class { members, .. } = &codemap.declarations[1] else {
+            panic!("expected class");
+        };
+        assert_eq!(codemap.declarations[1].location().cell, Some(5));
+        assert_eq!(members[0].location().start_line, 2);
+        assert_eq!(members[0].location().cell, Some(5));
+
+        assert_eq!(codemap.todos[0].line, 1);
+        assert_eq!(codemap.todos[0].cell, Some(3));
+    }
+
+    #[test]
+    fn test_notebook_rejects_other_kernels() {
+        let content = r#"{"cells": [], "metadata": {"language_info": {"name": "R"}}}"#;
+        let codemap = extract(
+            Path::new("analysis.ipynb"),
+            content,
+            &ExtractOptions::default(),
+        );
+        assert_eq!(
+            codemap.parse_errors,
+            vec![ParseIssue::whole_file("unsupported notebook language: R")]
+        );
+
+        let codemap = extract(
+            Path::new("broken.ipynb"),
+            "{\n  \"cells\": [}\n",
+            &ExtractOptions::default(),
+        );
+        let issue = &codemap.parse_errors[0];
+        assert_eq!((issue.line, issue.column), (2, 13));
+        assert!(issue.message.starts_with("invalid notebook: "));
+        assert!(!issue.message.contains("at line"));
+    }
+}
@@ -1,22 +1,19 @@
 //! Python codemap extraction using tree-sitter.
 
 use super::{
-    find_child_by_kind, node_text, with_python_parser, Declaration, ExtractOptions, Import,
-    Location, Visibility,
+    collect_syntax_errors, find_child_by_kind, function_metrics, node_text, parse_source,
+    with_python_parser, Declaration, ExtractOptions, Extracted, Field, Import, Location, Variant,
+    Visibility, VisibilityThreshold,
 };
 
 /// Extract imports and declarations from Python source code.
-pub fn extract(
-    content: &str,
-    options: &ExtractOptions,
-) -> Result<(Vec<Import>, Vec<Declaration>), String> {
+pub fn extract(content: &str, options: &ExtractOptions) -> Result<Extracted, String> {
     with_python_parser(|parser| {
-        let tree = parser
-            .parse(content, None)
-            .ok_or_else(|| "failed to parse".to_string())?;
+        let tree = parse_source(parser, content, options)?;
 
         let mut imports = Vec::new();
         let mut declarations = Vec::new();
+        let syntax_errors = collect_syntax_errors(tree.root_node(), content);
 
         extract_from_node(
             tree.root_node(),
@@ -26,7 +23,11 @@ pub fn extract(
             &mut declarations,
         );
 
-        Ok((imports, declarations))
+        if options.skip_tests {
+            declarations.retain(|decl| !is_test_declaration(decl));
+        }
+
+        Ok((imports, declarations, syntax_errors))
     })?
 }
 
@@ -48,13 +49,13 @@ fn extract_from_node(
             }
             "function_definition" => {
                 if let Some(func) = extract_function(child, content, options) {
-                    if options.include_private || func.visibility() == Visibility::Public {
+                    if options.visibility.allows(func.visibility()) {
                         declarations.push(func);
                     }
                 }
             }
             "class_definition" => {
-                if let Some(class) = extract_class(child, content, options) {
+                if let Some(class) = extract_class(child, content, options, false) {
                     declarations.push(class);
                 }
             }
@@ -62,11 +63,90 @@ fn extract_from_node(
                 // Handle decorated functions/classes
                 extract_decorated(child, content, options, declarations);
             }
+            "type_alias_statement" => {
+                if let Some(decl) = extract_type_alias_statement(child, content) {
+                    if options.visibility.allows(decl.visibility()) {
+                        declarations.push(decl);
+                    }
+                }
+            }
+            "expression_statement" => {
+                // Module-level `FOO = ...`, `x: int = ...` and type aliases
+                if let Some(assignment) = find_child_by_kind(child, "assignment") {
+                    if let Some(decl) = extract_assignment(assignment, content) {
+                        if options.visibility.allows(decl.visibility()) {
+                            declarations.push(decl);
+                        }
+                    }
+                }
+            }
+            "ERROR" => extract_from_node(child, content, options, imports, declarations),
             _ => {}
         }
     }
 }
 
+/// pytest conventions: `test_*` functions, and `Test*` classes containing `test_*` methods.
+fn is_test_declaration(decl: &Declaration) -> bool {
+    match decl {
+        Declaration::Function { name, .. } => name.starts_with("test_"),
+        Declaration::Class { name, members, .. } => {
+            name.starts_with("Test") && members.iter().any(|m| m.name().starts_with("test_"))
+        }
+        _ => false,
+    }
+}
+
+/// pytest-style test names: `test_*` functions and `Test*.test_*` methods.
+pub fn extract_test_names(content: &str) -> Result<Vec<String>, String> {
+    with_python_parser(|parser| {
+        let tree = parser
+            .parse(content, None)
+            .ok_or_else(|| "failed to parse".to_string())?;
+
+        let mut names = Vec::new();
+        for def in definitions(tree.root_node()) {
+            let Some(name) = def
+                .child_by_field_name("name")
+                .map(|n| node_text(n, content))
+            else {
+                continue;
+            };
+            match def.kind() {
+                "function_definition" if name.starts_with("test_") => names.push(name),
+                "class_definition" if name.starts_with("Test") => {
+                    let Some(body) = def.child_by_field_name("body") else {
+                        continue;
+                    };
+                    for method in definitions(body) {
+                        let method_name = method
+                            .child_by_field_name("name")
+                            .map(|n| node_text(n, content))
+                            .unwrap_or_default();
+                        if method.kind() == "function_definition"
+                            && method_name.starts_with("test_")
+                        {
+                            names.push(format!("{name}.{method_name}"));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(names)
+    })?
+}
+
+/// Direct child definitions of `node`, looking through decorators.
+fn definitions(node: tree_sitter::Node) -> Vec<tree_sitter::Node> {
+    node.children(&mut node.walk())
+        .filter_map(|child| match child.kind() {
+            "decorated_definition" => child.child_by_field_name("definition"),
+            _ => Some(child),
+        })
+        .collect()
+}
+
 fn extract_import(node: tree_sitter::Node, content: &str) -> Option<Import> {
     let text = node_text(node, content);
 
@@ -108,14 +188,7 @@ fn extract_function(
 
     let is_async = node.children(&mut node.walk()).any(|c| c.kind() == "async");
 
-    // Determine visibility from name convention
-    let visibility = if name.starts_with("__") && !name.ends_with("__") {
-        Visibility::Private
-    } else if name.starts_with('_') {
-        Visibility::Protected
-    } else {
-        Visibility::Public
-    };
+    let visibility = name_visibility(&name);
 
     // Build signature
     let mut signature = String::new();
@@ -146,18 +219,151 @@ fn extract_function(
 
     Some(Declaration::Function {
         name,
+        scope: None,
         signature,
         visibility,
         location,
         is_async,
+        overloads: Vec::new(),
+        component: None,
+        metrics: function_metrics(node),
+        attributes: Vec::new(),
         doc,
+        snippet: None,
     })
 }
 
+/// Determine visibility from the underscore naming convention.
+fn name_visibility(name: &str) -> Visibility {
+    if name.starts_with("__") && !name.ends_with("__") {
+        Visibility::Private
+    } else if name.starts_with('_') {
+        Visibility::Protected
+    } else {
+        Visibility::Public
+    }
+}
+
+/// Extract a module-level assignment to a single name as a type alias or a
+/// constant. A constant's type is the annotation if present, otherwise
+/// inferred from a literal value.
+fn extract_assignment(node: tree_sitter::Node, content: &str) -> Option<Declaration> {
+    let left = node.child_by_field_name("left")?;
+    if left.kind() != "identifier" {
+        return None; // Tuple unpacking, attribute or subscript targets
+    }
+    let name = node_text(left, content);
+    let annotation = node.child_by_field_name("type");
+    let value = node.child_by_field_name("right");
+    let location = Location::new(node.start_position().row + 1, node.end_position().row + 1);
+
+    // `Foo: TypeAlias = ...`, or an implicit alias like `UserMap = dict[str, User]`.
+    let is_alias = match (annotation, value) {
+        (Some(annotation), Some(_)) => {
+            matches!(
+                node_text(annotation, content).as_str(),
+                "TypeAlias" | "typing.TypeAlias"
+            )
+        }
+        (None, Some(value)) => is_pascal_case(&name) && is_type_expression(value, content),
+        _ => false,
+    };
+    if let (true, Some(value)) = (is_alias, value) {
+        return Some(Declaration::TypeAlias {
+            visibility: name_visibility(&name),
+            name,
+            generics: String::new(),
+            target: node_text(value, content),
+            location,
+            snippet: None,
+        });
+    }
+
+    let ty = match (annotation, value) {
+        (Some(annotation), _) => node_text(annotation, content),
+        (None, Some(value)) => infer_literal_type(value).unwrap_or_default().to_string(),
+        (None, None) => String::new(),
+    };
+
+    Some(Declaration::Const {
+        visibility: name_visibility(&name),
+        name,
+        ty,
+        location,
+        is_static: false,
+        snippet: None,
+    })
+}
+
+/// PEP 695 `type Pair[T] = tuple[T, T]`.
+fn extract_type_alias_statement(node: tree_sitter::Node, content: &str) -> Option<Declaration> {
+    let left = node_text(node.child_by_field_name("left")?, content);
+    let target = node_text(node.child_by_field_name("right")?, content);
+    let (name, generics) = match left.find('[') {
+        Some(open) => (left[..open].to_string(), left[open..].to_string()),
+        None => (left, String::new()),
+    };
+
+    Some(Declaration::TypeAlias {
+        visibility: name_visibility(&name),
+        name,
+        generics,
+        target,
+        location: Location::new(node.start_position().row + 1, node.end_position().row + 1),
+        snippet: None,
+    })
+}
+
+/// `UserMap`, not `MAX_SIZE` or `handler`.
+fn is_pascal_case(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase()) && name.chars().any(|c| c.is_lowercase())
+}
+
+/// Whether an assigned value reads as a type: a subscripted generic
+/// (`dict[str, int]`, `Optional[User]`) or a `|` union of such.
+fn is_type_expression(value: tree_sitter::Node, content: &str) -> bool {
+    match value.kind() {
+        "subscript" => true,
+        "binary_operator" => {
+            value
+                .child_by_field_name("operator")
+                .is_some_and(|op| node_text(op, content) == "|")
+                && [
+                    value.child_by_field_name("left"),
+                    value.child_by_field_name("right"),
+                ]
+                .into_iter()
+                .flatten()
+                .all(|side| {
+                    matches!(side.kind(), "identifier" | "attribute" | "none")
+                        || is_type_expression(side, content)
+                })
+        }
+        _ => false,
+    }
+}
+
+fn infer_literal_type(value: tree_sitter::Node) -> Option<&'static str> {
+    let ty = match value.kind() {
+        "integer" => "int",
+        "float" => "float",
+        "string" | "concatenated_string" => "str",
+        "true" | "false" => "bool",
+        "none" => "None",
+        "list" => "list",
+        "dictionary" => "dict",
+        "tuple" => "tuple",
+        "set" => "set",
+        _ => return None,
+    };
+    Some(ty)
+}
+
 fn extract_class(
     node: tree_sitter::Node,
     content: &str,
     options: &ExtractOptions,
+    is_dataclass: bool,
 ) -> Option<Declaration> {
     let name = find_child_by_kind(node, "identifier").map(|n| node_text(n, content))?;
 
@@ -167,14 +373,27 @@ fn extract_class(
         Visibility::Public
     };
 
+    let bases = base_classes(node, content);
+    if bases.iter().any(|b| is_enum_base(b)) {
+        return Some(extract_enum_class(node, content, options, name, visibility));
+    }
+
+    let mut fields = Vec::new();
     let mut members = Vec::new();
 
     if let Some(body) = find_child_by_kind(node, "block") {
         for child in body.children(&mut body.walk()) {
             match child.kind() {
+                "expression_statement" => {
+                    if let Some(field) = extract_class_field(child, content) {
+                        fields.push(field);
+                    }
+                }
                 "function_definition" => {
                     if let Some(method) = extract_function(child, content, options) {
-                        if options.include_private || method.visibility() != Visibility::Private {
+                        if options.visibility == VisibilityThreshold::All
+                            || method.visibility() != Visibility::Private
+                        {
                             members.push(method);
                         }
                     }
@@ -195,32 +414,202 @@ fn extract_class(
         None
     };
 
+    let is_abstract = bases.iter().any(|b| is_interface_base(b))
+        || node
+            .child_by_field_name("superclasses")
+            .is_some_and(|s| node_text(s, content).contains("metaclass=ABCMeta"))
+        || (!members.is_empty() && members.iter().all(is_abstract_method));
+    if is_abstract {
+        return Some(Declaration::Interface {
+            name,
+            members: interface_members(&fields, &members).into(),
+            visibility,
+            location,
+            doc,
+            snippet: None,
+        });
+    }
+
+    // Plain class attributes are only fields on dataclass-style classes.
+    if !is_dataclass {
+        fields.clear();
+    }
+
     Some(Declaration::Class {
         name,
+        generics: String::new(),
+        extends: Vec::new(),
+        implements: Vec::new(),
+        is_abstract: false,
+        fields: fields.into(),
         members,
+        component: None,
         visibility,
         location,
         doc,
+        snippet: None,
     })
 }
 
+/// Base class expressions of a class definition, as written (`Enum`, `abc.ABC`).
+/// Keyword arguments such as `metaclass=ABCMeta` are skipped.
+fn base_classes(node: tree_sitter::Node, content: &str) -> Vec<String> {
+    let Some(superclasses) = node.child_by_field_name("superclasses") else {
+        return Vec::new();
+    };
+    superclasses
+        .named_children(&mut superclasses.walk())
+        .filter(|c| c.kind() != "keyword_argument" && c.kind() != "comment")
+        .map(|c| node_text(c, content))
+        .collect()
+}
+
+fn is_enum_base(base: &str) -> bool {
+    let name = base.rsplit('.').next().unwrap_or(base);
+    matches!(name, "Enum" | "IntEnum" | "StrEnum" | "Flag" | "IntFlag")
+}
+
+fn is_interface_base(base: &str) -> bool {
+    let base = base.split('[').next().unwrap_or(base);
+    let name = base.rsplit('.').next().unwrap_or(base);
+    matches!(name, "Protocol" | "ABC")
+}
+
+fn is_abstract_method(member: &Declaration) -> bool {
+    match member {
+        Declaration::Function { attributes, .. } => attributes
+            .iter()
+            .any(|a| a == "@abstractmethod" || a == "@abc.abstractmethod"),
+        _ => false,
+    }
+}
+
+/// Render a Protocol/ABC body as interface members: annotated attributes
+/// (`name: str`) followed by method signatures with their decorators, minus
+/// the `@abstractmethod` marker every member would otherwise repeat.
+fn interface_members(fields: &[Field], methods: &[Declaration]) -> Vec<String> {
+    let attributes = fields.iter().map(|f| format!("{}: {}", f.name, f.ty));
+    let signatures = methods.iter().filter_map(|m| match m {
+        Declaration::Function {
+            signature,
+            attributes,
+            ..
+        } => {
+            let mut member: Vec<&str> = attributes
+                .iter()
+                .map(String::as_str)
+                .filter(|a| *a != "@abstractmethod" && *a != "@abc.abstractmethod")
+                .collect();
+            member.push(signature);
+            Some(member.join(" "))
+        }
+        _ => None,
+    });
+    attributes.chain(signatures).collect()
+}
+
+/// An `Enum` subclass: each class-level assignment is a member, kept as written
+/// (`RED = 1`, `PENDING = auto()`). Methods are kept as on other classes.
+fn extract_enum_class(
+    node: tree_sitter::Node,
+    content: &str,
+    options: &ExtractOptions,
+    name: String,
+    visibility: Visibility,
+) -> Declaration {
+    let mut variants = Vec::new();
+    let mut methods = Vec::new();
+    if let Some(body) = find_child_by_kind(node, "block") {
+        for child in body.children(&mut body.walk()) {
+            match child.kind() {
+                "expression_statement" => {
+                    let Some(assignment) = find_child_by_kind(child, "assignment") else {
+                        continue;
+                    };
+                    let is_member = assignment
+                        .child_by_field_name("left")
+                        .is_some_and(|left| left.kind() == "identifier");
+                    if is_member {
+                        variants.push(Variant {
+                            name: node_text(assignment, content)
+                                .split_whitespace()
+                                .collect::<Vec<_>>()
+                                .join(" "),
+                            doc: None,
+                        });
+                    }
+                }
+                "function_definition" => {
+                    if let Some(method) = extract_function(child, content, options) {
+                        if options.visibility == VisibilityThreshold::All
+                            || method.visibility() != Visibility::Private
+                        {
+                            methods.push(method);
+                        }
+                    }
+                }
+                "decorated_definition" => {
+                    extract_decorated(child, content, options, &mut methods);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let location = Location::new(node.start_position().row + 1, node.end_position().row + 1);
+
+    let doc = if options.include_docs {
+        extract_docstring(node, content)
+    } else {
+        None
+    };
+
+    Declaration::Enum {
+        name,
+        generics: String::new(),
+        variants: variants.into(),
+        visibility,
+        location,
+        methods,
+        implements: Vec::new(),
+        attributes: Vec::new(),
+        doc,
+        snippet: None,
+    }
+}
+
 fn extract_decorated(
     node: tree_sitter::Node,
     content: &str,
     options: &ExtractOptions,
     declarations: &mut Vec<Declaration>,
 ) {
+    let decorators: Vec<String> = node
+        .children(&mut node.walk())
+        .filter(|c| c.kind() == "decorator")
+        .map(|d| {
+            node_text(d, content)
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect();
+    let is_dataclass = decorators.iter().any(|d| is_dataclass_decorator(d));
+
     for child in node.children(&mut node.walk()) {
         match child.kind() {
             "function_definition" => {
-                if let Some(func) = extract_function(child, content, options) {
-                    if options.include_private || func.visibility() == Visibility::Public {
+                if let Some(mut func) = extract_function(child, content, options) {
+                    if let Declaration::Function { attributes, .. } = &mut func {
+                        attributes.clone_from(&decorators);
+                    }
+                    if options.visibility.allows(func.visibility()) {
                         declarations.push(func);
                     }
                 }
             }
             "class_definition" => {
-                if let Some(class) = extract_class(child, content, options) {
+                if let Some(class) = extract_class(child, content, options, is_dataclass) {
                     declarations.push(class);
                 }
             }
@@ -229,6 +618,86 @@ fn extract_decorated(
     }
 }
 
+/// Decorators that turn annotated class attributes into fields:
+/// `@dataclass`, `@dataclasses.dataclass(...)`, `@attr.s`, `@attrs.define`, ...
+fn is_dataclass_decorator(decorator: &str) -> bool {
+    let path = decorator.trim_start_matches('@').trim();
+    let path = path.split('(').next().unwrap_or(path).trim();
+    matches!(
+        path,
+        "dataclass"
+            | "dataclasses.dataclass"
+            | "attr.s"
+            | "attr.attrs"
+            | "attr.define"
+            | "attr.frozen"
+            | "attrs.define"
+            | "attrs.frozen"
+            | "define"
+            | "frozen"
+    )
+}
+
+/// A dataclass-style class attribute: annotated (`name: str` or
+/// `timeout: int = 30`) or a classic attrs `count = attr.ib(type=int)`.
+/// `ClassVar` annotations declare class-level state, not fields.
+fn extract_class_field(node: tree_sitter::Node, content: &str) -> Option<Field> {
+    let assignment = find_child_by_kind(node, "assignment")?;
+    let left = assignment.child_by_field_name("left")?;
+    if left.kind() != "identifier" {
+        return None;
+    }
+    let ty = match assignment.child_by_field_name("type") {
+        Some(ty) => {
+            let ty = node_text(ty, content);
+            if is_class_var(&ty) {
+                return None;
+            }
+            ty
+        }
+        None => attrib_type(assignment.child_by_field_name("right")?, content)?,
+    };
+    let name = node_text(left, content);
+
+    Some(Field {
+        visibility: name_visibility(&name),
+        name,
+        ty,
+        modifiers: Vec::new(),
+        tag: None,
+        doc: None,
+    })
+}
+
+/// `ClassVar`, `ClassVar[int]` or `typing.ClassVar[int]`.
+fn is_class_var(annotation: &str) -> bool {
+    let base = annotation.split('[').next().unwrap_or(annotation).trim();
+    base == "ClassVar" || base.ends_with(".ClassVar")
+}
+
+/// Type of a classic attrs attribute (`attr.ib(type=int)`), empty when it
+/// has none, or `None` if `value` is not an `attr.ib()` call.
+fn attrib_type(value: tree_sitter::Node, content: &str) -> Option<String> {
+    if value.kind() != "call" {
+        return None;
+    }
+    let function = node_text(value.child_by_field_name("function")?, content);
+    if !matches!(function.as_str(), "attr.ib" | "attr.attrib" | "attrib") {
+        return None;
+    }
+    let ty = value.child_by_field_name("arguments").and_then(|args| {
+        args.named_children(&mut args.walk())
+            .filter(|arg| arg.kind() == "keyword_argument")
+            .find(|arg| {
+                arg.child_by_field_name("name")
+                    .is_some_and(|name| node_text(name, content) == "type")
+            })
+            .and_then(|arg| arg.child_by_field_name("value"))
+            .map(|ty| node_text(ty, content))
+    });
+    Some(ty.unwrap_or_default())
+}
+
 fn extract_docstring(node: tree_sitter::Node, content: &str) -> Option<String> {
     // Look for docstring as first statement in block
     let block = find_child_by_kind(node, "block")?;
@@ -272,7 +741,7 @@ def greet(name: str) -> str:
             include_docs: true,
             ..Default::default()
         };
-        let (_, decls) = extract(code, &opts).unwrap();
+        let (_, decls, _) = extract(code, &opts).unwrap();
         assert_eq!(decls.len(), 1);
 
         match &decls[0] {
@@ -296,7 +765,7 @@ class Handler:
     async def handle(self, request) -> Response:
         pass
 "#;
-        let (_, decls) = extract(code, &ExtractOptions::default()).unwrap();
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
         assert_eq!(decls.len(), 1);
 
         match &decls[0] {
@@ -316,7 +785,7 @@ from typing import List, Optional
 import os
 from .utils import helper
 "#;
-        let (imports, _) = extract(code, &ExtractOptions::default()).unwrap();
+        let (imports, _, _) = extract(code, &ExtractOptions::default()).unwrap();
         assert_eq!(imports.len(), 3);
         assert_eq!(imports[0].source, "typing");
         assert_eq!(imports[0].items.len(), 2);
@@ -335,10 +804,10 @@ def __private_func():
     pass
 "#;
         let opts = ExtractOptions {
-            include_private: true,
+            visibility: VisibilityThreshold::All,
             ..Default::default()
         };
-        let (_, decls) = extract(code, &opts).unwrap();
+        let (_, decls, _) = extract(code, &opts).unwrap();
         assert_eq!(decls.len(), 3);
 
         assert_eq!(decls[0].visibility(), Visibility::Public);
@@ -352,7 +821,7 @@ def __private_func():
 async def fetch_data(url: str) -> bytes:
     pass
 "#;
-        let (_, decls) = extract(code, &ExtractOptions::default()).unwrap();
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
 
         match &decls[0] {
             Declaration::Function {
@@ -366,4 +835,338 @@ async def fetch_data(url: str) -> bytes:
             _ => panic!("expected function"),
         }
     }
+
+    #[test]
+    fn test_skip_tests() {
+        let code = r"
+def helper():
+    pass
+
+def test_helper():
+    pass
+
+class TestHelper:
+    def test_runs(self):
+        pass
+
+class TestClient:
+    def get(self, url):
+        pass
+";
+        let opts = ExtractOptions {
+            skip_tests: true,
+            ..Default::default()
+        };
+        let (_, decls, _) = extract(code, &opts).unwrap();
+        let names: Vec<&str> = decls.iter().map(Declaration::name).collect();
+        assert_eq!(names, ["helper", "TestClient"]);
+    }
+
+    #[test]
+    fn test_module_constants() {
+        let code = r#"
+MAX_RETRIES = 3
+TIMEOUT: float = 2.5
+NAME = "pith"
+handlers: dict[str, Handler] = {}
+DEFAULT = make_default()
+_cache = {}
+a, b = 1, 2
+"#;
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
+        let consts: Vec<(&str, &str)> = decls
+            .iter()
+            .map(|d| match d {
+                Declaration::Const { name, ty, .. } => (name.as_str(), ty.as_str()),
+                _ => panic!("expected const"),
+            })
+            .collect();
+
+        assert_eq!(
+            consts,
+            [
+                ("MAX_RETRIES", "int"),
+                ("TIMEOUT", "float"),
+                ("NAME", "str"),
+                ("handlers", "dict[str, Handler]"),
+                ("DEFAULT", ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dataclass_fields() {
+        let code = r"
+@dataclass(frozen=True)
+class Config:
+    name: str
+    timeout: int = 30
+    _cache: dict = field(default_factory=dict)
+    VERSION = 1
+
+    def validate(self) -> bool:
+        pass
+
+class Plain:
+    name: str
+";
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
+
+        match &decls[0] {
+            Declaration::Class {
+                fields, members, ..
+            } => {
+                let fields: Vec<_> = fields
+                    .iter()
+                    .map(|f| (f.name.as_str(), f.ty.as_str(), f.visibility))
+                    .collect();
+                assert_eq!(
+                    fields,
+                    [
+                        ("name", "str", Visibility::Public),
+                        ("timeout", "int", Visibility::Public),
+                        ("_cache", "dict", Visibility::Protected),
+                    ]
+                );
+                assert_eq!(members.len(), 1);
+            }
+            _ => panic!("expected class"),
+        }
+        match &decls[1] {
+            Declaration::Class { fields, .. } => assert!(fields.is_empty()),
+            _ => panic!("expected class"),
+        }
+    }
+
+    #[test]
+    fn test_dataclass_skips_class_vars() {
+        let code = r"
+@dataclass
+class Config:
+    registry: ClassVar[dict] = {}
+    limit: typing.ClassVar[int] = 10
+    name: str
+";
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
+        match &decls[0] {
+            Declaration::Class { fields, .. } => {
+                let names: Vec<_> = fields.iter().map(|f| f.name.as_str()).collect();
+                assert_eq!(names, ["name"]);
+            }
+            _ => panic!("expected class"),
+        }
+    }
+
+    #[test]
+    fn test_attrs_attrib_fields() {
+        let code = r"
+@attr.s
+class Point:
+    x = attr.ib()
+    y = attr.ib(type=int, default=0)
+    label: str = attr.ib(default='')
+    ORIGIN = (0, 0)
+";
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
+        match &decls[0] {
+            Declaration::Class { fields, .. } => {
+                let fields: Vec<_> = fields
+                    .iter()
+                    .map(|f| (f.name.as_str(), f.ty.as_str()))
+                    .collect();
+                assert_eq!(fields, [("x", ""), ("y", "int"), ("label", "str")]);
+            }
+            _ => panic!("expected class"),
+        }
+    }
+
+    #[test]
+    fn test_decorators_on_functions() {
+        let code = r#"
+@app.route("/users", methods=["GET"])
+@login_required
+def list_users():
+    pass
+
+class User:
+    @property
+    def name(self) -> str:
+        pass
+
+    @staticmethod
+    def create():
+        pass
+"#;
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
+
+        match &decls[0] {
+            Declaration::Function { attributes, .. } => {
+                assert_eq!(
+                    attributes,
+                    &[
+                        r#"@app.route("/users", methods=["GET"])"#,
+                        "@login_required"
+                    ]
+                );
+            }
+            _ => panic!("expected function"),
+        }
+        match &decls[1] {
+            Declaration::Class { members, .. } => {
+                let decorators: Vec<_> = members
+                    .iter()
+                    .map(|m| match m {
+                        Declaration::Function { attributes, .. } => attributes.join(" "),
+                        _ => panic!("expected method"),
+                    })
+                    .collect();
+                assert_eq!(decorators, ["@property", "@staticmethod"]);
+            }
+            _ => panic!("expected class"),
+        }
+    }
+
+    #[test]
+    fn test_enum_classes() {
+        let code = r#"
+class Color(enum.Enum):
+    """Primary colors."""
+    RED = 1
+    GREEN = auto()
+
+    def describe(self):
+        pass
+
+class Level(IntEnum):
+    LOW = 0
+
+class Config(Base):
+    LOW = 0
+"#;
+        let (_, decls, _) = extract(code, &ExtractOptions::with_docs()).unwrap();
+
+        match &decls[0] {
+            Declaration::Enum {
+                name,
+                variants,
+                methods,
+                doc,
+                ..
+            } => {
+                assert_eq!(name, "Color");
+                let names: Vec<_> = variants.iter().map(|v| v.name.as_str()).collect();
+                assert_eq!(names, ["RED = 1", "GREEN = auto()"]);
+                assert_eq!(doc.as_deref(), Some("Primary colors."));
+                assert_eq!(methods.len(), 1);
+                assert_eq!(methods[0].name(), "describe");
+            }
+            _ => panic!("expected enum"),
+        }
+        assert!(matches!(decls[1], Declaration::Enum { .. }));
+        assert!(matches!(decls[2], Declaration::Class { .. }));
+    }
+
+    #[test]
+    fn test_protocols_and_abcs() {
+        let code = r"
+class Handler(Protocol[T]):
+    name: str
+
+    def handle(self, req: Request) -> Response: ...
+
+class Store(abc.ABC):
+    @abstractmethod
+    def get(self, key: str) -> bytes:
+        pass
+
+    @property
+    @abstractmethod
+    def size(self) -> int:
+        pass
+
+class Repo(metaclass=ABCMeta):
+    def find(self): ...
+
+class Base:
+    @abstractmethod
+    def run(self):
+        pass
+
+class Service:
+    name: str
+
+    def run(self):
+        pass
+";
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
+
+        let interfaces: Vec<(&str, Vec<&str>)> = decls
+            .iter()
+            .filter_map(|d| match d {
+                Declaration::Interface { name, members, .. } => {
+                    Some((name.as_str(), members.iter().map(String::as_str).collect()))
+                }
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(
+            interfaces,
+            [
+                (
+                    "Handler",
+                    vec!["name: str", "def handle(self, req: Request) -> Response"]
+                ),
+                (
+                    "Store",
+                    vec![
+                        "def get(self, key: str) -> bytes",
+                        "@property def size(self) -> int"
+                    ]
+                ),
+                ("Repo", vec!["def find(self)"]),
+                ("Base", vec!["def run(self)"]),
+            ]
+        );
+        match &decls[4] {
+            Declaration::Class { fields, .. } => assert!(fields.is_empty()),
+            _ => panic!("expected class"),
+        }
+    }
+
+    #[test]
+    fn test_type_aliases() {
+        let code = r"
+UserMap = dict[str, User]
+Handler: TypeAlias = Callable[[Request], Response]
+MaybeInt = int | None
+type Pair[T] = tuple[T, T]
+DEFAULTS = dict(a=1)
+LIMITS = [1, 2]
+";
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
+
+        let aliases: Vec<(&str, &str, &str)> = decls
+            .iter()
+            .filter_map(|d| match d {
+                Declaration::TypeAlias {
+                    name,
+                    generics,
+                    target,
+                    ..
+                } => Some((name.as_str(), generics.as_str(), target.as_str())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            aliases,
+            [
+                ("UserMap", "", "dict[str, User]"),
+                ("Handler", "", "Callable[[Request], Response]"),
+                ("MaybeInt", "", "int | None"),
+                ("Pair", "[T]", "tuple[T, T]"),
+            ]
+        );
+        assert_eq!(decls.len(), 6);
+    }
 }
@@ -1,145 +1,341 @@
 //! Rust codemap extraction using tree-sitter.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use tree_sitter::Node;
 
 use super::{
-    find_child_by_kind, node_text, with_rust_parser, Declaration, ExtractOptions, Field, Import,
-    Location, Visibility,
+    collect_syntax_errors, find_child_by_kind, function_metrics, node_text, parse_source,
+    with_rust_parser, Declaration, ExtractOptions, Extracted, Field, Import, Location, StructKind,
+    Variant, Visibility,
 };
 
 /// Extract imports and declarations from Rust source code.
-pub fn extract(
-    content: &str,
-    options: &ExtractOptions,
-) -> Result<(Vec<Import>, Vec<Declaration>), String> {
+pub fn extract(content: &str, options: &ExtractOptions) -> Result<Extracted, String> {
     with_rust_parser(|parser| {
-        let tree = parser
-            .parse(content, None)
-            .ok_or_else(|| "failed to parse".to_string())?;
+        let tree = parse_source(parser, content, options)?;
 
         let mut imports = Vec::new();
         let mut declarations = Vec::new();
-        let mut impl_blocks: Vec<(String, Vec<Declaration>)> = Vec::new();
+        let mut impl_blocks: Vec<ImplBlock> = Vec::new();
+        let syntax_errors = collect_syntax_errors(tree.root_node(), content);
 
         extract_from_node(
             tree.root_node(),
             content,
             options,
+            &Module {
+                path: String::new(),
+                visibility: Visibility::Public,
+            },
             &mut imports,
             &mut declarations,
             &mut impl_blocks,
         );
 
-        // Merge impl methods with their structs using HashMap for O(1) lookup
+        // Merge impl blocks with their types using HashMap for O(1) lookup
         if !impl_blocks.is_empty() {
-            // Build index: struct name -> position in declarations (clone names to avoid borrow)
-            let struct_indices: HashMap<String, usize> = declarations
+            // Build index: type name -> position in declarations (clone names to avoid borrow)
+            let type_indices: HashMap<String, usize> = declarations
                 .iter()
                 .enumerate()
                 .filter_map(|(i, d)| match d {
-                    Declaration::Struct { name, .. } => Some((name.clone(), i)),
+                    Declaration::Struct { name, .. } | Declaration::Enum { name, .. } => {
+                        Some((name.clone(), i))
+                    }
                     _ => None,
                 })
                 .collect();
 
-            // Merge impl methods using the index
-            for (impl_type, methods) in impl_blocks {
-                if let Some(&idx) = struct_indices.get(&impl_type) {
-                    if let Declaration::Struct {
-                        methods: struct_methods,
-                        ..
-                    } = &mut declarations[idx]
-                    {
-                        struct_methods.extend(methods);
+            // Every type declared here, including ones filtered out, so an
+            // impl for a hidden type isn't mistaken for one from elsewhere.
+            let mut declared = HashSet::new();
+            collect_type_names(tree.root_node(), content, "", &mut declared);
+
+            for block in impl_blocks {
+                let target = type_indices
+                    .get(&block.type_name)
+                    .map(|&idx| &mut declarations[idx]);
+
+                match (block.trait_name, target) {
+                    // Trait impls are recorded by name; the trait declaration
+                    // already documents the method signatures.
+                    (
+                        Some(trait_name),
+                        Some(
+                            Declaration::Struct { implements, .. }
+                            | Declaration::Enum { implements, .. },
+                        ),
+                    ) => {
+                        implements.push(trait_name);
+                    }
+                    (Some(_), _) if declared.contains(&block.type_name) => {}
+                    // Trait impl for a type declared elsewhere: keep its
+                    // methods, named by the implementing type.
+                    (Some(_), _) => {
+                        for mut method in block.methods {
+                            method.qualify(&block.type_name, "::");
+                            declarations.push(method);
+                        }
+                    }
+                    (
+                        None,
+                        Some(
+                            Declaration::Struct { methods, .. } | Declaration::Enum { methods, .. },
+                        ),
+                    ) => {
+                        methods.extend(block.methods);
+                    }
+                    (None, _) => {
+                        // No matching type found, add methods as standalone
+                        declarations.extend(block.methods);
                     }
-                } else {
-                    // No matching struct found, add methods as standalone
-                    declarations.extend(methods);
                 }
             }
         }
 
-        Ok((imports, declarations))
+        Ok((imports, declarations, syntax_errors))
     })?
 }
 
+/// The inline module an item is declared in.
+struct Module {
+    /// `::`-separated path from the file root, empty at the top level.
+    path: String,
+    /// The module's effective visibility, which caps the items in it.
+    visibility: Visibility,
+}
+
 fn extract_from_node(
     node: Node,
     content: &str,
     options: &ExtractOptions,
+    module: &Module,
     imports: &mut Vec<Import>,
     declarations: &mut Vec<Declaration>,
-    impl_blocks: &mut Vec<(String, Vec<Declaration>)>,
+    impl_blocks: &mut Vec<ImplBlock>,
 ) {
     let mut cursor = node.walk();
+    let module_path = module.path.as_str();
+    let module_visibility = module.visibility;
+
+    // Push a declaration if visible, qualifying its name with the module path.
+    // Items are no more visible than the module they're declared in.
+    let push = |declarations: &mut Vec<Declaration>, mut decl: Declaration| {
+        cap_visibility(&mut decl, module_visibility);
+        if options.visibility.allows(decl.visibility()) {
+            if !module_path.is_empty() {
+                decl.qualify(module_path, "::");
+            }
+            declarations.push(decl);
+        }
+    };
 
     for child in node.children(&mut cursor) {
+        if options.skip_tests && is_test_item(child, content) {
+            continue;
+        }
+
         match child.kind() {
             "use_declaration" => {
                 if let Some(import) = extract_use(child, content) {
-                    imports.push(import);
+                    if find_child_by_kind(child, "visibility_modifier").is_some() {
+                        // `pub use` is part of this module's API, not a dependency.
+                        let mut decl = Declaration::ReExport {
+                            name: import.source,
+                            items: import.items,
+                            visibility: extract_visibility(child, content),
+                            location: Location::new(
+                                child.start_position().row + 1,
+                                child.end_position().row + 1,
+                            ),
+                            snippet: None,
+                        };
+                        cap_visibility(&mut decl, module_visibility);
+                        if options.visibility.allows(decl.visibility()) {
+                            declarations.push(decl);
+                        }
+                    } else if module_path.is_empty() {
+                        // Imports of inline modules (`use super::*` in
+                        // `mod tests`) aren't dependencies of the file.
+                        imports.push(import);
+                    }
                 }
             }
             "function_item" => {
                 if let Some(func) = extract_function(child, content, options) {
-                    if options.include_private || func.visibility() == Visibility::Public {
-                        declarations.push(func);
-                    }
+                    push(declarations, func);
                 }
             }
-            "struct_item" => {
+            "struct_item" | "union_item" => {
                 if let Some(decl) = extract_struct(child, content, options) {
-                    if options.include_private || decl.visibility() == Visibility::Public {
-                        declarations.push(decl);
-                    }
+                    push(declarations, decl);
                 }
             }
             "enum_item" => {
                 if let Some(decl) = extract_enum(child, content, options) {
-                    if options.include_private || decl.visibility() == Visibility::Public {
-                        declarations.push(decl);
-                    }
+                    push(declarations, decl);
                 }
             }
             "trait_item" => {
                 if let Some(decl) = extract_trait(child, content, options) {
-                    if options.include_private || decl.visibility() == Visibility::Public {
-                        declarations.push(decl);
-                    }
+                    push(declarations, decl);
                 }
             }
             "type_item" => {
                 if let Some(decl) = extract_type_alias(child, content) {
-                    if options.include_private || decl.visibility() == Visibility::Public {
-                        declarations.push(decl);
-                    }
+                    push(declarations, decl);
                 }
             }
             "const_item" | "static_item" => {
                 if let Some(decl) = extract_const(child, content) {
-                    if options.include_private || decl.visibility() == Visibility::Public {
-                        declarations.push(decl);
-                    }
+                    push(declarations, decl);
+                }
+            }
+            "foreign_mod_item" => {
+                for decl in extract_foreign_items(child, content, options) {
+                    push(declarations, decl);
                 }
             }
             "impl_item" => {
                 // Collect impl blocks for later merging with HashMap
-                if let Some(impl_data) = extract_impl(child, content, options) {
+                if let Some(mut impl_data) = extract_impl(child, content, options) {
+                    qualify(&mut impl_data.type_name, module_path);
+                    for method in &mut impl_data.methods {
+                        cap_visibility(method, module_visibility);
+                    }
+                    impl_data
+                        .methods
+                        .retain(|method| options.visibility.allows(method.visibility()));
                     impl_blocks.push(impl_data);
                 }
             }
+            "mod_item" => {
+                // Inline modules: recurse into the body with an extended path.
+                // `mod foo;` declarations have no body and live in another file.
+                if let (Some(name), Some(body)) = (
+                    child.child_by_field_name("name"),
+                    child.child_by_field_name("body"),
+                ) {
+                    let nested_path = if module_path.is_empty() {
+                        node_text(name, content)
+                    } else {
+                        format!("{}::{}", module_path, node_text(name, content))
+                    };
+                    extract_from_node(
+                        body,
+                        content,
+                        options,
+                        &Module {
+                            path: nested_path,
+                            visibility: narrowest(
+                                module_visibility,
+                                extract_visibility(child, content),
+                            ),
+                        },
+                        imports,
+                        declarations,
+                        impl_blocks,
+                    );
+                }
+            }
+            "ERROR" => extract_from_node(
+                child,
+                content,
+                options,
+                module,
+                imports,
+                declarations,
+                impl_blocks,
+            ),
+            _ => {}
+        }
+    }
+}
+
+/// Names of the structs, unions, enums and type aliases declared under
+/// `node`, qualified with their inline module path.
+fn collect_type_names(node: Node, content: &str, module_path: &str, names: &mut HashSet<String>) {
+    for child in node.children(&mut node.walk()) {
+        match child.kind() {
+            "struct_item" | "union_item" | "enum_item" | "type_item" => {
+                if let Some(name) = child.child_by_field_name("name") {
+                    let mut name = node_text(name, content);
+                    qualify(&mut name, module_path);
+                    names.insert(name);
+                }
+            }
+            "mod_item" => {
+                if let (Some(name), Some(body)) = (
+                    child.child_by_field_name("name"),
+                    child.child_by_field_name("body"),
+                ) {
+                    let mut nested_path = node_text(name, content);
+                    qualify(&mut nested_path, module_path);
+                    collect_type_names(body, content, &nested_path, names);
+                }
+            }
+            "ERROR" => collect_type_names(child, content, module_path, names),
+            _ => {}
+        }
+    }
+}
+
+/// Names of test functions (`#[test]`, `#[tokio::test]`, ...), qualified
+/// with their inline module path (`tests::it_works`).
+pub fn extract_test_names(content: &str) -> Result<Vec<String>, String> {
+    with_rust_parser(|parser| {
+        let tree = parser
+            .parse(content, None)
+            .ok_or_else(|| "failed to parse".to_string())?;
+
+        let mut names = Vec::new();
+        collect_test_names(tree.root_node(), content, "", &mut names);
+        Ok(names)
+    })?
+}
+
+fn collect_test_names(node: Node, content: &str, module_path: &str, names: &mut Vec<String>) {
+    for child in node.children(&mut node.walk()) {
+        match child.kind() {
+            "function_item" => {
+                let is_test = preceding_attributes(child, content)
+                    .iter()
+                    .any(|attr| is_test_attribute(attr));
+                if let (true, Some(name)) = (is_test, child.child_by_field_name("name")) {
+                    let mut name = node_text(name, content);
+                    qualify(&mut name, module_path);
+                    names.push(name);
+                }
+            }
+            "mod_item" => {
+                if let (Some(name), Some(body)) = (
+                    child.child_by_field_name("name"),
+                    child.child_by_field_name("body"),
+                ) {
+                    let mut nested_path = node_text(name, content);
+                    qualify(&mut nested_path, module_path);
+                    collect_test_names(body, content, &nested_path, names);
+                }
+            }
             _ => {}
         }
     }
 }
 
+/// Prefix `name` with `module_path` (`foo::Bar`); no-op at the crate root.
+fn qualify(name: &mut String, module_path: &str) {
+    if !module_path.is_empty() {
+        *name = format!("{}::{}", module_path, name);
+    }
+}
+
 fn extract_use(node: Node, content: &str) -> Option<Import> {
     let text = node_text(node, content);
 
     // Parse use statement to extract source and items
-    // Strip "pub " for re-exports, then "use ", then trailing ";"
+    // Strip the visibility for re-exports, then "use ", then trailing ";"
     let text = text
+        .trim_start_matches("pub(crate) ")
         .trim_start_matches("pub ")
         .trim_start_matches("use ")
         .trim_end_matches(';')
@@ -215,11 +411,17 @@ fn extract_function(node: Node, content: &str, options: &ExtractOptions) -> Opti
 
     Some(Declaration::Function {
         name,
+        scope: None,
         signature,
         visibility,
         location,
         is_async,
+        overloads: Vec::new(),
+        component: None,
+        metrics: function_metrics(node),
+        attributes: extract_attributes(node, content),
         doc,
+        snippet: None,
     })
 }
 
@@ -228,7 +430,7 @@ fn build_function_signature(node: Node, content: &str) -> String {
 
     for child in node.children(&mut node.walk()) {
         match child.kind() {
-            "block" => break, // Stop before the function body
+            "block" | ";" => break, // Stop before the function body
             _ => {
                 let text = node_text(child, content);
                 if !signature.is_empty() && !text.starts_with(',') && !text.starts_with(')') {
@@ -240,7 +442,32 @@ fn build_function_signature(node: Node, content: &str) -> String {
     }
 
     // Clean up whitespace
-    signature.split_whitespace().collect::<Vec<_>>().join(" ")
+    normalize_whitespace(&signature)
+}
+
+/// Collect the generic parameters, supertrait bounds and where clause that
+/// follow a type's name. A where clause is prefixed with a space so the
+/// result can be appended directly to the name.
+fn extract_generics(node: Node, content: &str) -> String {
+    let mut generics = String::new();
+    for child in node.children(&mut node.walk()) {
+        match child.kind() {
+            "type_parameters" | "trait_bounds" => {
+                generics.push_str(&normalize_whitespace(&node_text(child, content)));
+            }
+            "where_clause" => {
+                generics.push(' ');
+                generics.push_str(&normalize_whitespace(&node_text(child, content)));
+            }
+            _ => {}
+        }
+    }
+    generics
+}
+
+/// Collapse runs of whitespace (including newlines) into single spaces.
+fn normalize_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
 }
 
 fn extract_struct(node: Node, content: &str, options: &ExtractOptions) -> Option<Declaration> {
@@ -249,14 +476,23 @@ fn extract_struct(node: Node, content: &str, options: &ExtractOptions) -> Option
     let visibility = extract_visibility(node, content);
 
     let mut fields = Vec::new();
+    let mut kind = StructKind::Unit;
     if let Some(field_list) = find_child_by_kind(node, "field_declaration_list") {
+        kind = if node.kind() == "union_item" {
+            StructKind::Union
+        } else {
+            StructKind::Named
+        };
         for field_node in field_list.children(&mut field_list.walk()) {
             if field_node.kind() == "field_declaration" {
-                if let Some(field) = extract_field(field_node, content) {
+                if let Some(field) = extract_field(field_node, content, options) {
                     fields.push(field);
                 }
             }
         }
+    } else if let Some(field_list) = find_child_by_kind(node, "ordered_field_declaration_list") {
+        kind = StructKind::Tuple;
+        fields = extract_tuple_fields(field_list, content);
     }
 
     let location = Location::new(node.start_position().row + 1, node.end_position().row + 1);
@@ -269,33 +505,74 @@ fn extract_struct(node: Node, content: &str, options: &ExtractOptions) -> Option
 
     Some(Declaration::Struct {
         name,
+        generics: extract_generics(node, content),
+        kind,
         fields: fields.into(),
         visibility,
         location,
         methods: Vec::new(), // Will be populated by impl extraction
+        implements: Vec::new(),
+        attributes: extract_attributes(node, content),
         doc,
+        snippet: None,
     })
 }
 
-fn extract_field(node: Node, content: &str) -> Option<Field> {
+fn extract_field(node: Node, content: &str, options: &ExtractOptions) -> Option<Field> {
     let name = find_child_by_kind(node, "field_identifier").map(|n| node_text(n, content))?;
 
-    let ty = find_child_by_kind(node, "type_identifier")
-        .or_else(|| find_child_by_kind(node, "generic_type"))
-        .or_else(|| find_child_by_kind(node, "reference_type"))
-        .or_else(|| find_child_by_kind(node, "primitive_type"))
-        .map(|n| node_text(n, content))
+    let ty = node
+        .child_by_field_name("type")
+        .map(|n| normalize_whitespace(&node_text(n, content)))
         .unwrap_or_default();
 
     let visibility = extract_visibility(node, content);
 
+    let doc = if options.include_docs {
+        extract_doc_comment(node, content)
+    } else {
+        None
+    };
+
     Some(Field {
         name,
         ty,
         visibility,
+        modifiers: Vec::new(),
+        tag: None,
+        doc,
     })
 }
 
+/// Tuple struct fields are named by position (`0`, `1`, ...). Each type may be
+/// preceded by its own visibility modifier and attributes.
+fn extract_tuple_fields(field_list: Node, content: &str) -> Vec<Field> {
+    let mut fields = Vec::new();
+    let mut visibility = Visibility::Private;
+
+    for child in field_list.named_children(&mut field_list.walk()) {
+        match child.kind() {
+            "visibility_modifier" => {
+                visibility = parse_visibility(&node_text(child, content));
+            }
+            "attribute_item" | "line_comment" | "block_comment" => {}
+            _ => {
+                fields.push(Field {
+                    name: fields.len().to_string(),
+                    ty: normalize_whitespace(&node_text(child, content)),
+                    visibility,
+                    modifiers: Vec::new(),
+                    tag: None,
+                    doc: None,
+                });
+                visibility = Visibility::Private;
+            }
+        }
+    }
+
+    fields
+}
+
 fn extract_enum(node: Node, content: &str, options: &ExtractOptions) -> Option<Declaration> {
     let name = find_child_by_kind(node, "type_identifier").map(|n| node_text(n, content))?;
 
@@ -305,8 +582,15 @@ fn extract_enum(node: Node, content: &str, options: &ExtractOptions) -> Option<D
     if let Some(variant_list) = find_child_by_kind(node, "enum_variant_list") {
         for variant_node in variant_list.children(&mut variant_list.walk()) {
             if variant_node.kind() == "enum_variant" {
-                let variant_text = node_text(variant_node, content);
-                variants.push(variant_text);
+                let doc = if options.include_docs {
+                    extract_doc_comment(variant_node, content)
+                } else {
+                    None
+                };
+                variants.push(Variant {
+                    name: node_text(variant_node, content),
+                    doc,
+                });
             }
         }
     }
@@ -321,10 +605,15 @@ fn extract_enum(node: Node, content: &str, options: &ExtractOptions) -> Option<D
 
     Some(Declaration::Enum {
         name,
+        generics: extract_generics(node, content),
         variants: variants.into(),
         visibility,
         location,
+        methods: Vec::new(), // Will be populated by impl extraction
+        implements: Vec::new(),
+        attributes: extract_attributes(node, content),
         doc,
+        snippet: None,
     })
 }
 
@@ -334,9 +623,26 @@ fn extract_trait(node: Node, content: &str, options: &ExtractOptions) -> Option<
     let mut methods = Vec::new();
     if let Some(body) = find_child_by_kind(node, "declaration_list") {
         for item in body.children(&mut body.walk()) {
-            if item.kind() == "function_signature_item" {
-                let sig = node_text(item, content);
-                methods.push(sig.trim_end_matches(';').to_string());
+            match item.kind() {
+                "function_signature_item" | "associated_type" => {
+                    let sig = node_text(item, content);
+                    methods.push(normalize_whitespace(sig.trim_end_matches(';')));
+                }
+                "const_item" => {
+                    // Keep the declared type, drop any default value.
+                    let sig = node_text(item, content);
+                    let sig = sig.split_once('=').map_or(sig.as_str(), |(decl, _)| decl);
+                    methods.push(normalize_whitespace(sig.trim_end_matches(';')));
+                }
+                "function_item" => {
+                    // Default method: signature only, body dropped. Sliced from
+                    // source so it reads like the required methods around it.
+                    let end = item
+                        .child_by_field_name("body")
+                        .map_or(item.end_byte(), |b| b.start_byte());
+                    methods.push(normalize_whitespace(&content[item.start_byte()..end]));
+                }
+                _ => {}
             }
         }
     }
@@ -353,10 +659,13 @@ fn extract_trait(node: Node, content: &str, options: &ExtractOptions) -> Option<
 
     Some(Declaration::Trait {
         name,
+        generics: extract_generics(node, content),
         methods: methods.into(),
         visibility,
         location,
+        attributes: extract_attributes(node, content),
         doc,
+        snippet: None,
     })
 }
 
@@ -377,9 +686,11 @@ fn extract_type_alias(node: Node, content: &str) -> Option<Declaration> {
 
     Some(Declaration::TypeAlias {
         name,
+        generics: extract_generics(node, content),
         target,
         visibility,
         location,
+        snippet: None,
     })
 }
 
@@ -388,9 +699,9 @@ fn extract_const(node: Node, content: &str) -> Option<Declaration> {
 
     let visibility = extract_visibility(node, content);
 
-    let ty = find_child_by_kind(node, "type_identifier")
-        .or_else(|| find_child_by_kind(node, "primitive_type"))
-        .map(|n| node_text(n, content))
+    let ty = node
+        .child_by_field_name("type")
+        .map(|n| normalize_whitespace(&node_text(n, content)))
         .unwrap_or_default();
 
     let location = Location::new(node.start_position().row + 1, node.end_position().row + 1);
@@ -400,25 +711,77 @@ fn extract_const(node: Node, content: &str) -> Option<Declaration> {
         ty,
         visibility,
         location,
+        is_static: node.kind() == "static_item",
+        snippet: None,
     })
 }
 
-/// Extract impl block and return (type_name, methods) for later merging.
-fn extract_impl(
-    node: Node,
-    content: &str,
-    options: &ExtractOptions,
-) -> Option<(String, Vec<Declaration>)> {
+/// Extract function signatures and statics from an `extern "C" { ... }` block.
+/// The ABI is folded into each signature: `pub extern "C" fn strlen(s: *const c_char) -> usize`.
+fn extract_foreign_items(node: Node, content: &str, options: &ExtractOptions) -> Vec<Declaration> {
+    let abi = find_child_by_kind(node, "extern_modifier")
+        .map_or_else(|| "extern".to_string(), |n| node_text(n, content));
+    let Some(body) = find_child_by_kind(node, "declaration_list") else {
+        return Vec::new();
+    };
+
+    let mut items = Vec::new();
+    for child in body.children(&mut body.walk()) {
+        match child.kind() {
+            "function_signature_item" => {
+                if let Some(mut func) = extract_function(child, content, options) {
+                    if let Declaration::Function { signature, .. } = &mut func {
+                        *signature = signature.replacen("fn ", &format!("{} fn ", abi), 1);
+                    }
+                    items.push(func);
+                }
+            }
+            "static_item" => {
+                items.extend(extract_const(child, content));
+            }
+            _ => {}
+        }
+    }
+    items
+}
+
+/// An `impl` block collected during extraction, merged into its type afterwards.
+struct ImplBlock {
+    /// Base name of the implementing type (`Wrapper` for `impl<T> Wrapper<T>`).
+    type_name: String,
+    /// Implemented trait as written (`fmt::Display`, `From<String>`), if any.
+    trait_name: Option<String>,
+    methods: Vec<Declaration>,
+}
+
+/// Extract impl block for later merging.
+fn extract_impl(node: Node, content: &str, options: &ExtractOptions) -> Option<ImplBlock> {
     // Get the type being implemented
-    let impl_type = find_child_by_kind(node, "type_identifier").map(|n| node_text(n, content))?;
+    let type_node = node.child_by_field_name("type")?;
+    let type_name = match type_node.kind() {
+        "generic_type" => type_node
+            .child_by_field_name("type")
+            .map(|n| node_text(n, content))?,
+        _ => node_text(type_node, content),
+    };
+
+    let trait_name = node
+        .child_by_field_name("trait")
+        .map(|n| node_text(n, content));
 
     // Extract methods from the impl block
     let mut methods = Vec::new();
     if let Some(body) = find_child_by_kind(node, "declaration_list") {
         for item in body.children(&mut body.walk()) {
             if item.kind() == "function_item" {
-                if let Some(func) = extract_function(item, content, options) {
-                    if options.include_private || func.visibility() == Visibility::Public {
+                if let Some(mut func) = extract_function(item, content, options) {
+                    // Trait impl methods are as visible as the trait, not `pub`.
+                    if let (Some(_), Declaration::Function { visibility, .. }) =
+                        (&trait_name, &mut func)
+                    {
+                        *visibility = Visibility::Public;
+                    }
+                    if options.visibility.allows(func.visibility()) {
                         methods.push(func);
                     }
                 }
@@ -426,25 +789,104 @@ fn extract_impl(
         }
     }
 
-    if methods.is_empty() {
+    if methods.is_empty() && trait_name.is_none() {
         None
     } else {
-        Some((impl_type, methods))
+        Some(ImplBlock {
+            type_name,
+            trait_name,
+            methods,
+        })
     }
 }
 
 fn extract_visibility(node: Node, content: &str) -> Visibility {
-    for child in node.children(&mut node.walk()) {
-        if child.kind() == "visibility_modifier" {
-            let text = node_text(child, content);
-            if text.contains("pub(crate)") {
-                return Visibility::Crate;
-            } else if text.starts_with("pub") {
-                return Visibility::Public;
-            }
+    find_child_by_kind(node, "visibility_modifier").map_or(Visibility::Private, |n| {
+        parse_visibility(&node_text(n, content))
+    })
+}
+
+/// Lower `decl`'s visibility to `cap` if it's more visible.
+fn cap_visibility(decl: &mut Declaration, cap: Visibility) {
+    let visibility = decl.visibility_mut();
+    *visibility = narrowest(*visibility, cap);
+}
+
+fn narrowest(a: Visibility, b: Visibility) -> Visibility {
+    let rank = |v: Visibility| match v {
+        Visibility::Public => 0,
+        Visibility::Crate => 1,
+        Visibility::Private | Visibility::Protected => 2,
+    };
+    if rank(a) >= rank(b) {
+        a
+    } else {
+        b
+    }
+}
+
+fn parse_visibility(modifier: &str) -> Visibility {
+    if modifier.contains("pub(crate)") {
+        Visibility::Crate
+    } else if modifier.starts_with("pub") {
+        Visibility::Public
+    } else {
+        Visibility::Private
+    }
+}
+
+/// Attribute paths recorded on declarations; lint and codegen hints such as
+/// `#[allow]` or `#[inline]` are dropped as noise.
+const RECORDED_ATTRIBUTES: &[&str] = &["derive", "serde", "cfg", "deprecated", "non_exhaustive"];
+
+fn extract_attributes(node: Node, content: &str) -> Vec<String> {
+    preceding_attributes(node, content)
+        .into_iter()
+        .filter(|attr| {
+            let path = attr
+                .trim_start_matches("#[")
+                .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .next()
+                .unwrap_or_default();
+            RECORDED_ATTRIBUTES.contains(&path)
+        })
+        .collect()
+}
+
+/// All outer attributes attached to `node`, in source order, whitespace-normalized.
+fn preceding_attributes(node: Node, content: &str) -> Vec<String> {
+    let mut attributes = Vec::new();
+    let mut prev = node.prev_sibling();
+
+    while let Some(sibling) = prev {
+        match sibling.kind() {
+            "attribute_item" => attributes.push(normalize_whitespace(&node_text(sibling, content))),
+            "line_comment" | "block_comment" => {}
+            _ => break,
         }
+        prev = sibling.prev_sibling();
+    }
+
+    attributes.reverse();
+    attributes
+}
+
+/// Whether an item is test-only: `#[cfg(test)]` anything, or a `#[test]` /
+/// `#[tokio::test]` style test function.
+fn is_test_item(node: Node, content: &str) -> bool {
+    if node.kind() == "attribute_item" || node.kind().ends_with("comment") {
+        return false;
     }
-    Visibility::Private
+    preceding_attributes(node, content)
+        .iter()
+        .any(|attr| attr == "#[cfg(test)]" || is_test_attribute(attr))
+}
+
+/// `#[test]`, or a runtime's test macro such as `#[tokio::test]`.
+fn is_test_attribute(attr: &str) -> bool {
+    let inner = attr.trim_start_matches("#[").trim_end_matches(']');
+    let path = inner.split('(').next().unwrap_or_default().trim();
+    path == "test" || path.ends_with("::test")
 }
 
 fn extract_doc_comment(node: Node, content: &str) -> Option<String> {
@@ -460,6 +902,8 @@ fn extract_doc_comment(node: Node, content: &str) -> Option<String> {
             } else {
                 break;
             }
+        } else if sibling.kind() == "attribute_item" {
+            // Doc comments may sit above or between attributes.
         } else if sibling.kind() == "block_comment" {
             let text = node_text(sibling, content);
             if text.starts_with("/**") {
@@ -485,6 +929,7 @@ fn extract_doc_comment(node: Node, content: &str) -> Option<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::codemap::VisibilityThreshold;
 
     #[test]
     fn test_extract_function() {
@@ -493,7 +938,7 @@ pub fn hello(name: &str) -> String {
     format!("Hello, {}", name)
 }
 "#;
-        let (_, decls) = extract(code, &ExtractOptions::default()).unwrap();
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
         assert_eq!(decls.len(), 1);
 
         match &decls[0] {
@@ -515,7 +960,7 @@ pub struct Config {
     timeout: u64,
 }
 "#;
-        let (_, decls) = extract(code, &ExtractOptions::default()).unwrap();
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
         assert_eq!(decls.len(), 1);
 
         match &decls[0] {
@@ -542,7 +987,7 @@ pub enum Status {
     Error(String),
 }
 "#;
-        let (_, decls) = extract(code, &ExtractOptions::default()).unwrap();
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
         assert_eq!(decls.len(), 1);
 
         match &decls[0] {
@@ -563,7 +1008,7 @@ pub enum Status {
  use foo::bar as baz;
  use foo::{self, qux};
  "#;
-        let (imports, _) = extract(code, &ExtractOptions::default()).unwrap();
+        let (imports, _, _) = extract(code, &ExtractOptions::default()).unwrap();
 
         assert!(imports.iter().any(|i| {
             i.source == "std::collections" && i.items.iter().any(|it| it == "HashMap")
@@ -601,30 +1046,63 @@ pub enum Status {
      fn b(&self);
  }
  "#;
-        // With include_private = false (default)
-        let (_, decls) = extract(code, &ExtractOptions::default()).unwrap();
+        // With the default Public threshold
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
         assert_eq!(decls.len(), 2);
         assert!(decls.iter().any(|d| d.name() == "public_fn"));
         assert!(decls.iter().any(|d| d.name() == "PublicTrait"));
         assert!(!decls.iter().any(|d| d.name() == "PrivateTrait"));
 
-        // With include_private = true
+        // With the All threshold
         let opts = ExtractOptions {
-            include_private: true,
+            visibility: VisibilityThreshold::All,
             ..Default::default()
         };
-        let (_, decls) = extract(code, &opts).unwrap();
+        let (_, decls, _) = extract(code, &opts).unwrap();
         assert_eq!(decls.len(), 4);
         assert!(decls.iter().any(|d| d.name() == "private_fn"));
         assert!(decls.iter().any(|d| d.name() == "PrivateTrait"));
     }
 
+    #[test]
+    fn test_crate_visibility_threshold() {
+        let code = r"
+pub fn public_fn() {}
+pub(crate) fn crate_fn() {}
+fn private_fn() {}
+
+pub struct Config {
+    pub name: String,
+    pub(crate) cache: u32,
+    secret: String,
+}
+
+impl Config {
+    pub(crate) fn reload(&self) {}
+    fn validate(&self) {}
+}
+";
+        let opts = ExtractOptions {
+            visibility: VisibilityThreshold::Crate,
+            ..Default::default()
+        };
+        let (_, decls, _) = extract(code, &opts).unwrap();
+        let names: Vec<_> = decls.iter().map(Declaration::name).collect();
+        assert_eq!(names, ["public_fn", "crate_fn", "Config"]);
+
+        let Declaration::Struct { methods, .. } = &decls[2] else {
+            panic!("expected struct");
+        };
+        let methods: Vec<_> = methods.iter().map(Declaration::name).collect();
+        assert_eq!(methods, ["reload"]);
+    }
+
     #[test]
     fn test_async_function() {
         let code = r#"
 pub async fn fetch_data() -> Result<(), Error> {}
 "#;
-        let (_, decls) = extract(code, &ExtractOptions::default()).unwrap();
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
 
         match &decls[0] {
             Declaration::Function { is_async, .. } => {
@@ -633,4 +1111,504 @@ pub async fn fetch_data() -> Result<(), Error> {}
             _ => panic!("expected function"),
         }
     }
+
+    #[test]
+    fn test_trait_impls_recorded_on_type() {
+        let code = r"
+pub struct Config {}
+pub enum Mode { A }
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { Ok(()) }
+}
+impl From<String> for Config {
+    fn from(s: String) -> Self { Config {} }
+}
+impl Default for Mode {
+    fn default() -> Self { Mode::A }
+}
+impl Config {
+    pub fn new() -> Self { Config {} }
+}
+";
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
+        assert_eq!(decls.len(), 2);
+
+        match &decls[0] {
+            Declaration::Struct {
+                implements,
+                methods,
+                ..
+            } => {
+                assert_eq!(implements, &["fmt::Display", "From<String>"]);
+                assert_eq!(methods.len(), 1);
+                assert_eq!(methods[0].name(), "new");
+            }
+            _ => panic!("expected struct"),
+        }
+
+        match &decls[1] {
+            Declaration::Enum { implements, .. } => {
+                assert_eq!(implements, &["Default"]);
+            }
+            _ => panic!("expected enum"),
+        }
+    }
+
+    #[test]
+    fn test_trait_impl_for_foreign_type_keeps_methods() {
+        let code = r"
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { Ok(()) }
+}
+";
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
+        assert_eq!(decls.len(), 1);
+        assert_eq!(decls[0].name(), "Config::fmt");
+        assert_eq!(decls[0].visibility(), Visibility::Public);
+    }
+
+    #[test]
+    fn test_trait_impl_for_private_type_is_hidden() {
+        let code = r"
+struct Hidden;
+impl std::fmt::Display for Hidden {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { Ok(()) }
+}
+";
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
+        assert!(decls.is_empty());
+
+        let all = ExtractOptions {
+            visibility: VisibilityThreshold::All,
+            ..ExtractOptions::default()
+        };
+        let (_, decls, _) = extract(code, &all).unwrap();
+        assert_eq!(decls.len(), 1);
+        match &decls[0] {
+            Declaration::Struct { implements, .. } => {
+                assert_eq!(implements, &["std::fmt::Display"]);
+            }
+            _ => panic!("expected struct"),
+        }
+    }
+
+    #[test]
+    fn test_enum_impl_methods_attach_to_enum() {
+        let code = r"
+pub enum Mode { Fast, Slow }
+
+impl Mode {
+    pub fn is_fast(&self) -> bool { matches!(self, Mode::Fast) }
+}
+";
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
+        assert_eq!(decls.len(), 1);
+
+        match &decls[0] {
+            Declaration::Enum { methods, .. } => {
+                assert_eq!(methods.len(), 1);
+                assert_eq!(methods[0].name(), "is_fast");
+            }
+            _ => panic!("expected enum"),
+        }
+    }
+
+    #[test]
+    fn test_generic_impl_merges_into_struct() {
+        let code = r"
+pub struct Wrapper<T> { inner: T }
+
+impl<T: Clone> Wrapper<T> {
+    pub fn get(&self) -> T { self.inner.clone() }
+}
+";
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
+        assert_eq!(decls.len(), 1);
+
+        match &decls[0] {
+            Declaration::Struct { methods, .. } => {
+                assert_eq!(methods.len(), 1);
+                assert_eq!(methods[0].name(), "get");
+            }
+            _ => panic!("expected struct"),
+        }
+    }
+
+    #[test]
+    fn test_nested_modules() {
+        let code = r"
+pub mod net {
+    pub struct Client;
+
+    impl Client {
+        pub fn connect(&self) {}
+    }
+
+    pub mod http {
+        pub fn get(url: &str) {}
+    }
+}
+
+mod external;
+";
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
+        assert_eq!(decls.len(), 2);
+
+        match &decls[0] {
+            Declaration::Struct { name, methods, .. } => {
+                assert_eq!(name, "net::Client");
+                assert_eq!(methods.len(), 1);
+            }
+            _ => panic!("expected struct"),
+        }
+        match &decls[1] {
+            Declaration::Function { name, scope, .. } => {
+                assert_eq!(name, "net::http::get");
+                assert_eq!(scope.as_deref(), Some("net::http"));
+            }
+            _ => panic!("expected function"),
+        }
+    }
+
+    #[test]
+    fn test_nested_module_visibility_and_imports() {
+        let code = r"
+use std::io;
+
+pub fn top() {}
+
+mod private {
+    use std::fmt;
+    pub fn hidden() {}
+}
+
+pub mod api {
+    pub struct Handle;
+
+    impl Handle {
+        pub fn open(&self) {}
+    }
+
+    pub(crate) mod internal {
+        pub fn helper() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+}
+";
+        let (imports, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
+        let sources: Vec<_> = imports.iter().map(|i| i.source.as_str()).collect();
+        assert_eq!(sources, ["std"]);
+        let names: Vec<_> = decls.iter().map(Declaration::name).collect();
+        assert_eq!(names, ["top", "api::Handle"]);
+        assert_eq!(decls[1].nested().len(), 1);
+
+        let options = ExtractOptions {
+            visibility: VisibilityThreshold::Crate,
+            ..Default::default()
+        };
+        let (_, decls, _) = extract(code, &options).unwrap();
+        let helper = decls
+            .iter()
+            .find(|d| d.name() == "api::internal::helper")
+            .expect("helper is crate-visible");
+        assert_eq!(helper.visibility(), Visibility::Crate);
+        assert!(decls.iter().all(|d| d.name() != "private::hidden"));
+    }
+
+    #[test]
+    fn test_trait_associated_items() {
+        let code = r"
+pub trait Stream {
+    type Item: Clone;
+    const CAPACITY: usize = 16;
+    fn next(&mut self) -> Option<Self::Item>;
+    fn count(self) -> usize where Self: Sized {
+        0
+    }
+}
+";
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
+
+        match &decls[0] {
+            Declaration::Trait { methods, .. } => {
+                assert_eq!(
+                    methods.as_slice(),
+                    [
+                        "type Item: Clone",
+                        "const CAPACITY: usize",
+                        "fn next(&mut self) -> Option<Self::Item>",
+                        "fn count(self) -> usize where Self: Sized",
+                    ]
+                );
+            }
+            _ => panic!("expected trait"),
+        }
+    }
+
+    #[test]
+    fn test_generics_and_where_clauses() {
+        let code = r"
+pub struct Wrapper<T: Clone>(T) where T: Debug;
+pub enum Tree<'a, T> where T: 'a { Leaf(&'a T) }
+pub trait Store<K>: Send + Sync {}
+pub type Res<T> = Result<T, Error>;
+";
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
+        let generics: Vec<&str> = decls
+            .iter()
+            .map(|d| match d {
+                Declaration::Struct { generics, .. }
+                | Declaration::Enum { generics, .. }
+                | Declaration::Trait { generics, .. }
+                | Declaration::TypeAlias { generics, .. } => generics.as_str(),
+                _ => panic!("unexpected declaration"),
+            })
+            .collect();
+
+        assert_eq!(
+            generics,
+            [
+                "<T: Clone> where T: Debug",
+                "<'a, T> where T: 'a",
+                "<K>: Send + Sync",
+                "<T>",
+            ]
+        );
+        assert_eq!(decls[0].name(), "Wrapper");
+    }
+
+    #[test]
+    fn test_attributes() {
+        let code = r#"
+/// A config.
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+#[serde(rename_all = "camelCase")]
+pub struct Config {}
+
+#[inline]
+#[deprecated(note = "use run")]
+pub fn start() {}
+"#;
+        let (_, decls, _) = extract(code, &ExtractOptions::with_docs()).unwrap();
+
+        match &decls[0] {
+            Declaration::Struct {
+                attributes, doc, ..
+            } => {
+                assert_eq!(
+                    attributes,
+                    &[
+                        "#[derive(Debug, Clone)]",
+                        "#[serde(rename_all = \"camelCase\")]"
+                    ]
+                );
+                assert_eq!(doc.as_deref(), Some("A config."));
+            }
+            _ => panic!("expected struct"),
+        }
+        match &decls[1] {
+            Declaration::Function { attributes, .. } => {
+                assert_eq!(attributes, &["#[deprecated(note = \"use run\")]"]);
+            }
+            _ => panic!("expected function"),
+        }
+    }
+
+    #[test]
+    fn test_pub_use_reexports() {
+        let code = r"
+use std::fmt;
+pub use crate::config::{Config, Error as ConfigError};
+pub(crate) use crate::util::helper;
+";
+        let (imports, decls, _) = extract(code, &ExtractOptions::with_docs()).unwrap();
+        assert_eq!(imports.len(), 1);
+        assert_eq!(imports[0].source, "std");
+        assert_eq!(decls.len(), 2);
+
+        match &decls[0] {
+            Declaration::ReExport {
+                name,
+                items,
+                visibility,
+                ..
+            } => {
+                assert_eq!(name, "crate::config");
+                assert_eq!(items.as_slice(), ["Config", "Error as ConfigError"]);
+                assert_eq!(*visibility, Visibility::Public);
+            }
+            _ => panic!("expected re-export"),
+        }
+        assert_eq!(decls[1].visibility(), Visibility::Crate);
+
+        let (_, public, _) = extract(code, &ExtractOptions::public_only()).unwrap();
+        assert_eq!(public.len(), 1);
+    }
+
+    #[test]
+    fn test_tuple_and_unit_structs() {
+        let code = r"
+pub struct Meters(pub f64);
+pub struct Pair(pub(crate) String, Vec<u8>);
+pub struct Marker;
+";
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
+        assert_eq!(decls.len(), 3);
+
+        match &decls[0] {
+            Declaration::Struct { kind, fields, .. } => {
+                assert_eq!(*kind, StructKind::Tuple);
+                assert_eq!(fields.len(), 1);
+                assert_eq!(fields[0].name, "0");
+                assert_eq!(fields[0].ty, "f64");
+                assert_eq!(fields[0].visibility, Visibility::Public);
+            }
+            _ => panic!("expected struct"),
+        }
+        match &decls[1] {
+            Declaration::Struct { fields, .. } => {
+                let summary: Vec<_> = fields
+                    .iter()
+                    .map(|f| (f.name.as_str(), f.ty.as_str(), f.visibility))
+                    .collect();
+                assert_eq!(
+                    summary,
+                    [
+                        ("0", "String", Visibility::Crate),
+                        ("1", "Vec<u8>", Visibility::Private),
+                    ]
+                );
+            }
+            _ => panic!("expected struct"),
+        }
+        match &decls[2] {
+            Declaration::Struct { kind, fields, .. } => {
+                assert_eq!(*kind, StructKind::Unit);
+                assert!(fields.is_empty());
+            }
+            _ => panic!("expected struct"),
+        }
+    }
+
+    #[test]
+    fn test_unions_statics_and_extern_blocks() {
+        let code = r#"
+pub union IntOrFloat {
+    pub i: u32,
+    pub f: [u8; 4],
+}
+
+pub static REGISTRY: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+extern "C" {
+    pub fn strlen(s: *const c_char) -> usize;
+    pub static errno: c_int;
+}
+"#;
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
+        assert_eq!(decls.len(), 4);
+
+        match &decls[0] {
+            Declaration::Struct { kind, fields, .. } => {
+                assert_eq!(*kind, StructKind::Union);
+                assert_eq!(fields[1].ty, "[u8; 4]");
+            }
+            _ => panic!("expected union"),
+        }
+        match &decls[1] {
+            Declaration::Const { ty, is_static, .. } => {
+                assert_eq!(ty, "Mutex<Vec<&'static str>>");
+                assert!(is_static);
+            }
+            _ => panic!("expected static"),
+        }
+        match &decls[2] {
+            Declaration::Function {
+                name, signature, ..
+            } => {
+                assert_eq!(name, "strlen");
+                assert_eq!(
+                    signature,
+                    r#"pub extern "C" fn strlen (s: *const c_char) -> usize"#
+                );
+            }
+            _ => panic!("expected function"),
+        }
+        assert_eq!(decls[3].name(), "errno");
+    }
+
+    #[test]
+    fn test_field_and_variant_docs() {
+        let code = r"
+pub struct Config {
+    /// Display name.
+    pub name: String,
+    #[serde(default)]
+    /// Seconds before giving up.
+    pub timeout: u64,
+}
+
+pub enum Mode {
+    /// Run once.
+    Once,
+    Forever,
+}
+";
+        let (_, decls, _) = extract(code, &ExtractOptions::with_docs()).unwrap();
+        match &decls[0] {
+            Declaration::Struct { fields, .. } => {
+                assert_eq!(fields[0].doc.as_deref(), Some("Display name."));
+                assert_eq!(fields[1].doc.as_deref(), Some("Seconds before giving up."));
+            }
+            _ => panic!("expected struct"),
+        }
+        match &decls[1] {
+            Declaration::Enum { variants, .. } => {
+                assert_eq!(variants[0].doc.as_deref(), Some("Run once."));
+                assert_eq!(variants[1].doc, None);
+            }
+            _ => panic!("expected enum"),
+        }
+
+        let (_, decls, _) = extract(code, &ExtractOptions::default()).unwrap();
+        match &decls[0] {
+            Declaration::Struct { fields, .. } => assert_eq!(fields[0].doc, None),
+            _ => panic!("expected struct"),
+        }
+    }
+
+    #[test]
+    fn test_skip_tests() {
+        let code = r#"
+pub fn run() {}
+
+#[test]
+fn standalone_test() {}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn async_test() {}
+
+#[cfg(test)]
+mod tests {
+    pub fn helper() {}
+}
+"#;
+        let opts = ExtractOptions {
+            visibility: VisibilityThreshold::All,
+            skip_tests: true,
+            ..Default::default()
+        };
+        let (_, decls, _) = extract(code, &opts).unwrap();
+        let names: Vec<&str> = decls.iter().map(Declaration::name).collect();
+        assert_eq!(names, ["run"]);
+
+        let (_, decls, _) = extract(code, &ExtractOptions::with_docs()).unwrap();
+        assert_eq!(decls.len(), 4);
+    }
 }
@@ -0,0 +1,123 @@
+//! `TODO`/`FIXME`/`HACK` comment inventory.
+
+use serde::{Deserialize, Serialize};
+
+use crate::filter::Language;
+
+/// Markers recognized in comments.
+const MARKERS: &[&str] = &["TODO", "FIXME", "HACK"];
+
+/// A `TODO`, `FIXME` or `HACK` comment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TodoComment {
+    /// The marker: "TODO", "FIXME" or "HACK".
+    pub kind: String,
+    /// Text following the marker (e.g., "(alice): handle timeouts").
+    pub text: String,
+    /// 1-indexed line of the comment.
+    pub line: usize,
+    /// 1-indexed notebook cell holding the comment; `line` is then relative
+    /// to the cell.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cell: Option<usize>,
+}
+
+/// Collect marker comments line by line. Only text after a comment token
+/// (`#` for Python, `//`, `/*` or a leading `*` otherwise) is searched, so
+/// identifiers like `TODO_LIST` in code are ignored.
+pub(crate) fn collect_todos(content: &str, language: Language) -> Vec<TodoComment> {
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let comment = comment_text(line, language)?;
+            let (kind, text) = find_marker(comment)?;
+            Some(TodoComment {
+                kind: kind.to_string(),
+                text,
+                line: i + 1,
+                cell: None,
+            })
+        })
+        .collect()
+}
+
+fn comment_text(line: &str, language: Language) -> Option<&str> {
+    if language == Language::Python {
+        return line.find('#').map(|i| &line[i + 1..]);
+    }
+
+    let trimmed = line.trim_start();
+    if let Some(rest) = trimmed.strip_prefix('*') {
+        // Continuation line of a block comment.
+        return Some(rest);
+    }
+    [line.find("//"), line.find("/*")]
+        .into_iter()
+        .flatten()
+        .min()
+        .map(|i| &line[i + 2..])
+}
+
+/// The first marker standing as its own word, and the text after it.
+fn find_marker(comment: &str) -> Option<(&'static str, String)> {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+
+    MARKERS
+        .iter()
+        .filter_map(|&marker| {
+            comment.match_indices(marker).find_map(|(i, _)| {
+                let before = comment[..i].chars().next_back();
+                let after = comment[i + marker.len()..].chars().next();
+                let standalone =
+                    !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char);
+                standalone.then_some((i, marker))
+            })
+        })
+        .min_by_key(|&(i, _)| i)
+        .map(|(i, marker)| {
+            let text = comment[i + marker.len()..]
+                .trim_end()
+                .trim_end_matches("*/")
+                .trim_start_matches(':')
+                .trim();
+            (marker, text.to_string())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_todos() {
+        let code = r"
+// TODO: handle timeouts
+fn main() {
+    let todo_list = TODO_ITEMS; // FIXME(alice): not thread safe
+    /* HACK work around the parser */
+}
+";
+        let todos = collect_todos(code, Language::Rust);
+        let found: Vec<(&str, &str, usize)> = todos
+            .iter()
+            .map(|t| (t.kind.as_str(), t.text.as_str(), t.line))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("TODO", "handle timeouts", 2),
+                ("FIXME", "(alice): not thread safe", 4),
+                ("HACK", "work around the parser", 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collect_todos_python() {
+        let code = "x = 1  # TODO: remove\n# TODOS are ignored\n";
+        let todos = collect_todos(code, Language::Python);
+        assert_eq!(todos.len(), 1);
+        assert_eq!(todos[0].text, "remove");
+    }
+}
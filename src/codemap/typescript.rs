@@ -1,9 +1,13 @@
 //! TypeScript/TSX codemap extraction using tree-sitter.
 
+use smallvec::SmallVec;
+use std::path::Path;
+
 use tree_sitter::Parser;
 
 use super::{
-    find_child_by_kind, node_text, with_ts_parser, with_tsx_parser, Declaration, ExtractOptions,
+    collect_syntax_errors, find_child_by_kind, function_metrics, node_text, parse_source,
+    with_ts_parser, with_tsx_parser, Component, Declaration, ExtractOptions, Extracted, Field,
     Import, Location, Visibility,
 };
 use crate::filter::Language;
@@ -13,14 +17,13 @@ pub fn extract(
     content: &str,
     language: Language,
     options: &ExtractOptions,
-) -> Result<(Vec<Import>, Vec<Declaration>), String> {
+) -> Result<Extracted, String> {
     let extract_fn = |parser: &mut Parser| {
-        let tree = parser
-            .parse(content, None)
-            .ok_or_else(|| "failed to parse".to_string())?;
+        let tree = parse_source(parser, content, options)?;
 
         let mut imports = Vec::new();
         let mut declarations = Vec::new();
+        let syntax_errors = collect_syntax_errors(tree.root_node(), content);
 
         extract_from_node(
             tree.root_node(),
@@ -29,8 +32,10 @@ pub fn extract(
             &mut imports,
             &mut declarations,
         );
+        merge_overloads(&mut declarations);
+        resolve_component_props(&mut declarations);
 
-        Ok((imports, declarations))
+        Ok((imports, declarations, syntax_errors))
     };
 
     match language {
@@ -39,6 +44,85 @@ pub fn extract(
     }
 }
 
+/// Declaration-only files: `.d.ts`, `.d.mts` and `.d.cts`.
+pub(crate) fn is_type_definition_file(path: &Path) -> bool {
+    path.file_name().and_then(|n| n.to_str()).is_some_and(|n| {
+        [".d.ts", ".d.mts", ".d.cts"]
+            .iter()
+            .any(|ext| n.ends_with(ext))
+    })
+}
+
+/// Test names from `describe`/`it`/`test` calls, nested as `suite > case`.
+pub fn extract_test_names(content: &str, language: Language) -> Result<Vec<String>, String> {
+    let extract_fn = |parser: &mut Parser| {
+        let tree = parser
+            .parse(content, None)
+            .ok_or_else(|| "failed to parse".to_string())?;
+
+        let mut names = Vec::new();
+        collect_test_calls(tree.root_node(), content, "", &mut names);
+        Ok(names)
+    };
+
+    match language {
+        Language::Tsx => with_tsx_parser(extract_fn)?,
+        _ => with_ts_parser(extract_fn)?,
+    }
+}
+
+fn collect_test_calls(
+    node: tree_sitter::Node,
+    content: &str,
+    suite: &str,
+    names: &mut Vec<String>,
+) {
+    for child in node.named_children(&mut node.walk()) {
+        if let Some((callee, title)) = test_call(child, content) {
+            let name = if suite.is_empty() {
+                title
+            } else {
+                format!("{suite} > {title}")
+            };
+            if callee == "describe" {
+                if let Some(args) = child.child_by_field_name("arguments") {
+                    collect_test_calls(args, content, &name, names);
+                }
+            } else {
+                names.push(name);
+            }
+        } else {
+            collect_test_calls(child, content, suite, names);
+        }
+    }
+}
+
+/// `describe("title", ...)`, `it("title", ...)` or `test("title", ...)`,
+/// including modifiers like `it.only`. Returns the callee and title.
+fn test_call(node: tree_sitter::Node, content: &str) -> Option<(String, String)> {
+    if node.kind() != "call_expression" {
+        return None;
+    }
+    let function = node.child_by_field_name("function")?;
+    let callee = match function.kind() {
+        "identifier" => node_text(function, content),
+        "member_expression" => node_text(function.child_by_field_name("object")?, content),
+        _ => return None,
+    };
+    if !["describe", "it", "test"].contains(&callee.as_str()) {
+        return None;
+    }
+
+    let title = node.child_by_field_name("arguments")?.named_child(0)?;
+    if !matches!(title.kind(), "string" | "template_string") {
+        return None;
+    }
+    let title = node_text(title, content)
+        .trim_matches(|c| matches!(c, '"' | '\'' | '`'))
+        .to_string();
+    Some((callee, title))
+}
+
 fn extract_from_node(
     node: tree_sitter::Node,
     content: &str,
@@ -56,14 +140,14 @@ fn extract_from_node(
                 }
             }
             "export_statement" => {
-                extract_export(child, content, options, declarations);
+                extract_export(child, content, options, imports, declarations);
             }
-            "function_declaration" => {
+            "function_declaration" | "function_signature" => {
                 if let Some(func) = extract_function(child, content, options, false) {
                     declarations.push(func);
                 }
             }
-            "class_declaration" => {
+            "class_declaration" | "abstract_class_declaration" => {
                 if let Some(class) = extract_class(child, content, options, false) {
                     declarations.push(class);
                 }
@@ -78,15 +162,272 @@ fn extract_from_node(
                     declarations.push(alias);
                 }
             }
-            "lexical_declaration" => {
-                // const/let declarations
+            "lexical_declaration" | "variable_declaration" => {
+                // const/let/var declarations
                 extract_lexical(child, content, options, declarations, false);
+                extract_requires(child, content, imports);
+            }
+            "internal_module" | "module" | "ambient_declaration" => {
+                extract_module(child, content, options, imports, declarations);
+            }
+            "expression_statement" => {
+                // A bare `namespace Foo { }` can parse as an expression.
+                if let Some(module) = find_child_by_kind(child, "internal_module") {
+                    extract_module(module, content, options, imports, declarations);
+                } else if let Some(import) = child
+                    .named_child(0)
+                    .and_then(|c| require_source(c, content))
+                {
+                    // Bare `require('./polyfill')`.
+                    imports.push(Import {
+                        source: import,
+                        items: SmallVec::new(),
+                    });
+                } else {
+                    extract_commonjs_export(child, content, options, declarations);
+                }
             }
+            "ERROR" => extract_from_node(child, content, options, imports, declarations),
             _ => {}
         }
     }
 }
 
+/// Recurse into `namespace Foo { }`, `declare module "foo" { }` and other
+/// `declare` blocks, prefixing contained names with the namespace (`Foo.Bar`).
+fn extract_module(
+    node: tree_sitter::Node,
+    content: &str,
+    options: &ExtractOptions,
+    imports: &mut Vec<Import>,
+    declarations: &mut Vec<Declaration>,
+) {
+    if node.kind() == "ambient_declaration" {
+        // `declare namespace`, `declare module`, `declare global`, `declare function`, ...
+        extract_from_node(node, content, options, imports, declarations);
+        for child in node.children(&mut node.walk()) {
+            if matches!(child.kind(), "lexical_declaration" | "variable_declaration") {
+                extract_declared_variables(child, content, declarations);
+            }
+        }
+        return;
+    }
+
+    let Some(body) = node.child_by_field_name("body") else {
+        return;
+    };
+    let namespace = node
+        .child_by_field_name("name")
+        .map(|n| node_text(n, content).trim_matches(['"', '\'']).to_string())
+        .unwrap_or_default();
+
+    let start = declarations.len();
+    extract_from_node(body, content, options, imports, declarations);
+    for decl in &mut declarations[start..] {
+        decl.qualify(&namespace, ".");
+    }
+}
+
+/// `declare const VERSION: string;` and friends: one constant per declarator.
+fn extract_declared_variables(
+    node: tree_sitter::Node,
+    content: &str,
+    declarations: &mut Vec<Declaration>,
+) {
+    for declarator in node.children(&mut node.walk()) {
+        if declarator.kind() != "variable_declarator" {
+            continue;
+        }
+        let Some(name) = declarator.child_by_field_name("name") else {
+            continue;
+        };
+        let ty = declarator
+            .child_by_field_name("type")
+            .map(|n| {
+                node_text(n, content)
+                    .trim_start_matches(':')
+                    .trim()
+                    .to_string()
+            })
+            .unwrap_or_default();
+        declarations.push(Declaration::Const {
+            name: node_text(name, content),
+            ty,
+            visibility: Visibility::Public,
+            location: Location::new(
+                declarator.start_position().row + 1,
+                declarator.end_position().row + 1,
+            ),
+            is_static: false,
+            snippet: None,
+        });
+    }
+}
+
+/// The module of a `require('y')` call, if `node` is one.
+fn require_source(node: tree_sitter::Node, content: &str) -> Option<String> {
+    if node.kind() != "call_expression" {
+        return None;
+    }
+    let function = node.child_by_field_name("function")?;
+    if node_text(function, content) != "require" {
+        return None;
+    }
+    let arguments = node.child_by_field_name("arguments")?;
+    let source = arguments.named_child(0).filter(|a| a.kind() == "string")?;
+    Some(
+        node_text(source, content)
+            .trim_matches(['"', '\'', '`'])
+            .to_string(),
+    )
+}
+
+/// CommonJS imports: `const fs = require('fs')` and
+/// `const { join, resolve: r } = require('path')`.
+fn extract_requires(node: tree_sitter::Node, content: &str, imports: &mut Vec<Import>) {
+    for declarator in node.children(&mut node.walk()) {
+        if declarator.kind() != "variable_declarator" {
+            continue;
+        }
+        let Some(source) = declarator
+            .child_by_field_name("value")
+            .and_then(|v| require_source(v, content))
+        else {
+            continue;
+        };
+
+        // Destructured names are the imported items, as with `import { a } from`.
+        let mut items = SmallVec::new();
+        if let Some(pattern) = declarator
+            .child_by_field_name("name")
+            .filter(|n| n.kind() == "object_pattern")
+        {
+            for property in pattern.named_children(&mut pattern.walk()) {
+                match property.kind() {
+                    "shorthand_property_identifier_pattern" => {
+                        items.push(node_text(property, content));
+                    }
+                    "pair_pattern" => {
+                        if let Some(key) = property.child_by_field_name("key") {
+                            items.push(node_text(key, content));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        imports.push(Import { source, items });
+    }
+}
+
+/// CommonJS exports: `module.exports = ...`, `module.exports.foo = ...` and
+/// `exports.foo = ...`. A single exported value is named `default`, like an
+/// ES default export.
+fn extract_commonjs_export(
+    node: tree_sitter::Node,
+    content: &str,
+    options: &ExtractOptions,
+    declarations: &mut Vec<Declaration>,
+) {
+    let Some(assignment) = node
+        .named_child(0)
+        .filter(|c| c.kind() == "assignment_expression")
+    else {
+        return;
+    };
+    let (Some(left), Some(right)) = (
+        assignment.child_by_field_name("left"),
+        assignment.child_by_field_name("right"),
+    ) else {
+        return;
+    };
+    let target = node_text(left, content);
+
+    if target == "module.exports" {
+        match right.kind() {
+            "object" => {
+                for member in right.named_children(&mut right.walk()) {
+                    match member.kind() {
+                        "pair" => {
+                            if let (Some(key), Some(value)) = (
+                                member.child_by_field_name("key"),
+                                member.child_by_field_name("value"),
+                            ) {
+                                let name = node_text(key, content);
+                                let prefix = format!("module.exports.{} = ", name);
+                                declarations.push(commonjs_value(
+                                    member, value, name, &prefix, content, options,
+                                ));
+                            }
+                        }
+                        "method_definition" => {
+                            declarations.extend(extract_method(member, content, options));
+                        }
+                        // `{ helper }` re-exports a declaration already extracted.
+                        _ => {}
+                    }
+                }
+            }
+            "class" => declarations.extend(extract_class(right, content, options, true)),
+            "identifier" => {}
+            _ => declarations.push(commonjs_value(
+                node,
+                right,
+                "default".to_string(),
+                "module.exports = ",
+                content,
+                options,
+            )),
+        }
+        return;
+    }
+
+    let Some(name) = target
+        .strip_prefix("module.exports.")
+        .or_else(|| target.strip_prefix("exports."))
+    else {
+        return;
+    };
+    let prefix = format!("{} = ", target);
+    declarations.push(commonjs_value(
+        node,
+        right,
+        name.to_string(),
+        &prefix,
+        content,
+        options,
+    ));
+}
+
+/// A single exported CommonJS value: functions keep their signature, anything
+/// else becomes an untyped constant.
+fn commonjs_value(
+    statement: tree_sitter::Node,
+    value: tree_sitter::Node,
+    name: String,
+    prefix: &str,
+    content: &str,
+    options: &ExtractOptions,
+) -> Declaration {
+    match value.kind() {
+        "function_expression" | "function" | "arrow_function" => {
+            extract_function_value(statement, value, name, prefix, content, options)
+        }
+        _ => Declaration::Const {
+            name,
+            ty: String::new(),
+            visibility: Visibility::Public,
+            location: Location::new(
+                statement.start_position().row + 1,
+                statement.end_position().row + 1,
+            ),
+            is_static: false,
+            snippet: None,
+        },
+    }
+}
+
 fn extract_import(node: tree_sitter::Node, content: &str) -> Option<Import> {
     let text = node_text(node, content);
 
@@ -122,16 +463,27 @@ fn extract_export(
     node: tree_sitter::Node,
     content: &str,
     options: &ExtractOptions,
+    imports: &mut Vec<Import>,
     declarations: &mut Vec<Declaration>,
 ) {
+    if find_child_by_kind(node, "default").is_some() {
+        extract_default_export(node, content, options, declarations);
+        return;
+    }
+
+    if let Some(source) = node.child_by_field_name("source") {
+        declarations.push(extract_re_export(node, source, content));
+        return;
+    }
+
     for child in node.children(&mut node.walk()) {
         match child.kind() {
-            "function_declaration" => {
+            "function_declaration" | "function_signature" => {
                 if let Some(func) = extract_function(child, content, options, true) {
                     declarations.push(func);
                 }
             }
-            "class_declaration" => {
+            "class_declaration" | "abstract_class_declaration" => {
                 if let Some(class) = extract_class(child, content, options, true) {
                     declarations.push(class);
                 }
@@ -149,11 +501,249 @@ fn extract_export(
             "lexical_declaration" => {
                 extract_lexical(child, content, options, declarations, true);
             }
+            "internal_module" | "module" | "ambient_declaration" => {
+                extract_module(child, content, options, imports, declarations);
+            }
             _ => {}
         }
     }
 }
 
+/// Fold bodiless overload signatures (`function_signature` and
+/// `method_signature` nodes, which have no metrics) into the same-named
+/// function that follows, keeping them as its `overloads`. Accessors are
+/// never overloads, and static and instance methods don't mix.
+fn merge_overloads(declarations: &mut Vec<Declaration>) {
+    let mut merged: Vec<Declaration> = Vec::with_capacity(declarations.len());
+    for mut decl in declarations.drain(..) {
+        let continues_run = matches!(
+            (merged.last(), &decl),
+            (
+                Some(Declaration::Function {
+                    name: prev,
+                    signature: prev_signature,
+                    metrics: None,
+                    ..
+                }),
+                Declaration::Function { name, signature, .. },
+            ) if prev == name
+                && !is_accessor(prev_signature)
+                && !is_accessor(signature)
+                && is_static(prev_signature) == is_static(signature)
+        );
+        if continues_run {
+            if let (
+                Some(Declaration::Function {
+                    signature: prev_signature,
+                    location: prev_location,
+                    overloads: prev_overloads,
+                    doc: prev_doc,
+                    ..
+                }),
+                Declaration::Function {
+                    location,
+                    overloads,
+                    doc,
+                    ..
+                },
+            ) = (merged.pop(), &mut decl)
+            {
+                *overloads = prev_overloads;
+                overloads.push(prev_signature);
+                *location = Location::new(prev_location.start_line, location.end_line);
+                if doc.is_none() {
+                    *doc = prev_doc;
+                }
+            }
+        }
+        merged.push(decl);
+    }
+    *declarations = merged;
+}
+
+fn is_accessor(signature: &str) -> bool {
+    signature.starts_with("get ")
+        || signature.starts_with("set ")
+        || signature.starts_with("static get ")
+        || signature.starts_with("static set ")
+}
+
+fn is_static(signature: &str) -> bool {
+    signature.starts_with("static ")
+}
+
+/// `export * from "./x"`, `export * as ns from "./x"` and
+/// `export { a, b as c } from "./x"`, each optionally `export type`.
+fn extract_re_export(
+    node: tree_sitter::Node,
+    source: tree_sitter::Node,
+    content: &str,
+) -> Declaration {
+    let items: SmallVec<[String; 4]> =
+        if let Some(clause) = find_child_by_kind(node, "export_clause") {
+            clause
+                .children(&mut clause.walk())
+                .filter(|c| c.kind() == "export_specifier")
+                .map(|c| node_text(c, content))
+                .collect()
+        } else if let Some(namespace) = find_child_by_kind(node, "namespace_export") {
+            std::iter::once(node_text(namespace, content)).collect()
+        } else {
+            std::iter::once("*".to_string()).collect()
+        };
+    // `export type { A } from`: mark each item type-only, as `{ type A }` does.
+    // (`export type *` parses with the keyword in an ERROR node.)
+    let type_only = node
+        .children(&mut node.walk())
+        .any(|c| node_text(c, content) == "type");
+    let items = if type_only {
+        items.iter().map(|item| format!("type {}", item)).collect()
+    } else {
+        items
+    };
+
+    Declaration::ReExport {
+        name: node_text(source, content)
+            .trim_matches(['"', '\'', '`'])
+            .to_string(),
+        items,
+        visibility: Visibility::Public,
+        location: Location::new(node.start_position().row + 1, node.end_position().row + 1),
+        snippet: None,
+    }
+}
+
+/// `export default ...`. Named functions and classes keep their name; anonymous
+/// ones are named `default`. `export default foo` marks the existing `foo`
+/// function; any other value becomes a `default` constant.
+fn extract_default_export(
+    node: tree_sitter::Node,
+    content: &str,
+    options: &ExtractOptions,
+    declarations: &mut Vec<Declaration>,
+) {
+    let location = Location::new(node.start_position().row + 1, node.end_position().row + 1);
+
+    if let Some(declaration) = node.child_by_field_name("declaration") {
+        let decl = match declaration.kind() {
+            "function_declaration" => extract_function(declaration, content, options, true),
+            "class_declaration" | "abstract_class_declaration" => {
+                extract_class(declaration, content, options, true)
+            }
+            _ => None,
+        };
+        if let Some(mut decl) = decl {
+            mark_default_export(&mut decl);
+            declarations.push(decl);
+        }
+        return;
+    }
+
+    let Some(value) = node.child_by_field_name("value") else {
+        return;
+    };
+    match value.kind() {
+        "function_expression" | "function" | "arrow_function" => {
+            declarations.push(extract_function_value(
+                node,
+                value,
+                "default".to_string(),
+                "export default ",
+                content,
+                options,
+            ));
+        }
+        "class" => {
+            declarations.extend(extract_class(value, content, options, true));
+        }
+        "identifier" => {
+            let name = node_text(value, content);
+            if let Some(decl) = declarations.iter_mut().find(|d| d.name() == name) {
+                mark_default_export(decl);
+            }
+        }
+        _ => {
+            declarations.push(Declaration::Const {
+                name: "default".to_string(),
+                ty: String::new(),
+                visibility: Visibility::Public,
+                location,
+                is_static: false,
+                snippet: None,
+            });
+        }
+    }
+}
+
+/// A function or arrow function assigned to `name`, e.g. `export default (x) => ...`
+/// or `exports.run = async function (x) {...}`. The signature is `prefix`
+/// followed by the function head.
+fn extract_function_value(
+    statement: tree_sitter::Node,
+    value: tree_sitter::Node,
+    name: String,
+    prefix: &str,
+    content: &str,
+    options: &ExtractOptions,
+) -> Declaration {
+    let is_async = value
+        .children(&mut value.walk())
+        .any(|c| c.kind() == "async");
+    let generics = type_parameters(value, content);
+    // A lone arrow parameter (`x => x`) has no parentheses.
+    let params = value
+        .child_by_field_name("parameters")
+        .map(|n| node_text(n, content))
+        .or_else(|| {
+            value
+                .child_by_field_name("parameter")
+                .map(|n| format!("({})", node_text(n, content)))
+        })
+        .unwrap_or_default();
+    let ret = value
+        .child_by_field_name("return_type")
+        .map(|n| node_text(n, content))
+        .unwrap_or_default();
+    let head = if value.kind() == "arrow_function" {
+        format!("{}{}{} =>", generics, params, ret)
+    } else {
+        format!("function{}{}{}", generics, params, ret)
+    };
+    let signature = format!("{}{}{}", prefix, if is_async { "async " } else { "" }, head);
+
+    let doc = if options.include_docs {
+        extract_jsdoc(statement, content)
+    } else {
+        None
+    };
+
+    Declaration::Function {
+        name,
+        scope: None,
+        signature,
+        visibility: Visibility::Public,
+        location: Location::new(
+            statement.start_position().row + 1,
+            statement.end_position().row + 1,
+        ),
+        is_async,
+        overloads: Vec::new(),
+        component: None,
+        metrics: function_metrics(value),
+        attributes: Vec::new(),
+        doc,
+        snippet: None,
+    }
+}
+
+/// Rewrite a function signature's leading `export ` as `export default `.
+fn mark_default_export(decl: &mut Declaration) {
+    if let Declaration::Function { signature, .. } = decl {
+        let rest = signature.strip_prefix("export ").unwrap_or(signature);
+        *signature = format!("export default {}", rest);
+    }
+}
+
 fn extract_function(
     node: tree_sitter::Node,
     content: &str,
@@ -174,6 +764,7 @@ fn extract_function(
     }
     signature.push_str("function ");
     signature.push_str(&name);
+    signature.push_str(&type_parameters(node, content));
 
     // Add parameters
     if let Some(params) = find_child_by_kind(node, "formal_parameters") {
@@ -193,13 +784,21 @@ fn extract_function(
         None
     };
 
+    let component = function_component(node, &name, content);
+
     Some(Declaration::Function {
         name,
+        scope: None,
         signature,
         visibility: Visibility::Public,
         location,
         is_async,
+        overloads: Vec::new(),
+        component,
+        metrics: function_metrics(node),
+        attributes: Vec::new(),
         doc,
+        snippet: None,
     })
 }
 
@@ -209,20 +808,51 @@ fn extract_class(
     options: &ExtractOptions,
     _is_exported: bool,
 ) -> Option<Declaration> {
-    let name = find_child_by_kind(node, "type_identifier").map(|n| node_text(n, content))?;
+    // Anonymous `export default class { }` has no name.
+    let name = find_child_by_kind(node, "type_identifier")
+        .map(|n| node_text(n, content))
+        .or_else(|| (node.kind() == "class").then(|| "default".to_string()))?;
 
+    let mut fields = SmallVec::new();
     let mut members = Vec::new();
 
     if let Some(body) = find_child_by_kind(node, "class_body") {
         for child in body.children(&mut body.walk()) {
             match child.kind() {
-                "method_definition" => {
+                "method_definition" | "method_signature" | "abstract_method_signature" => {
                     if let Some(method) = extract_method(child, content, options) {
                         members.push(method);
                     }
                 }
                 "public_field_definition" | "field_definition" => {
-                    // Could extract fields here
+                    if let Some(field) = extract_field(child, content, options) {
+                        fields.push(field);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    merge_overloads(&mut members);
+
+    let mut extends = Vec::new();
+    let mut implements = Vec::new();
+    let mut component = None;
+    if let Some(heritage) = find_child_by_kind(node, "class_heritage") {
+        for clause in heritage.named_children(&mut heritage.walk()) {
+            let text = node_text(clause, content);
+            match clause.kind() {
+                "extends_clause" => {
+                    component = class_component(clause, content);
+                    extends.push(text.trim_start_matches("extends").trim().to_string());
+                }
+                "implements_clause" => {
+                    implements.extend(
+                        clause
+                            .named_children(&mut clause.walk())
+                            .map(|t| node_text(t, content)),
+                    );
                 }
                 _ => {}
             }
@@ -239,10 +869,85 @@ fn extract_class(
 
     Some(Declaration::Class {
         name,
+        generics: type_parameters(node, content),
+        extends,
+        implements,
+        is_abstract: node.kind() == "abstract_class_declaration",
+        fields,
         members,
+        component,
         visibility: Visibility::Public,
         location,
         doc,
+        snippet: None,
+    })
+}
+
+/// A class property. Modifiers are kept as written; `private`/`protected` and
+/// `#private` names also set the field's visibility.
+fn extract_field(
+    node: tree_sitter::Node,
+    content: &str,
+    options: &ExtractOptions,
+) -> Option<Field> {
+    let name_node = node
+        .child_by_field_name("name")
+        .or_else(|| node.child_by_field_name("property"))?;
+    let mut name = node_text(name_node, content);
+
+    let mut modifiers = Vec::new();
+    let mut optional = false;
+    for child in node.children(&mut node.walk()) {
+        match child.kind() {
+            "accessibility_modifier"
+            | "override_modifier"
+            | "static"
+            | "readonly"
+            | "declare"
+            | "abstract" => modifiers.push(node_text(child, content)),
+            "?" => optional = true,
+            _ => {}
+        }
+    }
+    if optional {
+        name.push('?');
+    }
+
+    let visibility = if name_node.kind() == "private_property_identifier" {
+        Visibility::Private
+    } else {
+        method_visibility(node, content)
+    };
+    if !options.visibility.allows(visibility) {
+        return None;
+    }
+
+    let ty = node
+        .child_by_field_name("type")
+        .map(|n| {
+            node_text(n, content)
+                .trim_start_matches(':')
+                .trim()
+                .to_string()
+        })
+        .unwrap_or_default();
+
+    // Only a JSDoc block directly above the property belongs to it.
+    let doc = if options.include_docs {
+        node.prev_named_sibling()
+            .filter(|s| s.kind() == "comment" && node_text(*s, content).starts_with("/**"))
+            .and_then(|_| extract_jsdoc(node, content))
+    } else {
+        None
+    };
+
+    Some(Field {
+        name,
+        ty,
+        visibility,
+        modifiers,
+        tag: None,
+        doc,
     })
 }
 
@@ -256,10 +961,20 @@ fn extract_method(
     let is_async = node.children(&mut node.walk()).any(|c| c.kind() == "async");
 
     let mut signature = String::new();
+    if node.kind() == "abstract_method_signature" {
+        signature.push_str("abstract ");
+    }
+    for child in node.children(&mut node.walk()) {
+        if matches!(child.kind(), "static" | "get" | "set") {
+            signature.push_str(child.kind());
+            signature.push(' ');
+        }
+    }
     if is_async {
         signature.push_str("async ");
     }
     signature.push_str(&name);
+    signature.push_str(&type_parameters(node, content));
 
     if let Some(params) = find_child_by_kind(node, "formal_parameters") {
         signature.push_str(&node_text(params, content));
@@ -278,17 +993,23 @@ fn extract_method(
     };
 
     let visibility = method_visibility(node, content);
-    if !options.include_private && visibility != Visibility::Public {
+    if !options.visibility.allows(visibility) {
         return None;
     }
 
     Some(Declaration::Function {
         name,
+        scope: None,
         signature,
         visibility,
         location,
         is_async,
+        overloads: Vec::new(),
+        component: None,
+        metrics: function_metrics(node),
+        attributes: Vec::new(),
         doc,
+        snippet: None,
     })
 }
 
@@ -319,21 +1040,10 @@ fn extract_interface(
 ) -> Option<Declaration> {
     let name = find_child_by_kind(node, "type_identifier").map(|n| node_text(n, content))?;
 
-    let mut members = Vec::new();
-
-    if let Some(body) = find_child_by_kind(node, "object_type")
+    let members = find_child_by_kind(node, "object_type")
         .or_else(|| find_child_by_kind(node, "interface_body"))
-    {
-        for child in body.children(&mut body.walk()) {
-            if child.kind() == "property_signature" || child.kind() == "method_signature" {
-                members.push(
-                    node_text(child, content)
-                        .trim_end_matches([',', ';'])
-                        .to_string(),
-                );
-            }
-        }
-    }
+        .map(|body| object_type_members(body, content))
+        .unwrap_or_default();
 
     let location = Location::new(node.start_position().row + 1, node.end_position().row + 1);
 
@@ -349,9 +1059,22 @@ fn extract_interface(
         visibility: Visibility::Public,
         location,
         doc,
+        snippet: None,
     })
 }
 
+/// Property and method signatures of an interface body or object type.
+fn object_type_members(body: tree_sitter::Node, content: &str) -> Vec<String> {
+    body.children(&mut body.walk())
+        .filter(|c| c.kind() == "property_signature" || c.kind() == "method_signature")
+        .map(|c| {
+            node_text(c, content)
+                .trim_end_matches([',', ';'])
+                .to_string()
+        })
+        .collect()
+}
+
 fn extract_type_alias(
     node: tree_sitter::Node,
     content: &str,
@@ -360,20 +1083,20 @@ fn extract_type_alias(
 ) -> Option<Declaration> {
     let name = find_child_by_kind(node, "type_identifier").map(|n| node_text(n, content))?;
 
-    let full_text = node_text(node, content);
-    let target = full_text
-        .split('=')
-        .nth(1)
-        .map(|s| s.trim().trim_end_matches(';').to_string())
+    let target = node
+        .child_by_field_name("value")
+        .map(|n| node_text(n, content))
         .unwrap_or_default();
 
     let location = Location::new(node.start_position().row + 1, node.end_position().row + 1);
 
     Some(Declaration::TypeAlias {
         name,
+        generics: type_parameters(node, content),
         target,
         visibility: Visibility::Public,
         location,
+        snippet: None,
     })
 }
 
@@ -413,6 +1136,11 @@ fn extract_lexical(
                     signature.push_str(" = ");
                     signature.push_str(&node_text(arrow, content));
 
+                    let component = child
+                        .child_by_field_name("type")
+                        .and_then(|t| annotated_component(t, content))
+                        .or_else(|| function_component(arrow, &name, content));
+
                     let location =
                         Location::new(node.start_position().row + 1, node.end_position().row + 1);
 
@@ -423,14 +1151,20 @@ fn extract_lexical(
                     };
 
                     // Top-level lexical arrow functions are included by default.
-                    // `include_private` only gates members/modifiers.
+                    // The visibility threshold only gates members/modifiers.
                     declarations.push(Declaration::Function {
                         name,
+                        scope: None,
                         signature,
                         visibility: Visibility::Public,
                         location,
                         is_async,
+                        overloads: Vec::new(),
+                        component,
+                        metrics: function_metrics(arrow),
+                        attributes: Vec::new(),
                         doc,
+                        snippet: None,
                     });
                 }
             }
@@ -438,6 +1172,149 @@ fn extract_lexical(
     }
 }
 
+/// React base classes for class components.
+const COMPONENT_BASES: &[&str] = &[
+    "Component",
+    "PureComponent",
+    "React.Component",
+    "React.PureComponent",
+];
+
+/// React function component types for annotated variables.
+const COMPONENT_TYPES: &[&str] = &[
+    "FC",
+    "VFC",
+    "FunctionComponent",
+    "React.FC",
+    "React.VFC",
+    "React.FunctionComponent",
+];
+
+/// Return types that mark a function as a component even without JSX in its body.
+const ELEMENT_TYPES: &[&str] = &[
+    "JSX.Element",
+    "React.JSX.Element",
+    "ReactElement",
+    "React.ReactElement",
+    "ReactNode",
+    "React.ReactNode",
+];
+
+/// A PascalCase function that renders JSX or declares an element return type.
+/// Props come from the first parameter's type annotation.
+fn function_component(node: tree_sitter::Node, name: &str, content: &str) -> Option<Component> {
+    if !name.starts_with(|c: char| c.is_ascii_uppercase()) {
+        return None;
+    }
+    let returns_element = node.child_by_field_name("return_type").is_some_and(|ret| {
+        let ty = node_text(ret, content);
+        ELEMENT_TYPES.contains(&ty.trim_start_matches(':').trim())
+    });
+    if !returns_element && !node.child_by_field_name("body").is_some_and(contains_jsx) {
+        return None;
+    }
+
+    let props = node
+        .child_by_field_name("parameters")
+        .and_then(|params| params.named_child(0))
+        .and_then(|param| param.child_by_field_name("type"))
+        .and_then(|ann| ann.named_child(0));
+    Some(props_component(props, content))
+}
+
+/// `const Card: React.FC<CardProps> = ...`.
+fn annotated_component(annotation: tree_sitter::Node, content: &str) -> Option<Component> {
+    let ty = annotation
+        .named_child(0)
+        .filter(|t| t.kind() == "generic_type")?;
+    let name = ty.child_by_field_name("name")?;
+    if !COMPONENT_TYPES.contains(&node_text(name, content).as_str()) {
+        return None;
+    }
+    let props = find_child_by_kind(ty, "type_arguments").and_then(|args| args.named_child(0));
+    Some(props_component(props, content))
+}
+
+/// `class Modal extends React.Component<ModalProps, State>`.
+fn class_component(extends: tree_sitter::Node, content: &str) -> Option<Component> {
+    let base = extends.child_by_field_name("value")?;
+    if !COMPONENT_BASES.contains(&node_text(base, content).as_str()) {
+        return None;
+    }
+    let props = extends
+        .child_by_field_name("type_arguments")
+        .and_then(|args| args.named_child(0));
+    Some(props_component(props, content))
+}
+
+/// Props type as written; inline object types also list their members.
+fn props_component(props: Option<tree_sitter::Node>, content: &str) -> Component {
+    let Some(props) = props else {
+        return Component::default();
+    };
+    let props_members = if props.kind() == "object_type" {
+        object_type_members(props, content)
+    } else {
+        Vec::new()
+    };
+    Component {
+        props: Some(node_text(props, content)),
+        props_members,
+    }
+}
+
+fn contains_jsx(node: tree_sitter::Node) -> bool {
+    matches!(
+        node.kind(),
+        "jsx_element" | "jsx_self_closing_element" | "jsx_fragment"
+    ) || node.children(&mut node.walk()).any(contains_jsx)
+}
+
+/// Fill in props members for components whose props type is an interface
+/// declared in the same file.
+fn resolve_component_props(declarations: &mut [Declaration]) {
+    let interfaces: Vec<(String, Vec<String>)> = declarations
+        .iter()
+        .filter_map(|d| match d {
+            Declaration::Interface { name, members, .. } => Some((name.clone(), members.to_vec())),
+            _ => None,
+        })
+        .collect();
+    if interfaces.is_empty() {
+        return;
+    }
+
+    for decl in declarations {
+        let (Declaration::Function {
+            component: Some(component),
+            ..
+        }
+        | Declaration::Class {
+            component: Some(component),
+            ..
+        }) = decl
+        else {
+            continue;
+        };
+        let Some(props) = &component.props else {
+            continue;
+        };
+        let props_name = props.split('<').next().unwrap_or(props);
+        if let Some((_, members)) = interfaces.iter().find(|(name, _)| name == props_name) {
+            if component.props_members.is_empty() {
+                component.props_members.clone_from(members);
+            }
+        }
+    }
+}
+
+/// Type parameter list as written (`<T extends object = {}>`), or empty.
+fn type_parameters(node: tree_sitter::Node, content: &str) -> String {
+    node.child_by_field_name("type_parameters")
+        .map(|n| node_text(n, content))
+        .unwrap_or_default()
+}
+
 fn extract_jsdoc(node: tree_sitter::Node, content: &str) -> Option<String> {
     let mut prev = node.prev_sibling();
 
@@ -465,6 +1342,7 @@ fn extract_jsdoc(node: tree_sitter::Node, content: &str) -> Option<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::codemap::VisibilityThreshold;
 
     #[test]
     fn test_extract_function() {
@@ -473,7 +1351,8 @@ export function greet(name: string): string {
     return `Hello, ${name}`;
 }
 "#;
-        let (_, decls) = extract(code, Language::TypeScript, &ExtractOptions::default()).unwrap();
+        let (_, decls, _) =
+            extract(code, Language::TypeScript, &ExtractOptions::default()).unwrap();
         assert_eq!(decls.len(), 1);
         assert_eq!(decls[0].name(), "greet");
     }
@@ -486,7 +1365,8 @@ export function greet(name: string): string {
      timeout: number;
  }
  "#;
-        let (_, decls) = extract(code, Language::TypeScript, &ExtractOptions::default()).unwrap();
+        let (_, decls, _) =
+            extract(code, Language::TypeScript, &ExtractOptions::default()).unwrap();
         assert_eq!(decls.len(), 1);
 
         match &decls[0] {
@@ -511,7 +1391,8 @@ export function greet(name: string): string {
       x: number;
   }
   "#;
-        let (_, decls) = extract(code, Language::TypeScript, &ExtractOptions::default()).unwrap();
+        let (_, decls, _) =
+            extract(code, Language::TypeScript, &ExtractOptions::default()).unwrap();
         assert_eq!(decls.len(), 1);
         assert_eq!(decls[0].name(), "Internal");
         assert_eq!(decls[0].visibility(), Visibility::Public);
@@ -522,7 +1403,8 @@ export function greet(name: string): string {
         let code = r#"
   type Internal = { x: number };
   "#;
-        let (_, decls) = extract(code, Language::TypeScript, &ExtractOptions::default()).unwrap();
+        let (_, decls, _) =
+            extract(code, Language::TypeScript, &ExtractOptions::default()).unwrap();
         assert_eq!(decls.len(), 1);
         assert_eq!(decls[0].name(), "Internal");
         assert_eq!(decls[0].visibility(), Visibility::Public);
@@ -534,7 +1416,8 @@ export function greet(name: string): string {
 import { useState, useEffect } from 'react';
 import * as utils from './utils';
 "#;
-        let (imports, _) = extract(code, Language::TypeScript, &ExtractOptions::default()).unwrap();
+        let (imports, _, _) =
+            extract(code, Language::TypeScript, &ExtractOptions::default()).unwrap();
         assert_eq!(imports.len(), 2);
         assert_eq!(imports[0].source, "react");
         assert_eq!(imports[0].items.len(), 2);
@@ -549,7 +1432,8 @@ import * as utils from './utils';
      }
  }
  "#;
-        let (_, decls) = extract(code, Language::TypeScript, &ExtractOptions::default()).unwrap();
+        let (_, decls, _) =
+            extract(code, Language::TypeScript, &ExtractOptions::default()).unwrap();
         assert_eq!(decls.len(), 1);
 
         match &decls[0] {
@@ -569,7 +1453,8 @@ import * as utils from './utils';
      public ok(): string { return "y"; }
  }
  "#;
-        let (_, decls) = extract(code, Language::TypeScript, &ExtractOptions::default()).unwrap();
+        let (_, decls, _) =
+            extract(code, Language::TypeScript, &ExtractOptions::default()).unwrap();
         assert_eq!(decls.len(), 1);
 
         match &decls[0] {
@@ -581,10 +1466,10 @@ import * as utils from './utils';
         }
 
         let opts = ExtractOptions {
-            include_private: true,
+            visibility: VisibilityThreshold::All,
             ..Default::default()
         };
-        let (_, decls) = extract(code, Language::TypeScript, &opts).unwrap();
+        let (_, decls, _) = extract(code, Language::TypeScript, &opts).unwrap();
         match &decls[0] {
             Declaration::Class { members, .. } => {
                 assert_eq!(members.len(), 2);
@@ -592,4 +1477,396 @@ import * as utils from './utils';
             _ => panic!("expected class"),
         }
     }
+
+    #[test]
+    fn test_namespaces_and_declared_modules() {
+        let code = r#"
+namespace Geometry {
+    export interface Point { x: number; y: number }
+    export namespace Shapes {
+        export function area(p: Point): number { return 0; }
+    }
+}
+
+export namespace Api {
+    export type Id = string;
+}
+
+declare module "express" {
+    interface Request { user: User }
+}
+"#;
+        let (_, decls, _) =
+            extract(code, Language::TypeScript, &ExtractOptions::default()).unwrap();
+        let names: Vec<&str> = decls.iter().map(Declaration::name).collect();
+        assert_eq!(
+            names,
+            [
+                "Geometry.Point",
+                "Geometry.Shapes.area",
+                "Api.Id",
+                "express.Request"
+            ]
+        );
+        match &decls[1] {
+            Declaration::Function { scope, .. } => {
+                assert_eq!(scope.as_deref(), Some("Geometry.Shapes"));
+            }
+            _ => panic!("expected function"),
+        }
+    }
+
+    #[test]
+    fn test_default_exports() {
+        let cases = [
+            (
+                "export default function handler(req: Request) {}",
+                "handler",
+            ),
+            ("export default async function () {}", "default"),
+            ("export default (x: number): number => x;", "default"),
+            ("export default class {}", "default"),
+            ("export default defineConfig({});", "default"),
+        ];
+        for (code, expected) in cases {
+            let (_, decls, _) =
+                extract(code, Language::TypeScript, &ExtractOptions::default()).unwrap();
+            assert_eq!(decls.len(), 1, "{}", code);
+            assert_eq!(decls[0].name(), expected, "{}", code);
+        }
+
+        let code = "function main() {}\nexport default main;\n";
+        let (_, decls, _) =
+            extract(code, Language::TypeScript, &ExtractOptions::default()).unwrap();
+        assert_eq!(decls.len(), 1);
+        match &decls[0] {
+            Declaration::Function { signature, .. } => {
+                assert_eq!(signature, "export default function main()");
+            }
+            _ => panic!("expected function"),
+        }
+
+        let code = "export default async function () {}";
+        let (_, decls, _) =
+            extract(code, Language::TypeScript, &ExtractOptions::default()).unwrap();
+        match &decls[0] {
+            Declaration::Function { signature, .. } => {
+                assert_eq!(signature, "export default async function()");
+            }
+            _ => panic!("expected function"),
+        }
+    }
+
+    #[test]
+    fn test_re_exports() {
+        let code = r#"export * from './x';
+export type { A, B as C } from "./a";
+export { type D, e } from "./d";
+export type * from "./t";
+export * as ns from "./z";
+export { a, b as c } from './y';
+"#;
+        let (imports, decls, _) =
+            extract(code, Language::TypeScript, &ExtractOptions::default()).unwrap();
+        assert!(imports.is_empty());
+        let re_exports: Vec<_> = decls
+            .iter()
+            .map(|d| match d {
+                Declaration::ReExport { name, items, .. } => (name.as_str(), items.to_vec()),
+                _ => panic!("expected re-export"),
+            })
+            .collect();
+        assert_eq!(
+            re_exports,
+            vec![
+                ("./x", vec!["*".to_string()]),
+                ("./a", vec!["type A".to_string(), "type B as C".to_string()]),
+                ("./d", vec!["type D".to_string(), "e".to_string()]),
+                ("./t", vec!["type *".to_string()]),
+                ("./z", vec!["* as ns".to_string()]),
+                ("./y", vec!["a".to_string(), "b as c".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generics_in_signatures() {
+        let code = r"
+export function id<T extends object = {}>(x: T): T { return x; }
+export class Box<T, U extends keyof T> {
+    map<V>(f: (t: T) => V): Box<V, any> {}
+}
+export type Pair<A, B = A> = [A, B];
+";
+        let (_, decls, _) =
+            extract(code, Language::TypeScript, &ExtractOptions::default()).unwrap();
+        match &decls[0] {
+            Declaration::Function { signature, .. } => {
+                assert_eq!(
+                    signature,
+                    "export function id<T extends object = {}>(x: T): T"
+                );
+            }
+            _ => panic!("expected function"),
+        }
+        match &decls[1] {
+            Declaration::Class {
+                generics, members, ..
+            } => {
+                assert_eq!(generics, "<T, U extends keyof T>");
+                match &members[0] {
+                    Declaration::Function { signature, .. } => {
+                        assert_eq!(signature, "map<V>(f: (t: T) => V): Box<V, any>");
+                    }
+                    _ => panic!("expected method"),
+                }
+            }
+            _ => panic!("expected class"),
+        }
+        match &decls[2] {
+            Declaration::TypeAlias {
+                generics, target, ..
+            } => {
+                assert_eq!(generics, "<A, B = A>");
+                assert_eq!(target, "[A, B]");
+            }
+            _ => panic!("expected type alias"),
+        }
+    }
+
+    #[test]
+    fn test_class_fields_with_modifiers() {
+        let code = r"
+class Account {
+    /** Display name. */
+    name: string;
+    private readonly id: number = 1;
+    protected static count?: number;
+    static MAX = 10;
+    #secret: string;
+}
+";
+        let (_, decls, _) =
+            extract(code, Language::TypeScript, &ExtractOptions::default()).unwrap();
+        match &decls[0] {
+            Declaration::Class { fields, .. } => {
+                let names: Vec<_> = fields.iter().map(|f| f.name.as_str()).collect();
+                assert_eq!(names, vec!["name", "MAX"]);
+                assert_eq!(fields[0].ty, "string");
+                assert_eq!(fields[1].modifiers, vec!["static"]);
+            }
+            _ => panic!("expected class"),
+        }
+
+        let options = ExtractOptions {
+            include_docs: true,
+            visibility: VisibilityThreshold::All,
+            ..ExtractOptions::default()
+        };
+        let (_, decls, _) = extract(code, Language::TypeScript, &options).unwrap();
+        match &decls[0] {
+            Declaration::Class { fields, .. } => {
+                assert_eq!(fields.len(), 5);
+                assert_eq!(fields[0].doc.as_deref(), Some("Display name."));
+                assert_eq!(fields[1].visibility, Visibility::Private);
+                assert_eq!(fields[1].modifiers, vec!["private", "readonly"]);
+                assert_eq!(fields[2].name, "count?");
+                assert_eq!(fields[2].visibility, Visibility::Protected);
+                assert_eq!(fields[2].modifiers, vec!["protected", "static"]);
+                assert_eq!(fields[3].doc, None);
+                assert_eq!(fields[4].name, "#secret");
+                assert_eq!(fields[4].visibility, Visibility::Private);
+            }
+            _ => panic!("expected class"),
+        }
+    }
+
+    #[test]
+    fn test_class_heritage_and_abstract() {
+        let code = r"
+export abstract class Repo<T> extends Base<T> implements Store<T>, Disposable {
+    abstract find(id: string): T;
+}
+";
+        let (_, decls, _) =
+            extract(code, Language::TypeScript, &ExtractOptions::default()).unwrap();
+        match &decls[0] {
+            Declaration::Class {
+                name,
+                extends,
+                implements,
+                is_abstract,
+                members,
+                ..
+            } => {
+                assert_eq!(name, "Repo");
+                assert_eq!(extends, &vec!["Base<T>".to_string()]);
+                assert_eq!(
+                    implements,
+                    &vec!["Store<T>".to_string(), "Disposable".to_string()]
+                );
+                assert!(*is_abstract);
+                match &members[0] {
+                    Declaration::Function { signature, .. } => {
+                        assert_eq!(signature, "abstract find(id: string): T");
+                    }
+                    _ => panic!("expected method"),
+                }
+            }
+            _ => panic!("expected class"),
+        }
+    }
+
+    #[test]
+    fn test_overloads_merged_into_implementation() {
+        let code = r"
+export function parse(input: string): Ast;
+export function parse(input: Buffer): Ast;
+export function parse(input: string | Buffer): Ast { return x; }
+class Cache {
+    get(k: string): number;
+    get(k: any): number { return 1; }
+}
+";
+        let (_, decls, _) =
+            extract(code, Language::TypeScript, &ExtractOptions::default()).unwrap();
+        assert_eq!(decls.len(), 2);
+        match &decls[0] {
+            Declaration::Function {
+                signature,
+                overloads,
+                location,
+                ..
+            } => {
+                assert_eq!(
+                    signature,
+                    "export function parse(input: string | Buffer): Ast"
+                );
+                assert_eq!(
+                    overloads,
+                    &vec![
+                        "export function parse(input: string): Ast".to_string(),
+                        "export function parse(input: Buffer): Ast".to_string(),
+                    ]
+                );
+                assert_eq!(*location, Location::new(2, 4));
+            }
+            _ => panic!("expected function"),
+        }
+        match &decls[1] {
+            Declaration::Class { members, .. } => {
+                assert_eq!(members.len(), 1);
+                match &members[0] {
+                    Declaration::Function { overloads, .. } => {
+                        assert_eq!(overloads, &vec!["get(k: string): number".to_string()]);
+                    }
+                    _ => panic!("expected method"),
+                }
+            }
+            _ => panic!("expected class"),
+        }
+    }
+
+    #[test]
+    fn test_accessors_and_static_methods_are_not_overloads() {
+        let code = r"
+class Animal<T> {
+    get id(): number { return 1; }
+    set id(v: number) {}
+    static create(): Animal<any> { return new Animal(); }
+    create(): void {}
+}
+";
+        let (_, decls, _) =
+            extract(code, Language::TypeScript, &ExtractOptions::default()).unwrap();
+        match &decls[0] {
+            Declaration::Class { members, .. } => {
+                let signatures: Vec<(&str, usize)> = members
+                    .iter()
+                    .map(|m| match m {
+                        Declaration::Function {
+                            signature,
+                            overloads,
+                            ..
+                        } => (signature.as_str(), overloads.len()),
+                        _ => panic!("expected method"),
+                    })
+                    .collect();
+                assert_eq!(
+                    signatures,
+                    [
+                        ("get id(): number", 0),
+                        ("set id(v: number)", 0),
+                        ("static create(): Animal<any>", 0),
+                        ("create(): void", 0),
+                    ]
+                );
+            }
+            _ => panic!("expected class"),
+        }
+    }
+
+    #[test]
+    fn test_declare_statements() {
+        let code = r"
+declare const VERSION: string;
+declare let a: number, b: boolean;
+export declare function f(x: number): void;
+export declare class C { m(): void; }
+";
+        let (_, decls, _) =
+            extract(code, Language::TypeScript, &ExtractOptions::default()).unwrap();
+        let names: Vec<_> = decls.iter().map(Declaration::name).collect();
+        assert_eq!(names, vec!["VERSION", "a", "b", "f", "C"]);
+        match &decls[1] {
+            Declaration::Const { ty, .. } => assert_eq!(ty, "number"),
+            _ => panic!("expected const"),
+        }
+        match &decls[4] {
+            Declaration::Class { members, .. } => assert_eq!(members.len(), 1),
+            _ => panic!("expected class"),
+        }
+    }
+
+    #[test]
+    fn test_react_components() {
+        let code = r"
+interface ButtonProps { label: string; onClick(): void }
+export function Button({ label, onClick }: ButtonProps): JSX.Element { return <button>{label}</button>; }
+export const Card: React.FC<CardProps> = ({ children }) => <div>{children}</div>;
+const Inline = (props: { title: string }) => { return <><h1/></>; };
+export class Modal extends React.Component<ModalProps, State> { render() { return null; } }
+export function formatLabel(label: string): string { return label; }
+";
+        let (_, decls, _) = extract(code, Language::Tsx, &ExtractOptions::default()).unwrap();
+        let component = |name: &str| {
+            decls
+                .iter()
+                .find(|d| d.name() == name)
+                .and_then(|d| match d {
+                    Declaration::Function { component, .. }
+                    | Declaration::Class { component, .. } => component.clone(),
+                    _ => None,
+                })
+        };
+
+        let button = component("Button").expect("Button is a component");
+        assert_eq!(button.props.as_deref(), Some("ButtonProps"));
+        assert_eq!(
+            button.props_members,
+            vec!["label: string", "onClick(): void"]
+        );
+
+        let card = component("Card").expect("Card is a component");
+        assert_eq!(card.props.as_deref(), Some("CardProps"));
+        assert!(card.props_members.is_empty());
+
+        let inline = component("Inline").expect("Inline is a component");
+        assert_eq!(inline.props_members, vec!["title: string"]);
+
+        let modal = component("Modal").expect("Modal is a component");
+        assert_eq!(modal.props.as_deref(), Some("ModalProps"));
+
+        assert!(component("formatLabel").is_none());
+    }
 }
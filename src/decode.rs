@@ -0,0 +1,130 @@
+//! Decoding source files that are not valid UTF-8.
+//!
+//! Legacy code often has Latin-1 comments, and Windows editors save files as
+//! UTF-16. Such files are skipped by default; with lossy decoding they are
+//! read anyway, and the run records how each one was transcoded.
+
+use std::char::REPLACEMENT_CHARACTER;
+
+/// File content as text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decoded {
+    pub text: String,
+    /// How the bytes were changed to get UTF-8 (e.g., "decoded from
+    /// UTF-16LE"); `None` if they already were UTF-8.
+    pub transcoding: Option<String>,
+}
+
+/// Decode `bytes` as UTF-8.
+///
+/// Without `lossy`, anything but valid UTF-8 is `None`. With it, a UTF-16
+/// byte order mark switches to UTF-16, and invalid sequences are replaced
+/// with U+FFFD.
+///
+/// # Examples
+///
+/// ```
+/// use pith::decode::decode;
+///
+/// let latin1 = b"// caf\xe9\nfn main() {}\n".to_vec();
+/// assert!(decode(latin1.clone(), false).is_none());
+///
+/// let decoded = decode(latin1, true).unwrap();
+/// assert_eq!(decoded.text, "// caf\u{fffd}\nfn main() {}\n");
+/// assert_eq!(
+///     decoded.transcoding.as_deref(),
+///     Some("replaced 1 invalid UTF-8 sequence(s)")
+/// );
+/// ```
+pub fn decode(bytes: Vec<u8>, lossy: bool) -> Option<Decoded> {
+    if lossy {
+        if let Some((text, encoding)) = decode_utf16(&bytes) {
+            return Some(Decoded {
+                text,
+                transcoding: Some(format!("decoded from {}", encoding)),
+            });
+        }
+    }
+
+    match String::from_utf8(bytes) {
+        Ok(text) => Some(Decoded {
+            text,
+            transcoding: None,
+        }),
+        Err(_) if !lossy => None,
+        Err(e) => {
+            let bytes = e.into_bytes();
+            let invalid = bytes
+                .utf8_chunks()
+                .filter(|chunk| !chunk.invalid().is_empty())
+                .count();
+            Some(Decoded {
+                text: String::from_utf8_lossy(&bytes).into_owned(),
+                transcoding: Some(format!("replaced {} invalid UTF-8 sequence(s)", invalid)),
+            })
+        }
+    }
+}
+
+/// Text and encoding name of UTF-16 content with a byte order mark, or `None`
+/// without one. Unpaired surrogates and a trailing odd byte become U+FFFD.
+pub fn decode_utf16(bytes: &[u8]) -> Option<(String, &'static str)> {
+    let (little_endian, encoding) = match bytes {
+        // A UTF-32LE mark starts with the UTF-16LE one.
+        [0xFF, 0xFE, 0, 0, ..] => return None,
+        [0xFF, 0xFE, ..] => (true, "UTF-16LE"),
+        [0xFE, 0xFF, ..] => (false, "UTF-16BE"),
+        _ => return None,
+    };
+
+    let body = &bytes[2..];
+    let units = body.chunks_exact(2).map(|pair| {
+        let pair = [pair[0], pair[1]];
+        if little_endian {
+            u16::from_le_bytes(pair)
+        } else {
+            u16::from_be_bytes(pair)
+        }
+    });
+    let mut text: String = char::decode_utf16(units)
+        .map(|c| c.unwrap_or(REPLACEMENT_CHARACTER))
+        .collect();
+    if body.len() % 2 == 1 {
+        text.push(REPLACEMENT_CHARACTER);
+    }
+    Some((text, encoding))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        let utf8 = decode(b"fn main() {}\n".to_vec(), false).unwrap();
+        assert_eq!(utf8.text, "fn main() {}\n");
+        assert_eq!(utf8.transcoding, None);
+
+        let mut utf16le = vec![0xFF, 0xFE];
+        utf16le.extend("x = 'é'\n".encode_utf16().flat_map(u16::to_le_bytes));
+        assert!(decode(utf16le.clone(), false).is_none());
+        let decoded = decode(utf16le, true).unwrap();
+        assert_eq!(decoded.text, "x = 'é'\n");
+        assert_eq!(
+            decoded.transcoding.as_deref(),
+            Some("decoded from UTF-16LE")
+        );
+
+        let mut utf16be = vec![0xFE, 0xFF];
+        utf16be.extend("fn a() {}".encode_utf16().flat_map(u16::to_be_bytes));
+        utf16be.push(b'x');
+        assert_eq!(
+            decode_utf16(&utf16be),
+            Some(("fn a() {}\u{fffd}".to_string(), "UTF-16BE"))
+        );
+
+        // Only a UTF-16 byte order mark selects UTF-16.
+        assert_eq!(decode_utf16(&[0xFF, 0xFE, 0, 0, b'a', 0, 0, 0]), None);
+        assert_eq!(decode_utf16(b"a\0b\0"), None);
+    }
+}
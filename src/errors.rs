@@ -1,7 +1,11 @@
 //! Error types for Pith.
 
+use std::fmt;
 use std::path::PathBuf;
 
+use serde::Serialize;
+
+use crate::budget::BudgetExceeded;
 use crate::codemap::CodemapError;
 use crate::filter::FilterError;
 use crate::output::OutputError;
@@ -33,6 +37,77 @@ pub enum PithError {
 
     #[error("output error: {0}")]
     Output(#[from] OutputError),
+
+    #[error("{0}")]
+    BudgetExceeded(#[from] BudgetExceeded),
+
+    #[error("cancelled")]
+    Cancelled,
+
+    #[error("{0}")]
+    Unreadable(Diagnostic),
+}
+
+/// What went wrong with a path left out of a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticKind {
+    /// The file or directory could not be opened for lack of permission.
+    PermissionDenied,
+    /// Reading failed for another reason.
+    Unreadable,
+    /// The file is not valid UTF-8.
+    InvalidUtf8,
+    /// The file was not UTF-8 and was decoded lossily; it is still included.
+    Transcoded,
+}
+
+impl DiagnosticKind {
+    /// Whether the path was left out of the run.
+    pub fn is_skipped(self) -> bool {
+        self != DiagnosticKind::Transcoded
+    }
+}
+
+/// A problem with one path that did not stop the run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub path: PathBuf,
+    pub kind: DiagnosticKind,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Diagnostic for an I/O error at `path`.
+    pub fn io(path: PathBuf, error: &std::io::Error) -> Self {
+        let kind = if error.kind() == std::io::ErrorKind::PermissionDenied {
+            DiagnosticKind::PermissionDenied
+        } else {
+            DiagnosticKind::Unreadable
+        };
+        Self {
+            path,
+            kind,
+            message: error.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+impl From<Diagnostic> for PithError {
+    fn from(diagnostic: Diagnostic) -> Self {
+        match diagnostic.kind {
+            DiagnosticKind::PermissionDenied => PithError::PermissionDenied(diagnostic.path),
+            DiagnosticKind::Unreadable
+            | DiagnosticKind::InvalidUtf8
+            | DiagnosticKind::Transcoded => PithError::Unreadable(diagnostic),
+        }
+    }
 }
 
 /// Map an error to its exit code.
@@ -46,5 +121,8 @@ pub fn exit_code(error: &PithError) -> i32 {
         PithError::Filter(_) => 1,
         PithError::Codemap(_) => 1,
         PithError::Output(_) => 1,
+        PithError::BudgetExceeded(_) => 6,
+        PithError::Cancelled => 130,
+        PithError::Unreadable(_) => 1,
     }
 }
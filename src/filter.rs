@@ -2,18 +2,26 @@
 //!
 //! Determines which files should be processed for codemap extraction.
 
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 
+use crate::decode::decode_utf16;
+
 /// Errors that can occur during file filtering.
 #[derive(Debug, Error)]
 pub enum FilterError {
     #[error("failed to read file for heuristics: {path}")]
     ReadFailed { path: PathBuf },
+
+    #[error("invalid filter config {path}: {message}")]
+    InvalidConfig { path: PathBuf, message: String },
 }
 
 /// Supported programming languages for codemap extraction.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Language {
     Rust,
     TypeScript,
@@ -73,7 +81,7 @@ impl Language {
     pub fn extensions(&self) -> &'static [&'static str] {
         match self {
             Language::Rust => &["rs"],
-            Language::TypeScript => &["ts"],
+            Language::TypeScript => &["ts", "mts", "cts"],
             Language::Tsx => &["tsx"],
             Language::JavaScript => &["js", "mjs", "cjs"],
             Language::Jsx => &["jsx"],
@@ -83,6 +91,154 @@ impl Language {
     }
 }
 
+/// Project config file, loaded from the scanned directory when present.
+pub const CONFIG_FILE: &str = "pith.toml";
+
+/// User-configured extension handling, layered over the built-in tables.
+///
+/// Keys are lowercase extensions without the leading dot; the builder
+/// methods normalize them.
+///
+/// # Examples
+///
+/// ```
+/// use std::path::Path;
+/// use pith::filter::{should_process_with, FilterOptions, FilterResult, Language};
+///
+/// let options = FilterOptions::default()
+///     .map_extension(".pyx", Language::Python)
+///     .skip_extension("gotmpl");
+///
+/// let result = should_process_with(Path::new("fast.pyx"), None, &options);
+/// assert_eq!(result, FilterResult::Accept(Language::Python));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilterOptions {
+    /// Extra extension → language mappings. These take precedence over the
+    /// built-in allowlist and blocklist.
+    pub additional_extensions: HashMap<String, Language>,
+    /// Extensions that are always rejected, even if otherwise supported.
+    pub skipped_extensions: HashSet<String>,
+    /// Detect the language of files with a missing or unknown extension
+    /// from a Vim or Emacs modeline in their first lines.
+    pub detect_modelines: bool,
+    /// Thresholds for the binary/minified/generated content heuristics.
+    pub heuristics: FilterConfig,
+    /// Read files that aren't valid UTF-8 instead of skipping them: UTF-16
+    /// with a byte order mark is transcoded and invalid bytes are replaced
+    /// (see [`crate::decode::decode`]).
+    pub lossy_decoding: bool,
+}
+
+impl FilterOptions {
+    /// Treat files with `extension` as `language`.
+    pub fn map_extension(mut self, extension: &str, language: Language) -> Self {
+        let extension = normalize_extension(extension);
+        self.skipped_extensions.remove(&extension);
+        self.additional_extensions.insert(extension, language);
+        self
+    }
+
+    /// Reject files with `extension`.
+    pub fn skip_extension(mut self, extension: &str) -> Self {
+        let extension = normalize_extension(extension);
+        self.additional_extensions.remove(&extension);
+        self.skipped_extensions.insert(extension);
+        self
+    }
+
+    /// Fall back to Vim/Emacs modelines for files the extension doesn't identify.
+    pub fn detect_modelines(mut self, detect: bool) -> Self {
+        self.detect_modelines = detect;
+        self
+    }
+
+    /// Decode non-UTF-8 files lossily instead of skipping them.
+    pub fn lossy_decoding(mut self, lossy: bool) -> Self {
+        self.lossy_decoding = lossy;
+        self
+    }
+
+    /// Load options from a config file: TOML if the path ends in `.toml`
+    /// (like [`CONFIG_FILE`]), JSON otherwise. Missing fields keep their
+    /// defaults.
+    pub fn load(path: &Path) -> Result<Self, FilterError> {
+        let content = std::fs::read_to_string(path).map_err(|_| FilterError::ReadFailed {
+            path: path.to_path_buf(),
+        })?;
+        let is_toml = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("toml"));
+        let parsed = if is_toml {
+            toml::from_str(&content).map_err(|e| e.message().to_string())
+        } else {
+            serde_json::from_str(&content).map_err(|e| e.to_string())
+        };
+        parsed.map_err(|message| FilterError::InvalidConfig {
+            path: path.to_path_buf(),
+            message,
+        })
+    }
+
+    /// Check if there are no custom mappings.
+    pub fn is_empty(&self) -> bool {
+        self.additional_extensions.is_empty() && self.skipped_extensions.is_empty()
+    }
+}
+
+/// Thresholds for the content heuristics applied by [`should_process_with`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilterConfig {
+    /// Content with a line longer than this many bytes is minified.
+    pub max_line_length: usize,
+    /// Content whose non-whitespace bytes are more than this fraction ASCII
+    /// punctuation is minified. `None` (the default) disables the check.
+    pub max_symbol_density: Option<f64>,
+    /// Markers whose presence marks a file as generated.
+    pub generated_markers: Vec<String>,
+    /// Number of leading bytes searched for generated markers.
+    pub generated_scan_bytes: usize,
+}
+
+impl Default for FilterConfig {
+    fn default() -> Self {
+        Self {
+            max_line_length: MAX_LINE_LENGTH,
+            max_symbol_density: None,
+            generated_markers: GENERATED_MARKERS.iter().map(ToString::to_string).collect(),
+            generated_scan_bytes: GENERATED_SCAN_BYTES,
+        }
+    }
+}
+
+impl FilterConfig {
+    /// Check if content has a line longer than `max_line_length`.
+    pub fn is_minified(&self, content: &[u8]) -> bool {
+        has_long_line(content, self.max_line_length)
+    }
+
+    /// Check if content exceeds `max_symbol_density`.
+    pub fn is_dense(&self, content: &[u8]) -> bool {
+        self.max_symbol_density
+            .is_some_and(|max| symbol_density(content).is_some_and(|density| density > max))
+    }
+
+    /// Check if content contains one of `generated_markers`.
+    pub fn is_generated(&self, content: &[u8]) -> bool {
+        has_marker(
+            content,
+            self.generated_scan_bytes,
+            self.generated_markers.iter().map(String::as_str),
+        )
+    }
+}
+
+fn normalize_extension(extension: &str) -> String {
+    extension.trim_start_matches('.').to_lowercase()
+}
+
 /// Result of filtering a file.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FilterResult {
@@ -105,6 +261,8 @@ pub enum RejectReason {
     BinaryContent,
     /// File appears to be minified (very long lines)
     MinifiedContent,
+    /// File appears to be minified (mostly punctuation)
+    DenseContent,
     /// File appears to be generated (contains markers)
     GeneratedFile,
 }
@@ -117,6 +275,7 @@ impl std::fmt::Display for RejectReason {
             RejectReason::NoExtension => write!(f, "no extension"),
             RejectReason::BinaryContent => write!(f, "binary content"),
             RejectReason::MinifiedContent => write!(f, "minified content"),
+            RejectReason::DenseContent => write!(f, "high symbol density"),
             RejectReason::GeneratedFile => write!(f, "generated file"),
         }
     }
@@ -166,6 +325,15 @@ const GENERATED_MARKERS: &[&str] = &[
 /// Maximum line length before considering a file minified.
 const MAX_LINE_LENGTH: usize = 500;
 
+/// Leading bytes searched for generated markers.
+const GENERATED_SCAN_BYTES: usize = 2048;
+
+/// Minimum non-whitespace bytes before symbol density is meaningful.
+const MIN_DENSITY_SAMPLE: usize = 64;
+
+/// Extension of Jupyter notebooks, whose code cells are extracted as Python.
+const NOTEBOOK_EXTENSION: &str = "ipynb";
+
 /// Check if an extension is blocklisted.
 pub fn is_blocklisted(extension: &str) -> bool {
     let ext_lower = extension.to_lowercase();
@@ -179,8 +347,24 @@ pub fn is_blocklisted_filename(filename: &str) -> bool {
 
 /// Detect language from file path based on extension.
 pub fn detect_language(path: &Path) -> Option<Language> {
+    detect_language_with(path, &FilterOptions::default())
+}
+
+/// Detect language from file path, consulting custom mappings first.
+/// Skipped extensions have no language.
+pub fn detect_language_with(path: &Path, options: &FilterOptions) -> Option<Language> {
     let ext = path.extension()?.to_str()?.to_lowercase();
 
+    if options.skipped_extensions.contains(&ext) {
+        return None;
+    }
+    if let Some(lang) = options.additional_extensions.get(&ext) {
+        return Some(*lang);
+    }
+    if ext == NOTEBOOK_EXTENSION {
+        return Some(Language::Python);
+    }
+
     for lang in Language::all() {
         if lang.extensions().contains(&ext.as_str()) {
             return Some(*lang);
@@ -189,6 +373,76 @@ pub fn detect_language(path: &Path) -> Option<Language> {
     None
 }
 
+/// Check if `path` is a Jupyter notebook.
+pub fn is_notebook(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case(NOTEBOOK_EXTENSION))
+}
+
+/// Number of leading lines searched for a modeline.
+const MODELINE_LINES: usize = 5;
+
+/// Detect language from a Vim (`# vim: ft=python`, `vim: set filetype=go :`)
+/// or Emacs (`-*- mode: python -*-`, `-*- rust -*-`) modeline in the first
+/// few lines of `content`.
+pub fn detect_modeline(content: &[u8]) -> Option<Language> {
+    let text = String::from_utf8_lossy(content);
+    text.lines()
+        .take(MODELINE_LINES)
+        .find_map(|line| vim_modeline(line).or_else(|| emacs_modeline(line)))
+        .and_then(modeline_language)
+}
+
+/// Filetype from a Vim modeline (`vim:`, `vi:` or `ex:` marker).
+fn vim_modeline(line: &str) -> Option<&str> {
+    let start = ["vim:", "vi:", "ex:"].iter().find_map(|marker| {
+        line.match_indices(marker)
+            .find(|(i, _)| *i == 0 || line[..*i].ends_with(char::is_whitespace))
+            .map(|(i, _)| i + marker.len())
+    })?;
+
+    line[start..]
+        .split(|c: char| c.is_whitespace() || c == ':')
+        .find_map(|option| {
+            let (key, value) = option.split_once('=')?;
+            matches!(key, "ft" | "filetype" | "syntax").then_some(value)
+        })
+}
+
+/// Mode from an Emacs `-*- ... -*-` line.
+fn emacs_modeline(line: &str) -> Option<&str> {
+    let (_, rest) = line.split_once("-*-")?;
+    let (inner, _) = rest.split_once("-*-")?;
+
+    if !inner.contains(':') {
+        return Some(inner.trim());
+    }
+    inner.split(';').find_map(|var| {
+        let (key, value) = var.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case("mode")
+            .then(|| value.trim())
+    })
+}
+
+/// Map a Vim filetype or Emacs mode name to a language.
+fn modeline_language(name: &str) -> Option<Language> {
+    let name = name.to_lowercase();
+    // Emacs tree-sitter modes (`python-ts`) share the language.
+    let name = name.strip_suffix("-ts").unwrap_or(&name);
+    match name {
+        "rust" | "rs" => Some(Language::Rust),
+        "typescript" | "ts" => Some(Language::TypeScript),
+        "typescriptreact" | "tsx" => Some(Language::Tsx),
+        "javascript" | "js" | "js2" => Some(Language::JavaScript),
+        "javascriptreact" | "jsx" | "rjsx" => Some(Language::Jsx),
+        "python" | "python3" | "py" => Some(Language::Python),
+        "go" | "golang" => Some(Language::Go),
+        _ => None,
+    }
+}
+
 /// Check if content contains binary data (null bytes).
 pub fn is_binary(content: &[u8]) -> bool {
     content.contains(&0)
@@ -197,13 +451,17 @@ pub fn is_binary(content: &[u8]) -> bool {
 /// Check if content appears to be minified (very long lines).
 /// Uses early exit - returns as soon as a long line is found.
 pub fn is_minified(content: &[u8]) -> bool {
+    has_long_line(content, MAX_LINE_LENGTH)
+}
+
+fn has_long_line(content: &[u8], max_line_length: usize) -> bool {
     let mut line_len = 0;
     for &b in content {
         if b == b'\n' {
             line_len = 0;
         } else {
             line_len += 1;
-            if line_len > MAX_LINE_LENGTH {
+            if line_len > max_line_length {
                 return true; // Early exit
             }
         }
@@ -211,15 +469,40 @@ pub fn is_minified(content: &[u8]) -> bool {
     false
 }
 
+/// Fraction of non-whitespace bytes that are ASCII punctuation, or `None`
+/// if the sample is too small to judge.
+fn symbol_density(content: &[u8]) -> Option<f64> {
+    let (symbols, total) = content
+        .iter()
+        .filter(|b| !b.is_ascii_whitespace())
+        .fold((0usize, 0usize), |(symbols, total), b| {
+            (symbols + usize::from(b.is_ascii_punctuation()), total + 1)
+        });
+    #[allow(clippy::cast_precision_loss)] // Sample sizes are far below 2^52
+    (total >= MIN_DENSITY_SAMPLE).then(|| symbols as f64 / total as f64)
+}
+
 /// Check if content appears to be generated.
 pub fn is_generated(content: &[u8]) -> bool {
-    // Only check first 2KB for efficiency
-    let check_len = content.len().min(2048);
+    has_marker(
+        content,
+        GENERATED_SCAN_BYTES,
+        GENERATED_MARKERS.iter().copied(),
+    )
+}
+
+fn has_marker<'a>(
+    content: &[u8],
+    scan_bytes: usize,
+    mut markers: impl Iterator<Item = &'a str>,
+) -> bool {
+    // Only check the first few KB for efficiency
+    let check_len = content.len().min(scan_bytes);
     let Ok(text) = std::str::from_utf8(&content[..check_len]) else {
         return false; // Can't check non-UTF8
     };
 
-    GENERATED_MARKERS.iter().any(|marker| text.contains(marker))
+    markers.any(|marker| text.contains(marker))
 }
 
 /// Determine if a file should be processed for codemap extraction.
@@ -251,6 +534,26 @@ pub fn is_generated(content: &[u8]) -> bool {
 /// assert!(matches!(result, FilterResult::Accept(Language::Rust)));
 /// ```
 pub fn should_process(path: &Path, content: Option<&[u8]>) -> FilterResult {
+    should_process_with(path, content, &FilterOptions::default())
+}
+
+/// Like [`should_process`], with custom extension mappings. A skipped
+/// extension is rejected as blocklisted; a mapped extension bypasses the
+/// extension blocklist but still goes through content heuristics.
+pub fn should_process_with(
+    path: &Path,
+    content: Option<&[u8]>,
+    options: &FilterOptions,
+) -> FilterResult {
+    // UTF-16 text is full of NUL bytes; judge it by its decoded content.
+    let utf16 = content
+        .filter(|_| options.lossy_decoding)
+        .and_then(decode_utf16);
+    let content = match &utf16 {
+        Some((text, _)) => Some(text.as_bytes()),
+        None => content,
+    };
+
     // Check filename blocklist first
     if let Some(filename) = path.file_name().and_then(|f| f.to_str()) {
         if is_blocklisted_filename(filename) {
@@ -261,11 +564,13 @@ pub fn should_process(path: &Path, content: Option<&[u8]>) -> FilterResult {
     // Get extension
     let ext = match path.extension().and_then(|e| e.to_str()) {
         Some(e) => e.to_lowercase(),
-        None => return FilterResult::Reject(RejectReason::NoExtension),
+        None => return modeline_fallback(content, options, RejectReason::NoExtension),
     };
 
     // Layer 1: Blocklist
-    if is_blocklisted(&ext) {
+    if options.skipped_extensions.contains(&ext)
+        || (is_blocklisted(&ext) && !options.additional_extensions.contains_key(&ext))
+    {
         return FilterResult::Reject(RejectReason::BlocklistedExtension);
     }
 
@@ -277,21 +582,44 @@ pub fn should_process(path: &Path, content: Option<&[u8]>) -> FilterResult {
     }
 
     // Layer 2: Allowlist (language detection)
-    let Some(language) = detect_language(path) else {
-        return FilterResult::Reject(RejectReason::UnknownExtension);
+    let Some(language) = detect_language_with(path, options) else {
+        return modeline_fallback(content, options, RejectReason::UnknownExtension);
     };
 
-    // Layer 3: Content heuristics (if content provided)
+    // Notebook JSON keeps outputs (base64 images, HTML) on single long lines,
+    // but only code cells are extracted, so only the binary check applies.
+    if language == Language::Python && is_notebook(path) {
+        return match content {
+            Some(content) if is_binary(content) => {
+                FilterResult::Reject(RejectReason::BinaryContent)
+            }
+            _ => FilterResult::Accept(language),
+        };
+    }
+
+    check_content(content, language, &options.heuristics)
+}
+
+/// Layer 3: content heuristics (if content provided).
+fn check_content(
+    content: Option<&[u8]>,
+    language: Language,
+    config: &FilterConfig,
+) -> FilterResult {
     if let Some(content) = content {
         if is_binary(content) {
             return FilterResult::Reject(RejectReason::BinaryContent);
         }
 
-        if is_minified(content) {
+        if config.is_minified(content) {
             return FilterResult::Reject(RejectReason::MinifiedContent);
         }
 
-        if is_generated(content) {
+        if config.is_dense(content) {
+            return FilterResult::Reject(RejectReason::DenseContent);
+        }
+
+        if config.is_generated(content) {
             return FilterResult::Reject(RejectReason::GeneratedFile);
         }
     }
@@ -299,6 +627,33 @@ pub fn should_process(path: &Path, content: Option<&[u8]>) -> FilterResult {
     FilterResult::Accept(language)
 }
 
+/// Accept a file the extension didn't identify if modeline detection is on
+/// and its content has a recognized modeline; otherwise reject with `reason`.
+fn modeline_fallback(
+    content: Option<&[u8]>,
+    options: &FilterOptions,
+    reason: RejectReason,
+) -> FilterResult {
+    if options.detect_modelines {
+        if let Some(language) = content.and_then(detect_modeline) {
+            return check_content(content, language, &options.heuristics);
+        }
+    }
+    FilterResult::Reject(reason)
+}
+
+/// Check if a path may be accepted once its content is read: either its
+/// extension is accepted, or modeline detection could still identify it.
+pub fn is_candidate(path: &Path, options: &FilterOptions) -> bool {
+    match should_process_with(path, None, options) {
+        FilterResult::Accept(_) => true,
+        FilterResult::Reject(RejectReason::NoExtension | RejectReason::UnknownExtension) => {
+            options.detect_modelines
+        }
+        FilterResult::Reject(_) => false,
+    }
+}
+
 /// Check if a path passes basic extension filtering (no content check).
 ///
 /// Useful for quick filtering before reading file content.
@@ -349,6 +704,17 @@ mod tests {
         assert_eq!(result, FilterResult::Accept(Language::Python));
     }
 
+    #[test]
+    fn test_notebook_file() {
+        let result = should_process(Path::new("train.ipynb"), None);
+        assert_eq!(result, FilterResult::Accept(Language::Python));
+
+        // Output cells hold long base64 lines; they don't count as minified.
+        let content = format!("{{\n \"image/png\": \"{}\"\n}}", "A".repeat(2000));
+        let result = should_process(Path::new("train.ipynb"), Some(content.as_bytes()));
+        assert_eq!(result, FilterResult::Accept(Language::Python));
+    }
+
     #[test]
     fn test_go_file() {
         let result = should_process(Path::new("main.go"), None);
@@ -455,6 +821,207 @@ mod tests {
         assert!("invalid".parse::<Language>().is_err());
     }
 
+    #[test]
+    fn test_custom_extension_mappings() {
+        let options = FilterOptions::default()
+            .map_extension(".PYX", Language::Python)
+            .map_extension("map", Language::JavaScript)
+            .skip_extension("go");
+
+        assert_eq!(
+            should_process_with(Path::new("fast.pyx"), None, &options),
+            FilterResult::Accept(Language::Python)
+        );
+        // Mappings override the blocklist.
+        assert_eq!(
+            should_process_with(Path::new("routes.map"), None, &options),
+            FilterResult::Accept(Language::JavaScript)
+        );
+        assert_eq!(
+            should_process_with(Path::new("main.go"), None, &options),
+            FilterResult::Reject(RejectReason::BlocklistedExtension)
+        );
+        assert_eq!(detect_language_with(Path::new("main.go"), &options), None);
+        // Unmapped extensions use the built-in table.
+        assert_eq!(
+            should_process_with(Path::new("lib.rs"), None, &options),
+            FilterResult::Accept(Language::Rust)
+        );
+        // Heuristics still apply to mapped files.
+        assert_eq!(
+            should_process_with(Path::new("gen.pyx"), Some(b"# DO NOT EDIT\n"), &options),
+            FilterResult::Reject(RejectReason::GeneratedFile)
+        );
+    }
+
+    #[test]
+    fn test_filter_options_from_json() {
+        let options: FilterOptions = serde_json::from_str(
+            r#"{"additional_extensions": {"pyx": "python"}, "skipped_extensions": ["gotmpl"]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            options,
+            FilterOptions::default()
+                .map_extension("pyx", Language::Python)
+                .skip_extension("gotmpl")
+        );
+    }
+
+    #[test]
+    fn test_detect_modeline() {
+        let cases: [(&str, Option<Language>); 9] = [
+            ("#!/bin/sh\n# vim: ft=python\n", Some(Language::Python)),
+            ("// vim: set filetype=go ts=4 :\n", Some(Language::Go)),
+            ("/* vi:syntax=typescriptreact */\n", Some(Language::Tsx)),
+            (
+                "# -*- mode: python; coding: utf-8 -*-\n",
+                Some(Language::Python),
+            ),
+            ("// -*- rust -*-\n", Some(Language::Rust)),
+            ("// -*- mode: js2 -*-\n", Some(Language::JavaScript)),
+            ("# vim: ft=ruby\n", None),
+            ("let svim: ft=python\n", None),
+            ("1\n2\n3\n4\n5\n# vim: ft=python\n", None),
+        ];
+        for (content, expected) in cases {
+            assert_eq!(detect_modeline(content.as_bytes()), expected, "{content:?}");
+        }
+    }
+
+    #[test]
+    fn test_modeline_fallback_is_opt_in() {
+        let content = b"#!/usr/bin/env bash\n# vim: ft=python\nimport os\n";
+        assert_eq!(
+            should_process(Path::new("bin/tool"), Some(content)),
+            FilterResult::Reject(RejectReason::NoExtension)
+        );
+
+        let options = FilterOptions::default().detect_modelines(true);
+        assert_eq!(
+            should_process_with(Path::new("bin/tool"), Some(content), &options),
+            FilterResult::Accept(Language::Python)
+        );
+        assert_eq!(
+            should_process_with(Path::new("tool.in"), Some(content), &options),
+            FilterResult::Accept(Language::Python)
+        );
+        // The extension wins when it is known.
+        assert_eq!(
+            should_process_with(Path::new("tool.rs"), Some(content), &options),
+            FilterResult::Accept(Language::Rust)
+        );
+        assert!(is_candidate(Path::new("bin/tool"), &options));
+        assert!(!is_candidate(Path::new("image.png"), &options));
+        assert!(!is_candidate(
+            Path::new("bin/tool"),
+            &FilterOptions::default()
+        ));
+    }
+
+    #[test]
+    fn test_configured_heuristics() {
+        let long_line = format!("let x = \"{}\";\n", "a".repeat(600));
+        let path = Path::new("app.ts");
+
+        let relaxed = FilterOptions {
+            heuristics: FilterConfig {
+                max_line_length: 1000,
+                ..FilterConfig::default()
+            },
+            ..FilterOptions::default()
+        };
+        assert_eq!(
+            should_process_with(path, Some(long_line.as_bytes()), &relaxed),
+            FilterResult::Accept(Language::TypeScript)
+        );
+
+        let markers = FilterOptions {
+            heuristics: FilterConfig {
+                generated_markers: vec!["@autogen".into()],
+                ..FilterConfig::default()
+            },
+            ..FilterOptions::default()
+        };
+        assert_eq!(
+            should_process_with(path, Some(b"// @autogen\nexport {};\n"), &markers),
+            FilterResult::Reject(RejectReason::GeneratedFile)
+        );
+        assert_eq!(
+            should_process_with(path, Some(b"// DO NOT EDIT\nexport {};\n"), &markers),
+            FilterResult::Accept(Language::TypeScript)
+        );
+    }
+
+    #[test]
+    fn test_symbol_density() {
+        let dense = "a(b,c){return d.e(f)};g[h]=i=>j&&k||l;".repeat(4);
+        let prose =
+            "export function add(first: number, second: number) { return first + second; }\n";
+        let options = FilterOptions {
+            heuristics: FilterConfig {
+                max_symbol_density: Some(0.4),
+                ..FilterConfig::default()
+            },
+            ..FilterOptions::default()
+        };
+
+        let path = Path::new("app.js");
+        assert_eq!(
+            should_process_with(path, Some(dense.as_bytes()), &options),
+            FilterResult::Reject(RejectReason::DenseContent)
+        );
+        assert_eq!(
+            should_process_with(path, Some(prose.as_bytes()), &options),
+            FilterResult::Accept(Language::JavaScript)
+        );
+        // Disabled by default.
+        assert_eq!(
+            should_process(path, Some(dense.as_bytes())),
+            FilterResult::Accept(Language::JavaScript)
+        );
+        // Too short to judge.
+        assert!(!options.heuristics.is_dense(b"a(b);"));
+    }
+
+    #[test]
+    fn test_load_filter_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("filter.json");
+        std::fs::write(
+            &path,
+            r#"{"detect_modelines": true, "heuristics": {"max_line_length": 2000}}"#,
+        )
+        .unwrap();
+
+        let options = FilterOptions::load(&path).unwrap();
+        assert!(options.detect_modelines);
+        assert_eq!(options.heuristics.max_line_length, 2000);
+        assert_eq!(
+            options.heuristics.generated_markers,
+            FilterConfig::default().generated_markers
+        );
+
+        std::fs::write(&path, r#"{"heuristics": {"max_line_length": "long"}}"#).unwrap();
+        assert!(matches!(
+            FilterOptions::load(&path),
+            Err(FilterError::InvalidConfig { .. })
+        ));
+
+        let path = dir.path().join(CONFIG_FILE);
+        std::fs::write(
+            &path,
+            "skipped_extensions = [\"gotmpl\"]\n\n[additional_extensions]\npyx = \"python\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            FilterOptions::load(&path).unwrap(),
+            FilterOptions::default()
+                .map_extension("pyx", Language::Python)
+                .skip_extension("gotmpl")
+        );
+    }
+
     #[test]
     fn test_passes_extension_filter() {
         assert_eq!(
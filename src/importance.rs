@@ -0,0 +1,311 @@
+//! Importance ranking of declarations.
+//!
+//! When a codemap section has to shrink, the declarations worth keeping are
+//! the ones the rest of the codebase leans on. Each top-level declaration is
+//! scored from what the codemaps show: whether it is exported, how often
+//! other files name it (in imports and in signatures, fields and bases), how
+//! many files do, and whether it is documented.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::codemap::{Codemap, Declaration, Visibility};
+
+/// Points for a public declaration.
+const EXPORTED: usize = 4;
+/// Points for each file that references a declaration.
+const PER_FILE: usize = 2;
+/// Points for each reference.
+const PER_REFERENCE: usize = 1;
+/// Points for having a doc comment.
+const DOCUMENTED: usize = 1;
+
+/// Signals behind a declaration's importance.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Score {
+    /// Declared public.
+    pub exported: bool,
+    /// Times other files name the declaration in imports or declarations.
+    pub references: usize,
+    /// Other files that name it (fan-in).
+    pub files: usize,
+    /// Has a doc comment (only known when docs were extracted).
+    pub documented: bool,
+}
+
+impl Score {
+    /// Weighted sum of the signals: 4 for exported, 2 per referencing file,
+    /// 1 per reference and 1 for documented.
+    pub fn total(&self) -> usize {
+        usize::from(self.exported) * EXPORTED
+            + self.files * PER_FILE
+            + self.references * PER_REFERENCE
+            + usize::from(self.documented) * DOCUMENTED
+    }
+
+    /// Signals visible on the declaration itself, without references.
+    fn intrinsic(decl: &Declaration) -> Self {
+        Self {
+            exported: decl.visibility() == Visibility::Public,
+            references: 0,
+            files: 0,
+            documented: decl.doc().is_some(),
+        }
+    }
+}
+
+/// Scores of the top-level declarations across a set of codemaps.
+#[derive(Debug, Clone, Default)]
+pub struct Importance {
+    /// Keyed by file, first line and name.
+    scores: HashMap<(PathBuf, usize, String), Score>,
+}
+
+impl Importance {
+    /// Score every top-level declaration in `codemaps`. Names are matched
+    /// as whole identifiers, so a declaration sharing its name with one in
+    /// another file gets that one's references too.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::path::Path;
+    /// use pith::codemap::{extract_codemap, ExtractOptions};
+    /// use pith::filter::Language;
+    /// use pith::importance::Importance;
+    ///
+    /// let options = ExtractOptions::with_docs();
+    /// let config = extract_codemap(
+    ///     Path::new("src/config.rs"),
+    ///     "pub struct Config;\npub fn helper() {}\n",
+    ///     Language::Rust,
+    ///     &options,
+    /// );
+    /// let main = extract_codemap(
+    ///     Path::new("src/main.rs"),
+    ///     "use crate::config::Config;\nfn run(config: &Config) {}\n",
+    ///     Language::Rust,
+    ///     &options,
+    /// );
+    /// let codemaps = [config, main];
+    /// let importance = Importance::from_codemaps(&codemaps);
+    ///
+    /// let config = &codemaps[0];
+    /// let score = importance.score(&config.path, &config.declarations[0]);
+    /// assert_eq!((score.references, score.files), (2, 1));
+    /// assert!(score.total() > importance.score(&config.path, &config.declarations[1]).total());
+    /// ```
+    pub fn from_codemaps(codemaps: &[Codemap]) -> Self {
+        // Declarations by name, as (codemap, declaration) indices.
+        let mut by_name: HashMap<&str, Vec<(usize, usize)>> = HashMap::new();
+        for (file, codemap) in codemaps.iter().enumerate() {
+            for (index, decl) in codemap.declarations.iter().enumerate() {
+                if !matches!(decl, Declaration::ReExport { .. }) {
+                    by_name.entry(decl.name()).or_default().push((file, index));
+                }
+            }
+        }
+
+        let mut scores: Vec<Vec<Score>> = codemaps
+            .iter()
+            .map(|c| c.declarations.iter().map(Score::intrinsic).collect())
+            .collect();
+        for (file, codemap) in codemaps.iter().enumerate() {
+            let mut texts: Vec<&str> = Vec::new();
+            for import in &codemap.imports {
+                if import.items.is_empty() {
+                    texts.push(import.source.as_str());
+                } else {
+                    texts.extend(import.items.iter().map(String::as_str));
+                }
+            }
+            for decl in &codemap.declarations {
+                referencing_text(decl, &mut texts);
+            }
+
+            let mut seen: HashSet<(usize, usize)> = HashSet::new();
+            for identifier in texts.iter().flat_map(|text| identifiers(text)) {
+                let Some(targets) = by_name.get(identifier) else {
+                    continue;
+                };
+                for &(target_file, index) in targets.iter().filter(|(f, _)| *f != file) {
+                    let score = &mut scores[target_file][index];
+                    score.references += 1;
+                    if seen.insert((target_file, index)) {
+                        score.files += 1;
+                    }
+                }
+            }
+        }
+
+        let scores = codemaps
+            .iter()
+            .zip(scores)
+            .flat_map(|(codemap, scores)| {
+                codemap
+                    .declarations
+                    .iter()
+                    .zip(scores)
+                    .map(|(decl, score)| {
+                        let key = (
+                            codemap.path.clone(),
+                            decl.location().start_line,
+                            decl.name().to_string(),
+                        );
+                        (key, score)
+                    })
+            })
+            .collect();
+        Self { scores }
+    }
+
+    /// Score of `decl`, a top-level declaration in the codemap for `path`.
+    /// Declarations that weren't scored only get their own signals.
+    pub fn score(&self, path: &Path, decl: &Declaration) -> Score {
+        let key = (
+            path.to_path_buf(),
+            decl.location().start_line,
+            decl.name().to_string(),
+        );
+        self.scores
+            .get(&key)
+            .copied()
+            .unwrap_or_else(|| Score::intrinsic(decl))
+    }
+}
+
+/// Push the parts of `decl` that can name other declarations: signatures,
+/// field types, generics, bases and re-exported items.
+fn referencing_text<'a>(decl: &'a Declaration, texts: &mut Vec<&'a str>) {
+    match decl {
+        Declaration::Function {
+            signature,
+            overloads,
+            ..
+        } => {
+            texts.push(signature);
+            texts.extend(overloads.iter().map(String::as_str));
+        }
+        Declaration::Struct {
+            generics,
+            fields,
+            methods,
+            implements,
+            ..
+        } => {
+            texts.push(generics);
+            texts.extend(fields.iter().map(|f| f.ty.as_str()));
+            texts.extend(implements.iter().map(String::as_str));
+            for method in methods {
+                referencing_text(method, texts);
+            }
+        }
+        Declaration::Enum {
+            generics,
+            variants,
+            methods,
+            implements,
+            ..
+        } => {
+            texts.push(generics);
+            texts.extend(variants.iter().map(|v| v.name.as_str()));
+            texts.extend(implements.iter().map(String::as_str));
+            for method in methods {
+                referencing_text(method, texts);
+            }
+        }
+        Declaration::Trait {
+            generics, methods, ..
+        } => {
+            texts.push(generics);
+            texts.extend(methods.iter().map(String::as_str));
+        }
+        Declaration::TypeAlias {
+            generics, target, ..
+        } => {
+            texts.push(generics);
+            texts.push(target);
+        }
+        Declaration::Const { ty, .. } => texts.push(ty),
+        Declaration::Interface { members, .. } => {
+            texts.extend(members.iter().map(String::as_str));
+        }
+        Declaration::Class {
+            generics,
+            extends,
+            implements,
+            fields,
+            members,
+            ..
+        } => {
+            texts.push(generics);
+            texts.extend(extends.iter().map(String::as_str));
+            texts.extend(implements.iter().map(String::as_str));
+            texts.extend(fields.iter().map(|f| f.ty.as_str()));
+            for member in members {
+                referencing_text(member, texts);
+            }
+        }
+        Declaration::ReExport { items, .. } => {
+            texts.extend(items.iter().map(String::as_str));
+        }
+    }
+}
+
+fn identifiers(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .filter(|word| !word.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codemap::{extract_codemap, ExtractOptions};
+    use crate::filter::Language;
+
+    #[test]
+    fn test_scores() {
+        let options = ExtractOptions::with_docs();
+        let extract = |path: &str, content: &str, language| {
+            extract_codemap(Path::new(path), content, language, &options)
+        };
+        let codemaps = vec![
+            extract(
+                "src/model.rs",
+                "/// A user.\npub struct User { pub id: u64 }\npub struct Unused;\nfn private() {}\n",
+                Language::Rust,
+            ),
+            extract(
+                "src/db.rs",
+                "use crate::model::User;\npub fn load(id: u64) -> User { todo!() }\npub fn save(user: &User) {}\n",
+                Language::Rust,
+            ),
+            extract(
+                "web/api.ts",
+                "import { User } from './model';\nexport function show(user: User): string { return ''; }\n",
+                Language::TypeScript,
+            ),
+        ];
+        let importance = Importance::from_codemaps(&codemaps);
+        let score = |file: usize, index: usize| {
+            importance.score(&codemaps[file].path, &codemaps[file].declarations[index])
+        };
+
+        assert_eq!(
+            score(0, 0),
+            Score {
+                exported: true,
+                references: 5,
+                files: 2,
+                documented: true,
+            }
+        );
+        assert_eq!(score(0, 0).total(), 4 + 2 * 2 + 5 + 1);
+        assert_eq!(score(0, 1).total(), 4);
+        assert_eq!(score(0, 2).total(), 0);
+        // References from the declaring file don't count.
+        assert_eq!(score(1, 0).references, 0);
+    }
+}
@@ -0,0 +1,203 @@
+//! Starter `.pithignore` and `pith.toml` for `pith init`.
+//!
+//! The project type is detected from its manifest files, and each detected
+//! ecosystem contributes its own exclusions on top of
+//! [`DEFAULT_IGNORES`](crate::walker::DEFAULT_IGNORES).
+
+use std::fmt;
+use std::path::Path;
+
+/// Ecosystem detected from a manifest file in the project root.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectKind {
+    Rust,
+    Node,
+    Python,
+    Go,
+}
+
+impl fmt::Display for ProjectKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProjectKind::Rust => write!(f, "rust"),
+            ProjectKind::Node => write!(f, "node"),
+            ProjectKind::Python => write!(f, "python"),
+            ProjectKind::Go => write!(f, "go"),
+        }
+    }
+}
+
+impl ProjectKind {
+    /// All project kinds, in detection order.
+    pub fn all() -> &'static [ProjectKind] {
+        &[
+            ProjectKind::Rust,
+            ProjectKind::Node,
+            ProjectKind::Python,
+            ProjectKind::Go,
+        ]
+    }
+
+    /// Files whose presence in the root identifies this kind of project.
+    pub fn manifests(self) -> &'static [&'static str] {
+        match self {
+            ProjectKind::Rust => &["Cargo.toml"],
+            ProjectKind::Node => &["package.json"],
+            ProjectKind::Python => &[
+                "pyproject.toml",
+                "setup.py",
+                "setup.cfg",
+                "requirements.txt",
+                "Pipfile",
+            ],
+            ProjectKind::Go => &["go.mod"],
+        }
+    }
+
+    /// `.pithignore` patterns for this ecosystem, beyond the default ignores.
+    fn ignores(self) -> &'static [&'static str] {
+        match self {
+            ProjectKind::Rust => &["*.rs.bk", "*.profraw", "*.snap"],
+            ProjectKind::Node => &[
+                "build/",
+                "out/",
+                "coverage/",
+                ".turbo/",
+                ".nuxt/",
+                ".svelte-kit/",
+                "storybook-static/",
+                "*.tsbuildinfo",
+            ],
+            ProjectKind::Python => &[
+                "__pycache__/",
+                "*.pyc",
+                ".venv/",
+                "venv/",
+                ".tox/",
+                ".nox/",
+                ".mypy_cache/",
+                ".pytest_cache/",
+                ".ruff_cache/",
+                ".ipynb_checkpoints/",
+                "build/",
+                "*.egg-info/",
+            ],
+            ProjectKind::Go => &["bin/", "testdata/", "*.test", "coverage.out"],
+        }
+    }
+}
+
+/// Detect project kinds from manifest files directly in `root`.
+pub fn detect_project(root: &Path) -> Vec<ProjectKind> {
+    ProjectKind::all()
+        .iter()
+        .copied()
+        .filter(|kind| {
+            kind.manifests()
+                .iter()
+                .any(|manifest| root.join(manifest).is_file())
+        })
+        .collect()
+}
+
+/// Contents of a starter `.pithignore` for `kinds`.
+pub fn pithignore_template(kinds: &[ProjectKind]) -> String {
+    let mut out = String::from(
+        "# Paths pith skips, in .gitignore syntax.\n\
+         # node_modules/, vendor/, dist/, .next/, target/, *.lock and *.min.*\n\
+         # are already skipped unless --no-default-ignores is given.\n",
+    );
+
+    let mut seen: Vec<&str> = Vec::new();
+    for kind in kinds {
+        let patterns: Vec<&str> = kind
+            .ignores()
+            .iter()
+            .copied()
+            .filter(|pattern| !seen.contains(pattern))
+            .collect();
+        if patterns.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("\n# {}\n", kind));
+        for pattern in patterns {
+            out.push_str(pattern);
+            out.push('\n');
+            seen.push(pattern);
+        }
+    }
+    out
+}
+
+/// Contents of a starter `pith.toml` for `kinds`. Every setting is a
+/// [`FilterOptions`](crate::filter::FilterOptions) field.
+pub fn config_template(kinds: &[ProjectKind]) -> String {
+    let detected = if kinds.is_empty() {
+        "none".to_string()
+    } else {
+        kinds
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    // Python projects often ship extensionless scripts (`bin/manage`).
+    let detect_modelines = kinds.contains(&ProjectKind::Python);
+
+    format!(
+        "# pith configuration, read by `pith codemap` and `pith context` when run\n\
+         # on this directory. Detected project type: {detected}.\n\
+         \n\
+         # Detect the language of extensionless scripts from Vim/Emacs modelines.\n\
+         detect_modelines = {detect_modelines}\n\
+         \n\
+         # Extensions to leave out entirely, e.g. [\"gotmpl\"].\n\
+         skipped_extensions = []\n\
+         \n\
+         # Extra extensions to treat as a supported language, e.g. pyx = \"python\".\n\
+         [additional_extensions]\n\
+         \n\
+         # Content heuristics. Uncomment to change the defaults.\n\
+         [heuristics]\n\
+         # max_line_length = 500\n\
+         # max_symbol_density = 0.5\n\
+         # generated_scan_bytes = 2048\n"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::FilterOptions;
+
+    #[test]
+    fn test_detect_project() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(detect_project(dir.path()).is_empty());
+
+        std::fs::write(dir.path().join("package.json"), "{}").unwrap();
+        std::fs::write(dir.path().join("pyproject.toml"), "").unwrap();
+        assert_eq!(
+            detect_project(dir.path()),
+            vec![ProjectKind::Node, ProjectKind::Python]
+        );
+    }
+
+    #[test]
+    fn test_templates() {
+        let kinds = [ProjectKind::Node, ProjectKind::Python];
+
+        let ignore = pithignore_template(&kinds);
+        assert!(ignore.contains("# node\nbuild/\n"));
+        assert!(ignore.contains("__pycache__/\n"));
+        // Shared patterns are listed once.
+        assert_eq!(ignore.matches("build/\n").count(), 1);
+
+        let config: FilterOptions = toml::from_str(&config_template(&kinds)).unwrap();
+        assert!(config.detect_modelines);
+        assert_eq!(config.heuristics, FilterOptions::default().heuristics);
+
+        let config: FilterOptions = toml::from_str(&config_template(&[])).unwrap();
+        assert_eq!(config, FilterOptions::default());
+    }
+}
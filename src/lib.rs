@@ -17,6 +17,16 @@
 #![allow(clippy::format_push_string)] // Acceptable in output formatting
 #![allow(clippy::single_match_else)] // Match is sometimes clearer
 #![allow(clippy::unnecessary_wraps)] // Some wraps are for API consistency
+// Existing test fixtures predate these lints
+#![cfg_attr(
+    test,
+    allow(
+        clippy::needless_raw_string_hashes,
+        clippy::len_zero,
+        clippy::unreadable_literal,
+        clippy::cast_possible_truncation
+    )
+)]
 
 //! Pith - Generate optimized codebase context for LLMs.
 //!
@@ -44,9 +54,22 @@
 //!
 //! - [`tokens`] - Token counting for LLM context budgets
 //! - [`filter`] - File filtering with blocklist/allowlist/heuristics
+//! - [`decode`] - Lossy decoding of non-UTF-8 source files
 //! - [`tree`] - File tree representation and rendering
 //! - [`walker`] - Directory traversal with gitignore support
+//! - [`attributes`] - `.gitattributes` linguist overrides
+//! - [`init`] - Starter `.pithignore` and `pith.toml` per project type
+//! - [`license`] - License header detection and stripping
+//! - [`minify`] - Whitespace minification for selected files
 //! - [`codemap`] - Tree-sitter based code extraction
+//! - [`architecture`] - Module overview from codemaps and their imports
+//! - [`importance`] - Ranking declarations by exports, references and docs
+//! - [`records`] - One embedding-ready record per declaration, for RAG pipelines
+//! - [`tools`] - Public functions as OpenAI or MCP tool definitions
+//! - [`semver`] - Public Rust API changes between two snapshots, classified by semver impact
+//! - [`budget`] - Pruning output to fit a token budget
+//! - [`progress`] - NDJSON progress events for long runs
+//! - [`cancel`] - Cooperative cancellation of long runs
 //! - [`builder`] - Fluent API for extraction
 //!
 //! # Supported Languages
@@ -54,24 +77,40 @@
 //! - Rust (`.rs`)
 //! - TypeScript (`.ts`, `.tsx`)
 //! - JavaScript (`.js`, `.jsx`, `.mjs`, `.cjs`)
-//! - Python (`.py`, `.pyi`)
+//! - Python (`.py`, `.pyi`, `.ipynb`)
 //! - Go (`.go`)
 
+pub mod architecture;
+pub mod attributes;
+pub mod budget;
 pub mod builder;
+pub mod cancel;
 pub mod codemap;
+pub mod decode;
 pub mod errors;
 pub mod filter;
+pub mod importance;
+pub mod init;
+pub mod license;
+pub mod minify;
 pub mod output;
+pub mod progress;
+pub mod records;
+pub mod semver;
 pub mod tokens;
+pub mod tools;
 pub mod tree;
 pub mod walker;
 
 // Re-export key types at crate root for convenience
 pub use builder::{Pith, PithResult};
-pub use codemap::{Codemap, CodemapError, Declaration, Location, Visibility};
+pub use codemap::{
+    Codemap, CodemapError, Declaration, DeclarationIds, Location, ParseIssue, Visibility,
+    VisibilityThreshold,
+};
 pub use errors::PithError;
-pub use filter::{FilterError, Language};
+pub use filter::{FilterError, FilterOptions, Language};
 pub use output::OutputError;
 pub use tokens::{count_tokens, Encoding, TokenCounter};
-pub use tree::{FileNode, NodeKind, RenderOptions};
+pub use tree::{DirectoryTotals, FileNode, NodeKind, RenderOptions, TreeDiff, TreeMarkers};
 pub use walker::WalkError;
@@ -0,0 +1,119 @@
+//! License header detection.
+//!
+//! Many projects open every file with the same copyright or license comment.
+//! It carries no information about the code, so it can be stripped from
+//! selected files (and from doc comments) to save tokens.
+
+/// Words that mark a comment as a license header, matched case-insensitively.
+const LICENSE_MARKERS: &[&str] = &[
+    "copyright",
+    "license",
+    "licence",
+    "spdx-license-identifier",
+    "all rights reserved",
+];
+
+/// Check if `text` reads like a copyright or license notice.
+pub fn is_license_text(text: &str) -> bool {
+    let lower = text.to_lowercase();
+    LICENSE_MARKERS.iter().any(|marker| lower.contains(marker))
+}
+
+/// Number of lines taken by a license header at the top of `content`,
+/// including blank lines around it. `None` if the file does not open with a
+/// comment block that reads like a license.
+///
+/// Recognizes `/* ... */` blocks and runs of `//`, `#` or `--` line comments.
+/// A shebang or anything else before the comment means there is no header.
+///
+/// # Examples
+///
+/// ```
+/// use pith::license::license_header_lines;
+///
+/// let content = "// Copyright 2024 Acme\n// SPDX-License-Identifier: MIT\n\nfn main() {}\n";
+/// assert_eq!(license_header_lines(content), Some(3));
+/// assert_eq!(license_header_lines("// Entry point\nfn main() {}\n"), None);
+/// ```
+pub fn license_header_lines(content: &str) -> Option<usize> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut index = lines.iter().take_while(|l| l.trim().is_empty()).count();
+    let start = index;
+
+    let first = lines.get(index)?.trim_start();
+    if let Some(opening) = first.strip_prefix("/*") {
+        // A block comment, up to the line that closes it.
+        let close = if opening.contains("*/") {
+            index
+        } else {
+            index + 1 + lines[index + 1..].iter().position(|l| l.contains("*/"))?
+        };
+        index = close + 1;
+    } else {
+        index += lines[index..]
+            .iter()
+            .take_while(|line| is_line_comment(line))
+            .count();
+    }
+
+    if index == start || !is_license_text(&lines[start..index].join("\n")) {
+        return None;
+    }
+    index += lines[index..]
+        .iter()
+        .take_while(|l| l.trim().is_empty())
+        .count();
+    Some(index)
+}
+
+/// Split `content` into the license header lines count and what follows.
+pub fn strip_license_header(content: &str) -> Option<(usize, &str)> {
+    let lines = license_header_lines(content)?;
+    let offset = content
+        .split_inclusive('\n')
+        .take(lines)
+        .map(str::len)
+        .sum();
+    Some((lines, &content[offset..]))
+}
+
+/// `//`, `--`, or `#` followed by whitespace, another `#` or nothing. Rust
+/// attributes (`#[`), shebangs and C preprocessor lines don't qualify.
+fn is_line_comment(line: &str) -> bool {
+    let line = line.trim_start();
+    if line.starts_with("//") || line.starts_with("--") {
+        return true;
+    }
+    match line.strip_prefix('#') {
+        Some(rest) => rest.is_empty() || rest.starts_with([' ', '\t', '#']),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_license_header_detection() {
+        let block = "/*\n * Copyright (c) 2024 Acme Corp.\n * Licensed under Apache-2.0.\n */\n\npackage main\n";
+        assert_eq!(license_header_lines(block), Some(5));
+
+        let python = "# SPDX-License-Identifier: MIT\n#\n# Tools.\nimport os\n";
+        assert_eq!(license_header_lines(python), Some(3));
+        assert_eq!(strip_license_header(python), Some((3, "import os\n")));
+
+        // Ordinary comments, shebangs and attributes are left alone.
+        assert_eq!(
+            license_header_lines("// Parses input.\nfn parse() {}\n"),
+            None
+        );
+        assert_eq!(
+            license_header_lines("#!/usr/bin/env python\n# Copyright 2024\n"),
+            None
+        );
+        assert_eq!(license_header_lines("#[derive(Debug)]\nstruct A;\n"), None);
+        assert_eq!(license_header_lines("/* Copyright, never closed\n"), None);
+        assert_eq!(license_header_lines(""), None);
+    }
+}
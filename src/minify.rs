@@ -0,0 +1,132 @@
+//! Whitespace minification for selected file content.
+//!
+//! Trailing whitespace and runs of blank lines cost tokens without changing
+//! what the code means. Minifying keeps every remaining line's original line
+//! number, so line-numbered output still points into the file on disk.
+
+/// How much whitespace to remove from selected files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Minify {
+    /// Keep content as-is.
+    #[default]
+    Off,
+    /// Strip trailing whitespace and collapse runs of blank lines into one.
+    /// Leading and trailing blank lines are dropped.
+    Whitespace,
+    /// As [`Minify::Whitespace`], and also remove indentation shared by every
+    /// non-blank line.
+    Dedent,
+}
+
+/// Lines of `content` that survive minification, each paired with its line
+/// number, counting the first line as `first`.
+///
+/// # Examples
+///
+/// ```
+/// use pith::minify::{minify_lines, Minify};
+///
+/// let content = "    a  \n\n\n      b\n";
+/// assert_eq!(
+///     minify_lines(content, 1, Minify::Dedent),
+///     vec![(1, "a"), (2, ""), (4, "  b")]
+/// );
+/// ```
+pub fn minify_lines(content: &str, first: usize, mode: Minify) -> Vec<(usize, &str)> {
+    let numbered = content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (first + i, line));
+    if mode == Minify::Off {
+        return numbered.collect();
+    }
+
+    let mut lines: Vec<(usize, &str)> = Vec::new();
+    for (number, line) in numbered {
+        let line = line.trim_end();
+        let previous_blank = lines.last().is_none_or(|(_, last)| last.is_empty());
+        if line.is_empty() && previous_blank {
+            continue;
+        }
+        lines.push((number, line));
+    }
+    if lines.last().is_some_and(|(_, last)| last.is_empty()) {
+        lines.pop();
+    }
+
+    if mode == Minify::Dedent {
+        let indent = common_indent(lines.iter().map(|(_, line)| *line));
+        for (_, line) in &mut lines {
+            if !line.is_empty() {
+                *line = &line[indent..];
+            }
+        }
+    }
+    lines
+}
+
+/// Minify `content`, ending each remaining line with `\n`.
+pub fn minify(content: &str, mode: Minify) -> String {
+    let mut out = String::with_capacity(content.len());
+    for (_, line) in minify_lines(content, 1, mode) {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+/// Length in bytes of the leading whitespace shared by all non-blank lines.
+/// Tabs and spaces only match themselves.
+fn common_indent<'a>(lines: impl Iterator<Item = &'a str>) -> usize {
+    let mut common: Option<&str> = None;
+    for line in lines.filter(|line| !line.is_empty()) {
+        let indent = &line[..line.len() - line.trim_start().len()];
+        common = Some(match common {
+            None => indent,
+            Some(prefix) => {
+                let shared = prefix
+                    .bytes()
+                    .zip(indent.bytes())
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                &prefix[..shared]
+            }
+        });
+    }
+    common.map_or(0, str::len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minify() {
+        let content = "\n\nfn main() {   \n    let a = 1;\n\n\n\n    a\n}\n\n";
+        assert_eq!(minify(content, Minify::Off), content);
+        assert_eq!(
+            minify(content, Minify::Whitespace),
+            "fn main() {\n    let a = 1;\n\n    a\n}\n"
+        );
+        // Top-level code shares no indentation.
+        assert_eq!(
+            minify(content, Minify::Dedent),
+            minify(content, Minify::Whitespace)
+        );
+
+        let snippet = "\t\tif x {\n\t\t\ty()\n\n\t\t}\n";
+        assert_eq!(minify(snippet, Minify::Dedent), "if x {\n\ty()\n\n}\n");
+        // Tabs and spaces don't match each other.
+        assert_eq!(minify("\t a\n\t  b\n", Minify::Dedent), "a\n b\n");
+        assert_eq!(minify("\ta\n    b\n", Minify::Dedent), "\ta\n    b\n");
+
+        // Line numbers are those of the original content.
+        assert_eq!(
+            minify_lines(content, 10, Minify::Whitespace)
+                .iter()
+                .map(|(number, _)| *number)
+                .collect::<Vec<_>>(),
+            vec![12, 13, 14, 17, 18]
+        );
+    }
+}
@@ -1,10 +1,16 @@
 //! Output formatting for pith.
 //!
-//! Formats file trees, codemaps, and selected files into
-//! XML-style or JSON output suitable for LLM consumption.
-
-use std::collections::{BTreeMap, HashSet};
-use std::path::PathBuf;
+//! Formats file trees, codemaps, and selected files into XML-style,
+//! Markdown, compact, JSON or YAML output suitable for LLM consumption.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
 
 use serde::Serialize;
 use thiserror::Error;
@@ -15,14 +21,30 @@ pub enum OutputError {
     #[error("serialization failed: {0}")]
     Serialization(#[from] serde_json::Error),
 
+    #[error("YAML serialization failed: {0}")]
+    Yaml(#[from] serde_yaml_ng::Error),
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
+
+    #[error("output file already exists: {path} (use --force to overwrite)")]
+    FileExists { path: PathBuf },
 }
 
-use crate::codemap::{Codemap, Declaration, Location, Visibility};
+use crate::architecture::Architecture;
+use crate::codemap::{
+    combined_fingerprint, Codemap, Component, Declaration, DeclarationIds, Field, Location,
+    StructKind, Visibility, VisibilityThreshold,
+};
 use crate::filter::Language;
-use crate::tokens::{Encoding, TokenCounter};
-use crate::tree::{format_number, render_tree, FileNode, NodeKind, RenderOptions};
+use crate::importance::Importance;
+use crate::license::strip_license_header;
+use crate::minify::{minify_lines, Minify};
+use crate::semver::ApiDiff;
+use crate::tokens::{Encoding, EncodingTotal, TokenCounter};
+use crate::tree::{
+    format_number, render_tree_with_legend, FileNode, NodeKind, RenderOptions, TreeMarkers,
+};
 
 /// Output format selection.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -32,6 +54,76 @@ pub enum OutputFormat {
     Xml,
     /// JSON for programmatic access.
     Json,
+    /// Markdown headings, with the tree and selected files in code fences.
+    Markdown,
+    /// The JSON structure as YAML.
+    Yaml,
+    /// Signatures only, one per line, without metadata or markup.
+    Compact,
+    /// Well-formed XML: one `<context>` document, a `<file path="...">`
+    /// element per codemap and selected file, and all text in CDATA.
+    StrictXml,
+}
+
+impl OutputFormat {
+    /// Whether this format serializes the JSON structure rather than text.
+    pub fn is_structured(self) -> bool {
+        matches!(self, OutputFormat::Json | OutputFormat::Yaml)
+    }
+}
+
+/// Declaration that starts every [`OutputFormat::StrictXml`] document.
+pub const XML_DECLARATION: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n";
+
+/// Opening of a strict XML document, up to the first section.
+const STRICT_XML_OPEN: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<context>\n";
+/// Closing of a strict XML document.
+const STRICT_XML_CLOSE: &str = "</context>\n";
+
+/// Wrap `text` in a CDATA section. A `]]>` inside is split across two
+/// sections, and characters XML does not allow at all (most control
+/// characters) become U+FFFD.
+pub fn xml_cdata(text: &str) -> String {
+    let text: String = text
+        .chars()
+        .map(|c| if is_xml_char(c) { c } else { '\u{FFFD}' })
+        .collect();
+    format!("<![CDATA[{}]]>", text.replace("]]>", "]]]]><![CDATA[>"))
+}
+
+/// Escape `text` for use inside a double-quoted XML attribute.
+pub fn xml_attribute(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' => escaped.push_str("&#9;"),
+            '\n' => escaped.push_str("&#10;"),
+            '\r' => escaped.push_str("&#13;"),
+            c if is_xml_char(c) => escaped.push(c),
+            _ => escaped.push('\u{FFFD}'),
+        }
+    }
+    escaped
+}
+
+/// Characters allowed in an XML 1.0 document.
+fn is_xml_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | '\u{20}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}' | '\u{10000}'..)
+}
+
+/// Serialize `value` as pretty JSON, or as YAML for [`OutputFormat::Yaml`].
+pub fn serialize<T: Serialize + ?Sized>(
+    value: &T,
+    format: OutputFormat,
+) -> Result<String, OutputError> {
+    match format {
+        OutputFormat::Yaml => Ok(serde_yaml_ng::to_string(value)?),
+        _ => Ok(serde_json::to_string_pretty(value)?),
+    }
 }
 
 /// Options controlling what to include in output.
@@ -39,16 +131,116 @@ pub enum OutputFormat {
 pub struct OutputOptions {
     /// Output format.
     pub format: OutputFormat,
+    /// Open with an overview of top-level modules, the modules each imports,
+    /// and entry points, derived from the codemaps.
+    pub include_architecture: bool,
     /// Include file tree section.
     pub include_tree: bool,
+    /// Render at most this many entries per directory in the XML file tree.
+    pub tree_max_children: Option<usize>,
+    /// Show each file's token cost in the XML file tree, summed into
+    /// directory totals.
+    pub tree_tokens: bool,
+    /// Render only selected files and files with codemaps (plus their
+    /// ancestor directories) in the XML file tree.
+    pub prune_tree: bool,
+    /// Collapse directories at this depth in the XML file tree into
+    /// one-line summaries.
+    pub tree_max_depth: Option<usize>,
+    /// Marker glyphs for selected files and files with codemaps in the XML
+    /// file tree. The legend lists only the markers that appear.
+    pub tree_markers: TreeMarkers,
     /// Include codemaps section.
     pub include_codemaps: bool,
     /// Include selected file contents.
     pub include_selected_files: bool,
+    /// Prefix each line of selected file content with its line number,
+    /// padded to the width of the largest. Text formats only.
+    pub line_numbers: bool,
+    /// Drop a copyright or license comment at the top of selected files,
+    /// noting the removal in the file's header. Text formats only.
+    pub strip_license_headers: bool,
+    /// Whitespace to remove from selected files. Line numbers still refer to
+    /// the original file. Text formats only.
+    pub minify: Minify,
     /// Include token summary.
     pub include_summary: bool,
-    /// Only show public declarations.
-    pub public_only: bool,
+    /// Minimum visibility of shown declarations, fields and members.
+    pub visibility: VisibilityThreshold,
+    /// Only show top-level declarations whose name matches; files with no
+    /// matching declarations are left out.
+    pub symbol_filter: Option<SymbolFilter>,
+    /// Order of top-level declarations within each codemap.
+    pub sort: DeclarationOrder,
+    /// Scores for [`DeclarationOrder::Importance`] and declaration trimming.
+    /// Computed from the rendered codemaps when `None`.
+    pub importance: Option<Arc<Importance>>,
+    /// Let [`crate::budget::fit_output`] drop the least important
+    /// declarations before whole codemaps.
+    pub trim_declarations: bool,
+    /// Token budget enforced by [`crate::budget::fit_output`]; `None` for no
+    /// limit. `format_output` itself ignores it.
+    pub max_tokens: Option<usize>,
+    /// Further encodings to report the output's total in. Budgets and
+    /// per-file counts use the encoding the output is rendered with.
+    pub extra_encodings: Vec<Encoding>,
+    /// Files left out because they could not be read, listed with the
+    /// reason in the token summary.
+    pub skipped: Vec<SkippedFile>,
+    /// Directory the codemaps were extracted from. Declaration IDs in JSON
+    /// and YAML output are relative to it; empty keeps paths as given.
+    pub root: PathBuf,
+}
+
+/// Ordering of declarations within a codemap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DeclarationOrder {
+    /// Source order (default).
+    #[default]
+    Source,
+    /// Alphabetical by name, ignoring case.
+    Name,
+    /// Grouped by kind (functions, structs, enums, traits, type aliases,
+    /// constants, interfaces, classes, re-exports), source order within a kind.
+    Kind,
+    /// Public first, then `pub(crate)`, protected and private, source order
+    /// within a level.
+    Visibility,
+    /// Most important first (see [`crate::importance`]), source order among
+    /// equal scores.
+    Importance,
+}
+
+/// A pattern over declaration names, as used by `OutputOptions::symbol_filter`.
+#[derive(Debug, Clone)]
+pub enum SymbolFilter {
+    /// Regular expression, matched anywhere in the name unless anchored (`^Handle`).
+    Regex(regex::Regex),
+    /// Glob matched against the whole name (`Handle*`).
+    Glob(glob::Pattern),
+}
+
+impl SymbolFilter {
+    /// Compile a regular expression filter.
+    pub fn regex(pattern: &str) -> Result<Self, regex::Error> {
+        regex::Regex::new(pattern).map(SymbolFilter::Regex)
+    }
+
+    /// Compile a glob filter.
+    pub fn glob(pattern: &str) -> Result<Self, glob::PatternError> {
+        glob::Pattern::new(pattern).map(SymbolFilter::Glob)
+    }
+
+    /// Whether a declaration name passes the filter. Qualified names
+    /// (`outer::f`, `NS.inner`) also pass when their last segment does.
+    pub fn matches(&self, name: &str) -> bool {
+        let last = name.rsplit(['.', ':']).next().unwrap_or(name);
+        let is_match = |name: &str| match self {
+            SymbolFilter::Regex(re) => re.is_match(name),
+            SymbolFilter::Glob(pattern) => pattern.matches(name),
+        };
+        is_match(name) || (last != name && is_match(last))
+    }
 }
 
 impl Default for OutputOptions {
@@ -56,10 +248,27 @@ impl Default for OutputOptions {
         Self {
             format: OutputFormat::Xml,
             include_tree: true,
+            tree_max_children: None,
+            tree_tokens: false,
+            prune_tree: false,
+            tree_max_depth: None,
+            tree_markers: TreeMarkers::default(),
             include_codemaps: true,
             include_selected_files: false,
+            line_numbers: false,
+            strip_license_headers: false,
+            minify: Minify::Off,
+            include_architecture: false,
             include_summary: true,
-            public_only: true,
+            visibility: VisibilityThreshold::Public,
+            symbol_filter: None,
+            sort: DeclarationOrder::Source,
+            importance: None,
+            trim_declarations: false,
+            max_tokens: None,
+            extra_encodings: Vec::new(),
+            skipped: Vec::new(),
+            root: PathBuf::new(),
         }
     }
 }
@@ -100,27 +309,198 @@ impl OutputOptions {
 #[derive(Debug, Clone)]
 pub struct SelectedFile {
     pub path: PathBuf,
+    /// Detected language, used to tag the code fence. Without one, the
+    /// file extension is used.
+    pub language: Option<Language>,
     pub content: String,
     pub lines: usize,
     pub tokens: usize,
 }
 
+/// A file left out of the output, and why.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SkippedFile {
+    pub path: PathBuf,
+    /// Why the file could not be used, e.g. `invalid UTF-8`.
+    pub reason: String,
+}
+
 /// Token breakdown for a file.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct FileTokenInfo {
     pub tokens: usize,
     pub selected: bool,
     pub has_codemap: bool,
+    /// Tokens minification removed from the file's block.
+    pub minify_saved: usize,
+    /// Language of the file, if supported.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<Language>,
+    /// Top-level declarations shown in the file's codemap.
+    pub declarations: usize,
 }
 
 /// Summary of token usage.
 #[derive(Debug, Clone)]
 pub struct TokenSummary {
     pub total: usize,
+    pub architecture_tokens: usize,
     pub tree_tokens: usize,
     pub codemap_tokens: usize,
     pub selected_tokens: usize,
     pub file_breakdown: BTreeMap<PathBuf, FileTokenInfo>,
+    /// The total under each of [`OutputOptions::extra_encodings`].
+    pub extra_totals: Vec<EncodingTotal>,
+    /// Files left out because they could not be read.
+    pub skipped: Vec<SkippedFile>,
+}
+
+/// Files listed under "Largest files" in the token summary.
+pub const SUMMARY_TOP_FILES: usize = 5;
+
+/// A file's share of the output.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Contributor {
+    pub path: PathBuf,
+    pub tokens: usize,
+    /// Percentage of the total, to one decimal.
+    pub percent: f64,
+}
+
+impl TokenSummary {
+    /// The `n` files contributing the most tokens, most first, with their
+    /// share of the total. Ties keep path order.
+    pub fn largest_files(&self, n: usize) -> Vec<Contributor> {
+        largest_contributors(
+            self.file_breakdown
+                .iter()
+                .map(|(path, info)| (path.clone(), info.tokens)),
+            self.total,
+            n,
+        )
+    }
+
+    /// Files, tokens and declarations per language, most tokens first.
+    pub fn languages(&self) -> Vec<LanguageTokens> {
+        language_totals(self.file_breakdown.values())
+    }
+
+    /// Tokens saved by minifying selected files, over all files.
+    pub fn minify_saved(&self) -> usize {
+        self.file_breakdown
+            .values()
+            .map(|info| info.minify_saved)
+            .sum()
+    }
+}
+
+/// Files, tokens and declarations of one language in the output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LanguageTokens {
+    /// `None` for files in no supported language.
+    pub language: Option<Language>,
+    pub files: usize,
+    pub tokens: usize,
+    pub declarations: usize,
+}
+
+impl fmt::Display for LanguageTokens {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.language {
+            Some(language) => write!(f, "{}", language),
+            None => write!(f, "other"),
+        }
+    }
+}
+
+fn language_totals<'a>(files: impl Iterator<Item = &'a FileTokenInfo>) -> Vec<LanguageTokens> {
+    let mut totals: Vec<LanguageTokens> = Vec::new();
+    for info in files {
+        let index = match totals.iter().position(|t| t.language == info.language) {
+            Some(index) => index,
+            None => {
+                totals.push(LanguageTokens {
+                    language: info.language,
+                    files: 0,
+                    tokens: 0,
+                    declarations: 0,
+                });
+                totals.len() - 1
+            }
+        };
+        let total = &mut totals[index];
+        total.files += 1;
+        total.tokens += info.tokens;
+        total.declarations += info.declarations;
+    }
+    totals.sort_by_cached_key(|t| (std::cmp::Reverse(t.tokens), t.to_string()));
+    totals
+}
+
+fn largest_contributors(
+    files: impl Iterator<Item = (PathBuf, usize)>,
+    total: usize,
+    n: usize,
+) -> Vec<Contributor> {
+    let mut files: Vec<(PathBuf, usize)> = files.collect();
+    // Stable sort keeps ties in path order.
+    files.sort_by_key(|(_, tokens)| std::cmp::Reverse(*tokens));
+    files
+        .into_iter()
+        .take(n)
+        .map(|(path, tokens)| Contributor {
+            path,
+            tokens,
+            percent: if total == 0 {
+                0.0
+            } else {
+                (tokens as f64 * 1000.0 / total as f64).round() / 10.0
+            },
+        })
+        .collect()
+}
+
+/// Text output split into its sections. Displaying it joins the sections
+/// in order, which is exactly what [`format_output`] returns.
+///
+/// Sections that were not requested, or have nothing to show, are empty.
+#[derive(Debug, Clone)]
+pub struct RenderedContext {
+    /// Overview of modules and their imports.
+    pub architecture_section: String,
+    /// The file map.
+    pub tree_section: String,
+    /// Codemaps of all extracted files.
+    pub codemap_section: String,
+    /// Full content of selected files.
+    pub selected_section: String,
+    /// The rendered token summary.
+    pub summary_section: String,
+    /// Token counts of the sections above, computed even when the summary
+    /// section is not included.
+    pub summary: TokenSummary,
+    /// Format the sections are rendered in. For
+    /// [`OutputFormat::StrictXml`], displaying wraps the sections in the
+    /// `<context>` document element.
+    pub format: OutputFormat,
+}
+
+impl fmt::Display for RenderedContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let strict = self.format == OutputFormat::StrictXml;
+        if strict {
+            f.write_str(STRICT_XML_OPEN)?;
+        }
+        f.write_str(&self.architecture_section)?;
+        f.write_str(&self.tree_section)?;
+        f.write_str(&self.codemap_section)?;
+        f.write_str(&self.selected_section)?;
+        f.write_str(&self.summary_section)?;
+        if strict {
+            f.write_str(STRICT_XML_CLOSE)?;
+        }
+        Ok(())
+    }
 }
 
 // ============================================================================
@@ -136,10 +516,163 @@ pub fn format_output(
     encoding: Encoding,
 ) -> String {
     let counter = TokenCounter::new(encoding);
+    let codemaps = matching_codemaps(codemaps, options);
+    let options: &OutputOptions = &with_importance(&codemaps, options);
 
-    match options.format {
-        OutputFormat::Xml => format_output_xml(tree, codemaps, selected_files, options, &counter),
-        OutputFormat::Json => format_output_json(tree, codemaps, selected_files, options, &counter),
+    let start = Instant::now();
+    let output = if options.format.is_structured() {
+        format_output_data(tree, &codemaps, selected_files, options, &counter)
+    } else {
+        render_sections(tree, &codemaps, selected_files, options, &counter).to_string()
+    };
+    tracing::debug!(
+        "rendered {} codemaps and {} selected files ({} bytes) in {:.2?}",
+        codemaps.len(),
+        selected_files.len(),
+        output.len(),
+        start.elapsed()
+    );
+    output
+}
+
+/// Format output as separate sections, for callers that post-process or
+/// reorder them.
+///
+/// Structured formats ([`OutputFormat::is_structured`]) are one document and
+/// cannot be split, so they are rendered with the XML layout here.
+///
+/// # Examples
+///
+/// ```
+/// use pith::output::{render_context, OutputOptions};
+/// use pith::Encoding;
+///
+/// let rendered = render_context(None, &[], &[], &OutputOptions::default(), Encoding::default());
+/// assert_eq!(rendered.summary.codemap_tokens, 0);
+/// // Put the summary first.
+/// let output = format!("{}{}", rendered.summary_section, rendered.tree_section);
+/// ```
+pub fn render_context(
+    tree: Option<&FileNode>,
+    codemaps: &[Codemap],
+    selected_files: &[SelectedFile],
+    options: &OutputOptions,
+    encoding: Encoding,
+) -> RenderedContext {
+    let counter = TokenCounter::new(encoding);
+    let codemaps = matching_codemaps(codemaps, options);
+    let options: &OutputOptions = &with_importance(&codemaps, options);
+    if options.format.is_structured() {
+        let options = OutputOptions {
+            format: OutputFormat::Xml,
+            ..options.clone()
+        };
+        render_sections(tree, &codemaps, selected_files, &options, &counter)
+    } else {
+        render_sections(tree, &codemaps, selected_files, options, &counter)
+    }
+}
+
+/// `options` with importance scores over `codemaps` if sorting by importance
+/// needs them and none were given.
+fn with_importance<'a>(codemaps: &[Codemap], options: &'a OutputOptions) -> Cow<'a, OutputOptions> {
+    if options.sort != DeclarationOrder::Importance || options.importance.is_some() {
+        return Cow::Borrowed(options);
+    }
+    Cow::Owned(OutputOptions {
+        importance: Some(Arc::new(Importance::from_codemaps(codemaps))),
+        ..options.clone()
+    })
+}
+
+/// Codemaps left after `options.symbol_filter`: those with at least one
+/// visible declaration.
+fn matching_codemaps<'a>(codemaps: &'a [Codemap], options: &OutputOptions) -> Cow<'a, [Codemap]> {
+    if options.symbol_filter.is_none() {
+        return Cow::Borrowed(codemaps);
+    }
+    Cow::Owned(
+        codemaps
+            .iter()
+            .filter(|c| visible_declarations(c, options).next().is_some())
+            .cloned()
+            .collect(),
+    )
+}
+
+/// Top-level declarations of `codemap` shown under `options`.
+fn visible_declarations<'a>(
+    codemap: &'a Codemap,
+    options: &'a OutputOptions,
+) -> impl Iterator<Item = &'a Declaration> {
+    codemap
+        .declarations
+        .iter()
+        .filter(|d| is_visible(d, options))
+}
+
+/// Whether a top-level declaration is shown under `options`.
+pub(crate) fn is_visible(decl: &Declaration, options: &OutputOptions) -> bool {
+    options.visibility.allows(decl.visibility())
+        && options
+            .symbol_filter
+            .as_ref()
+            .is_none_or(|filter| filter.matches(decl.name()))
+}
+
+/// Visible declarations of `codemap` in the order selected by `options.sort`.
+fn sorted_declarations<'a>(
+    codemap: &'a Codemap,
+    options: &'a OutputOptions,
+) -> Vec<&'a Declaration> {
+    let mut decls: Vec<_> = visible_declarations(codemap, options).collect();
+    match options.sort {
+        DeclarationOrder::Source => {}
+        DeclarationOrder::Name => {
+            decls.sort_by_cached_key(|d| (d.name().to_lowercase(), d.location().start_line));
+        }
+        DeclarationOrder::Kind => decls.sort_by_key(|d| (kind_rank(d), d.location().start_line)),
+        DeclarationOrder::Visibility => {
+            decls.sort_by_key(|d| (visibility_rank(d.visibility()), d.location().start_line));
+        }
+        DeclarationOrder::Importance => {
+            let own;
+            let importance = match &options.importance {
+                Some(importance) => importance.as_ref(),
+                None => {
+                    own = Importance::from_codemaps(std::slice::from_ref(codemap));
+                    &own
+                }
+            };
+            decls.sort_by_cached_key(|d| {
+                let score = importance.score(&codemap.path, d).total();
+                (std::cmp::Reverse(score), d.location().start_line)
+            });
+        }
+    }
+    decls
+}
+
+fn kind_rank(decl: &Declaration) -> u8 {
+    match decl {
+        Declaration::Function { .. } => 0,
+        Declaration::Struct { .. } => 1,
+        Declaration::Enum { .. } => 2,
+        Declaration::Trait { .. } => 3,
+        Declaration::TypeAlias { .. } => 4,
+        Declaration::Const { .. } => 5,
+        Declaration::Interface { .. } => 6,
+        Declaration::Class { .. } => 7,
+        Declaration::ReExport { .. } => 8,
+    }
+}
+
+fn visibility_rank(visibility: Visibility) -> u8 {
+    match visibility {
+        Visibility::Public => 0,
+        Visibility::Crate => 1,
+        Visibility::Protected => 2,
+        Visibility::Private => 3,
     }
 }
 
@@ -147,38 +680,76 @@ pub fn format_output(
 // XML Formatting
 // ============================================================================
 
-fn format_output_xml(
+fn render_sections(
     tree: Option<&FileNode>,
     codemaps: &[Codemap],
     selected_files: &[SelectedFile],
     options: &OutputOptions,
     counter: &TokenCounter,
-) -> String {
+) -> RenderedContext {
     // Build each section as a standalone string and count tokens from the
     // exact bytes that will be emitted.
 
+    let format = options.format;
+    let file_breakdown = build_file_breakdown(selected_files, codemaps, options, counter);
+
+    let architecture = options
+        .include_architecture
+        .then(|| Architecture::from_codemaps(codemaps))
+        .filter(|architecture| !architecture.is_empty());
+    let (architecture_section, architecture_tokens) = if let Some(architecture) = architecture {
+        let mut body = architecture.to_string();
+        if format == OutputFormat::StrictXml {
+            body = format!("{}\n", xml_cdata(&body));
+        }
+        let section = wrap_section(format, "architecture", "Architecture", &body);
+        let tokens = counter.count(&section);
+        (section, tokens)
+    } else {
+        (String::new(), 0)
+    };
+
     let (tree_section, tree_tokens) = if options.include_tree {
         if let Some(tree) = tree {
             let selected: HashSet<&PathBuf> = selected_files.iter().map(|f| &f.path).collect();
             let has_codemap: HashSet<&PathBuf> = codemaps.iter().map(|c| &c.path).collect();
+            let metadata = format != OutputFormat::Compact;
 
             let render_opts = RenderOptions {
-                show_size: true,
-                show_lines: true,
-                show_language: true,
+                show_size: metadata,
+                show_lines: metadata,
+                show_language: metadata,
+                show_totals: metadata,
+                max_children: options.tree_max_children,
+                color: false,
+                prune: options.prune_tree,
+                max_depth: options.tree_max_depth,
                 selected,
                 has_codemap,
+                markers: options.tree_markers.clone(),
+                tokens: if options.tree_tokens {
+                    file_breakdown
+                        .iter()
+                        .map(|(path, info)| (path, info.tokens))
+                        .collect()
+                } else {
+                    HashMap::new()
+                },
             };
 
-            let rendered_tree = render_tree(tree, &render_opts);
-
-            let mut section = String::new();
-            section.push_str("<file_map>\n");
-            section.push_str(&rendered_tree);
-            if !selected_files.is_empty() || !codemaps.is_empty() {
-                section.push_str("\nLegend: * = selected, + = has codemap\n");
-            }
-            section.push_str("</file_map>\n\n");
+            let rendered_tree = render_tree_with_legend(tree, &render_opts);
+            let section = match format {
+                OutputFormat::Markdown => {
+                    wrap_section(format, "file_map", "File Map", &fenced(&rendered_tree, ""))
+                }
+                OutputFormat::StrictXml => wrap_section(
+                    format,
+                    "file_map",
+                    "File Map",
+                    &format!("{}\n", xml_cdata(&rendered_tree)),
+                ),
+                _ => wrap_section(format, "file_map", "File Map", &rendered_tree),
+            };
 
             let tokens = counter.count(&section);
             (section, tokens)
@@ -190,15 +761,19 @@ fn format_output_xml(
     };
 
     let (codemap_section, codemap_tokens) = if options.include_codemaps && !codemaps.is_empty() {
-        let mut section = String::new();
-        section.push_str("<codemaps>\n");
+        let separator = match format {
+            OutputFormat::Compact => "\n",
+            OutputFormat::StrictXml => "",
+            _ => "\n---\n\n",
+        };
+        let mut body = String::new();
         for (i, codemap) in codemaps.iter().enumerate() {
             if i > 0 {
-                section.push_str("\n---\n\n");
+                body.push_str(separator);
             }
-            section.push_str(&format_codemap_xml(codemap, options.public_only));
+            body.push_str(&format_codemap_block(codemap, options));
         }
-        section.push_str("</codemaps>\n\n");
+        let section = wrap_section(format, "codemaps", "Codemaps", &body);
 
         let tokens = counter.count(&section);
         (section, tokens)
@@ -208,22 +783,11 @@ fn format_output_xml(
 
     let (selected_section, selected_tokens) =
         if options.include_selected_files && !selected_files.is_empty() {
-            let mut section = String::new();
-            section.push_str("<selected_files>\n");
-            for file in selected_files {
-                section.push_str(&format!(
-                    "--- {} ({} lines, {} tokens) ---\n",
-                    file.path.display(),
-                    format_number(file.lines),
-                    format_number(file.tokens)
-                ));
-                section.push_str(&file.content);
-                if !file.content.ends_with('\n') {
-                    section.push('\n');
-                }
-                section.push('\n');
-            }
-            section.push_str("</selected_files>\n\n");
+            let body: String = selected_files
+                .iter()
+                .map(|file| format_selected_block(file, options))
+                .collect();
+            let section = wrap_section(format, "selected_files", "Selected Files", &body);
 
             let tokens = counter.count(&section);
             (section, tokens)
@@ -231,36 +795,227 @@ fn format_output_xml(
             (String::new(), 0)
         };
 
-    let file_breakdown =
-        build_file_breakdown(selected_files, codemaps, options.public_only, counter);
+    // The strict XML document element is part of the output too.
+    let envelope: &[&str] = if format == OutputFormat::StrictXml {
+        &[STRICT_XML_OPEN, STRICT_XML_CLOSE]
+    } else {
+        &[]
+    };
+    let envelope_tokens = envelope.iter().map(|s| counter.count(s)).sum();
+
+    let extra_totals = options
+        .extra_encodings
+        .iter()
+        .map(|&encoding| {
+            let counter = TokenCounter::new(encoding);
+            let sections = [
+                &architecture_section,
+                &tree_section,
+                &codemap_section,
+                &selected_section,
+            ];
+            EncodingTotal {
+                encoding,
+                total: sections.iter().map(|s| counter.count(s)).sum::<usize>()
+                    + envelope.iter().map(|s| counter.count(s)).sum::<usize>(),
+            }
+        })
+        .collect();
 
-    let summary_section = if options.include_summary {
-        build_summary_section_fixed_point(
+    let summary = TokenSummary {
+        extra_totals,
+        skipped: options.skipped.clone(),
+        ..calculate_summary(
+            architecture_tokens,
             tree_tokens,
             codemap_tokens,
             selected_tokens,
             file_breakdown,
-            counter,
+            envelope_tokens,
         )
+    };
+    let (summary_section, summary) = if options.include_summary {
+        build_summary_section_fixed_point(summary, format, counter)
     } else {
-        String::new()
+        (String::new(), summary)
     };
 
-    let mut output = String::with_capacity(
-        tree_section.len() + codemap_section.len() + selected_section.len() + summary_section.len(),
-    );
+    RenderedContext {
+        architecture_section,
+        tree_section,
+        codemap_section,
+        selected_section,
+        summary_section,
+        summary,
+        format,
+    }
+}
 
-    output.push_str(&tree_section);
-    output.push_str(&codemap_section);
-    output.push_str(&selected_section);
-    output.push_str(&summary_section);
-    output
+/// Render an API diff as an `api_changes` section, or as the JSON or YAML
+/// structure for structured formats.
+pub fn format_api_diff(diff: &ApiDiff, format: OutputFormat) -> Result<String, OutputError> {
+    let title = "API Changes";
+    Ok(match format {
+        OutputFormat::Json | OutputFormat::Yaml => {
+            let mut out = serialize(diff, format)?;
+            if !out.ends_with('\n') {
+                out.push('\n');
+            }
+            out
+        }
+        OutputFormat::StrictXml => {
+            let body = format!("{}\n", xml_cdata(&diff.to_string()));
+            format!(
+                "{}{}",
+                XML_DECLARATION,
+                wrap_section(format, "api_changes", title, &body)
+            )
+        }
+        _ => wrap_section(format, "api_changes", title, &diff.to_string()),
+    })
+}
+
+/// Wrap a section body in `<tag>` for XML, under a `# Title` heading for
+/// Markdown, or not at all for compact output.
+fn wrap_section(format: OutputFormat, tag: &str, title: &str, body: &str) -> String {
+    match format {
+        OutputFormat::Markdown => format!("# {}\n\n{}\n", title, body),
+        OutputFormat::Compact => format!("{}\n", body),
+        OutputFormat::StrictXml => format!("<{tag}>\n{body}</{tag}>\n"),
+        _ => format!("<{tag}>\n{body}</{tag}>\n\n"),
+    }
+}
+
+/// Put `content` in a Markdown code fence one backtick longer than any run
+/// of backticks inside it.
+fn fenced(content: &str, info: &str) -> String {
+    let longest = content.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat((longest + 1).max(3));
+    let newline = if content.ends_with('\n') { "" } else { "\n" };
+    format!("{fence}{info}\n{content}{newline}{fence}\n")
+}
+
+/// Prefix each line of `content` with its line number, counting from
+/// `first` and right aligned: `  9 | ...`, ` 10 | ...`.
+fn number_lines(lines: &[(usize, &str)]) -> String {
+    let width = lines.last().map_or(1, |(last, _)| last.to_string().len());
+    let mut numbered = String::new();
+    for (number, line) in lines {
+        numbered.push_str(&format!("{:>width$} | {}\n", number, line));
+    }
+    numbered
+}
+
+/// Info string for the code fence around a selected file: its language, or
+/// its extension if the language is unknown.
+fn fence_info(file: &SelectedFile) -> String {
+    let extension = file.path.extension().and_then(|e| e.to_str());
+    match (file.language, extension) {
+        // Notebooks are selected as their JSON, not the Python inside.
+        (_, Some("ipynb")) => "json".to_string(),
+        (Some(language), _) => language.to_string(),
+        (None, extension) => extension.unwrap_or_default().to_string(),
+    }
+}
+
+/// One selected file as it appears in the selected files section.
+fn format_selected_block(file: &SelectedFile, options: &OutputOptions) -> String {
+    let format = options.format;
+    let stripped = if options.strip_license_headers {
+        strip_license_header(&file.content)
+    } else {
+        None
+    };
+    let (header_lines, content) = stripped.unwrap_or((0, &file.content));
+    let rewritten;
+    let content = if options.line_numbers || options.minify != Minify::Off {
+        let lines = minify_lines(content, header_lines + 1, options.minify);
+        rewritten = if options.line_numbers {
+            number_lines(&lines)
+        } else {
+            let mut joined = String::with_capacity(content.len());
+            for (_, line) in &lines {
+                joined.push_str(line);
+                joined.push('\n');
+            }
+            joined
+        };
+        &rewritten
+    } else {
+        content
+    };
+    let mut note = String::new();
+    if header_lines > 0 {
+        note.push_str(", license header removed");
+    }
+    if options.minify != Minify::Off {
+        note.push_str(", minified");
+    }
+
+    match format {
+        OutputFormat::StrictXml => {
+            let mut attributes = String::new();
+            if header_lines > 0 {
+                attributes.push_str(&format!(" license_header_lines=\"{}\"", header_lines));
+            }
+            if options.minify != Minify::Off {
+                attributes.push_str(" minified=\"true\"");
+            }
+            format!(
+                "<file path=\"{}\" lines=\"{}\" tokens=\"{}\"{}>{}</file>\n",
+                xml_attribute(&file.path.display().to_string()),
+                file.lines,
+                file.tokens,
+                attributes,
+                xml_cdata(content)
+            )
+        }
+        OutputFormat::Markdown => format!(
+            "## {} ({} lines, {} tokens{})\n\n{}\n",
+            file.path.display(),
+            format_number(file.lines),
+            format_number(file.tokens),
+            note,
+            fenced(content, &fence_info(file))
+        ),
+        OutputFormat::Compact => {
+            let mut block = format!("--- {} ---\n", file.path.display());
+            block.push_str(content);
+            if !content.ends_with('\n') {
+                block.push('\n');
+            }
+            block.push('\n');
+            block
+        }
+        _ => format!(
+            "--- {} ({} lines, {} tokens{}) ---\n{}\n",
+            file.path.display(),
+            format_number(file.lines),
+            format_number(file.tokens),
+            note,
+            fenced(content, &fence_info(file))
+        ),
+    }
+}
+
+/// One codemap as it appears in the codemaps section of text output.
+pub(crate) fn format_codemap_block(codemap: &Codemap, options: &OutputOptions) -> String {
+    match options.format {
+        OutputFormat::Compact => format_codemap_compact(codemap, options),
+        OutputFormat::StrictXml => format!(
+            "<file path=\"{}\" language=\"{}\">{}</file>\n",
+            xml_attribute(&codemap.path.display().to_string()),
+            codemap.language,
+            xml_cdata(&format_codemap_xml(codemap, options))
+        ),
+        _ => format_codemap_xml(codemap, options),
+    }
 }
 
 fn build_file_breakdown(
     selected_files: &[SelectedFile],
     codemaps: &[Codemap],
-    public_only: bool,
+    options: &OutputOptions,
     counter: &TokenCounter,
 ) -> BTreeMap<PathBuf, FileTokenInfo> {
     let mut breakdown = BTreeMap::new();
@@ -270,29 +1025,34 @@ fn build_file_breakdown(
     // <selected_files>. For a codemap-only file, this includes the codemap's
     // contribution under <codemaps>.
 
+    // Minification savings compare against the block as it would be without.
+    let unminified = (options.minify != Minify::Off).then(|| OutputOptions {
+        minify: Minify::Off,
+        ..options.clone()
+    });
+
+    let codemap_of = |path: &PathBuf| codemaps.iter().find(|c| &c.path == path);
+    let declarations = |codemap: &Codemap| visible_declarations(codemap, options).count();
+
     // Selected file blocks
     for file in selected_files {
-        let mut block = String::new();
-        block.push_str(&format!(
-            "--- {} ({} lines, {} tokens) ---\n",
-            file.path.display(),
-            format_number(file.lines),
-            format_number(file.tokens)
-        ));
-        block.push_str(&file.content);
-        if !file.content.ends_with('\n') {
-            block.push('\n');
-        }
-        block.push('\n');
-
-        let tokens = counter.count(&block);
+        let codemap = codemap_of(&file.path);
+        let tokens = counter.count(&format_selected_block(file, options));
+        let minify_saved = unminified.as_ref().map_or(0, |unminified| {
+            counter
+                .count(&format_selected_block(file, unminified))
+                .saturating_sub(tokens)
+        });
 
         breakdown.insert(
             file.path.clone(),
             FileTokenInfo {
                 tokens,
                 selected: true,
-                has_codemap: codemaps.iter().any(|c| c.path == file.path),
+                has_codemap: codemap.is_some(),
+                minify_saved,
+                language: file.language.or(codemap.map(|c| c.language)),
+                declarations: codemap.map_or(0, declarations),
             },
         );
     }
@@ -304,7 +1064,7 @@ fn build_file_breakdown(
             continue;
         }
 
-        let section = format_codemap_xml(codemap, public_only);
+        let section = format_codemap_block(codemap, options);
         let tokens = counter.count(&section);
 
         breakdown.insert(
@@ -313,6 +1073,9 @@ fn build_file_breakdown(
                 tokens,
                 selected: false,
                 has_codemap: true,
+                minify_saved: 0,
+                language: Some(codemap.language),
+                declarations: declarations(codemap),
             },
         );
     }
@@ -320,63 +1083,101 @@ fn build_file_breakdown(
     breakdown
 }
 
+/// Render the summary section for `base`, the summary of everything else,
+/// adding the section's own tokens to the total.
 fn build_summary_section_fixed_point(
-    tree_tokens: usize,
-    codemap_tokens: usize,
-    selected_tokens: usize,
-    file_breakdown: BTreeMap<PathBuf, FileTokenInfo>,
+    base: TokenSummary,
+    format: OutputFormat,
     counter: &TokenCounter,
-) -> String {
+) -> (String, TokenSummary) {
+    let render = |summary: &TokenSummary| match format {
+        OutputFormat::Markdown => format!("# Token Summary\n\n{}", format_summary_xml(summary)),
+        OutputFormat::Compact => {
+            let mut output = match summary.minify_saved() {
+                0 => format!("Total: {} tokens\n", format_number(summary.total)),
+                saved => format!(
+                    "Total: {} tokens ({} saved by minification)\n",
+                    format_number(summary.total),
+                    format_number(saved)
+                ),
+            };
+            output.push_str(&format_extra_totals(summary));
+            for file in &summary.skipped {
+                output.push_str(&format!(
+                    "Skipped {}: {}\n",
+                    file.path.display(),
+                    file.reason
+                ));
+            }
+            output
+        }
+        OutputFormat::StrictXml => format_summary_strict_xml(summary),
+        _ => format!(
+            "<token_summary>\n{}</token_summary>\n",
+            format_summary_xml(summary)
+        ),
+    };
+
+    // The section counts toward the total in every reported encoding.
+    let counters: Vec<TokenCounter> = std::iter::once(counter.encoding())
+        .chain(base.extra_totals.iter().map(|t| t.encoding))
+        .map(TokenCounter::new)
+        .collect();
+    let with_section = |summary_tokens: &[usize]| TokenSummary {
+        total: base.total + summary_tokens[0],
+        extra_totals: base
+            .extra_totals
+            .iter()
+            .zip(&summary_tokens[1..])
+            .map(|(t, tokens)| EncodingTotal {
+                total: t.total + tokens,
+                ..*t
+            })
+            .collect(),
+        ..base.clone()
+    };
+
     // Fixed-point iteration: the summary includes numbers that affect tokenization.
-    let mut summary_tokens = 0usize;
+    let mut summary_tokens = vec![0usize; counters.len()];
 
     for _ in 0..10 {
-        let summary = calculate_summary(
-            tree_tokens,
-            codemap_tokens,
-            selected_tokens,
-            file_breakdown.clone(),
-            summary_tokens,
-        );
+        let summary = with_section(&summary_tokens);
 
-        let mut section = String::new();
-        section.push_str("<token_summary>\n");
-        section.push_str(&format_summary_xml(&summary));
-        section.push_str("</token_summary>\n");
+        let section = render(&summary);
 
-        let next_summary_tokens = counter.count(&section);
+        let next_summary_tokens: Vec<usize> = counters.iter().map(|c| c.count(&section)).collect();
         if next_summary_tokens == summary_tokens {
-            return section;
+            return (section, summary);
         }
 
         summary_tokens = next_summary_tokens;
     }
 
     // If not converged, return last attempt.
-    let summary = calculate_summary(
-        tree_tokens,
-        codemap_tokens,
-        selected_tokens,
-        file_breakdown,
-        summary_tokens,
-    );
-
-    let mut section = String::new();
-    section.push_str("<token_summary>\n");
-    section.push_str(&format_summary_xml(&summary));
-    section.push_str("</token_summary>\n");
-    section
+    let summary = with_section(&summary_tokens);
+    (render(&summary), summary)
 }
 
-fn format_codemap_xml(codemap: &Codemap, public_only: bool) -> String {
+pub(crate) fn format_codemap_xml(codemap: &Codemap, options: &OutputOptions) -> String {
     let mut output = String::with_capacity(2048);
 
     // File header
-    output.push_str(&format!("## {}\n\n", codemap.path.display()));
+    if codemap.is_type_definition {
+        output.push_str(&format!(
+            "## {} (type definitions)\n\n",
+            codemap.path.display()
+        ));
+    } else {
+        output.push_str(&format!("## {}\n\n", codemap.path.display()));
+    }
 
     // Parse error warning
-    if let Some(ref error) = codemap.parse_error {
-        output.push_str(&format!("**Parse error:** {}\n\n", error));
+    if !codemap.parse_errors.is_empty() {
+        output.push_str("**Parse errors** (declarations there may be missing):\n");
+        for error in &codemap.parse_errors {
+            output.push_str(&format!("- {}\n", error));
+        }
+        output.push('\n');
     }
 
     // Imports section
@@ -397,152 +1198,215 @@ fn format_codemap_xml(codemap: &Codemap, public_only: bool) -> String {
     }
 
     // Declarations section
-    let decls: Vec<_> = if public_only {
-        codemap
-            .declarations
-            .iter()
-            .filter(|d| d.is_public())
-            .collect()
-    } else {
-        codemap.declarations.iter().collect()
-    };
+    let decls = sorted_declarations(codemap, options);
 
     if !decls.is_empty() {
         output.push_str("### Declarations\n\n");
         for decl in decls {
-            output.push_str(&format_declaration_xml(decl, public_only, 0));
+            output.push_str(&format_declaration_xml(
+                decl,
+                codemap.language,
+                options.visibility,
+                0,
+            ));
         }
     }
 
-    output
-}
-
-fn format_declaration_xml(decl: &Declaration, public_only: bool, indent: usize) -> String {
-    let prefix = "    ".repeat(indent);
-    let mut output = String::new();
-
+    // TODO comments section
+    if !codemap.todos.is_empty() {
+        output.push_str("### TODOs\n");
+        for todo in &codemap.todos {
+            output.push_str(&format!(
+                "- {} {}: {}\n",
+                format_location(&Location::single_line(todo.line).in_cell(todo.cell)),
+                todo.kind,
+                todo.text
+            ));
+        }
+        output.push('\n');
+    }
+
+    // Tests section
+    if !codemap.tests.is_empty() {
+        output.push_str("### Tests\n");
+        for test in &codemap.tests {
+            output.push_str(&format!("- {}\n", test));
+        }
+        output.push('\n');
+    }
+
+    output
+}
+
+fn format_declaration_xml(
+    decl: &Declaration,
+    language: Language,
+    threshold: VisibilityThreshold,
+    indent: usize,
+) -> String {
+    let prefix = "    ".repeat(indent);
+    let mut output = String::new();
+
     match decl {
         Declaration::Function {
+            scope,
             signature,
             location,
+            overloads,
+            component,
+            attributes,
             doc,
+            snippet,
             ..
         } => {
+            // Signatures only carry the bare name; surface the enclosing
+            // module (Rust `a::b`) or namespace (TypeScript `A.B`).
+            let scope = scope
+                .as_deref()
+                .map(|scope| format!("in {}, ", scope))
+                .unwrap_or_default();
+            // Attributes and decorators lead the signature, as in source.
+            let mut decorators = attributes.join(" ");
+            if !decorators.is_empty() {
+                decorators.push(' ');
+            }
             output.push_str(&format!(
-                "{}#### {} ({})\n",
+                "{}#### {}{} ({}{})\n",
                 prefix,
+                decorators,
                 signature,
+                scope,
                 format_location(location)
             ));
             if let Some(doc) = doc {
                 output.push_str(&format!("{}{}\n", prefix, doc));
             }
+            if !overloads.is_empty() {
+                output.push_str(&format!("{}Overloads:\n", prefix));
+                for overload in overloads {
+                    output.push_str(&format!("{}- {}\n", prefix, overload));
+                }
+            }
+            push_component(&mut output, &prefix, component.as_ref());
+            push_snippet(&mut output, &prefix, snippet.as_deref(), language);
             output.push('\n');
         }
 
         Declaration::Struct {
             name,
+            generics,
+            kind,
             fields,
             methods,
+            implements,
             location,
+            attributes,
             doc,
+            snippet,
             ..
         } => {
+            let keyword = if *kind == StructKind::Union {
+                "union"
+            } else {
+                "struct"
+            };
+            let terminator = if *kind == StructKind::Unit { ";" } else { "" };
             output.push_str(&format!(
-                "{}#### struct {} ({})\n",
+                "{}#### {} {}{}{} ({})\n",
                 prefix,
+                keyword,
                 name,
+                generics,
+                terminator,
                 format_location(location)
             ));
+            for attr in attributes {
+                output.push_str(&format!("{}{}\n", prefix, attr));
+            }
             if let Some(doc) = doc {
                 output.push_str(&format!("{}{}\n", prefix, doc));
             }
-
-            // Fields
-            let visible_fields: Vec<_> = if public_only {
-                fields
-                    .iter()
-                    .filter(|f| f.visibility == Visibility::Public)
-                    .collect()
-            } else {
-                fields.iter().collect()
-            };
-
-            if !visible_fields.is_empty() {
-                output.push_str(&format!("{}Fields:\n", prefix));
-                for field in visible_fields {
-                    let vis = if field.visibility == Visibility::Public {
-                        "pub "
-                    } else {
-                        ""
-                    };
-                    output.push_str(&format!(
-                        "{}- {}{}: {}\n",
-                        prefix, vis, field.name, field.ty
-                    ));
-                }
+            if !implements.is_empty() {
+                output.push_str(&format!(
+                    "{}Implements: {}\n",
+                    prefix,
+                    implements.join(", ")
+                ));
             }
 
-            // Methods
-            let visible_methods: Vec<_> = if public_only {
-                methods.iter().filter(|m| m.is_public()).collect()
-            } else {
-                methods.iter().collect()
-            };
-
-            if !visible_methods.is_empty() {
-                output.push_str(&format!("{}Methods:\n", prefix));
-                for method in visible_methods {
-                    if let Declaration::Function {
-                        signature,
-                        location,
-                        ..
-                    } = method
-                    {
-                        output.push_str(&format!(
-                            "{}- {} ({})\n",
-                            prefix,
-                            signature,
-                            format_location(location)
-                        ));
-                    }
-                }
-            }
+            push_fields(&mut output, &prefix, fields, threshold, true);
+            push_snippet(&mut output, &prefix, snippet.as_deref(), language);
+            push_methods(&mut output, &prefix, methods, language, threshold);
             output.push('\n');
         }
 
         Declaration::Enum {
             name,
+            generics,
             variants,
+            methods,
+            implements,
             location,
+            attributes,
             doc,
+            snippet,
             ..
         } => {
             output.push_str(&format!(
-                "{}#### enum {} ({})\n",
+                "{}#### enum {}{} ({})\n",
                 prefix,
                 name,
+                generics,
                 format_location(location)
             ));
+            for attr in attributes {
+                output.push_str(&format!("{}{}\n", prefix, attr));
+            }
             if let Some(doc) = doc {
                 output.push_str(&format!("{}{}\n", prefix, doc));
             }
-            output.push_str(&format!("{}Variants: {}\n\n", prefix, variants.join(", ")));
+            if !implements.is_empty() {
+                output.push_str(&format!(
+                    "{}Implements: {}\n",
+                    prefix,
+                    implements.join(", ")
+                ));
+            }
+            if variants.iter().any(|v| v.doc.is_some()) {
+                output.push_str(&format!("{}Variants:\n", prefix));
+                for variant in variants {
+                    output.push_str(&format!("{}- {}\n", prefix, variant.name));
+                    push_member_doc(&mut output, &prefix, variant.doc.as_deref());
+                }
+            } else {
+                let names: Vec<&str> = variants.iter().map(|v| v.name.as_str()).collect();
+                output.push_str(&format!("{}Variants: {}\n", prefix, names.join(", ")));
+            }
+            push_snippet(&mut output, &prefix, snippet.as_deref(), language);
+            push_methods(&mut output, &prefix, methods, language, threshold);
+            output.push('\n');
         }
 
         Declaration::Trait {
             name,
+            generics,
             methods,
             location,
+            attributes,
             doc,
+            snippet,
             ..
         } => {
             output.push_str(&format!(
-                "{}#### trait {} ({})\n",
+                "{}#### trait {}{} ({})\n",
                 prefix,
                 name,
+                generics,
                 format_location(location)
             ));
+            for attr in attributes {
+                output.push_str(&format!("{}{}\n", prefix, attr));
+            }
             if let Some(doc) = doc {
                 output.push_str(&format!("{}{}\n", prefix, doc));
             }
@@ -552,34 +1416,54 @@ fn format_declaration_xml(decl: &Declaration, public_only: bool, indent: usize)
                     output.push_str(&format!("{}- {}\n", prefix, method));
                 }
             }
+            push_snippet(&mut output, &prefix, snippet.as_deref(), language);
             output.push('\n');
         }
 
         Declaration::TypeAlias {
             name,
+            generics,
             target,
             location,
+            snippet,
             ..
         } => {
             output.push_str(&format!(
-                "{}#### type {} = {} ({})\n\n",
+                "{}#### type {}{} = {} ({})\n",
                 prefix,
                 name,
+                generics,
                 target,
                 format_location(location)
             ));
+            push_snippet(&mut output, &prefix, snippet.as_deref(), language);
+            output.push('\n');
         }
 
         Declaration::Const {
-            name, ty, location, ..
+            name,
+            ty,
+            location,
+            is_static,
+            snippet,
+            ..
         } => {
+            // Untyped constants (e.g. Python `FOO = make()`) omit the annotation.
+            let annotation = if ty.is_empty() {
+                String::new()
+            } else {
+                format!(": {}", ty)
+            };
             output.push_str(&format!(
-                "{}#### const {}: {} ({})\n\n",
+                "{}#### {} {}{} ({})\n",
                 prefix,
+                if *is_static { "static" } else { "const" },
                 name,
-                ty,
+                annotation,
                 format_location(location)
             ));
+            push_snippet(&mut output, &prefix, snippet.as_deref(), language);
+            output.push('\n');
         }
 
         Declaration::Interface {
@@ -587,6 +1471,7 @@ fn format_declaration_xml(decl: &Declaration, public_only: bool, indent: usize)
             members,
             location,
             doc,
+            snippet,
             ..
         } => {
             output.push_str(&format!(
@@ -604,56 +1489,483 @@ fn format_declaration_xml(decl: &Declaration, public_only: bool, indent: usize)
                     output.push_str(&format!("{}- {}\n", prefix, member));
                 }
             }
+            push_snippet(&mut output, &prefix, snippet.as_deref(), language);
             output.push('\n');
         }
 
         Declaration::Class {
             name,
+            generics,
+            extends,
+            implements,
+            is_abstract,
+            fields,
             members,
+            component,
             location,
             doc,
+            snippet,
             ..
         } => {
+            let mut heritage = String::new();
+            if !extends.is_empty() {
+                heritage.push_str(&format!(" extends {}", extends.join(", ")));
+            }
+            if !implements.is_empty() {
+                heritage.push_str(&format!(" implements {}", implements.join(", ")));
+            }
             output.push_str(&format!(
-                "{}#### class {} ({})\n",
+                "{}#### {}class {}{}{} ({})\n",
                 prefix,
+                if *is_abstract { "abstract " } else { "" },
                 name,
+                generics,
+                heritage,
                 format_location(location)
             ));
             if let Some(doc) = doc {
                 output.push_str(&format!("{}{}\n", prefix, doc));
             }
+            push_component(&mut output, &prefix, component.as_ref());
+            push_fields(&mut output, &prefix, fields, threshold, false);
+            push_snippet(&mut output, &prefix, snippet.as_deref(), language);
 
-            let visible_members: Vec<_> = if public_only {
-                members.iter().filter(|m| m.is_public()).collect()
-            } else {
-                members.iter().collect()
-            };
+            let visible_members: Vec<_> = members
+                .iter()
+                .filter(|m| threshold.allows(m.visibility()))
+                .collect();
 
+            // Members end with a blank line; without them, end the class here.
+            if visible_members.is_empty() {
+                output.push('\n');
+            }
             for member in visible_members {
-                output.push_str(&format_declaration_xml(member, public_only, indent + 1));
+                output.push_str(&format_declaration_xml(
+                    member,
+                    language,
+                    threshold,
+                    indent + 1,
+                ));
+            }
+        }
+
+        Declaration::ReExport {
+            name,
+            items,
+            visibility,
+            location,
+            snippet,
+        } => {
+            output.push_str(&format!(
+                "{}#### {} ({})\n",
+                prefix,
+                reexport_statement(name, items, *visibility, language),
+                format_location(location)
+            ));
+            push_snippet(&mut output, &prefix, snippet.as_deref(), language);
+            output.push('\n');
+        }
+    }
+
+    output
+}
+
+/// Source form of a re-export: `export { a } from "./x"` or `pub use a::b`.
+fn reexport_statement(
+    name: &str,
+    items: &[String],
+    visibility: Visibility,
+    language: Language,
+) -> String {
+    match language {
+        Language::TypeScript | Language::Tsx | Language::JavaScript | Language::Jsx => {
+            // All items type-only: `export type { a, b }`.
+            let type_only = items.iter().all(|item| item.starts_with("type "));
+            let (keyword, items): (_, Vec<&str>) = if type_only && !items.is_empty() {
+                (
+                    "export type",
+                    items
+                        .iter()
+                        .filter_map(|item| item.strip_prefix("type "))
+                        .collect(),
+                )
+            } else {
+                ("export", items.iter().map(String::as_str).collect())
+            };
+            match items.as_slice() {
+                [item] if item.starts_with('*') => {
+                    format!("{} {} from \"{}\"", keyword, item, name)
+                }
+                items => format!("{} {{ {} }} from \"{}\"", keyword, items.join(", "), name),
             }
         }
+        _ => {
+            let path = match items {
+                [] => name.to_string(),
+                [item] => format!("{}::{}", name, item),
+                items => format!("{}::{{{}}}", name, items.join(", ")),
+            };
+            format!("{} use {}", visibility, path)
+        }
     }
+}
 
+/// Compact codemap: the path, then one line per declaration with members
+/// indented beneath. No imports, docs, fields or locations.
+fn format_codemap_compact(codemap: &Codemap, options: &OutputOptions) -> String {
+    let mut output = format!("{}\n", codemap.path.display());
+    for error in &codemap.parse_errors {
+        output.push_str(&format!("  parse error: {}\n", error));
+    }
+    for decl in sorted_declarations(codemap, options) {
+        push_declaration_compact(&mut output, decl, codemap.language, options.visibility, 1);
+    }
+    for todo in &codemap.todos {
+        output.push_str(&format!("  {}: {}\n", todo.kind, todo.text));
+    }
+    for test in &codemap.tests {
+        output.push_str(&format!("  test {}\n", test));
+    }
     output
 }
 
+fn push_declaration_compact(
+    output: &mut String,
+    decl: &Declaration,
+    language: Language,
+    threshold: VisibilityThreshold,
+    indent: usize,
+) {
+    let prefix = "  ".repeat(indent);
+    output.push_str(&format!("{}{}\n", prefix, declaration_line(decl, language)));
+
+    let nested = format!("{}  ", prefix);
+    match decl {
+        Declaration::Struct { methods, .. } | Declaration::Enum { methods, .. } => {
+            for method in methods.iter().filter(|m| threshold.allows(m.visibility())) {
+                push_declaration_compact(output, method, language, threshold, indent + 1);
+            }
+        }
+        Declaration::Class { members, .. } => {
+            for member in members.iter().filter(|m| threshold.allows(m.visibility())) {
+                push_declaration_compact(output, member, language, threshold, indent + 1);
+            }
+        }
+        Declaration::Trait { methods, .. } => {
+            for method in methods {
+                output.push_str(&format!("{}{}\n", nested, method));
+            }
+        }
+        Declaration::Interface { members, .. } => {
+            for member in members {
+                output.push_str(&format!("{}{}\n", nested, member));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// One-line signature of a declaration, as in compact output: a function's
+/// signature, `struct Name<T>`, `enum Name { A, B }`, `type A = B`, ...
+pub(crate) fn declaration_line(decl: &Declaration, language: Language) -> String {
+    match decl {
+        Declaration::Function { signature, .. } => signature.clone(),
+        Declaration::Struct {
+            name,
+            generics,
+            kind,
+            ..
+        } => {
+            let keyword = if *kind == StructKind::Union {
+                "union"
+            } else {
+                "struct"
+            };
+            format!("{} {}{}", keyword, name, generics)
+        }
+        Declaration::Enum {
+            name,
+            generics,
+            variants,
+            ..
+        } => {
+            let names: Vec<&str> = variants.iter().map(|v| v.name.as_str()).collect();
+            format!("enum {}{} {{ {} }}", name, generics, names.join(", "))
+        }
+        Declaration::Trait { name, generics, .. } => format!("trait {}{}", name, generics),
+        Declaration::TypeAlias {
+            name,
+            generics,
+            target,
+            ..
+        } => format!("type {}{} = {}", name, generics, target),
+        Declaration::Const {
+            name,
+            ty,
+            is_static,
+            ..
+        } => {
+            let keyword = if *is_static { "static" } else { "const" };
+            if ty.is_empty() {
+                format!("{} {}", keyword, name)
+            } else {
+                format!("{} {}: {}", keyword, name, ty)
+            }
+        }
+        Declaration::Interface { name, .. } => format!("interface {}", name),
+        Declaration::Class {
+            name,
+            generics,
+            extends,
+            is_abstract,
+            ..
+        } => {
+            let mut line = format!(
+                "{}class {}{}",
+                if *is_abstract { "abstract " } else { "" },
+                name,
+                generics
+            );
+            if !extends.is_empty() {
+                line.push_str(&format!(" extends {}", extends.join(", ")));
+            }
+            line
+        }
+        Declaration::ReExport {
+            name,
+            items,
+            visibility,
+            ..
+        } => reexport_statement(name, items, *visibility, language),
+    }
+}
+
+/// Render a `Methods:` list of a struct's or enum's visible methods.
+fn push_methods(
+    output: &mut String,
+    prefix: &str,
+    methods: &[Declaration],
+    language: Language,
+    threshold: VisibilityThreshold,
+) {
+    let visible_methods: Vec<_> = methods
+        .iter()
+        .filter(|m| threshold.allows(m.visibility()))
+        .collect();
+
+    if visible_methods.is_empty() {
+        return;
+    }
+
+    output.push_str(&format!("{}Methods:\n", prefix));
+    for method in visible_methods {
+        if let Declaration::Function {
+            signature,
+            location,
+            ..
+        } = method
+        {
+            output.push_str(&format!(
+                "{}- {} ({})\n",
+                prefix,
+                signature,
+                format_location(location)
+            ));
+            let nested = format!("{}  ", prefix);
+            push_snippet(output, &nested, method.snippet(), language);
+        }
+    }
+}
+
+/// Render a `Fields:` list. `mark_public` prefixes public and crate-visible
+/// fields with `pub ` / `pub(crate) ` for languages where visibility is
+/// declared rather than conventional.
+fn push_fields(
+    output: &mut String,
+    prefix: &str,
+    fields: &[Field],
+    threshold: VisibilityThreshold,
+    mark_public: bool,
+) {
+    let visible_fields: Vec<_> = fields
+        .iter()
+        .filter(|f| threshold.allows(f.visibility))
+        .collect();
+
+    if visible_fields.is_empty() {
+        return;
+    }
+
+    output.push_str(&format!("{}Fields:\n", prefix));
+    for field in visible_fields {
+        let vis = match field.visibility {
+            Visibility::Public if mark_public => "pub ",
+            Visibility::Crate if mark_public => "pub(crate) ",
+            _ => "",
+        };
+        let mut modifiers = field.modifiers.join(" ");
+        if !modifiers.is_empty() {
+            modifiers.push(' ');
+        }
+        let mut ty = if field.ty.is_empty() {
+            String::new()
+        } else {
+            format!(": {}", field.ty)
+        };
+        if let Some(tag) = &field.tag {
+            ty.push(' ');
+            ty.push_str(tag);
+        }
+        output.push_str(&format!(
+            "{}- {}{}{}{}\n",
+            prefix, vis, modifiers, field.name, ty
+        ));
+        push_member_doc(output, prefix, field.doc.as_deref());
+    }
+}
+
+/// Mark a React component and list its props.
+fn push_component(output: &mut String, prefix: &str, component: Option<&Component>) {
+    let Some(component) = component else {
+        return;
+    };
+    match &component.props {
+        Some(props) => output.push_str(&format!("{}Component props: {}\n", prefix, props)),
+        None => output.push_str(&format!("{}Component\n", prefix)),
+    }
+    for member in &component.props_members {
+        output.push_str(&format!("{}- {}\n", prefix, member));
+    }
+}
+
+/// Render a declaration's source excerpt as a fenced code block.
+fn push_snippet(output: &mut String, prefix: &str, snippet: Option<&str>, language: Language) {
+    let Some(snippet) = snippet else {
+        return;
+    };
+    output.push_str(&format!("{}```{}\n", prefix, language));
+    for line in snippet.lines() {
+        let line = format!("{}{}", prefix, line);
+        output.push_str(line.trim_end());
+        output.push('\n');
+    }
+    output.push_str(&format!("{}```\n", prefix));
+}
+
+/// Render a field or variant doc comment indented under its list entry.
+fn push_member_doc(output: &mut String, prefix: &str, doc: Option<&str>) {
+    for line in doc.into_iter().flat_map(str::lines) {
+        output.push_str(&format!("{}  {}\n", prefix, line));
+    }
+}
+
 fn format_location(loc: &Location) -> String {
-    if loc.start_line == loc.end_line {
+    let lines = if loc.start_line == loc.end_line {
         format!("line {}", loc.start_line)
     } else {
         format!("lines {}-{}", loc.start_line, loc.end_line)
+    };
+    match loc.cell {
+        Some(cell) => format!("cell {}, {}", cell, lines),
+        None => lines,
+    }
+}
+
+/// The token summary as an element, with counts in attributes.
+fn format_summary_strict_xml(summary: &TokenSummary) -> String {
+    let mut extra = String::new();
+    if summary.architecture_tokens > 0 {
+        extra.push_str(&format!(
+            " architecture=\"{}\"",
+            summary.architecture_tokens
+        ));
+    }
+    let saved = summary.minify_saved();
+    if saved > 0 {
+        extra.push_str(&format!(" minify_saved=\"{}\"", saved));
+    }
+    let mut output = format!(
+        "<token_summary total=\"{}\" tree=\"{}\" codemaps=\"{}\" selected=\"{}\"{}>\n",
+        summary.total, summary.tree_tokens, summary.codemap_tokens, summary.selected_tokens, extra
+    );
+    for total in &summary.extra_totals {
+        output.push_str(&format!(
+            "<encoding name=\"{}\" total=\"{}\"/>\n",
+            total.encoding, total.total
+        ));
+    }
+    for (path, info) in &summary.file_breakdown {
+        output.push_str(&format!(
+            "<file path=\"{}\" tokens=\"{}\" selected=\"{}\" codemap=\"{}\"/>\n",
+            xml_attribute(&path.display().to_string()),
+            info.tokens,
+            info.selected,
+            info.has_codemap
+        ));
+    }
+    for language in summary.languages() {
+        output.push_str(&format!(
+            "<language name=\"{}\" files=\"{}\" tokens=\"{}\" declarations=\"{}\"/>\n",
+            language, language.files, language.tokens, language.declarations
+        ));
+    }
+    for file in summary.largest_files(SUMMARY_TOP_FILES) {
+        output.push_str(&format!(
+            "<largest_file path=\"{}\" tokens=\"{}\" percent=\"{:.1}\"/>\n",
+            xml_attribute(&file.path.display().to_string()),
+            file.tokens,
+            file.percent
+        ));
+    }
+    for file in &summary.skipped {
+        output.push_str(&format!(
+            "<skipped path=\"{}\" reason=\"{}\"/>\n",
+            xml_attribute(&file.path.display().to_string()),
+            xml_attribute(&file.reason)
+        ));
+    }
+    output.push_str("</token_summary>\n");
+    output
+}
+
+/// "Total (o200k_base): N tokens" lines for the extra encodings.
+fn format_extra_totals(summary: &TokenSummary) -> String {
+    let mut output = String::new();
+    for t in &summary.extra_totals {
+        output.push_str(&format!(
+            "Total ({}): {} tokens\n",
+            t.encoding,
+            format_number(t.total)
+        ));
     }
+    output
 }
 
 fn format_summary_xml(summary: &TokenSummary) -> String {
     let mut output = String::new();
 
     output.push_str(&format!("Total: {} tokens\n", format_number(summary.total)));
+    output.push_str(&format_extra_totals(summary));
+    let saved = summary.minify_saved();
+    if saved > 0 {
+        output.push_str(&format!(
+            "Saved by minification: {} tokens\n",
+            format_number(saved)
+        ));
+    }
 
-    if summary.tree_tokens > 0 || summary.codemap_tokens > 0 || summary.selected_tokens > 0 {
+    if summary.architecture_tokens > 0
+        || summary.tree_tokens > 0
+        || summary.codemap_tokens > 0
+        || summary.selected_tokens > 0
+    {
         output.push_str("\nComponent breakdown:\n");
+        if summary.architecture_tokens > 0 {
+            output.push_str(&format!(
+                "- Architecture: {} tokens\n",
+                format_number(summary.architecture_tokens)
+            ));
+        }
         if summary.tree_tokens > 0 {
             output.push_str(&format!(
                 "- File tree: {} tokens\n",
@@ -674,6 +1986,47 @@ fn format_summary_xml(summary: &TokenSummary) -> String {
         }
     }
 
+    let largest = summary.largest_files(SUMMARY_TOP_FILES);
+    if !largest.is_empty() {
+        output.push_str("\nLargest files:\n");
+        for file in &largest {
+            output.push_str(&format!(
+                "- {}: {} tokens ({:.1}%)\n",
+                file.path.display(),
+                format_number(file.tokens),
+                file.percent
+            ));
+        }
+    }
+
+    // One language would just repeat the total.
+    let languages = summary.languages();
+    if languages.len() > 1 {
+        output.push_str("\nLanguages:\n");
+        for language in &languages {
+            output.push_str(&format!(
+                "- {}: {} {}, {} tokens, {} {}\n",
+                language,
+                format_number(language.files),
+                if language.files == 1 { "file" } else { "files" },
+                format_number(language.tokens),
+                format_number(language.declarations),
+                if language.declarations == 1 {
+                    "declaration"
+                } else {
+                    "declarations"
+                }
+            ));
+        }
+    }
+
+    if !summary.skipped.is_empty() {
+        output.push_str("\nSkipped files:\n");
+        for file in &summary.skipped {
+            output.push_str(&format!("- {}: {}\n", file.path.display(), file.reason));
+        }
+    }
+
     if !summary.file_breakdown.is_empty() {
         output.push_str("\nPer-file breakdown:\n");
         for (path, info) in &summary.file_breakdown {
@@ -701,6 +2054,8 @@ fn format_summary_xml(summary: &TokenSummary) -> String {
 
 #[derive(Serialize, Clone)]
 struct JsonOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    architecture: Option<Architecture>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tree: Option<JsonTree>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
@@ -719,6 +2074,8 @@ struct JsonTree {
     #[serde(skip_serializing_if = "Option::is_none")]
     extension: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    file_count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     size: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     lines: Option<usize>,
@@ -738,8 +2095,15 @@ struct JsonCodemap {
     language: String,
     imports: Vec<JsonImport>,
     declarations: Vec<JsonDeclaration>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    parse_error: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    parse_errors: Vec<JsonParseIssue>,
+    fingerprint: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    is_type_definition: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    todos: Vec<JsonTodo>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tests: Vec<String>,
 }
 
 #[derive(Serialize, Clone)]
@@ -748,10 +2112,31 @@ struct JsonImport {
     items: Vec<String>,
 }
 
+#[derive(Serialize, Clone)]
+struct JsonParseIssue {
+    line: usize,
+    end_line: usize,
+    column: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cell: Option<usize>,
+    message: String,
+}
+
+#[derive(Serialize, Clone)]
+struct JsonTodo {
+    kind: String,
+    line: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cell: Option<usize>,
+    text: String,
+}
+
 #[derive(Serialize, Clone)]
 struct JsonDeclaration {
+    id: String,
     kind: String,
     name: String,
+    fingerprint: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     signature: Option<String>,
     visibility: String,
@@ -765,27 +2150,86 @@ struct JsonDeclaration {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     methods: Vec<JsonDeclaration>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
-    variants: Vec<String>,
+    variants: Vec<JsonVariant>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     members: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     target: Option<String>,
     #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
     ty: Option<String>,
-}
-
-#[derive(Serialize, Clone)]
-struct JsonField {
-    name: String,
-    #[serde(rename = "type")]
-    ty: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    generics: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    overloads: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    extends: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    implements: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    is_abstract: Option<bool>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    attributes: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    struct_kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    component: Option<JsonComponent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metrics: Option<JsonMetrics>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snippet: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+struct JsonMetrics {
+    lines: usize,
+    complexity: usize,
+}
+
+#[derive(Serialize, Clone)]
+struct JsonComponent {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    props: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    props_members: Vec<String>,
+}
+
+#[derive(Serialize, Clone)]
+struct JsonVariant {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    doc: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+struct JsonField {
+    name: String,
+    #[serde(rename = "type")]
+    ty: String,
     visibility: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    modifiers: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    doc: Option<String>,
 }
 
 #[derive(Serialize, Clone)]
 struct JsonLocation {
     start_line: usize,
     end_line: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cell: Option<usize>,
+}
+
+impl From<Location> for JsonLocation {
+    fn from(location: Location) -> Self {
+        Self {
+            start_line: location.start_line,
+            end_line: location.end_line,
+            cell: location.cell,
+        }
+    }
 }
 
 #[derive(Serialize, Clone)]
@@ -803,9 +2247,17 @@ struct JsonSummary {
     codemap_tokens: usize,
     selected_tokens: usize,
     file_breakdown: BTreeMap<String, FileTokenInfo>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    largest_files: Vec<Contributor>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    languages: Vec<LanguageTokens>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    extra_totals: Vec<EncodingTotal>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    skipped: Vec<SkippedFile>,
 }
 
-fn format_output_json(
+fn format_output_data(
     tree: Option<&FileNode>,
     codemaps: &[Codemap],
     selected_files: &[SelectedFile],
@@ -815,6 +2267,11 @@ fn format_output_json(
     let selected_set: HashSet<&PathBuf> = selected_files.iter().map(|f| &f.path).collect();
     let codemap_set: HashSet<&PathBuf> = codemaps.iter().map(|c| &c.path).collect();
 
+    let json_architecture = options
+        .include_architecture
+        .then(|| Architecture::from_codemaps(codemaps))
+        .filter(|architecture| !architecture.is_empty());
+
     let json_tree = if options.include_tree {
         tree.map(|t| file_node_to_json(t, &selected_set, &codemap_set))
     } else {
@@ -824,7 +2281,7 @@ fn format_output_json(
     let json_codemaps: Vec<JsonCodemap> = if options.include_codemaps {
         codemaps
             .iter()
-            .map(|c| codemap_to_json(c, options.public_only))
+            .map(|c| codemap_to_json(c, options))
             .collect()
     } else {
         Vec::new()
@@ -847,17 +2304,18 @@ fn format_output_json(
     let json_summary = if options.include_summary {
         let output_without_summary = {
             let tmp = JsonOutput {
+                architecture: json_architecture.clone(),
                 tree: json_tree.clone(),
                 codemaps: json_codemaps.clone(),
                 selected_files: json_selected.clone(),
                 summary: None,
             };
-            serde_json::to_string_pretty(&tmp).unwrap_or_default()
+            serialize(&tmp, options.format).unwrap_or_default()
         };
 
         let tree_tokens = if options.include_tree {
             json_tree.as_ref().map_or(0, |t| {
-                let s = serde_json::to_string_pretty(t).unwrap_or_default();
+                let s = serialize(t, options.format).unwrap_or_default();
                 counter.count(&s)
             })
         } else {
@@ -865,22 +2323,70 @@ fn format_output_json(
         };
 
         let codemap_tokens = if options.include_codemaps {
-            let s = serde_json::to_string_pretty(&json_codemaps).unwrap_or_default();
+            let s = serialize(&json_codemaps, options.format).unwrap_or_default();
             counter.count(&s)
         } else {
             0
         };
 
         let selected_tokens = if options.include_selected_files {
-            let s = serde_json::to_string_pretty(&json_selected).unwrap_or_default();
+            let s = serialize(&json_selected, options.format).unwrap_or_default();
             counter.count(&s)
         } else {
             0
         };
 
+        // Each file's codemap and selected content, as serialized.
+        let mut file_tokens: BTreeMap<PathBuf, FileTokenInfo> = BTreeMap::new();
+        if options.include_codemaps {
+            for (codemap, json) in codemaps.iter().zip(&json_codemaps) {
+                let s = serialize(json, options.format).unwrap_or_default();
+                let info = file_tokens.entry(codemap.path.clone()).or_default();
+                info.language = Some(codemap.language);
+                info.tokens += counter.count(&s);
+                info.has_codemap = true;
+                info.declarations = json.declarations.len();
+            }
+        }
+        if options.include_selected_files {
+            for (file, json) in selected_files.iter().zip(&json_selected) {
+                let s = serialize(json, options.format).unwrap_or_default();
+                let info = file_tokens.entry(file.path.clone()).or_default();
+                info.language = info.language.or(file.language);
+                info.tokens += counter.count(&s);
+                info.selected = true;
+            }
+        }
+        let languages = language_totals(file_tokens.values());
+        let largest_files = |total| {
+            largest_contributors(
+                file_tokens
+                    .iter()
+                    .map(|(path, info)| (path.clone(), info.tokens)),
+                total,
+                SUMMARY_TOP_FILES,
+            )
+        };
+
+        let extra_counters: Vec<TokenCounter> = options
+            .extra_encodings
+            .iter()
+            .map(|&encoding| TokenCounter::new(encoding))
+            .collect();
+        let count_extra = |output: &str| -> Vec<EncodingTotal> {
+            extra_counters
+                .iter()
+                .map(|c| EncodingTotal {
+                    encoding: c.encoding(),
+                    total: c.count(output),
+                })
+                .collect()
+        };
+
         // Fixed-point: summary includes total_tokens, which affects token count.
         let mut summary_tokens = 0usize;
         let mut total_tokens = counter.count(&output_without_summary);
+        let mut extra_totals = count_extra(&output_without_summary);
 
         for _ in 0..10 {
             let summary = JsonSummary {
@@ -889,26 +2395,33 @@ fn format_output_json(
                 codemap_tokens,
                 selected_tokens,
                 file_breakdown: BTreeMap::new(),
+                largest_files: largest_files(total_tokens),
+                languages: languages.clone(),
+                extra_totals: extra_totals.clone(),
+                skipped: options.skipped.clone(),
             };
 
             let tmp = JsonOutput {
+                architecture: json_architecture.clone(),
                 tree: json_tree.clone(),
                 codemaps: json_codemaps.clone(),
                 selected_files: json_selected.clone(),
                 summary: Some(summary),
             };
 
-            let full = serde_json::to_string_pretty(&tmp).unwrap_or_default();
+            let full = serialize(&tmp, options.format).unwrap_or_default();
             let next_total = counter.count(&full);
+            let next_extra_totals = count_extra(&full);
 
             // Keep summary_tokens in sync for completeness (not currently exported).
             summary_tokens = next_total.saturating_sub(counter.count(&output_without_summary));
 
-            if next_total == total_tokens {
+            if next_total == total_tokens && next_extra_totals == extra_totals {
                 break;
             }
 
             total_tokens = next_total;
+            extra_totals = next_extra_totals;
         }
 
         let _ = summary_tokens;
@@ -919,27 +2432,35 @@ fn format_output_json(
             codemap_tokens,
             selected_tokens,
             file_breakdown: BTreeMap::new(),
+            largest_files: largest_files(total_tokens),
+            languages,
+            extra_totals,
+            skipped: options.skipped.clone(),
         })
     } else {
         None
     };
 
     let output = JsonOutput {
+        architecture: json_architecture,
         tree: json_tree,
         codemaps: json_codemaps,
         selected_files: json_selected,
         summary: json_summary,
     };
 
-    serde_json::to_string_pretty(&output).unwrap_or_else(|e| {
+    serialize(&output, options.format).unwrap_or_else(|e| {
         #[derive(Serialize)]
         struct JsonError {
             error: String,
         }
 
-        serde_json::to_string_pretty(&JsonError {
-            error: e.to_string(),
-        })
+        serialize(
+            &JsonError {
+                error: e.to_string(),
+            },
+            options.format,
+        )
         .unwrap_or_else(|_| "{\"error\":\"serialization failed\"}".to_string())
     })
 }
@@ -949,8 +2470,18 @@ fn file_node_to_json(
     selected: &HashSet<&PathBuf>,
     has_codemap: &HashSet<&PathBuf>,
 ) -> JsonTree {
-    let (kind, extension, size, lines, language) = match &node.kind {
-        NodeKind::Directory => ("directory".to_string(), None, None, None, None),
+    let (kind, extension, file_count, size, lines, language) = match &node.kind {
+        NodeKind::Directory => {
+            let totals = node.totals();
+            (
+                "directory".to_string(),
+                None,
+                Some(totals.files),
+                Some(totals.size),
+                Some(totals.lines),
+                None,
+            )
+        }
         NodeKind::File {
             extension,
             size,
@@ -962,6 +2493,7 @@ fn file_node_to_json(
             (
                 "file".to_string(),
                 extension.clone(),
+                None,
                 Some(*size),
                 *lines,
                 lang,
@@ -977,6 +2509,7 @@ fn file_node_to_json(
         path: node.path.display().to_string(),
         kind,
         extension,
+        file_count,
         size,
         lines,
         language,
@@ -990,7 +2523,7 @@ fn file_node_to_json(
     }
 }
 
-fn codemap_to_json(codemap: &Codemap, public_only: bool) -> JsonCodemap {
+fn codemap_to_json(codemap: &Codemap, options: &OutputOptions) -> JsonCodemap {
     let imports: Vec<JsonImport> = codemap
         .imports
         .iter()
@@ -1000,11 +2533,10 @@ fn codemap_to_json(codemap: &Codemap, public_only: bool) -> JsonCodemap {
         })
         .collect();
 
-    let declarations: Vec<JsonDeclaration> = codemap
-        .declarations
-        .iter()
-        .filter(|d| !public_only || d.is_public())
-        .map(|d| declaration_to_json(d, public_only))
+    let ids = DeclarationIds::new(codemap, &options.root);
+    let declarations: Vec<JsonDeclaration> = sorted_declarations(codemap, options)
+        .into_iter()
+        .map(|d| declaration_to_json(d, &ids, None, options.visibility))
         .collect();
 
     JsonCodemap {
@@ -1012,28 +2544,95 @@ fn codemap_to_json(codemap: &Codemap, public_only: bool) -> JsonCodemap {
         language: codemap.language.to_string(),
         imports,
         declarations,
-        parse_error: codemap.parse_error.clone(),
+        parse_errors: codemap
+            .parse_errors
+            .iter()
+            .map(|e| JsonParseIssue {
+                line: e.line,
+                end_line: e.end_line,
+                column: e.column,
+                cell: e.cell,
+                message: e.message.clone(),
+            })
+            .collect(),
+        fingerprint: format_fingerprint(combined_fingerprint(visible_declarations(
+            codemap, options,
+        ))),
+        is_type_definition: codemap.is_type_definition,
+        todos: codemap
+            .todos
+            .iter()
+            .map(|t| JsonTodo {
+                kind: t.kind.clone(),
+                line: t.line,
+                cell: t.cell,
+                text: t.text.clone(),
+            })
+            .collect(),
+        tests: codemap.tests.clone(),
     }
 }
 
-fn declaration_to_json(decl: &Declaration, public_only: bool) -> JsonDeclaration {
+/// Fingerprints as fixed-width hex, since JSON numbers lose precision above 2^53.
+fn format_fingerprint(fingerprint: u64) -> String {
+    format!("{:016x}", fingerprint)
+}
+
+fn component_to_json(component: &Component) -> JsonComponent {
+    JsonComponent {
+        props: component.props.clone(),
+        props_members: component.props_members.clone(),
+    }
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    (!s.is_empty()).then(|| s.to_string())
+}
+
+fn fields_to_json(fields: &[Field], threshold: VisibilityThreshold) -> Vec<JsonField> {
+    fields
+        .iter()
+        .filter(|f| threshold.allows(f.visibility))
+        .map(|f| JsonField {
+            name: f.name.clone(),
+            ty: f.ty.clone(),
+            visibility: f.visibility.to_string(),
+            modifiers: f.modifiers.clone(),
+            tag: f.tag.clone(),
+            doc: f.doc.clone(),
+        })
+        .collect()
+}
+
+/// `container` is the enclosing type, for methods and members.
+fn declaration_to_json(
+    decl: &Declaration,
+    ids: &DeclarationIds,
+    container: Option<&str>,
+    threshold: VisibilityThreshold,
+) -> JsonDeclaration {
     match decl {
         Declaration::Function {
             name,
+            scope: _,
             signature,
             visibility,
             location,
             is_async,
+            overloads,
+            component,
+            metrics,
+            attributes,
             doc,
+            snippet,
         } => JsonDeclaration {
+            id: ids.id(decl, container),
             kind: "function".to_string(),
             name: name.clone(),
+            fingerprint: format_fingerprint(decl.fingerprint()),
             signature: Some(signature.clone()),
             visibility: visibility.to_string(),
-            location: JsonLocation {
-                start_line: location.start_line,
-                end_line: location.end_line,
-            },
+            location: JsonLocation::from(*location),
             is_async: Some(*is_async),
             doc: doc.clone(),
             fields: Vec::new(),
@@ -1042,41 +2641,50 @@ fn declaration_to_json(decl: &Declaration, public_only: bool) -> JsonDeclaration
             members: Vec::new(),
             target: None,
             ty: None,
+            generics: None,
+            overloads: overloads.clone(),
+            extends: Vec::new(),
+            implements: Vec::new(),
+            is_abstract: None,
+            attributes: attributes.clone(),
+            struct_kind: None,
+            component: component.as_ref().map(component_to_json),
+            metrics: metrics.map(|m| JsonMetrics {
+                lines: m.lines,
+                complexity: m.complexity,
+            }),
+            snippet: snippet.clone(),
         },
 
         Declaration::Struct {
             name,
+            generics,
+            kind,
             fields,
             visibility,
             location,
             methods,
+            implements,
+            attributes,
             doc,
+            snippet,
         } => {
-            let json_fields: Vec<JsonField> = fields
-                .iter()
-                .filter(|f| !public_only || f.visibility == Visibility::Public)
-                .map(|f| JsonField {
-                    name: f.name.clone(),
-                    ty: f.ty.clone(),
-                    visibility: f.visibility.to_string(),
-                })
-                .collect();
+            let json_fields = fields_to_json(fields, threshold);
 
             let json_methods: Vec<JsonDeclaration> = methods
                 .iter()
-                .filter(|m| !public_only || m.is_public())
-                .map(|m| declaration_to_json(m, public_only))
+                .filter(|m| threshold.allows(m.visibility()))
+                .map(|m| declaration_to_json(m, ids, Some(name), threshold))
                 .collect();
 
             JsonDeclaration {
+                id: ids.id(decl, container),
                 kind: "struct".to_string(),
                 name: name.clone(),
+                fingerprint: format_fingerprint(decl.fingerprint()),
                 signature: None,
                 visibility: visibility.to_string(),
-                location: JsonLocation {
-                    start_line: location.start_line,
-                    end_line: location.end_line,
-                },
+                location: JsonLocation::from(*location),
                 is_async: None,
                 doc: doc.clone(),
                 fields: json_fields,
@@ -1085,49 +2693,85 @@ fn declaration_to_json(decl: &Declaration, public_only: bool) -> JsonDeclaration
                 members: Vec::new(),
                 target: None,
                 ty: None,
+                generics: non_empty(generics),
+                overloads: Vec::new(),
+                extends: Vec::new(),
+                implements: implements.clone(),
+                is_abstract: None,
+                attributes: attributes.clone(),
+                struct_kind: (*kind != StructKind::Named).then(|| kind.to_string()),
+                component: None,
+                metrics: None,
+                snippet: snippet.clone(),
             }
         }
 
         Declaration::Enum {
             name,
+            generics,
             variants,
             visibility,
             location,
+            methods,
+            implements,
+            attributes,
             doc,
+            snippet,
         } => JsonDeclaration {
+            id: ids.id(decl, container),
             kind: "enum".to_string(),
             name: name.clone(),
+            fingerprint: format_fingerprint(decl.fingerprint()),
             signature: None,
             visibility: visibility.to_string(),
-            location: JsonLocation {
-                start_line: location.start_line,
-                end_line: location.end_line,
-            },
+            location: JsonLocation::from(*location),
             is_async: None,
             doc: doc.clone(),
             fields: Vec::new(),
-            methods: Vec::new(),
-            variants: variants.to_vec(),
+            methods: methods
+                .iter()
+                .filter(|m| threshold.allows(m.visibility()))
+                .map(|m| declaration_to_json(m, ids, Some(name), threshold))
+                .collect(),
+            variants: variants
+                .iter()
+                .map(|v| JsonVariant {
+                    name: v.name.clone(),
+                    doc: v.doc.clone(),
+                })
+                .collect(),
             members: Vec::new(),
             target: None,
             ty: None,
+            generics: non_empty(generics),
+            overloads: Vec::new(),
+            extends: Vec::new(),
+            implements: implements.clone(),
+            is_abstract: None,
+            attributes: attributes.clone(),
+            struct_kind: None,
+            component: None,
+            metrics: None,
+            snippet: snippet.clone(),
         },
 
         Declaration::Trait {
             name,
+            generics,
             methods,
             visibility,
             location,
+            attributes,
             doc,
+            snippet,
         } => JsonDeclaration {
+            id: ids.id(decl, container),
             kind: "trait".to_string(),
             name: name.clone(),
+            fingerprint: format_fingerprint(decl.fingerprint()),
             signature: None,
             visibility: visibility.to_string(),
-            location: JsonLocation {
-                start_line: location.start_line,
-                end_line: location.end_line,
-            },
+            location: JsonLocation::from(*location),
             is_async: None,
             doc: doc.clone(),
             fields: Vec::new(),
@@ -1136,22 +2780,33 @@ fn declaration_to_json(decl: &Declaration, public_only: bool) -> JsonDeclaration
             members: methods.to_vec(),
             target: None,
             ty: None,
+            generics: non_empty(generics),
+            overloads: Vec::new(),
+            extends: Vec::new(),
+            implements: Vec::new(),
+            is_abstract: None,
+            attributes: attributes.clone(),
+            struct_kind: None,
+            component: None,
+            metrics: None,
+            snippet: snippet.clone(),
         },
 
         Declaration::TypeAlias {
             name,
+            generics,
             target,
             visibility,
             location,
+            snippet,
         } => JsonDeclaration {
+            id: ids.id(decl, container),
             kind: "type_alias".to_string(),
             name: name.clone(),
+            fingerprint: format_fingerprint(decl.fingerprint()),
             signature: None,
             visibility: visibility.to_string(),
-            location: JsonLocation {
-                start_line: location.start_line,
-                end_line: location.end_line,
-            },
+            location: JsonLocation::from(*location),
             is_async: None,
             doc: None,
             fields: Vec::new(),
@@ -1160,6 +2815,16 @@ fn declaration_to_json(decl: &Declaration, public_only: bool) -> JsonDeclaration
             members: Vec::new(),
             target: Some(target.clone()),
             ty: None,
+            generics: non_empty(generics),
+            overloads: Vec::new(),
+            extends: Vec::new(),
+            implements: Vec::new(),
+            is_abstract: None,
+            attributes: Vec::new(),
+            struct_kind: None,
+            component: None,
+            metrics: None,
+            snippet: snippet.clone(),
         },
 
         Declaration::Const {
@@ -1167,15 +2832,16 @@ fn declaration_to_json(decl: &Declaration, public_only: bool) -> JsonDeclaration
             ty,
             visibility,
             location,
+            is_static,
+            snippet,
         } => JsonDeclaration {
-            kind: "const".to_string(),
+            id: ids.id(decl, container),
+            kind: if *is_static { "static" } else { "const" }.to_string(),
             name: name.clone(),
+            fingerprint: format_fingerprint(decl.fingerprint()),
             signature: None,
             visibility: visibility.to_string(),
-            location: JsonLocation {
-                start_line: location.start_line,
-                end_line: location.end_line,
-            },
+            location: JsonLocation::from(*location),
             is_async: None,
             doc: None,
             fields: Vec::new(),
@@ -1183,7 +2849,17 @@ fn declaration_to_json(decl: &Declaration, public_only: bool) -> JsonDeclaration
             variants: Vec::new(),
             members: Vec::new(),
             target: None,
-            ty: Some(ty.clone()),
+            ty: non_empty(ty),
+            generics: None,
+            overloads: Vec::new(),
+            extends: Vec::new(),
+            implements: Vec::new(),
+            is_abstract: None,
+            attributes: Vec::new(),
+            struct_kind: None,
+            component: None,
+            metrics: None,
+            snippet: snippet.clone(),
         },
 
         Declaration::Interface {
@@ -1192,15 +2868,15 @@ fn declaration_to_json(decl: &Declaration, public_only: bool) -> JsonDeclaration
             visibility,
             location,
             doc,
+            snippet,
         } => JsonDeclaration {
+            id: ids.id(decl, container),
             kind: "interface".to_string(),
             name: name.clone(),
+            fingerprint: format_fingerprint(decl.fingerprint()),
             signature: None,
             visibility: visibility.to_string(),
-            location: JsonLocation {
-                start_line: location.start_line,
-                end_line: location.end_line,
-            },
+            location: JsonLocation::from(*location),
             is_async: None,
             doc: doc.clone(),
             fields: Vec::new(),
@@ -1209,40 +2885,100 @@ fn declaration_to_json(decl: &Declaration, public_only: bool) -> JsonDeclaration
             members: members.to_vec(),
             target: None,
             ty: None,
+            generics: None,
+            overloads: Vec::new(),
+            extends: Vec::new(),
+            implements: Vec::new(),
+            is_abstract: None,
+            attributes: Vec::new(),
+            struct_kind: None,
+            component: None,
+            metrics: None,
+            snippet: snippet.clone(),
         },
 
         Declaration::Class {
             name,
+            generics,
+            extends,
+            implements,
+            is_abstract,
+            fields,
             members,
+            component,
             visibility,
             location,
             doc,
+            snippet,
         } => {
             let json_members: Vec<JsonDeclaration> = members
                 .iter()
-                .filter(|m| !public_only || m.is_public())
-                .map(|m| declaration_to_json(m, public_only))
+                .filter(|m| threshold.allows(m.visibility()))
+                .map(|m| declaration_to_json(m, ids, Some(name), threshold))
                 .collect();
 
             JsonDeclaration {
+                id: ids.id(decl, container),
                 kind: "class".to_string(),
                 name: name.clone(),
+                fingerprint: format_fingerprint(decl.fingerprint()),
                 signature: None,
                 visibility: visibility.to_string(),
-                location: JsonLocation {
-                    start_line: location.start_line,
-                    end_line: location.end_line,
-                },
+                location: JsonLocation::from(*location),
                 is_async: None,
                 doc: doc.clone(),
-                fields: Vec::new(),
+                fields: fields_to_json(fields, threshold),
                 methods: json_members,
                 variants: Vec::new(),
                 members: Vec::new(),
                 target: None,
                 ty: None,
+                generics: non_empty(generics),
+                overloads: Vec::new(),
+                extends: extends.clone(),
+                implements: implements.clone(),
+                is_abstract: is_abstract.then_some(true),
+                attributes: Vec::new(),
+                struct_kind: None,
+                component: component.as_ref().map(component_to_json),
+                metrics: None,
+                snippet: snippet.clone(),
             }
         }
+
+        Declaration::ReExport {
+            name,
+            items,
+            visibility,
+            location,
+            snippet,
+        } => JsonDeclaration {
+            id: ids.id(decl, container),
+            kind: "re_export".to_string(),
+            name: name.clone(),
+            fingerprint: format_fingerprint(decl.fingerprint()),
+            signature: None,
+            visibility: visibility.to_string(),
+            location: JsonLocation::from(*location),
+            is_async: None,
+            doc: None,
+            fields: Vec::new(),
+            methods: Vec::new(),
+            variants: Vec::new(),
+            members: items.to_vec(),
+            target: None,
+            ty: None,
+            generics: None,
+            overloads: Vec::new(),
+            extends: Vec::new(),
+            implements: Vec::new(),
+            is_abstract: None,
+            attributes: Vec::new(),
+            struct_kind: None,
+            component: None,
+            metrics: None,
+            snippet: snippet.clone(),
+        },
     }
 }
 
@@ -1251,6 +2987,7 @@ fn declaration_to_json(decl: &Declaration, public_only: bool) -> JsonDeclaration
 // ============================================================================
 
 fn calculate_summary(
+    architecture_tokens: usize,
     tree_tokens: usize,
     codemap_tokens: usize,
     selected_tokens: usize,
@@ -1258,12 +2995,62 @@ fn calculate_summary(
     summary_tokens: usize,
 ) -> TokenSummary {
     TokenSummary {
-        total: tree_tokens + codemap_tokens + selected_tokens + summary_tokens,
+        total: architecture_tokens
+            + tree_tokens
+            + codemap_tokens
+            + selected_tokens
+            + summary_tokens,
+        architecture_tokens,
         tree_tokens,
         codemap_tokens,
         selected_tokens,
         file_breakdown,
+        extra_totals: Vec::new(),
+        skipped: Vec::new(),
+    }
+}
+
+// ============================================================================
+// File Output
+// ============================================================================
+
+/// Write `contents` to `path` atomically: the data goes to a temporary file
+/// in the same directory, which is then renamed over `path`. Readers see
+/// either the old file or the complete new one, and a failed write leaves
+/// `path` untouched. An existing file is only replaced if `overwrite` is set.
+pub fn write_atomic(path: &Path, contents: &[u8], overwrite: bool) -> Result<(), OutputError> {
+    if !overwrite && path.exists() {
+        return Err(OutputError::FileExists {
+            path: path.to_path_buf(),
+        });
     }
+
+    let name = path.file_name().ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("not a file path: {}", path.display()),
+        )
+    })?;
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let temp = dir.join(format!(
+        ".{}.{}.tmp",
+        name.to_string_lossy(),
+        std::process::id()
+    ));
+
+    let result = fs::File::create(&temp)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&temp, path));
+    if result.is_err() {
+        fs::remove_file(&temp).ok();
+    }
+    Ok(result?)
 }
 
 #[cfg(test)]
@@ -1278,7 +3065,7 @@ mod tests {
         assert!(opts.include_codemaps);
         assert!(!opts.include_selected_files);
         assert!(opts.include_summary);
-        assert!(opts.public_only);
+        assert_eq!(opts.visibility, VisibilityThreshold::Public);
     }
 
     #[test]
@@ -1293,6 +3080,29 @@ mod tests {
         assert_eq!(format_location(&loc), "lines 5-10");
     }
 
+    #[test]
+    fn test_format_location_notebook_cell() {
+        let loc = Location::new(2, 4).in_cell(Some(3));
+        assert_eq!(format_location(&loc), "cell 3, lines 2-4");
+    }
+
+    #[test]
+    fn test_write_atomic() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("context.xml");
+
+        write_atomic(&path, b"first", false).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first");
+
+        let err = write_atomic(&path, b"second", false).unwrap_err();
+        assert!(matches!(err, OutputError::FileExists { .. }));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first");
+
+        write_atomic(&path, b"second", true).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
     #[test]
     fn test_empty_output_xml() {
         let opts = OutputOptions {
@@ -1320,22 +3130,49 @@ mod tests {
             imports: smallvec::smallvec![],
             declarations: smallvec::smallvec![Declaration::Function {
                 name: "a".into(),
+                scope: None,
                 signature: "pub fn a()".into(),
                 visibility: Visibility::Public,
                 location: Location::single_line(1),
                 is_async: false,
+                overloads: Vec::new(),
+                component: None,
+                metrics: None,
+                attributes: Vec::new(),
                 doc: None,
+                snippet: None,
             }],
-            parse_error: None,
+            parse_errors: Vec::new(),
+            is_type_definition: false,
+            todos: Vec::new(),
+            tests: Vec::new(),
+            token_count: 0,
         };
 
         let opts = OutputOptions {
             format: OutputFormat::Xml,
             include_tree: true,
+            tree_max_children: None,
+            tree_tokens: false,
+            prune_tree: false,
+            tree_max_depth: None,
+            tree_markers: TreeMarkers::default(),
             include_codemaps: true,
             include_selected_files: false,
+            line_numbers: false,
+            strip_license_headers: false,
+            minify: Minify::Off,
+            include_architecture: false,
             include_summary: true,
-            public_only: true,
+            visibility: VisibilityThreshold::Public,
+            symbol_filter: None,
+            sort: DeclarationOrder::Source,
+            importance: None,
+            trim_declarations: false,
+            max_tokens: None,
+            extra_encodings: Vec::new(),
+            skipped: Vec::new(),
+            root: PathBuf::new(),
         };
 
         let out = format_output(Some(&tree), &[codemap], &[], &opts, Encoding::Cl100kBase);
@@ -1356,6 +3193,36 @@ mod tests {
         assert_eq!(reported, actual);
     }
 
+    #[test]
+    fn test_render_context_sections() {
+        let tree = FileNode::directory("project", "project");
+        let selected = SelectedFile {
+            path: PathBuf::from("project/main.py"),
+            language: None,
+            content: "print('hi')\n".into(),
+            lines: 1,
+            tokens: 4,
+        };
+        let opts = OutputOptions::full_context();
+
+        let rendered = render_context(
+            Some(&tree),
+            &[],
+            std::slice::from_ref(&selected),
+            &opts,
+            Encoding::Cl100kBase,
+        );
+        assert!(rendered.tree_section.starts_with("<file_map>\n"));
+        assert!(rendered.codemap_section.is_empty());
+        assert!(rendered.selected_section.contains("print('hi')"));
+        assert!(rendered.summary_section.starts_with("<token_summary>\n"));
+        assert_eq!(rendered.summary.codemap_tokens, 0);
+        assert!(rendered.summary.file_breakdown[&selected.path].selected);
+
+        let out = format_output(Some(&tree), &[], &[selected], &opts, Encoding::Cl100kBase);
+        assert_eq!(rendered.to_string(), out);
+    }
+
     #[test]
     fn test_json_output_empty() {
         let opts = OutputOptions {
@@ -1385,22 +3252,49 @@ mod tests {
             imports: smallvec::smallvec![],
             declarations: smallvec::smallvec![Declaration::Function {
                 name: "a".into(),
+                scope: None,
                 signature: "pub fn a()".into(),
                 visibility: Visibility::Public,
                 location: Location::single_line(1),
                 is_async: false,
+                overloads: Vec::new(),
+                component: None,
+                metrics: None,
+                attributes: Vec::new(),
                 doc: None,
+                snippet: None,
             }],
-            parse_error: None,
+            parse_errors: Vec::new(),
+            is_type_definition: false,
+            todos: Vec::new(),
+            tests: Vec::new(),
+            token_count: 0,
         };
 
         let opts = OutputOptions {
             format: OutputFormat::Json,
             include_tree: true,
+            tree_max_children: None,
+            tree_tokens: false,
+            prune_tree: false,
+            tree_max_depth: None,
+            tree_markers: TreeMarkers::default(),
             include_codemaps: true,
             include_selected_files: false,
+            line_numbers: false,
+            strip_license_headers: false,
+            minify: Minify::Off,
+            include_architecture: false,
             include_summary: true,
-            public_only: true,
+            visibility: VisibilityThreshold::Public,
+            symbol_filter: None,
+            sort: DeclarationOrder::Source,
+            importance: None,
+            trim_declarations: false,
+            max_tokens: None,
+            extra_encodings: Vec::new(),
+            skipped: Vec::new(),
+            root: PathBuf::new(),
         };
 
         let out = format_output(Some(&tree), &[codemap], &[], &opts, Encoding::Cl100kBase);
@@ -1415,4 +3309,776 @@ mod tests {
 
         assert_eq!(reported, actual);
     }
+
+    #[test]
+    fn test_extra_encoding_totals_match_output() {
+        use crate::codemap::{extract_codemap, ExtractOptions};
+        use crate::filter::Language;
+        use crate::tokens::count_tokens_with_encoding;
+
+        let codemap = extract_codemap(
+            Path::new("src/lib.rs"),
+            "/// Entry point.\npub fn run(args: Vec<String>) -> Result<(), Error> { Ok(()) }\n",
+            Language::Rust,
+            &ExtractOptions::with_docs(),
+        );
+        for format in [
+            OutputFormat::Xml,
+            OutputFormat::Json,
+            OutputFormat::StrictXml,
+        ] {
+            let opts = OutputOptions {
+                format,
+                extra_encodings: vec![Encoding::O200kBase],
+                ..OutputOptions::codemap_only()
+            };
+            let out = format_output(
+                None,
+                std::slice::from_ref(&codemap),
+                &[],
+                &opts,
+                Encoding::Cl100kBase,
+            );
+            let o200k = count_tokens_with_encoding(&out, Encoding::O200kBase);
+            match format {
+                OutputFormat::Json => {
+                    let v: serde_json::Value = serde_json::from_str(&out).unwrap();
+                    let extra = &v["summary"]["extra_totals"][0];
+                    assert_eq!(extra["encoding"], "o200k_base");
+                    assert_eq!(extra["total"], o200k);
+                }
+                OutputFormat::StrictXml => assert!(out.contains(&format!(
+                    "<encoding name=\"o200k_base\" total=\"{}\"/>",
+                    o200k
+                ))),
+                _ => assert!(out.contains(&format!("Total (o200k_base): {} tokens\n", o200k))),
+            }
+        }
+    }
+
+    #[test]
+    fn test_json_declaration_ids() {
+        use crate::codemap::{extract_codemap, ExtractOptions};
+        use crate::filter::Language;
+
+        let codemap = extract_codemap(
+            Path::new("project/src/config.rs"),
+            "pub struct Config;\n\nimpl Config {\n    pub fn load() -> Self { Config }\n}\n",
+            Language::Rust,
+            &ExtractOptions::default(),
+        );
+        // Relative to the root, like record IDs; the path itself is as given.
+        let opts = OutputOptions {
+            format: OutputFormat::Json,
+            include_tree: false,
+            include_summary: false,
+            root: PathBuf::from("project"),
+            ..Default::default()
+        };
+
+        let out = format_output(None, &[codemap], &[], &opts, Encoding::default());
+        let v: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(v["codemaps"][0]["path"], "project/src/config.rs");
+        let config = &v["codemaps"][0]["declarations"][0];
+        assert_eq!(config["id"], "src/config.rs#Config@struct");
+        assert_eq!(
+            config["methods"][0]["id"],
+            "src/config.rs#Config::load@function"
+        );
+    }
+
+    #[test]
+    fn test_dataclass_without_methods_ends_with_blank_line() {
+        use crate::codemap::{extract_codemap, ExtractOptions};
+        use crate::filter::Language;
+
+        let code = "@dataclass\nclass Point:\n    x: int\n    y: int\n\ndef origin() -> Point:\n    pass\n";
+        let codemap = extract_codemap(
+            Path::new("point.py"),
+            code,
+            Language::Python,
+            &ExtractOptions::default(),
+        );
+        let rendered = format_codemap_xml(&codemap, &OutputOptions::codemap_only());
+        assert_eq!(
+            rendered,
+            "## point.py\n\n### Declarations\n\n\
+             #### class Point (lines 2-4)\nFields:\n- x: int\n- y: int\n\n\
+             #### def origin() -> Point (lines 6-7)\n\n"
+        );
+    }
+
+    #[test]
+    fn test_type_only_re_exports_keep_modifier() {
+        let render = |items: &[&str]| {
+            let items: Vec<String> = items.iter().map(|i| i.to_string()).collect();
+            reexport_statement("./a", &items, Visibility::Public, Language::TypeScript)
+        };
+        assert_eq!(
+            render(&["type A", "type B as C"]),
+            "export type { A, B as C } from \"./a\""
+        );
+        assert_eq!(render(&["type *"]), "export type * from \"./a\"");
+        assert_eq!(
+            render(&["type D", "e"]),
+            "export { type D, e } from \"./a\""
+        );
+        assert_eq!(render(&["* as ns"]), "export * as ns from \"./a\"");
+    }
+
+    #[test]
+    fn test_function_scope_comes_from_extractor() {
+        use crate::codemap::{Declaration, Location, Visibility};
+        use crate::filter::Language;
+
+        let function = |name: &str, scope: Option<&str>| Declaration::Function {
+            name: name.into(),
+            scope: scope.map(String::from),
+            signature: "pub fn get(map: HashMap<a::B, c.D>)".into(),
+            visibility: Visibility::Public,
+            location: Location::single_line(1),
+            is_async: false,
+            overloads: Vec::new(),
+            component: None,
+            metrics: None,
+            attributes: Vec::new(),
+            doc: None,
+            snippet: None,
+        };
+
+        let nested = format_declaration_xml(
+            &function("net::http::get", Some("net::http")),
+            Language::Rust,
+            VisibilityThreshold::Public,
+            0,
+        );
+        assert!(
+            nested.starts_with("#### pub fn get(map: HashMap<a::B, c.D>) (in net::http, line 1)")
+        );
+
+        // Separators in a top-level name are not mistaken for a scope.
+        let top_level = format_declaration_xml(
+            &function("exports.get", None),
+            Language::Rust,
+            VisibilityThreshold::Public,
+            0,
+        );
+        assert!(top_level.starts_with("#### pub fn get(map: HashMap<a::B, c.D>) (line 1)"));
+    }
+
+    #[test]
+    fn test_symbol_filter_drops_unmatched_declarations_and_files() {
+        use crate::codemap::{Codemap, Declaration, Location, Visibility};
+        use crate::filter::Language;
+
+        let function = |name: &str| Declaration::Function {
+            name: name.into(),
+            scope: None,
+            signature: format!("pub fn {name}()"),
+            visibility: Visibility::Public,
+            location: Location::single_line(1),
+            is_async: false,
+            overloads: Vec::new(),
+            component: None,
+            metrics: None,
+            attributes: Vec::new(),
+            doc: None,
+            snippet: None,
+        };
+        let codemap = |path: &str, names: &[&str]| Codemap {
+            path: PathBuf::from(path),
+            language: Language::Rust,
+            imports: smallvec::smallvec![],
+            declarations: names.iter().map(|n| function(n)).collect(),
+            parse_errors: Vec::new(),
+            is_type_definition: false,
+            todos: Vec::new(),
+            tests: Vec::new(),
+            token_count: 0,
+        };
+        let codemaps = vec![
+            codemap("a.rs", &["handle_get", "parse"]),
+            codemap("b.rs", &["render"]),
+        ];
+
+        for filter in [
+            SymbolFilter::regex("^handle_").unwrap(),
+            SymbolFilter::glob("handle_*").unwrap(),
+        ] {
+            let opts = OutputOptions {
+                symbol_filter: Some(filter),
+                ..OutputOptions::codemap_only()
+            };
+            let out = format_output(None, &codemaps, &[], &opts, Encoding::default());
+            assert!(out.contains("handle_get"));
+            assert!(!out.contains("parse"));
+            assert!(!out.contains("b.rs"));
+        }
+    }
+
+    #[test]
+    fn test_symbol_filter_matches_nested_names() {
+        use crate::codemap::{extract_codemap, ExtractOptions};
+        use crate::filter::Language;
+
+        let codemaps = vec![
+            extract_codemap(
+                Path::new("ns.ts"),
+                "export namespace NS { export function inner() {} }\nexport function other() {}\n",
+                Language::TypeScript,
+                &ExtractOptions::default(),
+            ),
+            extract_codemap(
+                Path::new("lib.rs"),
+                "pub mod outer { pub fn inner() {} }\npub fn other() {}\n",
+                Language::Rust,
+                &ExtractOptions::default(),
+            ),
+        ];
+
+        for filter in [
+            SymbolFilter::regex("^inner").unwrap(),
+            SymbolFilter::glob("inner*").unwrap(),
+        ] {
+            let opts = OutputOptions {
+                symbol_filter: Some(filter),
+                ..OutputOptions::codemap_only()
+            };
+            let out = format_output(None, &codemaps, &[], &opts, Encoding::default());
+            assert!(out.contains("inner() (in NS"), "{out}");
+            assert!(out.contains("inner () (in outer"), "{out}");
+            assert!(!out.contains("other"), "{out}");
+        }
+    }
+
+    #[test]
+    fn test_sorted_declarations() {
+        use crate::codemap::{Codemap, Declaration, Location, Visibility};
+        use crate::filter::Language;
+
+        let function = |name: &str, line: usize, visibility: Visibility| Declaration::Function {
+            name: name.into(),
+            scope: None,
+            signature: format!("fn {name}()"),
+            visibility,
+            location: Location::single_line(line),
+            is_async: false,
+            overloads: Vec::new(),
+            component: None,
+            metrics: None,
+            attributes: Vec::new(),
+            doc: None,
+            snippet: None,
+        };
+        let codemap = Codemap {
+            path: PathBuf::from("lib.rs"),
+            language: Language::Rust,
+            imports: smallvec::smallvec![],
+            declarations: smallvec::smallvec![
+                function("zeta", 1, Visibility::Private),
+                Declaration::Const {
+                    name: "Alpha".into(),
+                    ty: "u32".into(),
+                    visibility: Visibility::Public,
+                    location: Location::single_line(2),
+                    is_static: false,
+                    snippet: None,
+                },
+                function("beta", 3, Visibility::Crate),
+            ],
+            parse_errors: Vec::new(),
+            is_type_definition: false,
+            todos: Vec::new(),
+            tests: Vec::new(),
+            token_count: 0,
+        };
+
+        let names = |sort| {
+            let opts = OutputOptions {
+                visibility: VisibilityThreshold::All,
+                sort,
+                ..OutputOptions::default()
+            };
+            sorted_declarations(&codemap, &opts)
+                .into_iter()
+                .map(|d| d.name().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names(DeclarationOrder::Source), ["zeta", "Alpha", "beta"]);
+        assert_eq!(names(DeclarationOrder::Name), ["Alpha", "beta", "zeta"]);
+        assert_eq!(names(DeclarationOrder::Kind), ["zeta", "beta", "Alpha"]);
+        assert_eq!(
+            names(DeclarationOrder::Visibility),
+            ["Alpha", "beta", "zeta"]
+        );
+        assert_eq!(
+            names(DeclarationOrder::Importance),
+            ["Alpha", "zeta", "beta"]
+        );
+    }
+
+    #[test]
+    fn test_markdown_compact_and_yaml_formats() {
+        use crate::codemap::{Codemap, Declaration, Location, Visibility};
+        use crate::filter::Language;
+        use crate::tokens::count_tokens_with_encoding;
+
+        let codemap = Codemap {
+            declarations: smallvec::smallvec![Declaration::Function {
+                name: "run".into(),
+                scope: None,
+                signature: "pub fn run()".into(),
+                visibility: Visibility::Public,
+                location: Location::single_line(1),
+                is_async: false,
+                overloads: Vec::new(),
+                component: None,
+                metrics: None,
+                attributes: Vec::new(),
+                doc: None,
+                snippet: None,
+            }],
+            ..Codemap::empty(PathBuf::from("src/lib.rs"), Language::Rust)
+        };
+        let selected = SelectedFile {
+            path: PathBuf::from("src/lib.rs"),
+            language: Some(Language::Rust),
+            content: "/// ```\n/// run();\n/// ```\npub fn run() {}".into(),
+            lines: 4,
+            tokens: 12,
+        };
+        let render = |format| {
+            let opts = OutputOptions {
+                format,
+                include_tree: false,
+                ..OutputOptions::full_context()
+            };
+            format_output(
+                None,
+                std::slice::from_ref(&codemap),
+                std::slice::from_ref(&selected),
+                &opts,
+                Encoding::Cl100kBase,
+            )
+        };
+
+        let md = render(OutputFormat::Markdown);
+        assert!(md.starts_with("# Codemaps\n\n## src/lib.rs\n"));
+        // The fence outgrows the backticks in the file.
+        assert!(md.contains("````rust\n/// ```\n"));
+        assert!(md.contains("pub fn run() {}\n````\n"));
+        assert!(!md.contains('<'));
+
+        let compact = render(OutputFormat::Compact);
+        assert!(compact.starts_with("src/lib.rs\n  pub fn run()\n"));
+        assert!(compact.contains("--- src/lib.rs ---\n"));
+        let total: usize = compact
+            .lines()
+            .last()
+            .and_then(|l| l.strip_prefix("Total: "))
+            .and_then(|l| l.strip_suffix(" tokens"))
+            .and_then(|n| n.replace(',', "").parse().ok())
+            .expect("missing Total line");
+        assert_eq!(
+            total,
+            count_tokens_with_encoding(&compact, Encoding::Cl100kBase)
+        );
+
+        let yaml: serde_json::Value = serde_yaml_ng::from_str(&render(OutputFormat::Yaml)).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&render(OutputFormat::Json)).unwrap();
+        assert_eq!(yaml["codemaps"], json["codemaps"]);
+        assert_eq!(yaml["selected_files"], json["selected_files"]);
+    }
+
+    #[test]
+    fn test_strict_xml_format() {
+        use crate::tokens::count_tokens_with_encoding;
+
+        let selected = SelectedFile {
+            path: PathBuf::from("src/a&b.rs"),
+            language: None,
+            content: "let s = \"]]>\";\u{1}\nif a < b {}\n".into(),
+            lines: 2,
+            tokens: 12,
+        };
+        let opts = OutputOptions {
+            format: OutputFormat::StrictXml,
+            ..OutputOptions::full_context()
+        };
+        let tree = FileNode::directory("project", "project");
+        let out = format_output(
+            Some(&tree),
+            &[],
+            std::slice::from_ref(&selected),
+            &opts,
+            Encoding::Cl100kBase,
+        );
+
+        assert!(out.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<context>\n"));
+        assert!(out.ends_with("</token_summary>\n</context>\n"));
+        assert!(out.contains("<file_map>\n<![CDATA[project/"));
+        assert!(out.contains("]]>\n</file_map>\n<selected_files>\n"));
+        assert!(out.contains(
+            "<file path=\"src/a&amp;b.rs\" lines=\"2\" tokens=\"12\">\
+             <![CDATA[let s = \"]]]]><![CDATA[>\";\u{FFFD}\nif a < b {}\n]]></file>\n"
+        ));
+        assert!(out.contains("<file path=\"src/a&amp;b.rs\" tokens=\""));
+
+        let total: usize = out
+            .split("<token_summary total=\"")
+            .nth(1)
+            .and_then(|rest| rest.split('"').next())
+            .and_then(|n| n.parse().ok())
+            .expect("missing total");
+        assert_eq!(
+            total,
+            count_tokens_with_encoding(&out, Encoding::Cl100kBase)
+        );
+    }
+
+    #[test]
+    fn test_selected_file_line_numbers() {
+        let file = SelectedFile {
+            path: PathBuf::from("src/main.rs"),
+            language: None,
+            content: (1..=10)
+                .map(|i| format!("line {i}"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            lines: 10,
+            tokens: 30,
+        };
+        let options = OutputOptions {
+            line_numbers: true,
+            ..OutputOptions::full_context()
+        };
+        let block = format_selected_block(&file, &options);
+        assert!(block.contains("---\n```rs\n 1 | line 1\n 2 | line 2\n"));
+        assert!(block.contains("\n10 | line 10\n"));
+
+        let block = format_selected_block(&file, &OutputOptions::full_context());
+        assert!(block.contains("---\n```rs\nline 1\n"));
+    }
+
+    #[test]
+    fn test_selected_file_fences() {
+        let file = |path: &str, language| SelectedFile {
+            path: PathBuf::from(path),
+            language,
+            content: "x = 1\n".into(),
+            lines: 1,
+            tokens: 3,
+        };
+        let options = OutputOptions::full_context();
+
+        let block = format_selected_block(&file("app.py", Some(Language::Python)), &options);
+        assert_eq!(
+            block,
+            "--- app.py (1 lines, 3 tokens) ---\n```python\nx = 1\n```\n\n"
+        );
+        let block = format_selected_block(&file("train.ipynb", Some(Language::Python)), &options);
+        assert!(block.contains("```json\n"));
+        let block = format_selected_block(&file("Makefile", None), &options);
+        assert!(block.contains("---\n```\nx = 1\n"));
+
+        let compact = OutputOptions {
+            format: OutputFormat::Compact,
+            ..OutputOptions::full_context()
+        };
+        let block = format_selected_block(&file("app.py", Some(Language::Python)), &compact);
+        assert_eq!(block, "--- app.py ---\nx = 1\n\n");
+    }
+
+    #[test]
+    fn test_strip_license_headers() {
+        let file = SelectedFile {
+            path: PathBuf::from("main.go"),
+            language: Some(Language::Go),
+            content: "// Copyright 2024 Acme\n// SPDX-License-Identifier: MIT\n\npackage main\n"
+                .into(),
+            lines: 4,
+            tokens: 20,
+        };
+        let options = OutputOptions {
+            strip_license_headers: true,
+            line_numbers: true,
+            ..OutputOptions::full_context()
+        };
+        assert_eq!(
+            format_selected_block(&file, &options),
+            "--- main.go (4 lines, 20 tokens, license header removed) ---\n```go\n4 | package main\n```\n\n"
+        );
+
+        let block = format_selected_block(&file, &OutputOptions::full_context());
+        assert!(block.contains("// Copyright 2024 Acme\n"));
+    }
+
+    #[test]
+    fn test_largest_files() {
+        let info = |tokens| FileTokenInfo {
+            tokens,
+            selected: false,
+            has_codemap: true,
+            minify_saved: 0,
+            language: Some(Language::Rust),
+            declarations: 2,
+        };
+        let summary = calculate_summary(
+            0,
+            0,
+            400,
+            0,
+            BTreeMap::from([
+                (PathBuf::from("a.rs"), info(100)),
+                (PathBuf::from("b.rs"), info(300)),
+                (PathBuf::from("c.rs"), info(100)),
+            ]),
+            600,
+        );
+        let largest = summary.largest_files(2);
+        assert_eq!(largest.len(), 2);
+        assert_eq!(largest[0].path, PathBuf::from("b.rs"));
+        assert!((largest[0].percent - 30.0).abs() < f64::EPSILON);
+        // Ties keep path order.
+        assert_eq!(largest[1].path, PathBuf::from("a.rs"));
+
+        let text = format_summary_xml(&summary);
+        assert!(text.contains("\nLargest files:\n- b.rs: 300 tokens (30.0%)\n"));
+        assert!(format_summary_strict_xml(&summary)
+            .contains("<largest_file path=\"b.rs\" tokens=\"300\" percent=\"30.0\"/>"));
+    }
+
+    #[test]
+    fn test_language_breakdown() {
+        let info = |tokens, language, declarations| FileTokenInfo {
+            tokens,
+            language,
+            declarations,
+            ..FileTokenInfo::default()
+        };
+        let summary = calculate_summary(
+            0,
+            0,
+            500,
+            0,
+            BTreeMap::from([
+                (PathBuf::from("a.rs"), info(100, Some(Language::Rust), 3)),
+                (PathBuf::from("b.rs"), info(150, Some(Language::Rust), 4)),
+                (PathBuf::from("c.py"), info(200, Some(Language::Python), 1)),
+                (PathBuf::from("NOTES"), info(50, None, 0)),
+            ]),
+            0,
+        );
+        let languages = summary.languages();
+        assert_eq!(
+            languages[0],
+            LanguageTokens {
+                language: Some(Language::Rust),
+                files: 2,
+                tokens: 250,
+                declarations: 7,
+            }
+        );
+        assert_eq!(languages[1].language, Some(Language::Python));
+        assert_eq!(languages[2].to_string(), "other");
+
+        let text = format_summary_xml(&summary);
+        assert!(text.contains("\nLanguages:\n- rust: 2 files, 250 tokens, 7 declarations\n"));
+        assert!(text.contains("- python: 1 file, 200 tokens, 1 declaration\n"));
+        assert!(text.contains("- other: 1 file, 50 tokens, 0 declarations\n"));
+        assert!(format_summary_strict_xml(&summary)
+            .contains("<language name=\"python\" files=\"1\" tokens=\"200\" declarations=\"1\"/>"));
+    }
+
+    #[test]
+    fn test_skipped_files_in_summary() {
+        let codemaps = vec![Codemap::empty(PathBuf::from("src/lib.rs"), Language::Rust)];
+        let options = OutputOptions {
+            skipped: vec![SkippedFile {
+                path: PathBuf::from("src/legacy.py"),
+                reason: "invalid UTF-8".to_string(),
+            }],
+            ..OutputOptions::codemap_only()
+        };
+        let render = |format| {
+            let options = OutputOptions {
+                format,
+                ..options.clone()
+            };
+            format_output(None, &codemaps, &[], &options, Encoding::default())
+        };
+
+        assert!(render(OutputFormat::Xml)
+            .contains("\nSkipped files:\n- src/legacy.py: invalid UTF-8\n"));
+        assert!(render(OutputFormat::Compact).contains("Skipped src/legacy.py: invalid UTF-8\n"));
+        assert!(render(OutputFormat::StrictXml)
+            .contains("<skipped path=\"src/legacy.py\" reason=\"invalid UTF-8\"/>"));
+
+        let json: serde_json::Value = serde_json::from_str(&render(OutputFormat::Json)).unwrap();
+        assert_eq!(json["summary"]["skipped"][0]["path"], "src/legacy.py");
+        assert_eq!(json["summary"]["skipped"][0]["reason"], "invalid UTF-8");
+
+        // Nothing is listed when every file was read.
+        let clean = format_output(
+            None,
+            &codemaps,
+            &[],
+            &OutputOptions::codemap_only(),
+            Encoding::default(),
+        );
+        assert!(!clean.contains("Skipped"));
+    }
+
+    #[test]
+    fn test_parse_errors_rendered() {
+        use crate::codemap::{extract_codemap, ExtractOptions};
+
+        let codemaps = vec![extract_codemap(
+            Path::new("src/lib.rs"),
+            "pub fn before() {}\n\npub fn broken(x: i32 {\n    x\n}\n",
+            Language::Rust,
+            &ExtractOptions::default(),
+        )];
+        let render = |format| {
+            let options = OutputOptions {
+                format,
+                ..OutputOptions::codemap_only()
+            };
+            format_output(None, &codemaps, &[], &options, Encoding::default())
+        };
+
+        assert!(render(OutputFormat::Xml).contains(
+            "**Parse errors** (declarations there may be missing):\n- line 3:21: missing `)`\n"
+        ));
+        assert!(render(OutputFormat::Compact).contains("  parse error: line 3:21: missing `)`\n"));
+
+        let json: serde_json::Value = serde_json::from_str(&render(OutputFormat::Json)).unwrap();
+        let error = &json["codemaps"][0]["parse_errors"][0];
+        assert_eq!(error["line"], 3);
+        assert_eq!(error["column"], 21);
+        assert_eq!(error["message"], "missing `)`");
+    }
+
+    #[test]
+    fn test_unparsable_file_error_is_located() {
+        use crate::codemap::{extract_codemap, ExtractOptions};
+
+        // Invalid notebook JSON fails the whole file, at the spot serde stopped.
+        let codemaps = vec![extract_codemap(
+            Path::new("train.ipynb"),
+            "{\n  \"cells\": [}\n",
+            Language::Python,
+            &ExtractOptions::default(),
+        )];
+        let render = |format| {
+            let options = OutputOptions {
+                format,
+                ..OutputOptions::codemap_only()
+            };
+            format_output(None, &codemaps, &[], &options, Encoding::default())
+        };
+
+        assert!(render(OutputFormat::Xml).contains("- line 2:13: invalid notebook: "));
+        assert!(
+            render(OutputFormat::Compact).contains("  parse error: line 2:13: invalid notebook: ")
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&render(OutputFormat::Json)).unwrap();
+        let codemap = &json["codemaps"][0];
+        assert!(codemap.get("parse_error").is_none());
+        assert_eq!(codemap["parse_errors"][0]["line"], 2);
+        assert_eq!(codemap["parse_errors"][0]["end_line"], 2);
+        assert_eq!(codemap["parse_errors"][0]["column"], 13);
+        assert!(codemap["parse_errors"][0].get("cell").is_none());
+    }
+
+    #[test]
+    fn test_architecture_section() {
+        let codemaps = vec![
+            Codemap::empty(PathBuf::from("src/main.rs"), Language::Rust),
+            Codemap::empty(PathBuf::from("src/tree.rs"), Language::Rust),
+        ];
+        let options = OutputOptions {
+            include_architecture: true,
+            ..OutputOptions::full_context()
+        };
+        let rendered = render_context(None, &codemaps, &[], &options, Encoding::Cl100kBase);
+        assert!(rendered
+            .to_string()
+            .starts_with("<architecture>\nModules:\n- main (1 file, 0 declarations)\n"));
+        assert!(rendered.summary.architecture_tokens > 0);
+        assert!(rendered.summary_section.contains("- Architecture: "));
+
+        let json = format_output(
+            None,
+            &codemaps,
+            &[],
+            &OutputOptions {
+                format: OutputFormat::Json,
+                ..options
+            },
+            Encoding::Cl100kBase,
+        );
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["architecture"]["entry_points"][0], "src/main.rs");
+
+        let rendered = render_context(
+            None,
+            &codemaps,
+            &[],
+            &OutputOptions::full_context(),
+            Encoding::Cl100kBase,
+        );
+        assert!(rendered.architecture_section.is_empty());
+    }
+
+    #[test]
+    fn test_selected_file_minify() {
+        let file = SelectedFile {
+            path: PathBuf::from("lib.rs"),
+            language: Some(Language::Rust),
+            content: "fn a() {}   \n\n\n\n\nfn b() {}\n\n".into(),
+            lines: 7,
+            tokens: 12,
+        };
+        let options = OutputOptions {
+            minify: Minify::Whitespace,
+            line_numbers: true,
+            ..OutputOptions::full_context()
+        };
+        assert_eq!(
+            format_selected_block(&file, &options),
+            "--- lib.rs (7 lines, 12 tokens, minified) ---\n```rust\n1 | fn a() {}\n2 | \n6 | fn b() {}\n```\n\n"
+        );
+
+        let rendered = render_context(
+            None,
+            &[],
+            std::slice::from_ref(&file),
+            &options,
+            Encoding::Cl100kBase,
+        );
+        let saved = rendered.summary.minify_saved();
+        assert!(saved > 0);
+        assert!(rendered
+            .summary_section
+            .contains(&format!("Saved by minification: {} tokens\n", saved)));
+
+        let rendered = render_context(
+            None,
+            &[],
+            &[file],
+            &OutputOptions::full_context(),
+            Encoding::Cl100kBase,
+        );
+        assert_eq!(rendered.summary.minify_saved(), 0);
+        assert!(!rendered.summary_section.contains("minification"));
+    }
+
+    #[test]
+    fn test_xml_attribute_escaping() {
+        assert_eq!(xml_attribute("a\"<b>&\n"), "a&quot;&lt;b&gt;&amp;&#10;");
+        assert_eq!(xml_cdata("x]]>y"), "<![CDATA[x]]]]><![CDATA[>y]]>");
+    }
 }
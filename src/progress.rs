@@ -0,0 +1,222 @@
+//! Machine-readable progress events.
+//!
+//! A [`Progress`] writes newline-delimited JSON to a sink, one event per
+//! line, so wrappers and editor plugins can draw progress bars around long
+//! runs:
+//!
+//! ```text
+//! {"phase":"walk","done":812,"elapsed_ms":40}
+//! {"phase":"extract","done":120,"total":812,"elapsed_ms":431}
+//! {"phase":"done","done":812,"total":812,"elapsed_ms":2310}
+//! ```
+//!
+//! Updates within a phase are throttled; the first and last event of each
+//! phase are always written.
+
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+/// Minimum time between two throttled updates.
+const UPDATE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Stage of a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    /// Listing files. The total is not known yet.
+    Walk,
+    /// Reading, filtering and parsing files.
+    Extract,
+    /// Formatting output.
+    Render,
+    /// Finished.
+    Done,
+}
+
+/// One progress event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProgressEvent {
+    pub phase: Phase,
+    /// Files handled so far in this phase.
+    pub done: usize,
+    /// Files in this phase, once known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<usize>,
+    /// Milliseconds since the run started.
+    pub elapsed_ms: u64,
+}
+
+struct Sink {
+    writer: Box<dyn Write + Send>,
+    last: Option<(Phase, Instant)>,
+    /// `done` and `total` of the last event written.
+    counts: (usize, Option<usize>),
+}
+
+/// Progress reporter, shareable across threads.
+///
+/// # Examples
+///
+/// ```
+/// use pith::progress::{Phase, Progress};
+///
+/// let progress = Progress::ndjson(std::io::stderr());
+/// progress.update(Phase::Extract, 1, Some(10));
+/// progress.finish(Phase::Extract, 10, Some(10));
+/// ```
+pub struct Progress {
+    start: Instant,
+    sink: Option<Mutex<Sink>>,
+}
+
+impl Progress {
+    /// A reporter that writes nothing.
+    pub fn disabled() -> Self {
+        Self {
+            start: Instant::now(),
+            sink: None,
+        }
+    }
+
+    /// A reporter that writes NDJSON events to `writer`.
+    pub fn ndjson(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            start: Instant::now(),
+            sink: Some(Mutex::new(Sink {
+                writer: Box::new(writer),
+                last: None,
+                counts: (0, None),
+            })),
+        }
+    }
+
+    /// Check if events are written anywhere.
+    pub fn is_enabled(&self) -> bool {
+        self.sink.is_some()
+    }
+
+    /// Report progress within `phase`. Skipped if the last event was for the
+    /// same phase less than 100ms ago.
+    pub fn update(&self, phase: Phase, done: usize, total: Option<usize>) {
+        self.emit(phase, done, total, false);
+    }
+
+    /// Report the end of `phase`. Never skipped.
+    pub fn finish(&self, phase: Phase, done: usize, total: Option<usize>) {
+        self.emit(phase, done, total, true);
+    }
+
+    /// Report the end of the run, repeating the last phase's counts.
+    pub fn complete(&self) {
+        let counts = self
+            .sink
+            .as_ref()
+            .and_then(|sink| sink.lock().ok().map(|sink| sink.counts));
+        if let Some((done, total)) = counts {
+            self.finish(Phase::Done, done, total);
+        }
+    }
+
+    fn emit(&self, phase: Phase, done: usize, total: Option<usize>, force: bool) {
+        let Some(sink) = &self.sink else {
+            return;
+        };
+        let Ok(mut sink) = sink.lock() else {
+            return;
+        };
+
+        let now = Instant::now();
+        let throttled = matches!(
+            sink.last,
+            Some((last_phase, at)) if last_phase == phase && now - at < UPDATE_INTERVAL
+        );
+        if throttled && !force {
+            return;
+        }
+        sink.last = Some((phase, now));
+        sink.counts = (done, total);
+
+        let event = ProgressEvent {
+            phase,
+            done,
+            total,
+            elapsed_ms: u64::try_from((now - self.start).as_millis()).unwrap_or(u64::MAX),
+        };
+        if let Ok(line) = serde_json::to_string(&event) {
+            // Progress is best effort; a closed pipe must not fail the run.
+            let _ = writeln!(sink.writer, "{}", line);
+            let _ = sink.writer.flush();
+        }
+    }
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Self::disabled()
+    }
+}
+
+impl std::fmt::Debug for Progress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Progress")
+            .field("enabled", &self.is_enabled())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    /// A writer whose bytes stay readable after it is boxed.
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_ndjson_events_are_throttled_within_a_phase() {
+        let buffer = Shared::default();
+        let progress = Progress::ndjson(buffer.clone());
+
+        progress.update(Phase::Walk, 1, None);
+        progress.update(Phase::Walk, 2, None);
+        progress.finish(Phase::Walk, 3, Some(3));
+        progress.update(Phase::Extract, 1, Some(3));
+        progress.complete();
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let events: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[0]["phase"], "walk");
+        assert!(events[0].get("total").is_none());
+        assert_eq!(events[1]["done"], 3);
+        assert_eq!(events[1]["total"], 3);
+        assert_eq!(events[2]["phase"], "extract");
+        assert_eq!(events[3]["phase"], "done");
+        assert_eq!(events[3]["done"], 1);
+    }
+
+    #[test]
+    fn test_disabled_progress() {
+        let progress = Progress::disabled();
+        assert!(!progress.is_enabled());
+        progress.finish(Phase::Done, 0, None);
+    }
+}
@@ -0,0 +1,195 @@
+//! Embedding-ready declaration records.
+//!
+//! Vector databases and RAG pipelines ingest one chunk per item. [`records`]
+//! flattens codemaps into one [`Record`] per declaration, methods and class
+//! members included, each carrying the text worth embedding (signature, doc
+//! and snippet) along with the metadata needed to point back at the source.
+
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::codemap::{Codemap, Declaration, DeclarationIds};
+use crate::filter::Language;
+use crate::output::declaration_line;
+use crate::tokens::{Encoding, TokenCounter};
+
+/// One declaration, shaped for ingestion.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Record {
+    /// Stable ID, `path#container::name@kind`, unique within the file (see
+    /// [`DeclarationIds`]).
+    pub id: String,
+    /// Path relative to the extraction root.
+    pub path: String,
+    pub language: Language,
+    /// Kind as in JSON output ("function", "struct", ...).
+    pub kind: &'static str,
+    pub name: String,
+    /// Type or class a method or member belongs to.
+    pub container: Option<String>,
+    /// One-line signature, as in compact output.
+    pub signature: String,
+    pub doc: Option<String>,
+    pub snippet: Option<String>,
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Tokens in [`Record::text`].
+    pub tokens: usize,
+}
+
+impl Record {
+    /// Text to embed: the signature, then the doc and snippet if present,
+    /// separated by blank lines.
+    pub fn text(&self) -> String {
+        let mut text = self.signature.clone();
+        for part in [&self.doc, &self.snippet].into_iter().flatten() {
+            text.push_str("\n\n");
+            text.push_str(part);
+        }
+        text
+    }
+}
+
+/// Records for every declaration in `codemaps`, in file and source order.
+/// Paths are made relative to `root`; token counts use `encoding`.
+///
+/// # Examples
+///
+/// ```
+/// use std::path::Path;
+/// use pith::codemap::{extract_codemap, ExtractOptions};
+/// use pith::filter::Language;
+/// use pith::records::records;
+/// use pith::tokens::Encoding;
+///
+/// let codemap = extract_codemap(
+///     Path::new("project/src/lib.rs"),
+///     "/// Add two numbers.\npub fn add(a: i32, b: i32) -> i32 { a + b }\n",
+///     Language::Rust,
+///     &ExtractOptions::with_docs(),
+/// );
+/// let records = records(&[codemap], Path::new("project"), Encoding::default());
+/// assert_eq!(records[0].id, "src/lib.rs#add@function");
+/// assert_eq!(records[0].doc.as_deref(), Some("Add two numbers."));
+/// assert!(records[0].text().starts_with("pub fn add"));
+/// ```
+pub fn records(codemaps: &[Codemap], root: &Path, encoding: Encoding) -> Vec<Record> {
+    let counter = TokenCounter::new(encoding);
+    let mut records = Vec::new();
+    for codemap in codemaps {
+        let ids = DeclarationIds::new(codemap, root);
+        for decl in &codemap.declarations {
+            push_records(&mut records, decl, None, &ids, codemap.language, &counter);
+        }
+    }
+    records
+}
+
+/// One record per line (NDJSON).
+pub fn to_jsonl(records: &[Record]) -> Result<String, serde_json::Error> {
+    let mut out = String::new();
+    for record in records {
+        out.push_str(&serde_json::to_string(record)?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+fn push_records(
+    records: &mut Vec<Record>,
+    decl: &Declaration,
+    container: Option<&str>,
+    ids: &DeclarationIds,
+    language: Language,
+    counter: &TokenCounter,
+) {
+    let name = decl.name();
+    let location = decl.location();
+    let mut record = Record {
+        id: ids.id(decl, container),
+        path: ids.path().display().to_string(),
+        language,
+        kind: decl.kind(),
+        name: name.to_string(),
+        container: container.map(str::to_string),
+        signature: declaration_line(decl, language),
+        doc: decl.doc().map(str::to_string),
+        snippet: decl.snippet().map(str::to_string),
+        start_line: location.start_line,
+        end_line: location.end_line,
+        tokens: 0,
+    };
+    record.tokens = counter.count(&record.text());
+    records.push(record);
+
+    for child in decl.nested() {
+        push_records(records, child, Some(name), ids, language, counter);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codemap::{extract_codemap, ExtractOptions};
+
+    #[test]
+    fn test_records() {
+        let options = ExtractOptions {
+            include_snippet: Some(1),
+            ..ExtractOptions::with_docs()
+        };
+        let codemap = extract_codemap(
+            Path::new("root/app/store.py"),
+            "class Store:\n    \"\"\"Holds items.\"\"\"\n\n    def add(self, item: str) -> None:\n        \"\"\"Add one.\"\"\"\n",
+            Language::Python,
+            &options,
+        );
+        let records = records(&[codemap], Path::new("root"), Encoding::default());
+
+        assert_eq!(records.len(), 2);
+        let class = &records[0];
+        assert_eq!(class.id, "app/store.py#Store@class");
+        assert_eq!((class.kind, class.container.as_deref()), ("class", None));
+        assert_eq!(class.doc.as_deref(), Some("Holds items."));
+
+        let method = &records[1];
+        assert_eq!(method.id, "app/store.py#Store::add@function");
+        assert_eq!(method.container.as_deref(), Some("Store"));
+        assert_eq!(method.signature, "def add(self, item: str) -> None");
+        assert_eq!(
+            method.text(),
+            "def add(self, item: str) -> None\n\nAdd one.\n\ndef add(self, item: str) -> None:\n..."
+        );
+        assert_eq!(method.tokens, TokenCounter::default().count(&method.text()));
+
+        let jsonl = to_jsonl(&records).unwrap();
+        assert_eq!(jsonl.lines().count(), 2);
+        let first: serde_json::Value = serde_json::from_str(jsonl.lines().next().unwrap()).unwrap();
+        assert_eq!(first["path"], "app/store.py");
+        assert_eq!(first["snippet"], "class Store:\n...");
+    }
+
+    #[test]
+    fn test_record_ids_are_unique() {
+        let codemap = extract_codemap(
+            Path::new("root/cmd/main.go"),
+            "package main\n\nfunc Init() {}\n\nfunc init() {}\n\nfunc init() {}\n",
+            Language::Go,
+            &ExtractOptions {
+                visibility: crate::codemap::VisibilityThreshold::All,
+                ..ExtractOptions::default()
+            },
+        );
+        let records = records(&[codemap], Path::new("root"), Encoding::default());
+        let ids: Vec<&str> = records.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            [
+                "cmd/main.go#Init@function",
+                "cmd/main.go#init@function",
+                "cmd/main.go#init@function:2"
+            ]
+        );
+    }
+}
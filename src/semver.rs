@@ -0,0 +1,796 @@
+//! Public API changes between two snapshots of a Rust codebase.
+//!
+//! Both snapshots are extracted into codemaps; every `pub` item (functions,
+//! types, fields, variants, methods, trait items, trait impls and
+//! re-exports) is keyed by its crate and its module path: the module its
+//! file declares (`src/config.rs` is `crate::config`) followed by its path
+//! within the file. Items are then compared by signature and each
+//! difference is classified by its semver impact, the way a changelog would:
+//! removals and signature changes break callers, new items are additive, and
+//! doc changes are patch-level.
+//!
+//! Only `pub` visibility is considered, so a `pub` item in a private module
+//! counts as public. Re-exports aren't followed: an item moved to another
+//! file of the same module (or out of an inline `mod` into its own file)
+//! still matches, but one moved to another module shows up as removed from
+//! one and added to the other.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::codemap::{Codemap, Declaration, StructKind, Visibility};
+use crate::filter::Language;
+
+/// Semver impact of a change, ordered from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// Documentation only; a patch release.
+    #[default]
+    Patch,
+    /// New public items; a minor release.
+    Additive,
+    /// Removed or changed items that can break dependents; a major release.
+    Breaking,
+}
+
+impl ChangeKind {
+    /// The version component to bump: "patch", "minor" or "major".
+    pub fn bump(self) -> &'static str {
+        match self {
+            ChangeKind::Patch => "patch",
+            ChangeKind::Additive => "minor",
+            ChangeKind::Breaking => "major",
+        }
+    }
+}
+
+impl fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChangeKind::Patch => write!(f, "patch"),
+            ChangeKind::Additive => write!(f, "additive"),
+            ChangeKind::Breaking => write!(f, "breaking"),
+        }
+    }
+}
+
+/// One changed public item.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ApiChange {
+    pub kind: ChangeKind,
+    /// File holding the item, relative to the snapshot root: the new file
+    /// unless the item was removed.
+    pub path: PathBuf,
+    /// Item path within the file: `load`, `Config::load` (method),
+    /// `Config.timeout` (field), `Mode::Fast` (variant or trait item),
+    /// `impl Clone for Config`, `use crate::config::Config`.
+    pub item: String,
+    /// What happened: "removed", "added", "signature changed", ...
+    pub change: String,
+    /// Signature in the old snapshot.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old: Option<String>,
+    /// Signature in the new snapshot.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new: Option<String>,
+}
+
+/// Classified public API changes between two snapshots.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ApiDiff {
+    /// The most severe change, or `Patch` when nothing changed.
+    pub bump: ChangeKind,
+    /// Changes, most severe first, then by path and item.
+    pub changes: Vec<ApiChange>,
+}
+
+impl ApiDiff {
+    /// Compare the public Rust API in `old` (codemaps under `old_root`)
+    /// against `new` (under `new_root`). Codemaps should be extracted with
+    /// private items and, to report doc changes, doc comments.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::path::Path;
+    /// use pith::codemap::{extract_codemap, ExtractOptions};
+    /// use pith::filter::Language;
+    /// use pith::semver::{ApiDiff, ChangeKind};
+    ///
+    /// let options = ExtractOptions::with_docs();
+    /// let old = extract_codemap(
+    ///     Path::new("v1/src/lib.rs"),
+    ///     "pub fn load(path: &str) {}\n",
+    ///     Language::Rust,
+    ///     &options,
+    /// );
+    /// let new = extract_codemap(
+    ///     Path::new("v2/src/lib.rs"),
+    ///     "pub fn load(path: &str, strict: bool) {}\npub fn save() {}\n",
+    ///     Language::Rust,
+    ///     &options,
+    /// );
+    ///
+    /// let diff = ApiDiff::between(&[old], Path::new("v1"), &[new], Path::new("v2"));
+    /// assert_eq!(diff.bump, ChangeKind::Breaking);
+    /// assert_eq!(diff.changes[0].item, "load");
+    /// assert_eq!(diff.changes[0].change, "signature changed");
+    /// assert_eq!(diff.changes[1].kind, ChangeKind::Additive);
+    /// ```
+    pub fn between(old: &[Codemap], old_root: &Path, new: &[Codemap], new_root: &Path) -> Self {
+        let old_items = public_items(old, old_root);
+        let new_items = public_items(new, new_root);
+
+        let mut changes = Vec::new();
+        for (key, before) in &old_items {
+            let change = |kind, change: &str, new: Option<&Located>| ApiChange {
+                kind,
+                path: new.map_or(&before.file, |after| &after.file).clone(),
+                item: before.name.clone(),
+                change: change.to_string(),
+                old: Some(before.item.signature.clone()),
+                new: new.map(|after| after.item.signature.clone()),
+            };
+            match new_items.get(key) {
+                None => changes.push(change(ChangeKind::Breaking, "removed", None)),
+                Some(after)
+                    if signature_tokens(&after.item.signature)
+                        != signature_tokens(&before.item.signature) =>
+                {
+                    changes.push(change(
+                        ChangeKind::Breaking,
+                        "signature changed",
+                        Some(after),
+                    ));
+                }
+                Some(after) if after.item.doc != before.item.doc => {
+                    let mut change = change(ChangeKind::Patch, "doc changed", Some(after));
+                    change.old = None;
+                    change.new = None;
+                    changes.push(change);
+                }
+                Some(_) => {}
+            }
+        }
+        for ((krate, item), after) in &new_items {
+            if old_items.contains_key(&(krate.clone(), item.clone())) {
+                continue;
+            }
+            // Additions to an existing exhaustive parent break code that
+            // constructs, matches on or implements it.
+            let breaking =
+                after.item.addition_breaks.filter(|_| {
+                    after.item.parent.as_ref().is_some_and(|parent| {
+                        old_items.contains_key(&(krate.clone(), parent.clone()))
+                    })
+                });
+            changes.push(ApiChange {
+                kind: breaking.map_or(ChangeKind::Additive, |_| ChangeKind::Breaking),
+                path: after.file.clone(),
+                item: after.name.clone(),
+                change: breaking.unwrap_or("added").to_string(),
+                old: None,
+                new: Some(after.item.signature.clone()),
+            });
+        }
+
+        changes.sort_by(|a, b| {
+            b.kind
+                .cmp(&a.kind)
+                .then_with(|| a.path.cmp(&b.path))
+                .then_with(|| a.item.cmp(&b.item))
+        });
+        let bump = changes.iter().map(|c| c.kind).max().unwrap_or_default();
+        Self { bump, changes }
+    }
+
+    /// Check if the public API is unchanged.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Number of changes of `kind`.
+    pub fn count(&self, kind: ChangeKind) -> usize {
+        self.changes.iter().filter(|c| c.kind == kind).count()
+    }
+}
+
+impl fmt::Display for ApiDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No public API changes.");
+        }
+        writeln!(
+            f,
+            "Suggested bump: {} ({} breaking, {} additive, {} patch)",
+            self.bump.bump(),
+            self.count(ChangeKind::Breaking),
+            self.count(ChangeKind::Additive),
+            self.count(ChangeKind::Patch)
+        )?;
+
+        let mut heading = None;
+        for change in &self.changes {
+            if heading != Some(change.kind) {
+                heading = Some(change.kind);
+                let title = match change.kind {
+                    ChangeKind::Breaking => "Breaking",
+                    ChangeKind::Additive => "Additive",
+                    ChangeKind::Patch => "Patch",
+                };
+                writeln!(f, "\n{}:", title)?;
+            }
+            write!(
+                f,
+                "- {} `{}`: {}",
+                change.path.display(),
+                change.item,
+                change.change
+            )?;
+            match (&change.old, &change.new) {
+                (Some(old), Some(new)) => {
+                    writeln!(f)?;
+                    writeln!(f, "  - old: `{}`", old)?;
+                    writeln!(f, "  - new: `{}`", new)?;
+                }
+                // Impls and re-exports are named by their signature.
+                (Some(signature), None) | (None, Some(signature)) if *signature != change.item => {
+                    writeln!(f, " (`{}`)", signature)?;
+                }
+                _ => writeln!(f)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A public item as compared between snapshots.
+#[derive(Debug)]
+struct Item<'a> {
+    /// Normalized signature; only differences in its tokens are breaking.
+    signature: String,
+    doc: Option<&'a str>,
+    /// Module path of the struct, enum or trait holding this item.
+    parent: Option<String>,
+    /// Why adding this item to an existing parent breaks dependents, if it
+    /// does.
+    addition_breaks: Option<&'static str>,
+}
+
+/// An item and where it was found.
+#[derive(Debug)]
+struct Located<'a> {
+    /// File holding the item, relative to the snapshot root.
+    file: PathBuf,
+    /// Item path within the file.
+    name: String,
+    item: Item<'a>,
+}
+
+/// Items keyed by crate directory and module path.
+type Items<'a> = BTreeMap<(PathBuf, String), Located<'a>>;
+
+fn public_items<'a>(codemaps: &'a [Codemap], root: &Path) -> Items<'a> {
+    let mut items = Items::new();
+    for codemap in codemaps.iter().filter(|c| c.language == Language::Rust) {
+        let path = codemap
+            .path
+            .strip_prefix(root)
+            .unwrap_or(&codemap.path)
+            .to_path_buf();
+        let (krate, module) = module_path(&path);
+        let qualify = |name: &str| format!("{}::{}", module, name);
+        let mut insert = |name: String, mut item: Item<'a>| {
+            item.parent = item.parent.as_deref().map(qualify);
+            items
+                .entry((krate.clone(), qualify(&name)))
+                .or_insert(Located {
+                    file: path.clone(),
+                    name,
+                    item,
+                });
+        };
+        for decl in &codemap.declarations {
+            collect_declaration(decl, &mut insert);
+        }
+    }
+    items
+}
+
+/// Crate directory and module path of a Rust file relative to the snapshot
+/// root: `src/lib.rs` is `crate`, `src/config.rs` and `src/config/mod.rs`
+/// are `crate::config`, and `crates/io/src/fs.rs` is `crate::fs` in
+/// `crates/io`.
+fn module_path(file: &Path) -> (PathBuf, String) {
+    let parts: Vec<String> = file
+        .with_extension("")
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    let (krate, mut modules) = match parts.iter().position(|p| p == "src") {
+        Some(src) => (parts[..src].iter().collect(), &parts[src + 1..]),
+        None => (PathBuf::new(), &parts[..]),
+    };
+    if modules
+        .last()
+        .is_some_and(|file| file == "lib" || file == "mod")
+    {
+        modules = &modules[..modules.len() - 1];
+    }
+    let module = std::iter::once("crate")
+        .chain(modules.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join("::");
+    (krate, module)
+}
+
+fn collect_declaration<'a>(decl: &'a Declaration, insert: &mut impl FnMut(String, Item<'a>)) {
+    if decl.visibility() != Visibility::Public {
+        return;
+    }
+    let top = |signature: String, doc: &'a Option<String>| Item {
+        signature,
+        doc: doc.as_deref(),
+        parent: None,
+        addition_breaks: None,
+    };
+    let member = |parent: &str, signature: String, doc: Option<&'a str>, breaks| Item {
+        signature,
+        doc,
+        parent: Some(parent.to_string()),
+        addition_breaks: breaks,
+    };
+
+    match decl {
+        Declaration::Function {
+            name,
+            signature,
+            doc,
+            ..
+        } => insert(name.clone(), top(normalize_signature(signature), doc)),
+        Declaration::Struct {
+            name,
+            generics,
+            kind,
+            fields,
+            methods,
+            implements,
+            attributes,
+            doc,
+            ..
+        } => {
+            let body = match kind {
+                StructKind::Named | StructKind::Union => " { .. }",
+                StructKind::Tuple => "(..)",
+                StructKind::Unit => ";",
+            };
+            let keyword = if *kind == StructKind::Union {
+                "union"
+            } else {
+                "struct"
+            };
+            let signature = format!("{} {}{}{}", keyword, name, generics, body);
+            insert(name.clone(), top(normalize_signature(&signature), doc));
+
+            // Struct literals need every field, so a new public field breaks
+            // them unless some field was already out of reach.
+            let exhaustive = !is_non_exhaustive(attributes)
+                && fields.iter().all(|f| f.visibility == Visibility::Public);
+            let breaks = exhaustive.then_some("added to an exhaustive struct");
+            for field in fields.iter().filter(|f| f.visibility == Visibility::Public) {
+                let signature = normalize_signature(&format!("{}: {}", field.name, field.ty));
+                insert(
+                    format!("{}.{}", name, field.name),
+                    member(name, signature, field.doc.as_deref(), breaks),
+                );
+            }
+            insert_methods(name, methods, insert);
+            insert_impls(name, implements, attributes, insert);
+        }
+        Declaration::Enum {
+            name,
+            generics,
+            variants,
+            methods,
+            implements,
+            attributes,
+            doc,
+            ..
+        } => {
+            let signature = format!("enum {}{}", name, generics);
+            insert(name.clone(), top(normalize_signature(&signature), doc));
+            let breaks = (!is_non_exhaustive(attributes)).then_some("added to an exhaustive enum");
+            for variant in variants {
+                let variant_name = variant
+                    .name
+                    .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .next()
+                    .unwrap_or(&variant.name);
+                insert(
+                    format!("{}::{}", name, variant_name),
+                    member(
+                        name,
+                        normalize_signature(&variant.name),
+                        variant.doc.as_deref(),
+                        breaks,
+                    ),
+                );
+            }
+            insert_methods(name, methods, insert);
+            insert_impls(name, implements, attributes, insert);
+        }
+        Declaration::Trait {
+            name,
+            generics,
+            methods,
+            doc,
+            ..
+        } => {
+            let signature = format!("trait {}{}", name, generics);
+            insert(name.clone(), top(normalize_signature(&signature), doc));
+            for item in methods {
+                insert(
+                    format!("{}::{}", name, trait_item_name(item)),
+                    member(
+                        name,
+                        normalize_signature(item),
+                        None,
+                        Some("added to a trait (breaks implementors without a default)"),
+                    ),
+                );
+            }
+        }
+        Declaration::TypeAlias {
+            name,
+            generics,
+            target,
+            ..
+        } => {
+            let signature = format!("type {}{} = {}", name, generics, target);
+            insert(name.clone(), top(normalize_signature(&signature), &None));
+        }
+        Declaration::Const {
+            name,
+            ty,
+            is_static,
+            ..
+        } => {
+            let keyword = if *is_static { "static" } else { "const" };
+            let signature = format!("{} {}: {}", keyword, name, ty);
+            insert(name.clone(), top(normalize_signature(&signature), &None));
+        }
+        Declaration::ReExport { name, items, .. } => {
+            let paths: Vec<String> = if items.is_empty() {
+                vec![format!("{}::*", name)]
+            } else {
+                items
+                    .iter()
+                    .map(|item| format!("{}::{}", name, item))
+                    .collect()
+            };
+            for path in paths {
+                let signature = format!("use {}", path);
+                insert(signature.clone(), top(signature, &None));
+            }
+        }
+        Declaration::Interface { .. } | Declaration::Class { .. } => {}
+    }
+}
+
+/// Record each public inherent method of `name` as an item.
+fn insert_methods<'a>(
+    name: &str,
+    methods: &'a [Declaration],
+    insert: &mut impl FnMut(String, Item<'a>),
+) {
+    for method in methods {
+        if let Declaration::Function {
+            name: method_name,
+            signature,
+            visibility: Visibility::Public,
+            doc,
+            ..
+        } = method
+        {
+            insert(
+                format!("{}::{}", name, method_name),
+                Item {
+                    signature: normalize_signature(signature),
+                    doc: doc.as_deref(),
+                    parent: Some(name.to_string()),
+                    addition_breaks: None,
+                },
+            );
+        }
+    }
+}
+
+/// Record each implemented or derived trait of `name` as an item.
+fn insert_impls<'a>(
+    name: &str,
+    implements: &[String],
+    attributes: &[String],
+    insert: &mut impl FnMut(String, Item<'a>),
+) {
+    let derived = attributes
+        .iter()
+        .filter_map(|attr| attr.strip_prefix("#[derive(")?.strip_suffix(")]"))
+        .flat_map(|list| list.split(','))
+        .map(str::trim)
+        .filter(|t| !t.is_empty());
+    for trait_name in implements.iter().map(String::as_str).chain(derived) {
+        let signature = format!("impl {} for {}", trait_name, name);
+        insert(
+            signature.clone(),
+            Item {
+                signature,
+                doc: None,
+                parent: Some(name.to_string()),
+                addition_breaks: None,
+            },
+        );
+    }
+}
+
+fn is_non_exhaustive(attributes: &[String]) -> bool {
+    attributes.iter().any(|attr| attr == "#[non_exhaustive]")
+}
+
+/// Name of a trait item: the identifier after `fn`, `type` or `const`.
+fn trait_item_name(item: &str) -> &str {
+    let mut words = item.split_whitespace();
+    while let Some(word) = words.next() {
+        if matches!(word, "fn" | "type" | "const") {
+            let name = words.next().unwrap_or_default();
+            return name
+                .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .next()
+                .unwrap_or(name);
+        }
+    }
+    item
+}
+
+/// Tokens of a signature, for comparison: whitespace only separates
+/// adjacent words (`&mut self`, `&'a str`), and a trailing comma before a
+/// closing bracket is dropped, so `a:u32` and `a: u32` compare equal.
+fn signature_tokens(signature: &str) -> String {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '\'';
+    let mut tokens = String::with_capacity(signature.len());
+    for word in signature.split_whitespace() {
+        if tokens.ends_with(is_word) && word.starts_with(is_word) {
+            tokens.push(' ');
+        }
+        tokens.push_str(word);
+    }
+    let mut out = String::with_capacity(tokens.len());
+    let mut chars = tokens.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ',' && matches!(chars.peek(), Some(')' | ']' | '>' | '}')) {
+            continue;
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Collapse whitespace and drop spaces inside brackets, before commas and
+/// before a trailing comma's closing bracket, so reformatting a signature
+/// doesn't count as changing it.
+fn normalize_signature(signature: &str) -> String {
+    let collapsed = signature.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut out = String::with_capacity(collapsed.len());
+    let mut chars = collapsed.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' if matches!(chars.peek(), Some(')' | ']' | '>' | ',')) => {}
+            ' ' if out.ends_with(['(', '[', '<']) => {}
+            // `fn name (` as some signatures are extracted.
+            ' ' if chars.peek() == Some(&'(')
+                && out.ends_with(|c: char| c.is_alphanumeric() || c == '_' || c == '>') => {}
+            ',' => {
+                if chars.peek() == Some(&' ') {
+                    chars.next();
+                }
+                if !matches!(chars.peek(), Some(')' | ']' | '>') | None) {
+                    out.push_str(", ");
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codemap::{extract_codemap, ExtractOptions};
+
+    fn diff(old: &str, new: &str) -> ApiDiff {
+        let options = ExtractOptions::with_docs();
+        let old = extract_codemap(Path::new("a/lib.rs"), old, Language::Rust, &options);
+        let new = extract_codemap(Path::new("b/lib.rs"), new, Language::Rust, &options);
+        ApiDiff::between(&[old], Path::new("a"), &[new], Path::new("b"))
+    }
+
+    fn summary(diff: &ApiDiff) -> Vec<String> {
+        diff.changes
+            .iter()
+            .map(|c| format!("{} {}: {}", c.kind, c.item, c.change))
+            .collect()
+    }
+
+    #[test]
+    fn test_classifies_changes() {
+        let old = r"
+/// A config.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub name: String,
+    pub retries: u32,
+}
+
+impl Config {
+    pub fn load(path: &str) -> Config { todo!() }
+    fn helper() {}
+}
+
+#[non_exhaustive]
+pub enum Mode { Fast, Slow }
+
+pub trait Store {
+    fn get(&self, key: &str) -> Option<String>;
+}
+
+pub const LIMIT: usize = 10;
+pub use crate::util::{parse, render};
+fn private() {}
+";
+        let new = r"
+/// A configuration.
+#[derive(Debug)]
+pub struct Config {
+    pub name: String,
+    pub retries: u32,
+    pub verbose: bool,
+}
+
+impl Config {
+    pub fn load(
+        path: &str,
+    ) -> Config { todo!() }
+    pub fn reset(&mut self) {}
+}
+
+#[non_exhaustive]
+pub enum Mode { Fast, Slow, Auto }
+
+pub trait Store {
+    fn get(&self, key: &str) -> Option<String>;
+    fn put(&mut self, key: &str, value: String);
+}
+
+pub const LIMIT: u64 = 10;
+pub use crate::util::parse;
+pub fn fresh() {}
+fn private(x: u8) {}
+";
+        let diff = diff(old, new);
+        assert_eq!(
+            summary(&diff),
+            [
+                "breaking Config.verbose: added to an exhaustive struct",
+                "breaking LIMIT: signature changed",
+                "breaking Store::put: added to a trait (breaks implementors without a default)",
+                "breaking impl Clone for Config: removed",
+                "breaking use crate::util::render: removed",
+                "additive Config::reset: added",
+                "additive Mode::Auto: added",
+                "additive fresh: added",
+                "patch Config: doc changed",
+            ]
+        );
+        assert_eq!(diff.bump, ChangeKind::Breaking);
+        assert_eq!(diff.changes[1].old.as_deref(), Some("const LIMIT: usize"));
+        assert_eq!(diff.changes[1].new.as_deref(), Some("const LIMIT: u64"));
+
+        let rendered = diff.to_string();
+        assert!(rendered.starts_with("Suggested bump: major (5 breaking, 3 additive, 1 patch)\n"));
+        assert!(rendered.contains(
+            "\nAdditive:\n- lib.rs `Config::reset`: added (`pub fn reset(&mut self)`)\n"
+        ));
+        assert!(rendered.contains("  - old: `const LIMIT: usize`\n  - new: `const LIMIT: u64`\n"));
+    }
+
+    #[test]
+    fn test_unchanged_and_additive_only() {
+        let code = "pub struct A { pub x: u8, y: u8 }\n";
+        let unchanged = diff(code, code);
+        assert!(unchanged.is_empty());
+        assert_eq!(unchanged.bump, ChangeKind::Patch);
+        assert_eq!(unchanged.to_string(), "No public API changes.\n");
+
+        // A private field already rules out struct literals.
+        let additive = diff(code, "pub struct A { pub x: u8, pub z: u8, y: u8 }\n");
+        assert_eq!(summary(&additive), ["additive A.z: added"]);
+        assert_eq!(additive.bump, ChangeKind::Additive);
+    }
+
+    #[test]
+    fn test_formatting_only_changes() {
+        let old = "pub fn f(a:u32, b:&'a  str) {}\npub struct S { pub x:Vec<u8> }\n";
+        let new =
+            "pub fn f(\n    a: u32,\n    b: &'a str,\n) {}\npub struct S { pub x: Vec< u8 > }\n";
+        assert!(diff(old, new).is_empty());
+
+        // Whitespace between words still separates them.
+        let changed = diff("pub fn g(&mut self) {}\n", "pub fn g(&self) {}\n");
+        assert_eq!(summary(&changed), ["breaking g: signature changed"]);
+    }
+
+    #[test]
+    fn test_moved_items() {
+        let options = ExtractOptions::with_docs();
+        let snapshot = |root: &str, files: &[(&str, &str)]| -> Vec<Codemap> {
+            files
+                .iter()
+                .map(|(path, code)| {
+                    let path = Path::new(root).join(path);
+                    extract_codemap(&path, code, Language::Rust, &options)
+                })
+                .collect()
+        };
+        let old = snapshot(
+            "a",
+            &[
+                ("src/lib.rs", "pub mod config {\n    pub fn load() {}\n}\n"),
+                ("src/net.rs", "pub fn connect() {}\n"),
+            ],
+        );
+        // `load` moves out of the inline module into its own file, and
+        // `connect` into another module.
+        let new = snapshot(
+            "b",
+            &[
+                ("src/lib.rs", "pub mod config;\n"),
+                ("src/config.rs", "pub fn load() {}\n"),
+                ("src/io/mod.rs", "pub fn connect() {}\n"),
+            ],
+        );
+        let diff = ApiDiff::between(&old, Path::new("a"), &new, Path::new("b"));
+        assert_eq!(
+            summary(&diff),
+            ["breaking connect: removed", "additive connect: added"]
+        );
+        assert_eq!(diff.changes[0].path, Path::new("src/net.rs"));
+        assert_eq!(diff.changes[1].path, Path::new("src/io/mod.rs"));
+
+        assert_eq!(
+            module_path(Path::new("crates/io/src/fs/mod.rs")),
+            (PathBuf::from("crates/io"), "crate::fs".to_string())
+        );
+    }
+
+    #[test]
+    fn test_normalize_signature() {
+        assert_eq!(
+            normalize_signature("pub fn a(\n    x: u8,\n    y: Vec< u8 >,\n) -> u8"),
+            "pub fn a(x: u8, y: Vec<u8>) -> u8"
+        );
+        assert_eq!(
+            normalize_signature("fn b( x :u8 ,y: u8)"),
+            "fn b(x :u8, y: u8)"
+        );
+        assert_eq!(
+            normalize_signature("pub fn c<T> (x: T)"),
+            "pub fn c<T>(x: T)"
+        );
+        assert_eq!(trait_item_name("async fn get<T>(&self)"), "get");
+        assert_eq!(trait_item_name("type Item: Clone"), "Item");
+    }
+}
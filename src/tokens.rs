@@ -4,6 +4,8 @@
 //! with a fallback heuristic when tiktoken is unavailable.
 
 use std::sync::OnceLock;
+
+use serde::{Serialize, Serializer};
 use tiktoken_rs::CoreBPE;
 
 /// Token encoding to use for counting.
@@ -37,6 +39,20 @@ impl std::str::FromStr for Encoding {
     }
 }
 
+/// Serializes as the display name ("cl100k_base").
+impl Serialize for Encoding {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// A token count under one encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct EncodingTotal {
+    pub encoding: Encoding,
+    pub total: usize,
+}
+
 // Cached tokenizers - initialized once per encoding
 static CL100K: OnceLock<Option<CoreBPE>> = OnceLock::new();
 static O200K: OnceLock<Option<CoreBPE>> = OnceLock::new();
@@ -52,6 +68,28 @@ fn get_tokenizer(encoding: Encoding) -> Option<&'static CoreBPE> {
     }
 }
 
+/// Load the tokenizer for `encoding` on a background thread.
+///
+/// Building the BPE tables takes a noticeable moment on first use. Call this
+/// at startup so loading overlaps other work such as walking files; a count
+/// made before loading finishes waits for it rather than loading again.
+///
+/// # Examples
+///
+/// ```
+/// use pith::tokens::{count_tokens_with_encoding, preload, Encoding};
+///
+/// let loading = preload(Encoding::O200kBase);
+/// // ... walk files ...
+/// let count = count_tokens_with_encoding("Hello, world!", Encoding::O200kBase);
+/// loading.join().unwrap();
+/// ```
+pub fn preload(encoding: Encoding) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        get_tokenizer(encoding);
+    })
+}
+
 /// Count tokens using tiktoken.
 fn tiktoken_count(text: &str, encoding: Encoding) -> Option<usize> {
     let bpe = get_tokenizer(encoding)?;
@@ -100,6 +138,28 @@ pub fn count_tokens_with_encoding(text: &str, encoding: Encoding) -> usize {
     tiktoken_count(text, encoding).unwrap_or_else(|| fallback_count(text))
 }
 
+/// Count tokens in `text` under each of `encodings`, in order, for
+/// reporting one output's size to several model families at once.
+///
+/// # Examples
+///
+/// ```
+/// use pith::tokens::{count_tokens_multi, Encoding};
+///
+/// let totals = count_tokens_multi("Hello, world!", &[Encoding::Cl100kBase, Encoding::O200kBase]);
+/// assert_eq!(totals[1].encoding, Encoding::O200kBase);
+/// assert!(totals.iter().all(|t| t.total > 0));
+/// ```
+pub fn count_tokens_multi(text: &str, encodings: &[Encoding]) -> Vec<EncodingTotal> {
+    encodings
+        .iter()
+        .map(|&encoding| EncodingTotal {
+            encoding,
+            total: count_tokens_with_encoding(text, encoding),
+        })
+        .collect()
+}
+
 /// Reusable token counter with cached tokenizer.
 ///
 /// Use this when counting tokens for many strings to avoid
@@ -177,6 +237,12 @@ impl Default for TokenCounter {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_preload() {
+        preload(Encoding::O200kBase).join().unwrap();
+        assert!(O200K.get().is_some());
+    }
+
     #[test]
     fn test_empty_string() {
         assert_eq!(count_tokens(""), 0);
@@ -0,0 +1,712 @@
+//! Public functions as LLM tool definitions.
+//!
+//! Wrapping an existing codebase as agent tools starts with a manifest: one
+//! tool per public function, with a JSON Schema for its parameters. [`tools`]
+//! builds that skeleton from codemaps, resolving parameter types to JSON
+//! Schema where the signature makes it clear (`u64` is an integer, `&[String]`
+//! an array of strings) and keeping the written type as the description where
+//! it doesn't. [`format_tools`] renders it for OpenAI function calling or as
+//! an MCP `tools/list` result.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use serde::ser::{SerializeMap, Serializer};
+use serde::Serialize;
+
+use crate::codemap::{summarize_doc, Codemap, Declaration, DocSummary, Visibility};
+use crate::filter::Language;
+
+/// Longest tool name both OpenAI and MCP clients accept.
+const MAX_NAME_LEN: usize = 64;
+
+/// Manifest layout for [`format_tools`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ToolFormat {
+    /// `[{"type": "function", "function": {"name", "description", "parameters"}}]`.
+    #[default]
+    OpenAi,
+    /// `{"tools": [{"name", "description", "inputSchema"}]}`, as returned by
+    /// MCP `tools/list`.
+    Mcp,
+}
+
+/// JSON Schema of one parameter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParameterSchema {
+    /// `{"type": "string"}`, `{"type": "integer"}`, ...
+    Scalar(&'static str),
+    /// `{"type": "array", "items": ...}`.
+    Array(Box<ParameterSchema>),
+    /// `{"type": "object"}`, for maps and object literals.
+    Object,
+    /// A type with no JSON equivalent: `{"description": <type as written>}`,
+    /// or `{}` if the parameter has no type.
+    Unresolved(String),
+}
+
+impl Serialize for ParameterSchema {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        match self {
+            ParameterSchema::Scalar(ty) => map.serialize_entry("type", ty)?,
+            ParameterSchema::Array(items) => {
+                map.serialize_entry("type", "array")?;
+                map.serialize_entry("items", items)?;
+            }
+            ParameterSchema::Object => map.serialize_entry("type", "object")?,
+            ParameterSchema::Unresolved(ty) if ty.is_empty() => {}
+            ParameterSchema::Unresolved(ty) => map.serialize_entry("description", ty)?,
+        }
+        map.end()
+    }
+}
+
+/// A parameter of a tool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Parameter {
+    pub name: String,
+    /// Type as written, without `Option`/`Optional` wrappers; empty if untyped.
+    pub ty: String,
+    pub schema: ParameterSchema,
+    /// False for optional types, defaults and TypeScript `?` parameters.
+    pub required: bool,
+}
+
+/// A public function as a tool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tool {
+    /// Function name, with characters tools can't use replaced by `_` and a
+    /// `_2`, `_3`, ... suffix on repeats.
+    pub name: String,
+    /// First sentence of the doc comment.
+    pub description: Option<String>,
+    pub parameters: Vec<Parameter>,
+    /// File and line the function is declared at.
+    pub path: PathBuf,
+    pub line: usize,
+}
+
+/// One tool per public top-level function in `codemaps`. Descriptions come
+/// from doc comments, so extract with docs.
+///
+/// # Examples
+///
+/// ```
+/// use std::path::Path;
+/// use pith::codemap::{extract_codemap, ExtractOptions};
+/// use pith::filter::Language;
+/// use pith::tools::{format_tools, tools, ToolFormat};
+///
+/// let codemap = extract_codemap(
+///     Path::new("search.py"),
+///     "def search(query: str, limit: int = 10) -> list[str]:\n    \"\"\"Find documents.\"\"\"\n",
+///     Language::Python,
+///     &ExtractOptions::with_docs(),
+/// );
+/// let tools = tools(&[codemap]);
+/// assert_eq!(tools[0].name, "search");
+/// assert!(!tools[0].parameters[1].required);
+///
+/// let manifest = format_tools(&tools, ToolFormat::Mcp).unwrap();
+/// assert!(manifest.contains("\"inputSchema\""));
+/// assert!(manifest.contains("\"required\": [\n"));
+/// ```
+pub fn tools(codemaps: &[Codemap]) -> Vec<Tool> {
+    let mut names = HashSet::new();
+    let mut tools = Vec::new();
+    for codemap in codemaps {
+        for decl in &codemap.declarations {
+            let Declaration::Function {
+                name,
+                signature,
+                visibility: Visibility::Public,
+                location,
+                doc,
+                ..
+            } = decl
+            else {
+                continue;
+            };
+            let base = tool_name(name);
+            let mut name = base.clone();
+            let mut n = 1;
+            while !names.insert(name.clone()) {
+                n += 1;
+                name = format!("{}_{}", base, n);
+            }
+            tools.push(Tool {
+                name,
+                description: doc
+                    .as_deref()
+                    .map(|doc| summarize_doc(doc, DocSummary::FirstSentence))
+                    .filter(|doc| !doc.is_empty()),
+                parameters: parameters(signature, codemap.language),
+                path: codemap.path.clone(),
+                line: location.start_line,
+            });
+        }
+    }
+    tools
+}
+
+/// Render `tools` as a pretty-printed JSON manifest.
+pub fn format_tools(tools: &[Tool], format: ToolFormat) -> Result<String, serde_json::Error> {
+    #[derive(Serialize)]
+    struct OpenAiTool<'a> {
+        #[serde(rename = "type")]
+        kind: &'static str,
+        function: Function<'a>,
+    }
+
+    #[derive(Serialize)]
+    struct Function<'a> {
+        name: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        description: Option<&'a str>,
+        parameters: InputSchema<'a>,
+    }
+
+    #[derive(Serialize)]
+    struct McpTools<'a> {
+        tools: Vec<McpTool<'a>>,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct McpTool<'a> {
+        name: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        description: Option<&'a str>,
+        input_schema: InputSchema<'a>,
+    }
+
+    match format {
+        ToolFormat::OpenAi => {
+            let tools: Vec<OpenAiTool> = tools
+                .iter()
+                .map(|tool| OpenAiTool {
+                    kind: "function",
+                    function: Function {
+                        name: &tool.name,
+                        description: tool.description.as_deref(),
+                        parameters: InputSchema(&tool.parameters),
+                    },
+                })
+                .collect();
+            serde_json::to_string_pretty(&tools)
+        }
+        ToolFormat::Mcp => {
+            let tools = tools
+                .iter()
+                .map(|tool| McpTool {
+                    name: &tool.name,
+                    description: tool.description.as_deref(),
+                    input_schema: InputSchema(&tool.parameters),
+                })
+                .collect();
+            serde_json::to_string_pretty(&McpTools { tools })
+        }
+    }
+}
+
+/// `{"type": "object", "properties": {...}, "required": [...]}`, with
+/// properties in parameter order.
+struct InputSchema<'a>(&'a [Parameter]);
+
+impl Serialize for InputSchema<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        struct Properties<'a>(&'a [Parameter]);
+
+        impl Serialize for Properties<'_> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let mut map = serializer.serialize_map(Some(self.0.len()))?;
+                for parameter in self.0 {
+                    map.serialize_entry(&parameter.name, &parameter.schema)?;
+                }
+                map.end()
+            }
+        }
+
+        let required: Vec<&str> = self
+            .0
+            .iter()
+            .filter(|p| p.required)
+            .map(|p| p.name.as_str())
+            .collect();
+        let mut map = serializer.serialize_map(Some(3))?;
+        map.serialize_entry("type", "object")?;
+        map.serialize_entry("properties", &Properties(self.0))?;
+        map.serialize_entry("required", &required)?;
+        map.end()
+    }
+}
+
+fn tool_name(name: &str) -> String {
+    let mut name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    name.truncate(MAX_NAME_LEN);
+    name
+}
+
+/// Parameters of a function signature, without receivers (`self`, `cls`,
+/// `this`) and variadics.
+pub fn parameters(signature: &str, language: Language) -> Vec<Parameter> {
+    let Some(list) = parameter_list(signature, language) else {
+        return Vec::new();
+    };
+    let parts = split_top_level(list, ',', language);
+    let parts = parts.iter().map(|p| p.trim()).filter(|p| !p.is_empty());
+
+    let mut parameters = Vec::new();
+    if language == Language::Go {
+        let parts: Vec<&str> = parts.collect();
+        // `a, b int`: a bare name takes the type of the next typed one.
+        let named = parts.iter().any(|p| p.contains(char::is_whitespace));
+        let mut pending: Vec<&str> = Vec::new();
+        for (index, part) in parts.iter().enumerate() {
+            match part.split_once(char::is_whitespace) {
+                Some((name, ty)) => {
+                    for name in pending.drain(..).chain([name]) {
+                        parameters.push(parameter(name, ty.trim(), false, language));
+                    }
+                }
+                None if named => pending.push(part),
+                None => parameters.push(parameter(&format!("arg{}", index), part, false, language)),
+            }
+        }
+        return parameters;
+    }
+
+    for (index, part) in parts.enumerate() {
+        let (part, has_default) = match split_top_level(part, '=', language).as_slice() {
+            [param, _, ..] => (param.trim(), true),
+            _ => (part, false),
+        };
+        let (name, ty) = match split_top_level(part, ':', language).as_slice() {
+            [name, ty, ..] => (name.trim(), ty.trim()),
+            _ => (part, ""),
+        };
+        if name.starts_with('*') || name.starts_with("...") || name == "/" {
+            continue;
+        }
+        let name = name
+            .trim_start_matches("mut ")
+            .split_whitespace()
+            .last()
+            .unwrap_or_default();
+        let receiver = match language {
+            Language::Rust => name.trim_start_matches('&').ends_with("self"),
+            Language::Python => index == 0 && (name == "self" || name == "cls"),
+            _ => name == "this",
+        };
+        if receiver {
+            continue;
+        }
+        let (name, optional) = match name.strip_suffix('?') {
+            Some(name) => (name, true),
+            None => (name, false),
+        };
+        let name = if is_identifier(name) {
+            name.to_string()
+        } else {
+            // Destructured patterns like `{ a, b }: Props` or `(x, y): (u8, u8)`.
+            format!("arg{}", index)
+        };
+        parameters.push(parameter(&name, ty, has_default || optional, language));
+    }
+    parameters
+}
+
+fn parameter(name: &str, ty: &str, optional: bool, language: Language) -> Parameter {
+    let (inner, optional_type) = unwrap_optional(ty, language);
+    Parameter {
+        name: name.to_string(),
+        ty: inner.to_string(),
+        schema: schema(inner, language),
+        required: !optional && !optional_type,
+    }
+}
+
+/// The text between the parentheses of the parameter list.
+fn parameter_list(signature: &str, language: Language) -> Option<&str> {
+    let mut rest = signature;
+    // Go methods: `func (c *Client) Do(req *Request) error`.
+    if language == Language::Go {
+        if let Some(after) = rest.trim_start().strip_prefix("func (") {
+            let close = matching_close(after)?;
+            rest = &after[close + 1..];
+        }
+    }
+    // Skip generics before the list: `fn load<T: Into<String>>(x: T)`.
+    let mut angle = 0usize;
+    let mut open = None;
+    let mut prev = ' ';
+    for (i, c) in rest.char_indices() {
+        match c {
+            '<' => angle += 1,
+            '>' if prev != '-' && prev != '=' => angle = angle.saturating_sub(1),
+            '(' if angle == 0 => {
+                open = Some(i);
+                break;
+            }
+            _ => {}
+        }
+        prev = c;
+    }
+    let after = &rest[open? + 1..];
+    let close = matching_close(after)?;
+    Some(&after[..close])
+}
+
+/// Index of the `)` closing a list whose `(` came just before `text`.
+fn matching_close(text: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' if depth == 0 => return Some(i),
+            ')' | ']' | '}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Split on `separator` outside brackets, generics and (except in Rust,
+/// where `'` starts a lifetime) string literals.
+fn split_top_level(text: &str, separator: char, language: Language) -> Vec<&str> {
+    let quotes: &[char] = if language == Language::Rust {
+        &[]
+    } else {
+        &['"', '\'', '`']
+    };
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut start = 0;
+    let mut prev = ' ';
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q && prev != '\\' => quote = None,
+            (Some(_), _) => {}
+            (None, c) if quotes.contains(&c) => quote = Some(c),
+            (None, '(' | '[' | '{' | '<') => depth += 1,
+            (None, '>') if prev == '-' || prev == '=' => {}
+            (None, ')' | ']' | '}' | '>') => depth = depth.saturating_sub(1),
+            // `::` in paths isn't a type annotation.
+            (None, ':') if separator == ':' && (prev == ':' || text[i + 1..].starts_with(':')) => {}
+            // `==`, `=>` and `<=` aren't defaults.
+            (None, '=')
+                if separator == '='
+                    && (matches!(prev, '=' | '<' | '>' | '!')
+                        || text[i + 1..].starts_with(['=', '>'])) => {}
+            (None, c) if c == separator && depth == 0 => {
+                parts.push(&text[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+        prev = c;
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
+/// `ty` without an optional wrapper, and whether it had one.
+fn unwrap_optional(ty: &str, language: Language) -> (&str, bool) {
+    let ty = ty.trim();
+    if let Some(inner) = generic_argument(ty, &["Option"], '<', '>') {
+        return (inner, true);
+    }
+    if language == Language::Python {
+        if let Some(inner) = generic_argument(ty, &["Optional", "typing.Optional"], '[', ']') {
+            return (inner, true);
+        }
+    }
+    let alternatives: Vec<&str> = split_top_level(ty, '|', language)
+        .into_iter()
+        .map(str::trim)
+        .collect();
+    if alternatives.len() == 2 {
+        let nothing = ["None", "null", "undefined"];
+        match alternatives.as_slice() {
+            [inner, none] | [none, inner] if nothing.contains(none) => return (inner, true),
+            _ => {}
+        }
+    }
+    (ty, false)
+}
+
+/// JSON Schema for a parameter type, as far as it can be resolved.
+fn schema(ty: &str, language: Language) -> ParameterSchema {
+    let ty = strip_reference(ty.trim());
+    if ty.is_empty() {
+        return ParameterSchema::Unresolved(String::new());
+    }
+
+    let arrays = [
+        "Vec",
+        "VecDeque",
+        "HashSet",
+        "BTreeSet",
+        "Array",
+        "ReadonlyArray",
+        "Set",
+    ];
+    if let Some(items) = generic_argument(ty, &arrays, '<', '>') {
+        return ParameterSchema::Array(Box::new(schema(items, language)));
+    }
+    let python_arrays = [
+        "list", "List", "Sequence", "Iterable", "set", "Set", "tuple", "Tuple",
+    ];
+    if let Some(items) = generic_argument(ty, &python_arrays, '[', ']') {
+        let items = split_top_level(items, ',', language)[0];
+        return ParameterSchema::Array(Box::new(schema(items, language)));
+    }
+    if let Some(items) = ty.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+        // Rust slices and arrays: `[T]`, `[T; N]`.
+        let items = items.split(';').next().unwrap_or_default();
+        return ParameterSchema::Array(Box::new(schema(items, language)));
+    }
+    if let Some(items) = ty.strip_suffix("[]") {
+        return ParameterSchema::Array(Box::new(schema(items, language)));
+    }
+    if let Some(items) = ty.strip_prefix("[]").or_else(|| ty.strip_prefix("...")) {
+        return ParameterSchema::Array(Box::new(schema(items, language)));
+    }
+
+    let base = ty
+        .split(['<', '['])
+        .next()
+        .unwrap_or_default()
+        .rsplit(['.', ':'])
+        .next()
+        .unwrap_or_default();
+    let objects = [
+        "HashMap", "BTreeMap", "dict", "Dict", "Mapping", "Record", "Map", "object", "Object",
+    ];
+    if objects.contains(&base) || ty.starts_with("map[") || ty.starts_with('{') {
+        return ParameterSchema::Object;
+    }
+
+    let scalar = match base {
+        "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128"
+        | "usize" | "int" | "int8" | "int16" | "int32" | "int64" | "uint" | "uint8" | "uint16"
+        | "uint32" | "uint64" | "bigint" => "integer",
+        "f32" | "f64" | "float" | "float32" | "float64" | "number" | "Decimal" => "number",
+        "bool" | "boolean" => "boolean",
+        "str" | "String" | "string" | "char" | "rune" | "Path" | "PathBuf" | "OsStr" => "string",
+        _ => return ParameterSchema::Unresolved(ty.to_string()),
+    };
+    ParameterSchema::Scalar(scalar)
+}
+
+/// `ty` without Rust references, lifetimes and `mut`, or Go pointers.
+fn strip_reference(mut ty: &str) -> &str {
+    loop {
+        let stripped = ty.trim_start_matches(['&', '*']).trim_start();
+        let stripped = match stripped.strip_prefix('\'') {
+            Some(lifetime) => lifetime
+                .split_once(char::is_whitespace)
+                .map_or(lifetime, |(_, rest)| rest)
+                .trim_start(),
+            None => stripped,
+        };
+        let stripped = stripped
+            .strip_prefix("mut ")
+            .unwrap_or(stripped)
+            .trim_start();
+        if stripped == ty {
+            return ty;
+        }
+        ty = stripped;
+    }
+}
+
+/// The argument of `Name<arg>` (or `Name[arg]`) for one of `names`.
+fn generic_argument<'a>(ty: &'a str, names: &[&str], open: char, close: char) -> Option<&'a str> {
+    names.iter().find_map(|name| {
+        ty.strip_prefix(name)?
+            .strip_prefix(open)?
+            .strip_suffix(close)
+            .map(str::trim)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(signature: &str, language: Language) -> Vec<(String, ParameterSchema, bool)> {
+        parameters(signature, language)
+            .into_iter()
+            .map(|p| (p.name, p.schema, p.required))
+            .collect()
+    }
+
+    fn scalar(ty: &'static str) -> ParameterSchema {
+        ParameterSchema::Scalar(ty)
+    }
+
+    #[test]
+    fn test_parameters() {
+        assert_eq!(
+            params(
+                "pub fn load<T: Into<String>> (&self, id: u64, names: &[String], cb: impl Fn(u8) -> u8, limit: Option<usize>)",
+                Language::Rust
+            ),
+            [
+                ("id".into(), scalar("integer"), true),
+                (
+                    "names".into(),
+                    ParameterSchema::Array(Box::new(scalar("string"))),
+                    true
+                ),
+                (
+                    "cb".into(),
+                    ParameterSchema::Unresolved("impl Fn(u8) -> u8".into()),
+                    true
+                ),
+                ("limit".into(), scalar("integer"), false),
+            ]
+        );
+        assert_eq!(
+            params("pub fn tag<'a> (text: &'a str, mut n: i32)", Language::Rust),
+            [
+                ("text".into(), scalar("string"), true),
+                ("n".into(), scalar("integer"), true),
+            ]
+        );
+        assert_eq!(
+            params(
+                "def search(self, query: str, tags: list[str] = [], *args, limit: Optional[int] = None, **kwargs) -> None",
+                Language::Python
+            ),
+            [
+                ("query".into(), scalar("string"), true),
+                (
+                    "tags".into(),
+                    ParameterSchema::Array(Box::new(scalar("string"))),
+                    false
+                ),
+                ("limit".into(), scalar("integer"), false),
+            ]
+        );
+        assert_eq!(
+            params(
+                "export function show(user: User, opts?: Record<string, number>, { a, b }: Props, flag = true): string",
+                Language::TypeScript
+            ),
+            [
+                ("user".into(), ParameterSchema::Unresolved("User".into()), true),
+                ("opts".into(), ParameterSchema::Object, false),
+                ("arg2".into(), ParameterSchema::Unresolved("Props".into()), true),
+                ("flag".into(), ParameterSchema::Unresolved(String::new()), false),
+            ]
+        );
+        assert_eq!(
+            params(
+                "func (c *Client) Do(a, b int, names []string, opts ...string) error",
+                Language::Go
+            ),
+            [
+                ("a".into(), scalar("integer"), true),
+                ("b".into(), scalar("integer"), true),
+                (
+                    "names".into(),
+                    ParameterSchema::Array(Box::new(scalar("string"))),
+                    true
+                ),
+                (
+                    "opts".into(),
+                    ParameterSchema::Array(Box::new(scalar("string"))),
+                    true
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_tools() {
+        let tools = vec![Tool {
+            name: "load".into(),
+            description: Some("Load a user.".into()),
+            parameters: vec![
+                Parameter {
+                    name: "id".into(),
+                    ty: "u64".into(),
+                    schema: scalar("integer"),
+                    required: true,
+                },
+                Parameter {
+                    name: "cache".into(),
+                    ty: "Cache".into(),
+                    schema: ParameterSchema::Unresolved("Cache".into()),
+                    required: false,
+                },
+            ],
+            path: PathBuf::from("src/lib.rs"),
+            line: 1,
+        }];
+
+        let openai: serde_json::Value =
+            serde_json::from_str(&format_tools(&tools, ToolFormat::OpenAi).unwrap()).unwrap();
+        let function = &openai[0]["function"];
+        assert_eq!(openai[0]["type"], "function");
+        assert_eq!(function["name"], "load");
+        assert_eq!(function["description"], "Load a user.");
+        assert_eq!(
+            function["parameters"]["properties"]["id"]["type"],
+            "integer"
+        );
+        assert_eq!(
+            function["parameters"]["properties"]["cache"]["description"],
+            "Cache"
+        );
+        assert_eq!(
+            function["parameters"]["required"],
+            serde_json::json!(["id"])
+        );
+
+        let mcp: serde_json::Value =
+            serde_json::from_str(&format_tools(&tools, ToolFormat::Mcp).unwrap()).unwrap();
+        assert_eq!(mcp["tools"][0]["inputSchema"], function["parameters"]);
+    }
+
+    #[test]
+    fn test_tool_names() {
+        use crate::codemap::{extract_codemap, ExtractOptions};
+        use std::path::Path;
+
+        let extract = |path: &str| {
+            extract_codemap(
+                Path::new(path),
+                "pub fn run() {}\nfn private() {}\n",
+                Language::Rust,
+                &ExtractOptions::default(),
+            )
+        };
+        let codemaps: Vec<Codemap> = ["a.rs", "b.rs"].into_iter().map(extract).collect();
+        let tools = tools(&codemaps);
+        let names: Vec<&str> = tools.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["run", "run_2"]);
+        assert_eq!(tool_name("config::load"), "config__load");
+    }
+}
@@ -4,13 +4,18 @@
 //! functions for rendering them with box-drawing characters.
 
 use std::cmp::Ordering;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
 use crate::filter::Language;
+use crate::walker::count_lines_streaming;
 
 /// The type of a filesystem node.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum NodeKind {
     Directory,
     File {
@@ -32,8 +37,37 @@ impl NodeKind {
     }
 }
 
+/// Cumulative totals for the files under a node.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirectoryTotals {
+    /// Number of files.
+    pub files: usize,
+    /// Total size in bytes.
+    pub size: u64,
+    /// Total line count, over files with a known line count.
+    pub lines: usize,
+}
+
+/// Files that differ between two trees, as paths relative to each root.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TreeDiff {
+    /// Files only in the new tree.
+    pub added: Vec<PathBuf>,
+    /// Files only in the old tree.
+    pub removed: Vec<PathBuf>,
+    /// Files in both trees whose size or line count differs.
+    pub changed: Vec<PathBuf>,
+}
+
+impl TreeDiff {
+    /// Check if the trees have the same files.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
 /// A node in the file tree.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileNode {
     /// File or directory name (not full path).
     pub name: String,
@@ -42,6 +76,7 @@ pub struct FileNode {
     /// Type of node (file or directory).
     pub kind: NodeKind,
     /// Child nodes (empty for files). Vec needed for recursive type.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     children: Vec<FileNode>,
 }
 
@@ -126,6 +161,28 @@ impl FileNode {
         }
     }
 
+    /// Read the size and line count of every file under this node from
+    /// disk, in parallel. Fills in metadata deferred by building the tree
+    /// with [`WalkOptions::file_sizes`](crate::walker::WalkOptions::file_sizes)
+    /// or [`WalkOptions::count_lines`](crate::walker::WalkOptions::count_lines)
+    /// turned off. Files that can no longer be read keep what they had.
+    pub fn load_metadata(&mut self) {
+        match &mut self.kind {
+            NodeKind::File { size, lines, .. } => {
+                if let Ok(metadata) = std::fs::metadata(&self.path) {
+                    *size = metadata.len();
+                }
+                if let Some(count) = count_lines_streaming(&self.path) {
+                    *lines = Some(count);
+                }
+            }
+            NodeKind::Directory => self
+                .children
+                .par_iter_mut()
+                .for_each(FileNode::load_metadata),
+        }
+    }
+
     /// Count total files in this tree.
     pub fn file_count(&self) -> usize {
         match &self.kind {
@@ -134,6 +191,80 @@ impl FileNode {
         }
     }
 
+    /// Merge `other`'s children into this node. Directories with the same
+    /// name are merged recursively; a file in `other` replaces a node of the
+    /// same name. Merged paths are rebased from `other`'s root onto this
+    /// one, so the trees may come from different roots. New children are
+    /// appended, so call `sort_children` afterwards if order matters.
+    pub fn merge(&mut self, other: FileNode) {
+        for mut child in other.children {
+            if other.path != self.path {
+                child.rebase(&other.path, &self.path);
+            }
+            match self.children.iter_mut().find(|c| c.name == child.name) {
+                Some(existing) if existing.is_directory() && child.is_directory() => {
+                    existing.merge(child);
+                }
+                Some(existing) => *existing = child,
+                None => self.children.push(child),
+            }
+        }
+    }
+
+    /// Move this node and its descendants from under `from` to under `to`.
+    fn rebase(&mut self, from: &Path, to: &Path) {
+        if let Ok(relative) = self.path.strip_prefix(from) {
+            self.path = to.join(relative);
+        }
+        for child in &mut self.children {
+            child.rebase(from, to);
+        }
+    }
+
+    /// Compare the files in this tree (the old snapshot) against `other`
+    /// (the new one). Files are matched by their path relative to each root,
+    /// so the two roots need not share a path. Each list is sorted.
+    pub fn diff(&self, other: &FileNode) -> TreeDiff {
+        let old = self.files_by_relative_path();
+        let new = other.files_by_relative_path();
+
+        let mut diff = TreeDiff::default();
+        for (path, node) in &new {
+            match old.get(path) {
+                None => diff.added.push(path.clone()),
+                Some(previous) if previous.kind != node.kind => diff.changed.push(path.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.removed = old
+            .keys()
+            .filter(|path| !new.contains_key(*path))
+            .cloned()
+            .collect();
+        diff
+    }
+
+    fn files_by_relative_path(&self) -> BTreeMap<PathBuf, &FileNode> {
+        self.iter_files()
+            .map(|node| {
+                let relative = node.path.strip_prefix(&self.path).unwrap_or(&node.path);
+                (relative.to_path_buf(), node)
+            })
+            .collect()
+    }
+
+    /// Sum file count, size and lines over this tree.
+    pub fn totals(&self) -> DirectoryTotals {
+        match &self.kind {
+            NodeKind::File { size, lines, .. } => DirectoryTotals {
+                files: 1,
+                size: *size,
+                lines: lines.unwrap_or(0),
+            },
+            NodeKind::Directory => sum_totals(&self.children),
+        }
+    }
+
     /// Count total directories in this tree.
     pub fn directory_count(&self) -> usize {
         match &self.kind {
@@ -147,6 +278,134 @@ impl FileNode {
             }
         }
     }
+
+    /// Find the node at `path`, which is compared against node paths as
+    /// stored (so it must share the root's prefix).
+    pub fn find(&self, path: impl AsRef<Path>) -> Option<&FileNode> {
+        let path = path.as_ref();
+        if self.path == path {
+            return Some(self);
+        }
+        if !path.starts_with(&self.path) {
+            return None;
+        }
+        self.children.iter().find_map(|child| child.find(path))
+    }
+
+    /// Iterate over this node and all its descendants, depth-first, with
+    /// each directory before its children.
+    pub fn iter(&self) -> impl Iterator<Item = &FileNode> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            stack.extend(node.children.iter().rev());
+            Some(node)
+        })
+    }
+
+    /// Iterate over all files in this tree, depth-first.
+    pub fn iter_files(&self) -> impl Iterator<Item = &FileNode> {
+        self.iter().filter(|node| node.is_file())
+    }
+
+    /// Iterate over all directories in this tree, including this node if it
+    /// is one, depth-first.
+    pub fn iter_dirs(&self) -> impl Iterator<Item = &FileNode> {
+        self.iter().filter(|node| node.is_directory())
+    }
+
+    /// Copy of this tree keeping only the files for which `predicate` returns
+    /// true. Directories are kept while they contain a kept file; the root is
+    /// always kept.
+    #[must_use]
+    pub fn filter(&self, predicate: impl Fn(&FileNode) -> bool) -> FileNode {
+        self.filter_by(&predicate)
+    }
+
+    fn filter_by(&self, predicate: &impl Fn(&FileNode) -> bool) -> FileNode {
+        let children = self
+            .children
+            .iter()
+            .filter_map(|child| match child.kind {
+                NodeKind::File { .. } => predicate(child).then(|| child.clone()),
+                NodeKind::Directory => {
+                    let filtered = child.filter_by(predicate);
+                    (!filtered.children.is_empty()).then_some(filtered)
+                }
+            })
+            .collect();
+        FileNode {
+            name: self.name.clone(),
+            path: self.path.clone(),
+            kind: self.kind.clone(),
+            children,
+        }
+    }
+}
+
+/// Sum the totals of several sibling nodes.
+fn sum_totals<'a>(nodes: impl IntoIterator<Item = &'a FileNode>) -> DirectoryTotals {
+    nodes
+        .into_iter()
+        .map(FileNode::totals)
+        .fold(DirectoryTotals::default(), |acc, totals| DirectoryTotals {
+            files: acc.files + totals.files,
+            size: acc.size + totals.size,
+            lines: acc.lines + totals.lines,
+        })
+}
+
+/// Sum of the known token counts under `node`, or `None` if no file under it
+/// has one.
+fn subtree_tokens(node: &FileNode, options: &RenderOptions<'_>) -> Option<usize> {
+    if options.tokens.is_empty() {
+        return None;
+    }
+
+    match &node.kind {
+        NodeKind::File { .. } => options.tokens.get(&node.path).copied(),
+        NodeKind::Directory => node
+            .children
+            .iter()
+            .filter_map(|child| subtree_tokens(child, options))
+            .reduce(|a, b| a + b),
+    }
+}
+
+/// Whether `node` is selected or has a codemap, or is a directory containing
+/// such a file.
+fn is_marked(node: &FileNode, options: &RenderOptions<'_>) -> bool {
+    match &node.kind {
+        NodeKind::File { .. } => {
+            options.selected.contains(&node.path) || options.has_codemap.contains(&node.path)
+        }
+        NodeKind::Directory => node.children.iter().any(|c| is_marked(c, options)),
+    }
+}
+
+/// Glyphs appended to marked file names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeMarkers {
+    /// Marker for selected files (default `*`).
+    pub selected: String,
+    /// Marker for files with codemaps (default `+`).
+    pub has_codemap: String,
+}
+
+impl Default for TreeMarkers {
+    fn default() -> Self {
+        Self {
+            selected: "*".to_string(),
+            has_codemap: "+".to_string(),
+        }
+    }
+}
+
+/// Which markers a render actually emitted, so the legend can list only those.
+#[derive(Debug, Default)]
+struct MarkersUsed {
+    selected: bool,
+    has_codemap: bool,
 }
 
 /// Options for rendering the tree.
@@ -158,10 +417,28 @@ pub struct RenderOptions<'a> {
     pub show_lines: bool,
     /// Show detected language.
     pub show_language: bool,
-    /// Paths that are selected (marked with *).
+    /// Show cumulative file count, lines and size on directories.
+    pub show_totals: bool,
+    /// Render at most this many entries per directory, followed by a
+    /// summary line for the rest.
+    pub max_children: Option<usize>,
+    /// Color directories, languages and markers with ANSI escapes.
+    pub color: bool,
+    /// Render only selected files and files with codemaps, plus their
+    /// ancestor directories. Directory totals still cover every file.
+    pub prune: bool,
+    /// Collapse directories at this depth (the root is depth 0) into a
+    /// `dir/ (34 files, 12k lines)` summary instead of listing their contents.
+    pub max_depth: Option<usize>,
+    /// Paths that are selected (marked with `markers.selected`).
     pub selected: HashSet<&'a PathBuf>,
-    /// Paths that have codemaps (marked with +).
+    /// Paths that have codemaps (marked with `markers.has_codemap`).
     pub has_codemap: HashSet<&'a PathBuf>,
+    /// Glyphs marking selected files and files with codemaps.
+    pub markers: TreeMarkers,
+    /// Per-file token counts, shown in file metadata and summed into
+    /// directory totals. Files without an entry show no count.
+    pub tokens: HashMap<&'a PathBuf, usize>,
 }
 
 impl RenderOptions<'_> {
@@ -171,6 +448,7 @@ impl RenderOptions<'_> {
             show_size: true,
             show_lines: true,
             show_language: true,
+            show_totals: true,
             ..Default::default()
         }
     }
@@ -187,6 +465,35 @@ const LAST_BRANCH: &str = "└── ";
 const VERTICAL: &str = "│   ";
 const SPACE: &str = "    ";
 
+/// ANSI escapes for colored rendering.
+const RESET: &str = "\x1b[0m";
+const DIM: &str = "\x1b[2m";
+const DIRECTORY: &str = "\x1b[1;34m";
+const SELECTED: &str = "\x1b[1;33m";
+const HAS_CODEMAP: &str = "\x1b[32m";
+
+/// ANSI color for a language name.
+fn language_color(language: Language) -> &'static str {
+    match language {
+        Language::Rust => "\x1b[31m",
+        Language::TypeScript | Language::Tsx => "\x1b[34m",
+        Language::JavaScript | Language::Jsx => "\x1b[33m",
+        Language::Python => "\x1b[32m",
+        Language::Go => "\x1b[36m",
+    }
+}
+
+/// Push `text`, wrapped in `code` and a reset when coloring is on.
+fn push_colored(output: &mut String, text: &str, code: &str, options: &RenderOptions<'_>) {
+    if options.color {
+        output.push_str(code);
+        output.push_str(text);
+        output.push_str(RESET);
+    } else {
+        output.push_str(text);
+    }
+}
+
 /// Render a file tree to a string with box-drawing characters.
 ///
 /// # Examples
@@ -202,10 +509,36 @@ const SPACE: &str = "    ";
 /// assert!(output.contains("main.rs"));
 /// ```
 pub fn render_tree(root: &FileNode, options: &RenderOptions<'_>) -> String {
+    render(root, options).0
+}
+
+/// Render a file tree followed by a legend line, such as
+/// `Legend: * = selected, + = has codemap`, listing only the markers that
+/// appear in the rendered tree. No legend is added when no marker is shown.
+pub fn render_tree_with_legend(root: &FileNode, options: &RenderOptions<'_>) -> String {
+    let (mut output, used) = render(root, options);
+
+    let mut entries = Vec::new();
+    if used.selected {
+        entries.push(format!("{} = selected", options.markers.selected));
+    }
+    if used.has_codemap {
+        entries.push(format!("{} = has codemap", options.markers.has_codemap));
+    }
+    if !entries.is_empty() {
+        output.push_str("\nLegend: ");
+        output.push_str(&entries.join(", "));
+        output.push('\n');
+    }
+    output
+}
+
+fn render(root: &FileNode, options: &RenderOptions<'_>) -> (String, MarkersUsed) {
     // Pre-allocate for typical tree size
     let mut output = String::with_capacity(4096);
-    render_node(&mut output, root, "", true, true, options);
-    output
+    let mut used = MarkersUsed::default();
+    render_node(&mut output, root, "", true, 0, options, &mut used);
+    (output, used)
 }
 
 fn render_node(
@@ -213,9 +546,12 @@ fn render_node(
     node: &FileNode,
     prefix: &str,
     is_last: bool,
-    is_root: bool,
+    depth: usize,
     options: &RenderOptions<'_>,
+    used: &mut MarkersUsed,
 ) {
+    let is_root = depth == 0;
+
     // Render this node
     let branch = if is_root {
         "" // Root node has no branch
@@ -227,11 +563,56 @@ fn render_node(
 
     output.push_str(prefix);
     output.push_str(branch);
-    output.push_str(&node.name);
 
-    // Add trailing slash for directories
+    // Collapse directories at max_depth into a one-line summary
+    if node.is_directory()
+        && !node.children.is_empty()
+        && options.max_depth.is_some_and(|max| depth >= max)
+    {
+        push_colored(output, &format!("{}/", node.name), DIRECTORY, options);
+
+        let totals = node.totals();
+        let noun = if totals.files == 1 { "file" } else { "files" };
+        let summary = format!(
+            " ({} {}, {} lines)",
+            format_compact(totals.files),
+            noun,
+            format_compact(totals.lines)
+        );
+        push_colored(output, &summary, DIM, options);
+        output.push('\n');
+        return;
+    }
+
+    // Add trailing slash and totals for directories
     if node.is_directory() {
-        output.push('/');
+        push_colored(output, &format!("{}/", node.name), DIRECTORY, options);
+
+        if options.show_totals {
+            let totals = node.totals();
+            let mut metadata = vec![if totals.files == 1 {
+                "1 file".to_string()
+            } else {
+                format!("{} files", format_number(totals.files))
+            }];
+
+            if options.show_lines {
+                metadata.push(format!("{} lines", format_number(totals.lines)));
+            }
+
+            if options.show_size {
+                metadata.push(format_size(totals.size));
+            }
+
+            if let Some(tokens) = subtree_tokens(node, options) {
+                metadata.push(format!("{} tokens", format_number(tokens)));
+            }
+
+            let metadata = format!(" [{}]", metadata.join(", "));
+            push_colored(output, &metadata, DIM, options);
+        }
+    } else {
+        output.push_str(&node.name);
     }
 
     // Add metadata for files
@@ -246,7 +627,11 @@ fn render_node(
         if options.show_language {
             if let Some(ext) = extension {
                 if let Ok(lang) = ext.parse::<Language>() {
-                    metadata.push(lang.to_string());
+                    if options.color {
+                        metadata.push(format!("{}{}{}", language_color(lang), lang, RESET));
+                    } else {
+                        metadata.push(lang.to_string());
+                    }
                 }
             }
         }
@@ -261,6 +646,10 @@ fn render_node(
             metadata.push(format_size(*size));
         }
 
+        if let Some(tokens) = options.tokens.get(&node.path) {
+            metadata.push(format!("{} tokens", format_number(*tokens)));
+        }
+
         if !metadata.is_empty() {
             output.push_str(" [");
             output.push_str(&metadata.join(", "));
@@ -275,31 +664,66 @@ fn render_node(
     if is_selected || has_codemap {
         output.push(' ');
         if is_selected {
-            output.push('*');
+            push_colored(output, &options.markers.selected, SELECTED, options);
+            used.selected = true;
         }
         if has_codemap {
-            output.push('+');
+            push_colored(output, &options.markers.has_codemap, HAS_CODEMAP, options);
+            used.has_codemap = true;
         }
     }
 
     output.push('\n');
 
-    // Render children
-    let child_count = node.children.len();
-    for (i, child) in node.children.iter().enumerate() {
-        let is_last_child = i == child_count - 1;
-
-        // Build new prefix for children
-        let new_prefix = if is_root {
-            // Root's children have no prefix before their branch
-            String::new()
-        } else {
-            // Non-root: add continuation (vertical line or space) based on whether this node is last
-            let continuation = if is_last { SPACE } else { VERTICAL };
-            format!("{}{}", prefix, continuation)
-        };
+    // Build new prefix for children
+    let new_prefix = if is_root {
+        // Root's children have no prefix before their branch
+        String::new()
+    } else {
+        // Non-root: add continuation (vertical line or space) based on whether this node is last
+        let continuation = if is_last { SPACE } else { VERTICAL };
+        format!("{}{}", prefix, continuation)
+    };
 
-        render_node(output, child, &new_prefix, is_last_child, false, options);
+    // Render children, up to max_children
+    let children: Vec<&FileNode> = node
+        .children
+        .iter()
+        .filter(|child| !options.prune || is_marked(child, options))
+        .collect();
+    let child_count = children.len();
+    let shown = options
+        .max_children
+        .map_or(child_count, |max| max.min(child_count));
+    let (visible, hidden) = children.split_at(shown);
+
+    for (i, child) in visible.iter().enumerate() {
+        let is_last_child = hidden.is_empty() && i == shown - 1;
+        render_node(
+            output,
+            child,
+            &new_prefix,
+            is_last_child,
+            depth + 1,
+            options,
+            used,
+        );
+    }
+
+    if !hidden.is_empty() {
+        let totals = sum_totals(hidden.iter().copied());
+        let noun = if totals.files == 1 { "file" } else { "files" };
+
+        output.push_str(&new_prefix);
+        output.push_str(LAST_BRANCH);
+        let summary = format!(
+            "… {} more {} ({})",
+            format_number(totals.files),
+            noun,
+            format_size(totals.size)
+        );
+        push_colored(output, &summary, DIM, options);
+        output.push('\n');
     }
 }
 
@@ -317,6 +741,19 @@ fn format_size(bytes: u64) -> String {
     }
 }
 
+/// Format a count compactly: `950`, `1.2k`, `34k`, `2.1M`.
+fn format_compact(n: usize) -> String {
+    if n < 1_000 {
+        n.to_string()
+    } else if n < 10_000 {
+        format!("{:.1}k", n as f64 / 1_000.0).replace(".0k", "k")
+    } else if n < 1_000_000 {
+        format!("{}k", (n + 500) / 1_000)
+    } else {
+        format!("{:.1}M", n as f64 / 1_000_000.0).replace(".0M", "M")
+    }
+}
+
 /// Format number with thousands separators.
 pub fn format_number(n: usize) -> String {
     if n == 0 {
@@ -387,6 +824,231 @@ mod tests {
         assert_eq!(dir.children.len(), 1);
     }
 
+    fn sample_tree() -> FileNode {
+        let mut root = FileNode::directory("project", "project");
+        let mut src = FileNode::directory("src", "project/src");
+        let mut util = FileNode::directory("util", "project/src/util");
+        util.add_child(FileNode::file(
+            "fmt.rs",
+            "project/src/util/fmt.rs",
+            Some("rs".into()),
+            64,
+            Some(4),
+        ));
+        src.add_child(util);
+        src.add_child(FileNode::file(
+            "lib.rs",
+            "project/src/lib.rs",
+            Some("rs".into()),
+            512,
+            Some(25),
+        ));
+        root.add_child(src);
+        root.add_child(FileNode::directory("docs", "project/docs"));
+        root.add_child(FileNode::file(
+            "README.md",
+            "project/README.md",
+            Some("md".into()),
+            128,
+            Some(10),
+        ));
+        root
+    }
+
+    #[test]
+    fn test_find() {
+        let root = sample_tree();
+        assert_eq!(
+            root.find("project/src/util/fmt.rs")
+                .map(|n| n.name.as_str()),
+            Some("fmt.rs")
+        );
+        assert!(root.find("project/src").unwrap().is_directory());
+        assert!(root.find("project").is_some());
+        assert!(root.find("project/src/main.rs").is_none());
+        assert!(root.find("other/src").is_none());
+    }
+
+    #[test]
+    fn test_iter_files_and_dirs() {
+        let root = sample_tree();
+        let files: Vec<_> = root.iter_files().map(|n| n.name.as_str()).collect();
+        assert_eq!(files, vec!["fmt.rs", "lib.rs", "README.md"]);
+        let dirs: Vec<_> = root.iter_dirs().map(|n| n.name.as_str()).collect();
+        assert_eq!(dirs, vec!["project", "src", "util", "docs"]);
+        assert_eq!(root.iter().count(), 7);
+    }
+
+    #[test]
+    fn test_filter() {
+        let root = sample_tree();
+        let rust = root.filter(|n| n.extension() == Some("rs"));
+        let files: Vec<_> = rust.iter_files().map(|n| n.name.as_str()).collect();
+        assert_eq!(files, vec!["fmt.rs", "lib.rs"]);
+        // `docs` held no matching files, so it is dropped.
+        assert!(rust.find("project/docs").is_none());
+
+        let none = root.filter(|_| false);
+        assert_eq!(none.name, "project");
+        assert!(none.children().is_empty());
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut root = sample_tree();
+        let mut other = FileNode::directory("project", "project");
+        let mut src = FileNode::directory("src", "project/src");
+        src.add_child(FileNode::file(
+            "main.rs",
+            "project/src/main.rs",
+            Some("rs".into()),
+            32,
+            Some(3),
+        ));
+        src.add_child(FileNode::file(
+            "lib.rs",
+            "project/src/lib.rs",
+            Some("rs".into()),
+            600,
+            Some(30),
+        ));
+        other.add_child(src);
+
+        root.merge(other);
+        let src = root.find("project/src").unwrap();
+        let names: Vec<_> = src.children().iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, vec!["util", "lib.rs", "main.rs"]);
+        assert_eq!(root.find("project/src/lib.rs").unwrap().size(), Some(600));
+        assert_eq!(root.file_count(), 4);
+    }
+
+    #[test]
+    fn test_merge_from_another_root() {
+        let mut root = sample_tree();
+        let mut other = FileNode::directory("checkout", "/tmp/checkout");
+        let mut src = FileNode::directory("src", "/tmp/checkout/src");
+        src.add_child(FileNode::file(
+            "main.rs",
+            "/tmp/checkout/src/main.rs",
+            Some("rs".into()),
+            32,
+            Some(3),
+        ));
+        other.add_child(src);
+        other.add_child(FileNode::file(
+            "build.rs",
+            "/tmp/checkout/build.rs",
+            Some("rs".into()),
+            10,
+            Some(1),
+        ));
+
+        root.merge(other);
+        assert_eq!(root.find("project/src/main.rs").unwrap().size(), Some(32));
+        assert!(root.find("project/build.rs").is_some());
+        assert!(root.iter().all(|node| node.path.starts_with("project")));
+        assert!(root
+            .diff(&sample_tree())
+            .removed
+            .contains(&PathBuf::from("build.rs")));
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = sample_tree();
+        let mut new = FileNode::directory("snapshot", "/tmp/snapshot");
+        let mut src = FileNode::directory("src", "/tmp/snapshot/src");
+        src.add_child(FileNode::file(
+            "lib.rs",
+            "/tmp/snapshot/src/lib.rs",
+            Some("rs".into()),
+            600,
+            Some(30),
+        ));
+        src.add_child(FileNode::file(
+            "main.rs",
+            "/tmp/snapshot/src/main.rs",
+            Some("rs".into()),
+            32,
+            Some(3),
+        ));
+        new.add_child(src);
+        new.add_child(FileNode::file(
+            "README.md",
+            "/tmp/snapshot/README.md",
+            Some("md".into()),
+            128,
+            Some(10),
+        ));
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.added, vec![PathBuf::from("src/main.rs")]);
+        assert_eq!(diff.removed, vec![PathBuf::from("src/util/fmt.rs")]);
+        assert_eq!(diff.changed, vec![PathBuf::from("src/lib.rs")]);
+        assert!(old.diff(&old).is_empty());
+    }
+
+    #[test]
+    fn test_render_legend_lists_used_markers() {
+        let root = sample_tree();
+        let lib = PathBuf::from("project/src/lib.rs");
+        let readme = PathBuf::from("project/README.md");
+
+        let options = RenderOptions {
+            selected: [&lib].into_iter().collect(),
+            ..RenderOptions::minimal()
+        };
+        let output = render_tree_with_legend(&root, &options);
+        assert!(output.contains("lib.rs *\n"));
+        assert!(output.ends_with("\nLegend: * = selected\n"));
+
+        let options = RenderOptions {
+            selected: [&lib].into_iter().collect(),
+            has_codemap: [&lib, &readme].into_iter().collect(),
+            markers: TreeMarkers {
+                selected: "[S]".into(),
+                has_codemap: "[C]".into(),
+            },
+            ..RenderOptions::minimal()
+        };
+        let output = render_tree_with_legend(&root, &options);
+        assert!(output.contains("lib.rs [S][C]\n"));
+        assert!(output.contains("README.md [C]\n"));
+        assert!(output.ends_with("\nLegend: [S] = selected, [C] = has codemap\n"));
+
+        // A marked file hidden by max_depth does not produce a legend entry.
+        let options = RenderOptions {
+            selected: [&lib].into_iter().collect(),
+            max_depth: Some(1),
+            ..RenderOptions::minimal()
+        };
+        let output = render_tree_with_legend(&root, &options);
+        assert!(!output.contains("Legend"));
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let mut root = FileNode::directory("project", "project");
+        let mut src = FileNode::directory("src", "project/src");
+        src.add_child(FileNode::file(
+            "lib.rs",
+            "project/src/lib.rs",
+            Some("rs".into()),
+            512,
+            Some(25),
+        ));
+        root.add_child(src);
+
+        let json = serde_json::to_string(&root).unwrap();
+        let restored: FileNode = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.totals(), root.totals());
+        let options = RenderOptions::with_metadata();
+        assert_eq!(
+            render_tree(&restored, &options),
+            render_tree(&root, &options)
+        );
+    }
+
     #[test]
     fn test_sort_children() {
         let mut dir = FileNode::directory("src", "src");
@@ -569,4 +1231,254 @@ mod tests {
         // Verify box drawing characters
         assert!(output.contains("├──") || output.contains("└──"));
     }
+
+    #[test]
+    fn test_directory_totals() {
+        let mut root = FileNode::directory("project", "project");
+        let mut src = FileNode::directory("src", "project/src");
+        src.add_child(FileNode::file(
+            "main.rs",
+            "project/src/main.rs",
+            Some("rs".into()),
+            1024,
+            Some(40),
+        ));
+        src.add_child(FileNode::file(
+            "data.bin",
+            "project/src/data.bin",
+            Some("bin".into()),
+            2048,
+            None,
+        ));
+        root.add_child(src);
+        root.add_child(FileNode::file(
+            "README.md",
+            "project/README.md",
+            Some("md".into()),
+            1024,
+            Some(1200),
+        ));
+        root.sort_children();
+
+        assert_eq!(
+            root.totals(),
+            DirectoryTotals {
+                files: 3,
+                size: 4096,
+                lines: 1240,
+            }
+        );
+
+        let output = render_tree(&root, &RenderOptions::with_metadata());
+        assert!(output.starts_with("project/ [3 files, 1,240 lines, 4.0KB]\n"));
+        assert!(output.contains("src/ [2 files, 40 lines, 3.0KB]\n"));
+
+        let output = render_tree(&root, &RenderOptions::minimal());
+        assert!(output.starts_with("project/\n"));
+    }
+
+    #[test]
+    fn test_render_max_children() {
+        let mut root = FileNode::directory("project", "project");
+        for (name, size) in [("a.rs", 100), ("b.rs", 1024), ("c.rs", 1024)] {
+            root.add_child(FileNode::file(
+                name,
+                format!("project/{name}"),
+                Some("rs".into()),
+                size,
+                Some(5),
+            ));
+        }
+        root.sort_children();
+
+        let options = RenderOptions {
+            max_children: Some(1),
+            ..Default::default()
+        };
+        let output = render_tree(&root, &options);
+        assert_eq!(output, "project/\n├── a.rs\n└── … 2 more files (2.0KB)\n");
+
+        let options = RenderOptions {
+            max_children: Some(3),
+            ..Default::default()
+        };
+        let output = render_tree(&root, &options);
+        assert!(output.ends_with("└── c.rs\n"));
+        assert!(!output.contains("more"));
+    }
+
+    #[test]
+    fn test_render_color() {
+        let mut root = FileNode::directory("project", "project");
+        root.add_child(FileNode::file(
+            "main.rs",
+            "project/main.rs",
+            Some("rs".into()),
+            1024,
+            Some(50),
+        ));
+
+        let main_path = PathBuf::from("project/main.rs");
+        let options = RenderOptions {
+            show_language: true,
+            color: true,
+            selected: [&main_path].into_iter().collect(),
+            ..Default::default()
+        };
+
+        let output = render_tree(&root, &options);
+        assert!(output.starts_with("\x1b[1;34mproject/\x1b[0m\n"));
+        assert!(output.contains("main.rs [\x1b[31mrust\x1b[0m] \x1b[1;33m*\x1b[0m\n"));
+
+        let plain = render_tree(
+            &root,
+            &RenderOptions {
+                color: false,
+                ..options
+            },
+        );
+        assert!(!plain.contains('\x1b'));
+    }
+
+    #[test]
+    fn test_render_with_tokens() {
+        let mut root = FileNode::directory("project", "project");
+        let mut src = FileNode::directory("src", "project/src");
+        src.add_child(FileNode::file(
+            "lib.rs",
+            "project/src/lib.rs",
+            Some("rs".into()),
+            100,
+            Some(5),
+        ));
+        src.add_child(FileNode::file(
+            "main.rs",
+            "project/src/main.rs",
+            Some("rs".into()),
+            100,
+            Some(5),
+        ));
+        root.add_child(src);
+        root.add_child(FileNode::file(
+            "logo.png",
+            "project/logo.png",
+            Some("png".into()),
+            100,
+            None,
+        ));
+        root.sort_children();
+
+        let lib = PathBuf::from("project/src/lib.rs");
+        let main = PathBuf::from("project/src/main.rs");
+        let options = RenderOptions {
+            show_totals: true,
+            tokens: [(&lib, 1200), (&main, 34)].into_iter().collect(),
+            ..Default::default()
+        };
+
+        let output = render_tree(&root, &options);
+        assert!(output.starts_with("project/ [3 files, 1,234 tokens]\n"));
+        assert!(output.contains("src/ [2 files, 1,234 tokens]\n"));
+        assert!(output.contains("lib.rs [1,200 tokens]\n"));
+        assert!(output.contains("logo.png\n"));
+    }
+
+    #[test]
+    fn test_render_prune() {
+        let mut root = FileNode::directory("project", "project");
+        let mut src = FileNode::directory("src", "project/src");
+        src.add_child(FileNode::file(
+            "lib.rs",
+            "project/src/lib.rs",
+            Some("rs".into()),
+            100,
+            Some(5),
+        ));
+        src.add_child(FileNode::file(
+            "notes.txt",
+            "project/src/notes.txt",
+            Some("txt".into()),
+            100,
+            Some(5),
+        ));
+        root.add_child(src);
+        let mut assets = FileNode::directory("assets", "project/assets");
+        assets.add_child(FileNode::file(
+            "logo.png",
+            "project/assets/logo.png",
+            Some("png".into()),
+            100,
+            None,
+        ));
+        root.add_child(assets);
+        root.sort_children();
+
+        let lib = PathBuf::from("project/src/lib.rs");
+        let options = RenderOptions {
+            has_codemap: [&lib].into_iter().collect(),
+            prune: true,
+            ..Default::default()
+        };
+
+        let output = render_tree(&root, &options);
+        assert_eq!(output, "project/\n└── src/\n    └── lib.rs +\n");
+    }
+
+    #[test]
+    fn test_render_max_depth() {
+        let mut root = FileNode::directory("project", "project");
+        let mut src = FileNode::directory("src", "project/src");
+        let mut nested = FileNode::directory("nested", "project/src/nested");
+        nested.add_child(FileNode::file(
+            "deep.rs",
+            "project/src/nested/deep.rs",
+            Some("rs".into()),
+            100,
+            Some(12_345),
+        ));
+        src.add_child(nested);
+        src.add_child(FileNode::file(
+            "lib.rs",
+            "project/src/lib.rs",
+            Some("rs".into()),
+            100,
+            Some(5),
+        ));
+        root.add_child(src);
+        root.add_child(FileNode::file(
+            "Cargo.toml",
+            "project/Cargo.toml",
+            Some("toml".into()),
+            100,
+            Some(20),
+        ));
+        root.sort_children();
+
+        let options = RenderOptions {
+            max_depth: Some(1),
+            ..Default::default()
+        };
+        let output = render_tree(&root, &options);
+        assert_eq!(
+            output,
+            "project/\n├── src/ (2 files, 12k lines)\n└── Cargo.toml\n"
+        );
+
+        let options = RenderOptions {
+            max_depth: Some(2),
+            ..Default::default()
+        };
+        let output = render_tree(&root, &options);
+        assert!(output.contains("│   ├── nested/ (1 file, 12k lines)\n"));
+        assert!(output.contains("│   └── lib.rs\n"));
+    }
+
+    #[test]
+    fn test_format_compact() {
+        assert_eq!(format_compact(950), "950");
+        assert_eq!(format_compact(1_000), "1k");
+        assert_eq!(format_compact(1_234), "1.2k");
+        assert_eq!(format_compact(12_345), "12k");
+        assert_eq!(format_compact(2_100_000), "2.1M");
+    }
 }
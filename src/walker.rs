@@ -2,18 +2,23 @@
 //!
 //! Uses the `ignore` crate to walk directories while respecting
 //! .gitignore, .git/info/exclude, global gitignore, and .pithignore.
+//! Dependency directories, build output and lock files are skipped even
+//! when they are not gitignored (see [`DEFAULT_IGNORES`]).
 
 use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
 
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
 use ignore::WalkBuilder;
+use rayon::prelude::*;
 use thiserror::Error;
 
+use crate::cancel::CancellationToken;
 use crate::tree::FileNode;
 
 /// Count lines in a file using streaming (8KB buffer) instead of loading entire file.
 /// Much more memory-efficient for large files.
-fn count_lines_streaming(path: &Path) -> Option<usize> {
+pub(crate) fn count_lines_streaming(path: &Path) -> Option<usize> {
     let file = std::fs::File::open(path).ok()?;
     let reader = BufReader::new(file);
     let mut count = 0;
@@ -29,6 +34,40 @@ fn count_lines_streaming(path: &Path) -> Option<usize> {
     Some(count)
 }
 
+/// Patterns (gitignore syntax) skipped by default, whether or not a
+/// `.gitignore` lists them.
+pub const DEFAULT_IGNORES: &[&str] = &[
+    "node_modules/",
+    "vendor/",
+    "dist/",
+    ".next/",
+    "target/",
+    "*.lock",
+    "*.min.*",
+];
+
+/// Build a matcher for [`DEFAULT_IGNORES`] rooted at `root`.
+fn default_ignores(root: &Path) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(root);
+    for pattern in DEFAULT_IGNORES {
+        builder.add_line(None, pattern).ok();
+    }
+    builder.build().unwrap_or_else(|_| Gitignore::empty())
+}
+
+/// Build a matcher for the rules in the ignore files at `paths`. Patterns
+/// are anchored at `root`, wherever the files live. Missing files are
+/// skipped.
+fn custom_ignores(root: &Path, paths: &[PathBuf]) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(root);
+    for path in paths.iter().filter(|path| path.exists()) {
+        if let Some(e) = builder.add(path) {
+            tracing::warn!("{}", e);
+        }
+    }
+    builder.build().unwrap_or_else(|_| Gitignore::empty())
+}
+
 /// Errors that can occur during directory walking.
 #[derive(Debug, Error)]
 pub enum WalkError {
@@ -63,8 +102,23 @@ pub struct WalkOptions {
     pub include_hidden: bool,
     /// Respect .gitignore patterns.
     pub respect_gitignore: bool,
-    /// Additional ignore file paths (e.g., .pithignore).
+    /// Additional ignore files. Their patterns are anchored at the walk root,
+    /// wherever the files live.
     pub custom_ignores: Vec<PathBuf>,
+    /// Skip [`DEFAULT_IGNORES`] (dependency directories, build output, lock
+    /// files).
+    pub default_ignores: bool,
+    /// Stat every file for its size while walking. When off,
+    /// [`WalkEntry::size`] is `None` and tree nodes get size 0 until
+    /// [`FileNode::load_metadata`].
+    pub file_sizes: bool,
+    /// Count lines of every file when building a tree. Reading each file is
+    /// the slowest part of [`build_tree_with_options`]; turn this off when
+    /// line counts are not shown.
+    pub count_lines: bool,
+    /// Stop yielding entries once this token is cancelled. A cancelled walk
+    /// looks like a shorter one; callers check the token to tell them apart.
+    pub cancel: Option<CancellationToken>,
 }
 
 impl Default for WalkOptions {
@@ -75,6 +129,10 @@ impl Default for WalkOptions {
             include_hidden: false,
             respect_gitignore: true,
             custom_ignores: Vec::new(),
+            default_ignores: true,
+            file_sizes: true,
+            count_lines: true,
+            cancel: None,
         }
     }
 }
@@ -93,6 +151,20 @@ impl WalkOptions {
         self.max_depth = Some(depth);
         self
     }
+
+    /// Set whether walking reads file sizes and tree building counts lines.
+    /// Turning both off lets a tree be built from directory listings alone.
+    pub fn metadata(mut self, enabled: bool) -> Self {
+        self.file_sizes = enabled;
+        self.count_lines = enabled;
+        self
+    }
+
+    /// Set whether tree building counts lines.
+    pub fn count_lines(mut self, enabled: bool) -> Self {
+        self.count_lines = enabled;
+        self
+    }
 }
 
 /// Entry from directory walk.
@@ -104,7 +176,8 @@ pub struct WalkEntry {
     pub depth: usize,
     /// Whether this is a file or directory.
     pub is_file: bool,
-    /// File size in bytes (only for files).
+    /// File size in bytes (only for files, and only if
+    /// [`WalkOptions::file_sizes`] is on).
     pub size: Option<u64>,
 }
 
@@ -154,11 +227,31 @@ pub fn walk_with_options(
         builder.max_depth(Some(depth));
     }
 
-    // Add custom ignore files
-    for ignore_path in &options.custom_ignores {
-        if ignore_path.exists() {
-            builder.add_ignore(ignore_path);
-        }
+    let defaults = options.default_ignores.then(|| default_ignores(&root));
+    let custom = (!options.custom_ignores.is_empty())
+        .then(|| custom_ignores(&root, &options.custom_ignores));
+    if defaults.is_some() || custom.is_some() {
+        builder.filter_entry(move |entry| {
+            // The root itself is never skipped, even if it is named `vendor`.
+            if entry.depth() == 0 {
+                return true;
+            }
+            let is_dir = entry.file_type().is_some_and(|ft| ft.is_dir());
+            let ignored_by = |matcher: &Option<Gitignore>| {
+                matcher
+                    .as_ref()
+                    .is_some_and(|m| m.matched(entry.path(), is_dir).is_ignore())
+            };
+            if ignored_by(&defaults) {
+                tracing::info!("skipped {}: default ignore", entry.path().display());
+                false
+            } else if ignored_by(&custom) {
+                tracing::info!("skipped {}: ignore file", entry.path().display());
+                false
+            } else {
+                true
+            }
+        });
     }
 
     // Look for .pithignore in root
@@ -168,47 +261,54 @@ pub fn walk_with_options(
     }
 
     let walker = builder.build();
+    let file_sizes = options.file_sizes;
+    let cancel = options.cancel.clone();
+
+    let walker =
+        walker.take_while(move |_| !cancel.as_ref().is_some_and(CancellationToken::is_cancelled));
+    itertools_lite::Either::Right(walker.filter_map(move |result| match result {
+        Ok(entry) => {
+            let path = entry.path().to_path_buf();
+            let depth = entry.depth();
+            let is_file = entry.file_type().is_some_and(|ft| ft.is_file());
+
+            let size = if is_file && file_sizes {
+                entry.metadata().ok().map(|m| m.len())
+            } else {
+                None
+            };
+
+            Some(Ok(WalkEntry {
+                path,
+                depth,
+                is_file,
+                size,
+            }))
+        }
+        Err(e) => walk_error(e, None).map(Err),
+    }))
+}
 
-    itertools_lite::Either::Right(walker.filter_map(move |result| {
-        match result {
-            Ok(entry) => {
-                let path = entry.path().to_path_buf();
-                let depth = entry.depth();
-                let is_file = entry.file_type().is_some_and(|ft| ft.is_file());
-
-                let size = if is_file {
-                    entry.metadata().ok().map(|m| m.len())
-                } else {
-                    None
-                };
-
-                Some(Ok(WalkEntry {
-                    path,
-                    depth,
-                    is_file,
-                    size,
-                }))
-            }
-            Err(e) => {
-                // Convert ignore errors to our error type
-                match e {
-                    ignore::Error::Io(io_err) => {
-                        let path = PathBuf::from("<walk error>");
-                        if io_err.kind() == std::io::ErrorKind::PermissionDenied {
-                            Some(Err(WalkError::PermissionDenied { path }))
-                        } else {
-                            Some(Err(WalkError::Io {
-                                path,
-                                source: io_err,
-                            }))
-                        }
-                    }
-                    // Skip non-IO errors (like gitignore parse errors)
-                    _ => None,
-                }
+/// Convert an `ignore` error, keeping the path it happened at. I/O errors
+/// are usually wrapped with the path and depth; other errors (like gitignore
+/// parse errors) are logged and skipped.
+fn walk_error(error: ignore::Error, path: Option<PathBuf>) -> Option<WalkError> {
+    match error {
+        ignore::Error::WithPath { path, err } => walk_error(*err, Some(path)),
+        ignore::Error::WithDepth { err, .. } => walk_error(*err, path),
+        ignore::Error::Io(source) => {
+            let path = path.unwrap_or_else(|| PathBuf::from("<walk error>"));
+            if source.kind() == std::io::ErrorKind::PermissionDenied {
+                Some(WalkError::PermissionDenied { path })
+            } else {
+                Some(WalkError::Io { path, source })
             }
         }
-    }))
+        e => {
+            tracing::warn!("{}", e);
+            None
+        }
+    }
 }
 
 /// Build a complete file tree from a directory.
@@ -230,6 +330,9 @@ pub fn build_tree(root: &Path) -> Result<FileNode, WalkError> {
 }
 
 /// Build a complete file tree with custom options.
+///
+/// Files are read in parallel to count their lines, unless
+/// [`WalkOptions::count_lines`] is off.
 pub fn build_tree_with_options(root: &Path, options: &WalkOptions) -> Result<FileNode, WalkError> {
     if !root.exists() {
         return Err(WalkError::NotFound {
@@ -253,8 +356,11 @@ pub fn build_tree_with_options(root: &Path, options: &WalkOptions) -> Result<Fil
             .and_then(|e| e.to_str())
             .map(|s| s.to_lowercase());
 
-        // Count lines using streaming (8KB buffer instead of loading entire file)
-        let lines = count_lines_streaming(root);
+        let lines = if options.count_lines {
+            count_lines_streaming(root)
+        } else {
+            None
+        };
 
         return Ok(FileNode::file(
             name,
@@ -279,41 +385,17 @@ pub fn build_tree_with_options(root: &Path, options: &WalkOptions) -> Result<Fil
         .filter(|e| e.path != root)
         .collect();
 
-    // Build nodes for all entries
-    for entry in &entries {
-        let entry_name = entry
-            .path
-            .file_name()
-            .map(|n| n.to_string_lossy().into_owned())
-            .unwrap_or_default();
-
-        let node = if entry.is_file {
-            let extension = entry
-                .path
-                .extension()
-                .and_then(|e| e.to_str())
-                .map(|s| s.to_lowercase());
-
-            // Count lines using streaming (8KB buffer instead of loading entire file)
-            let lines = count_lines_streaming(&entry.path);
-
-            FileNode::file(
-                &entry_name,
-                &entry.path,
-                extension,
-                entry.size.unwrap_or(0),
-                lines,
-            )
-        } else {
-            FileNode::directory(&entry_name, &entry.path)
-        };
-
-        node_map.insert(entry.path.clone(), node);
-    }
+    // Build nodes for all entries. Line counting reads every file, so spread
+    // it across threads.
+    let nodes: Vec<(PathBuf, FileNode)> = entries
+        .par_iter()
+        .map(|entry| (entry.path.clone(), build_node(entry, options.count_lines)))
+        .collect();
+    node_map.extend(nodes);
 
     // Build parent-child relationships
     // Process in order of depth (deepest first) so children are added before parents are moved
-    entries.sort_by(|a, b| b.depth.cmp(&a.depth));
+    entries.sort_by_key(|e| std::cmp::Reverse(e.depth));
 
     for entry in &entries {
         if let Some(parent_path) = entry.path.parent() {
@@ -338,6 +420,40 @@ pub fn build_tree_with_options(root: &Path, options: &WalkOptions) -> Result<Fil
     Ok(result)
 }
 
+/// Build the node for one walked entry.
+fn build_node(entry: &WalkEntry, count_lines: bool) -> FileNode {
+    let entry_name = entry
+        .path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    if !entry.is_file {
+        return FileNode::directory(&entry_name, &entry.path);
+    }
+
+    let extension = entry
+        .path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|s| s.to_lowercase());
+
+    // Count lines using streaming (8KB buffer instead of loading entire file)
+    let lines = if count_lines {
+        count_lines_streaming(&entry.path)
+    } else {
+        None
+    };
+
+    FileNode::file(
+        &entry_name,
+        &entry.path,
+        extension,
+        entry.size.unwrap_or(0),
+        lines,
+    )
+}
+
 /// Simple Either type to avoid adding itertools dependency.
 mod itertools_lite {
     pub enum Either<L, R> {
@@ -364,6 +480,7 @@ mod itertools_lite {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tree::NodeKind;
     use std::fs;
     use tempfile::TempDir;
 
@@ -437,6 +554,75 @@ mod tests {
         assert!(!paths.iter().any(|p| p.ends_with("skip.rs")));
     }
 
+    #[test]
+    fn test_walk_custom_ignore_files() {
+        let dir = TempDir::new().unwrap();
+        let project = dir.path().join("project");
+
+        fs::create_dir_all(project.join("src/generated")).unwrap();
+        fs::write(project.join("src/lib.rs"), "// keep").unwrap();
+        fs::write(project.join("src/generated/api.rs"), "// generated").unwrap();
+        fs::write(project.join("schema.rs"), "// root only").unwrap();
+        fs::write(project.join("src/schema.rs"), "// nested").unwrap();
+        // Outside the walked root, with patterns anchored at the root.
+        fs::create_dir(dir.path().join("ci")).unwrap();
+        let ignore_file = dir.path().join("ci/pith.ignore");
+        fs::write(&ignore_file, "generated/\n/schema.rs\n").unwrap();
+
+        let options = WalkOptions {
+            custom_ignores: vec![ignore_file, dir.path().join("missing.ignore")],
+            ..Default::default()
+        };
+        let paths: Vec<_> = walk_with_options(&project, &options)
+            .filter_map(|r| r.ok())
+            .filter(|e| e.is_file)
+            .map(|e| e.path.strip_prefix(&project).unwrap().to_path_buf())
+            .collect();
+
+        assert!(paths.contains(&PathBuf::from("src/lib.rs")));
+        assert!(paths.contains(&PathBuf::from("src/schema.rs")));
+        assert!(!paths.contains(&PathBuf::from("schema.rs")));
+        assert!(!paths.contains(&PathBuf::from("src/generated/api.rs")));
+    }
+
+    #[test]
+    fn test_walk_default_ignores() {
+        let dir = TempDir::new().unwrap();
+
+        fs::create_dir_all(dir.path().join("node_modules/left-pad")).unwrap();
+        fs::write(dir.path().join("node_modules/left-pad/index.js"), "").unwrap();
+        fs::create_dir_all(dir.path().join("web/dist")).unwrap();
+        fs::write(dir.path().join("web/dist/app.js"), "").unwrap();
+        fs::write(dir.path().join("web/app.min.js"), "").unwrap();
+        fs::write(dir.path().join("web/app.js"), "").unwrap();
+        fs::write(dir.path().join("Cargo.lock"), "").unwrap();
+
+        let entries: Vec<_> = walk(dir.path()).filter_map(|r| r.ok()).collect();
+        let paths: Vec<_> = entries.iter().map(|e| &e.path).collect();
+        assert!(paths.iter().any(|p| p.ends_with("web/app.js")));
+        assert!(!paths.iter().any(|p| p.ends_with("node_modules")));
+        assert!(!paths.iter().any(|p| p.ends_with("dist/app.js")));
+        assert!(!paths.iter().any(|p| p.ends_with("app.min.js")));
+        assert!(!paths.iter().any(|p| p.ends_with("Cargo.lock")));
+
+        let options = WalkOptions {
+            default_ignores: false,
+            ..Default::default()
+        };
+        let entries: Vec<_> = walk_with_options(dir.path(), &options)
+            .filter_map(|r| r.ok())
+            .collect();
+        let paths: Vec<_> = entries.iter().map(|e| &e.path).collect();
+        assert!(paths.iter().any(|p| p.ends_with("left-pad/index.js")));
+        assert!(paths.iter().any(|p| p.ends_with("Cargo.lock")));
+
+        // Walking a directory that is itself on the list still works.
+        let entries: Vec<_> = walk(&dir.path().join("web/dist"))
+            .filter_map(|r| r.ok())
+            .collect();
+        assert!(entries.iter().any(|e| e.path.ends_with("app.js")));
+    }
+
     #[test]
     fn test_walk_hidden_files() {
         let dir = TempDir::new().unwrap();
@@ -487,6 +673,47 @@ mod tests {
         assert_eq!(tree.children()[2].name, "z.rs");
     }
 
+    #[test]
+    fn test_build_tree_count_lines() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/lib.rs"), "a\nb\nc\n").unwrap();
+        fs::write(dir.path().join("main.rs"), "a\n").unwrap();
+
+        let tree = build_tree(dir.path()).unwrap();
+        assert_eq!(tree.totals().lines, 4);
+
+        let options = WalkOptions::default().count_lines(false);
+        let tree = build_tree_with_options(dir.path(), &options).unwrap();
+        assert_eq!(tree.file_count(), 2);
+        assert_eq!(tree.totals().lines, 0);
+        assert!(tree
+            .children()
+            .iter()
+            .all(|child| !matches!(child.kind, NodeKind::File { lines: Some(_), .. })));
+    }
+
+    #[test]
+    fn test_build_tree_deferred_metadata() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/lib.rs"), "a\nb\n").unwrap();
+
+        let options = WalkOptions::default().metadata(false);
+        let entries: Vec<_> = walk_with_options(dir.path(), &options)
+            .filter_map(|r| r.ok())
+            .collect();
+        assert!(entries.iter().all(|e| e.size.is_none()));
+
+        let mut tree = build_tree_with_options(dir.path(), &options).unwrap();
+        assert_eq!((tree.totals().size, tree.totals().lines), (0, 0));
+
+        tree.load_metadata();
+        let lib = &tree.children()[0].children()[0];
+        assert_eq!(lib.size(), Some(4));
+        assert_eq!(tree.totals().lines, 2);
+    }
+
     #[test]
     fn test_walk_max_depth() {
         let dir = TempDir::new().unwrap();
@@ -504,4 +731,24 @@ mod tests {
         assert!(paths.iter().any(|p| p.ends_with("shallow.rs")));
         assert!(!paths.iter().any(|p| p.ends_with("deep.rs")));
     }
+
+    #[test]
+    fn test_walk_stops_when_cancelled() {
+        let dir = TempDir::new().unwrap();
+        for name in ["a.rs", "b.rs", "c.rs"] {
+            fs::write(dir.path().join(name), "").unwrap();
+        }
+
+        let token = CancellationToken::new();
+        let options = WalkOptions {
+            cancel: Some(token.clone()),
+            ..Default::default()
+        };
+        let mut seen = 0;
+        for _ in walk_with_options(dir.path(), &options) {
+            seen += 1;
+            token.cancel();
+        }
+        assert_eq!(seen, 1);
+    }
 }
@@ -103,3 +103,1426 @@ fn cli_json_error_output_is_valid_json_even_with_quotes_in_path() {
     let stderr = String::from_utf8(output.stderr).unwrap();
     let _: serde_json::Value = serde_json::from_str(stderr.trim()).unwrap();
 }
+
+#[test]
+fn cli_codemap_skip_tests_drops_test_items() {
+    let dir = tempdir().unwrap();
+
+    write_file(
+        &dir.path().join("lib.rs"),
+        "pub fn run() {}\n\n#[cfg(test)]\nmod tests {\n    #[test]\n    fn it_runs() {}\n}\n",
+    );
+    write_file(
+        &dir.path().join("test_app.py"),
+        "def helper():\n    pass\n\ndef test_helper():\n    pass\n",
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args([
+            "codemap",
+            dir.path().to_str().unwrap(),
+            "--include-private",
+            "--skip-tests",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("fn run"));
+    assert!(stdout.contains("def helper"));
+    assert!(!stdout.contains("it_runs"));
+    assert!(!stdout.contains("test_helper"));
+}
+
+#[test]
+fn cli_codemap_skip_tests_drops_go_test_files() {
+    let dir = tempdir().unwrap();
+
+    write_file(
+        &dir.path().join("api.go"),
+        "package api\n\nfunc Serve() {}\n",
+    );
+    write_file(
+        &dir.path().join("api_test.go"),
+        "package api\n\nfunc NewFixture() int { return 1 }\n",
+    );
+
+    let run = |skip_tests: bool| {
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_pith"));
+        cmd.args(["codemap", dir.path().to_str().unwrap()]);
+        if skip_tests {
+            cmd.arg("--skip-tests");
+        }
+        let output = cmd.output().unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    assert!(run(false).contains("api_test.go"));
+    let stdout = run(true);
+    assert!(stdout.contains("func Serve"));
+    assert!(!stdout.contains("api_test.go"));
+    assert!(!stdout.contains("NewFixture"));
+}
+
+#[test]
+fn cli_codemap_include_todos_lists_comments() {
+    let dir = tempdir().unwrap();
+
+    write_file(
+        &dir.path().join("lib.rs"),
+        "// TODO: add retries\npub fn run() {}\n",
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args([
+            "codemap",
+            dir.path().to_str().unwrap(),
+            "--json",
+            "--include-todos",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let v: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let todo = &v["codemaps"][0]["todos"][0];
+    assert_eq!(todo["kind"], "TODO");
+    assert_eq!(todo["line"], 1);
+    assert_eq!(todo["text"], "add retries");
+}
+
+#[test]
+fn cli_codemap_list_tests_adds_tests_section() {
+    let dir = tempdir().unwrap();
+
+    write_file(
+        &dir.path().join("lib.rs"),
+        "pub fn run() {}\n\n#[cfg(test)]\nmod tests {\n    #[test]\n    fn it_runs() {}\n}\n",
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args([
+            "codemap",
+            dir.path().to_str().unwrap(),
+            "--skip-tests",
+            "--list-tests",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("fn run"));
+    assert!(stdout.contains("### Tests\n- tests::it_runs\n"));
+}
+
+#[test]
+fn cli_stats_json_aggregates_languages_and_declarations() {
+    let dir = tempdir().unwrap();
+
+    write_file(
+        &dir.path().join("lib.rs"),
+        "pub struct Counter;\n\nimpl Counter {\n    pub fn get(&self) -> u32 {\n        1\n    }\n}\n\npub fn run() {}\n",
+    );
+    write_file(&dir.path().join("app.py"), "def main():\n    return 1\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args([
+            "stats",
+            dir.path().to_str().unwrap(),
+            "--json",
+            "--top",
+            "1",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let v: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(v["files"], 2);
+    assert_eq!(v["languages"]["rust"]["files"], 1);
+    assert_eq!(v["languages"]["python"]["lines"], 2);
+    assert_eq!(v["declarations"]["struct"], 1);
+    assert_eq!(v["declarations"]["method"], 1);
+    assert_eq!(v["declarations"]["function"], 2);
+    assert_eq!(v["largest_files"].as_array().unwrap().len(), 1);
+    assert_eq!(v["largest_files"][0]["path"], "lib.rs");
+    assert!(v["average_function_lines"].as_f64().unwrap() > 0.0);
+}
+
+#[test]
+fn cli_tokens_reports_each_encoding() {
+    let dir = tempdir().unwrap();
+
+    write_file(&dir.path().join("lib.rs"), "pub fn run() {}\n");
+    write_file(&dir.path().join("app.py"), "def main():\n    return 1\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args([
+            "tokens",
+            dir.path().to_str().unwrap(),
+            "--json",
+            "--encoding",
+            "o200k,cl100k,o200k",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let v: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(v["encoding"], "o200k_base");
+    let extra = v["extra_totals"].as_array().unwrap();
+    assert_eq!(extra.len(), 1);
+    assert_eq!(extra[0]["encoding"], "cl100k_base");
+    assert!(extra[0]["total"].as_u64().unwrap() > 0);
+}
+
+#[test]
+fn cli_tokens_tree_totals_files_and_directories() {
+    let dir = tempdir().unwrap();
+
+    write_file(&dir.path().join("src/lib.rs"), "pub fn run() {}\n");
+    write_file(
+        &dir.path().join("src/app.py"),
+        "def main():\n    return 1\n",
+    );
+    write_file(&dir.path().join("logo.png"), "\u{89}PNG\r\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args(["tokens", dir.path().to_str().unwrap(), "--tree"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    // "src/ [2 files, 12 tokens]" or "lib.rs [5 tokens]"
+    let count = |name: &str| -> usize {
+        let line = stdout.lines().find(|l| l.contains(name)).unwrap();
+        let before = line.split(" tokens]").next().unwrap();
+        before.rsplit([' ', '[']).next().unwrap().parse().unwrap()
+    };
+    assert!(stdout.contains("src/ [2 files, "));
+    assert_eq!(count("src/"), count("lib.rs [") + count("app.py ["));
+    assert!(!stdout.contains("logo.png"));
+    assert!(stdout.ends_with(&format!("\nTotal: {} tokens\n", count("src/"))));
+}
+
+#[test]
+fn cli_context_select_lang_includes_whole_language() {
+    let dir = tempdir().unwrap();
+
+    write_file(
+        &dir.path().join("lib.rs"),
+        "pub fn run() -> u32 {\n    41 + 1\n}\n",
+    );
+    write_file(
+        &dir.path().join("app.py"),
+        "def main():\n    return 'body'\n",
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args([
+            "context",
+            dir.path().to_str().unwrap(),
+            "--select-lang",
+            "python",
+            "--json",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let v: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let selected = v["selected_files"].as_array().unwrap();
+    assert_eq!(selected.len(), 1);
+    assert!(selected[0]["path"].as_str().unwrap().ends_with("app.py"));
+    assert_eq!(v["codemaps"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn cli_context_select_entrypoints() {
+    let dir = tempdir().unwrap();
+
+    write_file(&dir.path().join("src/main.rs"), "fn main() {}\n");
+    write_file(&dir.path().join("src/util.rs"), "pub fn util() {}\n");
+    write_file(
+        &dir.path().join("web/server.js"),
+        "export function serve() {}\n",
+    );
+    write_file(
+        &dir.path().join("web/helpers.js"),
+        "export function help() {}\n",
+    );
+    write_file(
+        &dir.path().join("web/package.json"),
+        r#"{"scripts": {"start": "node ./server.js --port 80"}}"#,
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args([
+            "context",
+            dir.path().to_str().unwrap(),
+            "--select-entrypoints",
+            "--json",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let v: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let mut selected: Vec<&str> = v["selected_files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| f["path"].as_str().unwrap())
+        .collect();
+    selected.sort_unstable();
+    assert_eq!(selected.len(), 2);
+    assert!(selected[0].ends_with("main.rs"));
+    assert!(selected[1].ends_with("server.js"));
+}
+
+#[test]
+fn cli_bench_reports_phases_and_slowest_files() {
+    let dir = tempdir().unwrap();
+
+    write_file(&dir.path().join("lib.rs"), "pub fn run() {}\n");
+    write_file(&dir.path().join("app.py"), "def main():\n    return 1\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args([
+            "bench",
+            dir.path().to_str().unwrap(),
+            "--json",
+            "--top",
+            "1",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let v: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(v["files"], 2);
+    let phases: Vec<&str> = v["phases"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["phase"].as_str().unwrap())
+        .collect();
+    assert_eq!(phases, ["walk", "read", "parse", "tokenize"]);
+    assert_eq!(v["phases"][2]["files"], 2);
+    let slowest = v["slowest_files"].as_array().unwrap();
+    assert_eq!(slowest.len(), 1);
+    assert!(["lib.rs", "app.py"].contains(&slowest[0]["path"].as_str().unwrap()));
+}
+
+#[test]
+fn cli_codemap_symbol_filter_keeps_matching_declarations() {
+    let dir = tempdir().unwrap();
+
+    write_file(
+        &dir.path().join("handlers.rs"),
+        "pub fn handle_get() {}\npub fn parse() {}\n",
+    );
+    write_file(&dir.path().join("render.rs"), "pub fn render() {}\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args([
+            "codemap",
+            dir.path().to_str().unwrap(),
+            "--json",
+            "--symbol-filter",
+            "^handle_",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let v: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let codemaps = v["codemaps"].as_array().unwrap();
+    assert_eq!(codemaps.len(), 1);
+    let names: Vec<_> = codemaps[0]["declarations"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| d["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["handle_get"]);
+}
+
+#[test]
+fn cli_codemap_rejects_invalid_symbol_filter() {
+    let dir = tempdir().unwrap();
+    write_file(&dir.path().join("lib.rs"), "pub fn run() {}\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args([
+            "codemap",
+            dir.path().to_str().unwrap(),
+            "--symbol-filter",
+            "(",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+}
+
+#[test]
+fn cli_codemap_map_ext_adds_and_skips_extensions() {
+    let dir = tempdir().unwrap();
+
+    write_file(
+        &dir.path().join("fast.pyx"),
+        "def speedup(x):\n    return x\n",
+    );
+    write_file(
+        &dir.path().join("main.go"),
+        "package main\n\nfunc Run() {}\n",
+    );
+    write_file(&dir.path().join("lib.rs"), "pub fn run() {}\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args([
+            "codemap",
+            dir.path().to_str().unwrap(),
+            "--json",
+            "--map-ext",
+            "pyx=python",
+            "--map-ext",
+            ".go=skip",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let v: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let mut paths: Vec<_> = v["codemaps"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| {
+            let path = c["path"].as_str().unwrap();
+            Path::new(path)
+                .file_name()
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect();
+    paths.sort();
+    assert_eq!(paths, ["fast.pyx", "lib.rs"]);
+}
+
+#[test]
+fn cli_codemap_modelines_detects_extensionless_scripts() {
+    let dir = tempdir().unwrap();
+
+    write_file(
+        &dir.path().join("bin/deploy"),
+        "#!/usr/bin/env python3\n# vim: ft=python\n\ndef deploy(target):\n    pass\n",
+    );
+    write_file(&dir.path().join("lib.rs"), "pub fn run() {}\n");
+
+    let count_codemaps = |extra: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+            .args(["codemap", dir.path().to_str().unwrap(), "--json"])
+            .args(extra)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let v: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        v["codemaps"].as_array().unwrap().len()
+    };
+
+    assert_eq!(count_codemaps(&[]), 1);
+    assert_eq!(count_codemaps(&["--modelines"]), 2);
+    assert_eq!(count_codemaps(&["--modelines", "--lang", "rust"]), 1);
+}
+
+#[test]
+fn cli_codemap_filter_config_and_verbose_rejections() {
+    let dir = tempdir().unwrap();
+
+    let long_line = format!("export const TABLE = \"{}\";\n", "x".repeat(800));
+    write_file(&dir.path().join("src/table.ts"), &long_line);
+    write_file(
+        &dir.path().join("src/gen.rs"),
+        "// @autogen\npub fn generated() {}\n",
+    );
+    write_file(&dir.path().join("src/lib.rs"), "pub fn run() {}\n");
+    write_file(
+        &dir.path().join("pith-filter.json"),
+        r#"{"heuristics": {"max_line_length": 1000, "generated_markers": ["@autogen"]}}"#,
+    );
+
+    let root = dir.path().to_str().unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args(["codemap", root, "--json", "--verbose"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("table.ts: minified content"), "{stderr}");
+    assert!(!stderr.contains("gen.rs"), "{stderr}");
+
+    let config = dir.path().join("pith-filter.json");
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args(["codemap", root, "--json", "-v", "--filter-config"])
+        .arg(&config)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("gen.rs: generated file"), "{stderr}");
+    assert!(!stderr.contains("table.ts"), "{stderr}");
+
+    let v: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let mut names: Vec<_> = v["codemaps"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| {
+            let path = c["path"].as_str().unwrap();
+            Path::new(path)
+                .file_name()
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect();
+    names.sort();
+    assert_eq!(names, ["lib.rs", "table.ts"]);
+}
+
+#[test]
+fn cli_context_respects_gitattributes_linguist_markers() {
+    let dir = tempdir().unwrap();
+
+    write_file(&dir.path().join("src/lib.rs"), "pub fn run() {}\n");
+    write_file(&dir.path().join("src/schema.rs"), "pub struct Schema;\n");
+    write_file(
+        &dir.path().join(".gitattributes"),
+        "src/schema.rs linguist-generated\n",
+    );
+
+    let context = |linguist: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+            .args([
+                "context",
+                dir.path().to_str().unwrap(),
+                "--json",
+                "--select",
+                "src/*.rs",
+                "--linguist",
+                linguist,
+            ])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let v: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        let names = |key: &str| -> Vec<String> {
+            let mut names: Vec<String> = v[key]
+                .as_array()
+                .map(|items| {
+                    items
+                        .iter()
+                        .map(|item| {
+                            let path = item["path"].as_str().unwrap();
+                            Path::new(path)
+                                .file_name()
+                                .unwrap()
+                                .to_str()
+                                .unwrap()
+                                .to_string()
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            names.sort();
+            names
+        };
+        (names("codemaps"), names("selected_files"))
+    };
+
+    let (codemaps, selected) = context("skip");
+    assert_eq!(codemaps, ["lib.rs"]);
+    assert_eq!(selected, ["lib.rs"]);
+
+    let (codemaps, selected) = context("codemap-only");
+    assert_eq!(codemaps, ["lib.rs", "schema.rs"]);
+    assert_eq!(selected, ["lib.rs"]);
+
+    let (codemaps, selected) = context("include");
+    assert_eq!(codemaps, ["lib.rs", "schema.rs"]);
+    assert_eq!(selected, ["lib.rs", "schema.rs"]);
+}
+
+#[test]
+fn cli_codemap_visibility_crate_keeps_crate_items() {
+    let dir = tempdir().unwrap();
+
+    write_file(
+        &dir.path().join("lib.rs"),
+        "pub fn public_fn() {}\npub(crate) fn crate_fn() {}\nfn private_fn() {}\n\npub struct Config {\n    pub(crate) cache: u32,\n    secret: String,\n}\n",
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args([
+            "codemap",
+            dir.path().to_str().unwrap(),
+            "--visibility",
+            "crate",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("fn public_fn"));
+    assert!(stdout.contains("pub(crate) fn crate_fn"));
+    assert!(stdout.contains("- pub(crate) cache: u32"));
+    assert!(!stdout.contains("private_fn"));
+    assert!(!stdout.contains("secret"));
+}
+
+#[test]
+fn cli_codemap_sort_by_name() {
+    let dir = tempdir().unwrap();
+
+    write_file(
+        &dir.path().join("lib.rs"),
+        "pub fn zeta() {}\npub struct Alpha;\npub fn beta() {}\n",
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args([
+            "codemap",
+            dir.path().to_str().unwrap(),
+            "--json",
+            "--sort",
+            "name",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let v: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let names: Vec<_> = v["codemaps"][0]["declarations"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| d["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["Alpha", "beta", "zeta"]);
+}
+
+#[test]
+fn cli_codemap_ranks_and_trims_by_importance() {
+    let dir = tempdir().unwrap();
+
+    write_file(
+        &dir.path().join("src/model.rs"),
+        "pub fn helper_one(input: &str) -> String { todo!() }\n\
+         pub fn helper_two(input: &str) -> String { todo!() }\n\
+         pub fn helper_three(input: &str) -> String { todo!() }\n\
+         pub struct User { pub id: u64 }\n",
+    );
+    write_file(
+        &dir.path().join("src/db.rs"),
+        "use crate::model::User;\npub fn load(id: u64) -> User { todo!() }\n",
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args([
+            "codemap",
+            dir.path().to_str().unwrap(),
+            "--json",
+            "--sort",
+            "importance",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let v: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let model = v["codemaps"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|c| c["path"].as_str().unwrap().ends_with("model.rs"))
+        .unwrap();
+    assert_eq!(model["declarations"][0]["name"], "User");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args([
+            "codemap",
+            dir.path().to_str().unwrap(),
+            "--format",
+            "compact",
+            "--max-tokens",
+            "40",
+            "--trim-declarations",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("low-importance declarations"), "{stderr}");
+    assert!(stdout.contains("struct User"), "{stdout}");
+    assert!(!stdout.contains("helper_three"), "{stdout}");
+    // Files stay listed while any of their declarations could go.
+    assert!(stdout.contains("db.rs"), "{stdout}");
+}
+
+#[test]
+fn cli_tree_reports_directory_totals() {
+    let dir = tempdir().unwrap();
+
+    write_file(&dir.path().join("src/a.rs"), "fn a() {}\nfn b() {}\n");
+    write_file(&dir.path().join("src/b.rs"), "fn c() {}\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args(["tree", dir.path().to_str().unwrap(), "--json"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let v: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(v["file_count"], 2);
+    assert_eq!(v["lines"], 3);
+    let src = &v["children"][0];
+    assert_eq!(src["name"], "src");
+    assert_eq!(src["file_count"], 2);
+    assert_eq!(src["size"], 30);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args(["tree", dir.path().to_str().unwrap()])
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("src/ [2 files, 3 lines, 30B]\n"));
+}
+
+#[test]
+fn cli_tree_tokens_annotates_files_and_directories() {
+    let dir = tempdir().unwrap();
+
+    write_file(&dir.path().join("src/lib.rs"), "pub fn run() {}\n");
+    write_file(&dir.path().join("logo.png"), "not really a png");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args(["tree", dir.path().to_str().unwrap(), "--json", "--tokens"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let v: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let src = &v["children"][0];
+    assert_eq!(src["name"], "src");
+    let file_tokens = src["children"][0]["tokens"].as_u64().unwrap();
+    assert!(file_tokens > 0);
+    assert_eq!(src["tokens"], file_tokens);
+    assert_eq!(v["tokens"], file_tokens);
+    assert!(v["children"][1].get("tokens").is_none());
+}
+
+#[test]
+fn cli_context_tokens_annotates_tree() {
+    let dir = tempdir().unwrap();
+
+    write_file(&dir.path().join("src/lib.rs"), "pub fn run() {}\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args(["context", dir.path().to_str().unwrap(), "--tokens"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("lib.rs [rust, 1 lines, 16B, "));
+    assert!(stdout.contains(" tokens]"));
+}
+
+#[test]
+fn cli_context_prune_tree_hides_unmapped_files() {
+    let dir = tempdir().unwrap();
+
+    write_file(&dir.path().join("src/lib.rs"), "pub fn run() {}\n");
+    write_file(&dir.path().join("assets/notes.txt"), "hello\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args(["context", dir.path().to_str().unwrap(), "--prune-tree"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let file_map = stdout.split("</file_map>").next().unwrap();
+    assert!(file_map.contains("lib.rs"));
+    assert!(!file_map.contains("assets/"));
+    assert!(!file_map.contains("notes.txt"));
+}
+
+#[test]
+fn cli_codemap_skips_default_ignores_unless_disabled() {
+    let dir = tempdir().unwrap();
+
+    write_file(&dir.path().join("src/lib.rs"), "pub fn run() {}\n");
+    write_file(
+        &dir.path().join("node_modules/dep/index.js"),
+        "export function dep() {}\n",
+    );
+    write_file(&dir.path().join("target/out.rs"), "pub fn built() {}\n");
+
+    let run = |extra: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+            .args(["codemap", dir.path().to_str().unwrap(), "--json"])
+            .args(extra)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let stdout = run(&[]);
+    assert!(stdout.contains("lib.rs"));
+    assert!(!stdout.contains("index.js"));
+    assert!(!stdout.contains("out.rs"));
+
+    let stdout = run(&["--no-default-ignores"]);
+    assert!(stdout.contains("index.js"));
+    assert!(stdout.contains("out.rs"));
+}
+
+#[test]
+fn cli_no_gitignore_includes_gitignored_files() {
+    let dir = tempdir().unwrap();
+
+    fs::create_dir(dir.path().join(".git")).unwrap();
+    write_file(&dir.path().join(".gitignore"), "snapshot/\n");
+    write_file(&dir.path().join("src/lib.rs"), "pub fn run() {}\n");
+    write_file(
+        &dir.path().join("snapshot/vendored.rs"),
+        "pub fn vendored() {}\n",
+    );
+
+    let run = |extra: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+            .args(["codemap", dir.path().to_str().unwrap(), "--json"])
+            .args(extra)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    assert!(!run(&[]).contains("vendored.rs"));
+    assert!(run(&["--no-gitignore"]).contains("vendored.rs"));
+}
+
+#[test]
+fn cli_lists_unreadable_files_in_summary() {
+    let dir = tempdir().unwrap();
+    write_file(&dir.path().join("lib.rs"), "pub fn run() {}\n");
+    // Latin-1 comment: not valid UTF-8.
+    fs::write(
+        dir.path().join("legacy.rs"),
+        b"// caf\xe9\npub fn old() {}\n",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args(["codemap", dir.path().to_str().unwrap(), "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let skipped = json["summary"]["skipped"].as_array().unwrap();
+    assert_eq!(skipped.len(), 1);
+    assert!(skipped[0]["path"].as_str().unwrap().ends_with("legacy.rs"));
+    assert_eq!(skipped[0]["reason"], "invalid UTF-8");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args(["context", dir.path().to_str().unwrap()])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Skipped files:"));
+    assert!(stdout.contains("legacy.rs: invalid UTF-8"));
+}
+
+#[test]
+fn cli_codemap_doc_summary_shortens_docs() {
+    let dir = tempdir().unwrap();
+    write_file(
+        &dir.path().join("lib.rs"),
+        "/// Load the config file.\n///\n/// Reads `pith.toml` from the root and merges\n/// it with the defaults.\npub fn load() {}\n",
+    );
+
+    let codemap = |summary: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+            .args(["codemap", dir.path().to_str().unwrap(), "--format", "md"])
+            .args(["--doc-summary", summary])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let sentence = codemap("sentence");
+    assert!(sentence.contains("Load the config file."));
+    assert!(!sentence.contains("merges"));
+    let tokens = codemap("2");
+    assert!(tokens.contains("Load the..."));
+    assert!(!tokens.contains("config"));
+}
+
+#[test]
+fn cli_codemap_normalize_docs_compacts_docstrings() {
+    let dir = tempdir().unwrap();
+    write_file(
+        &dir.path().join("users.py"),
+        "def fetch(user_id, timeout=5):\n    \"\"\"Fetch a user.\n\n    Looks in the cache first.\n\n    Args:\n        user_id (int): Who to fetch.\n        timeout: Seconds to wait.\n\n    Returns:\n        User: The user.\n    \"\"\"\n",
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args(["codemap", dir.path().to_str().unwrap(), "--json"])
+        .arg("--normalize-docs")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        json["codemaps"][0]["declarations"][0]["doc"],
+        "Fetch a user.\nParams: user_id, timeout\nReturns: The user."
+    );
+}
+
+#[test]
+fn cli_api_diff_classifies_changes() {
+    let dir = tempdir().unwrap();
+    let (old, new) = (dir.path().join("v1"), dir.path().join("v2"));
+    write_file(
+        &old.join("src/lib.rs"),
+        "pub fn load(path: &str) {}\npub fn legacy() {}\n",
+    );
+    write_file(
+        &new.join("src/lib.rs"),
+        "pub fn load(path: &str) {}\npub fn save() {}\n",
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args(["api-diff", old.to_str().unwrap(), new.to_str().unwrap()])
+        .args(["--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["bump"], "breaking");
+    assert_eq!(json["changes"][0]["item"], "legacy");
+    assert_eq!(json["changes"][0]["change"], "removed");
+    assert_eq!(json["changes"][0]["path"], "src/lib.rs");
+    assert_eq!(json["changes"][1]["kind"], "additive");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args(["api-diff", old.to_str().unwrap(), old.to_str().unwrap()])
+        .args(["--format", "md"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout, "# API Changes\n\nNo public API changes.\n\n");
+}
+
+#[test]
+fn cli_export_records() {
+    let dir = tempdir().unwrap();
+    let project = dir.path().join("project");
+    write_file(
+        &project.join("src/config.rs"),
+        "/// Settings.\npub struct Config;\n\nimpl Config {\n    /// Read from disk.\n    pub fn load() -> Self { Config }\n}\n",
+    );
+    let out = dir.path().join("records.jsonl");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args(["export", project.to_str().unwrap()])
+        .args(["--records", out.to_str().unwrap(), "--snippet", "1"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("Wrote 2 records"), "{stdout}");
+
+    let contents = fs::read_to_string(&out).unwrap();
+    let records: Vec<serde_json::Value> = contents
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["id"], "src/config.rs#Config@struct");
+    assert_eq!(records[0]["doc"], "Settings.");
+    assert_eq!(records[1]["id"], "src/config.rs#Config::load@function");
+    assert_eq!(records[1]["container"], "Config");
+    assert_eq!(records[1]["kind"], "function");
+    assert!(records[1]["snippet"]
+        .as_str()
+        .unwrap()
+        .starts_with("pub fn load"));
+    assert!(records[1]["tokens"].as_u64().unwrap() > 0);
+
+    // An existing file is only replaced with --force.
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args(["export", project.to_str().unwrap()])
+        .args(["--records", out.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn cli_export_tool_manifest() {
+    let dir = tempdir().unwrap();
+    write_file(
+        &dir.path().join("api.py"),
+        "def get_user(id: int, fields: list[str] | None = None) -> User:\n    \"\"\"Look up a user by id.\n\n    Reads the cache first.\n    \"\"\"\n\ndef _internal():\n    pass\n",
+    );
+    let out = dir.path().join("tools.json");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args(["export", dir.path().to_str().unwrap()])
+        .args(["--tools", out.to_str().unwrap(), "--tool-format", "mcp"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .starts_with("Wrote 1 tools"));
+
+    let manifest: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&out).unwrap()).unwrap();
+    let tool = &manifest["tools"][0];
+    assert_eq!(tool["name"], "get_user");
+    assert_eq!(tool["description"], "Look up a user by id.");
+    let schema = &tool["inputSchema"];
+    assert_eq!(schema["properties"]["id"]["type"], "integer");
+    assert_eq!(schema["properties"]["fields"]["items"]["type"], "string");
+    assert_eq!(schema["required"], serde_json::json!(["id"]));
+
+    // One of --records and --tools is required.
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args(["export", dir.path().to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn cli_parse_limits() {
+    let dir = tempdir().unwrap();
+    write_file(&dir.path().join("small.rs"), "pub fn small() {}\n");
+    write_file(
+        &dir.path().join("generated.rs"),
+        &"pub fn generated() {}\n".repeat(100),
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args(["codemap", dir.path().to_str().unwrap(), "--json"])
+        .args(["--max-parse-bytes", "1000", "--parse-timeout", "5000"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let codemaps = json["codemaps"].as_array().unwrap();
+    let generated = codemaps
+        .iter()
+        .find(|c| c["path"].as_str().unwrap().ends_with("generated.rs"))
+        .unwrap();
+    assert!(generated["declarations"].as_array().unwrap().is_empty());
+    assert_eq!(
+        generated["parse_errors"][0]["message"],
+        "2,200 bytes is over the 1,000-byte parse limit"
+    );
+    let small = codemaps
+        .iter()
+        .find(|c| c["path"].as_str().unwrap().ends_with("small.rs"))
+        .unwrap();
+    assert_eq!(small["declarations"][0]["name"], "small");
+}
+
+#[test]
+fn cli_lossy_decoding_reads_legacy_encodings() {
+    let dir = tempdir().unwrap();
+    fs::write(
+        dir.path().join("legacy.rs"),
+        b"// caf\xe9\npub fn old() {}\n",
+    )
+    .unwrap();
+    let mut utf16: Vec<u8> = vec![0xFF, 0xFE];
+    utf16.extend(
+        "def wide():\n    pass\n"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes),
+    );
+    fs::write(dir.path().join("wide.py"), utf16).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args(["codemap", dir.path().to_str().unwrap(), "--json"])
+        .arg("--lossy-decoding")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let names: Vec<&str> = json["codemaps"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["declarations"][0]["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["old", "wide"]);
+    assert!(json["summary"].get("skipped").is_none());
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("legacy.rs: replaced 1 invalid UTF-8 sequence(s)"));
+    assert!(stderr.contains("wide.py: decoded from UTF-16LE"));
+}
+
+#[test]
+fn cli_threads_limits_pool() {
+    let dir = tempdir().unwrap();
+    write_file(&dir.path().join("a.rs"), "pub fn a() {}\n");
+    write_file(&dir.path().join("b.rs"), "pub fn b() {}\n");
+
+    let run = |command: &str, threads: &str| {
+        Command::new(env!("CARGO_BIN_EXE_pith"))
+            .args([command, dir.path().to_str().unwrap(), "--threads", threads])
+            .output()
+            .unwrap()
+    };
+
+    // bench reports the size of the pool it ran on
+    let bench = run("bench", "3");
+    assert!(bench.status.success());
+    let stdout = String::from_utf8(bench.stdout).unwrap();
+    assert!(stdout.contains("Threads: 3"), "{stdout}");
+
+    let codemap = run("codemap", "1");
+    assert!(codemap.status.success());
+    assert_eq!(codemap.stdout, run("codemap", "4").stdout);
+    assert!(!run("codemap", "0").status.success());
+}
+
+#[test]
+fn cli_ignore_file_outside_root() {
+    let dir = tempdir().unwrap();
+    let project = dir.path().join("project");
+    write_file(&project.join("src/lib.rs"), "pub fn run() {}\n");
+    write_file(
+        &project.join("src/fixtures/big.rs"),
+        "pub fn fixture() {}\n",
+    );
+    let ignore_file = dir.path().join("ci/pith.ignore");
+    write_file(&ignore_file, "/src/fixtures/\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args(["codemap", project.to_str().unwrap(), "--ignore-file"])
+        .arg(&ignore_file)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("lib.rs"));
+    assert!(!stdout.contains("big.rs"));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args(["codemap", project.to_str().unwrap(), "--ignore-file"])
+        .arg(dir.path().join("missing.ignore"))
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("no such file"));
+}
+
+#[test]
+fn cli_output_writes_file_and_requires_force_to_overwrite() {
+    let dir = tempdir().unwrap();
+    let project = dir.path().join("project");
+    write_file(&project.join("src/lib.rs"), "pub fn run() {}\n");
+    let out = dir.path().join("codemap.xml");
+
+    let run = |extra: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_pith"))
+            .args(["codemap", project.to_str().unwrap(), "-o"])
+            .arg(&out)
+            .args(extra)
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&[]);
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(fs::read_to_string(&out).unwrap().contains("fn run"));
+
+    fs::write(&out, "keep").unwrap();
+    let output = run(&[]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("already exists"));
+    assert_eq!(fs::read_to_string(&out).unwrap(), "keep");
+
+    let output = run(&["--force"]);
+    assert!(output.status.success());
+    assert!(fs::read_to_string(&out).unwrap().contains("fn run"));
+}
+
+#[test]
+fn cli_init_writes_config_used_by_codemap() {
+    let dir = tempdir().unwrap();
+    write_file(
+        &dir.path().join("pyproject.toml"),
+        "[project]\nname = \"demo\"\n",
+    );
+    write_file(&dir.path().join("app.py"), "def main():\n    pass\n");
+    write_file(
+        &dir.path().join("bin/manage"),
+        "# vim: ft=python\ndef manage():\n    pass\n",
+    );
+
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_pith"))
+            .args(args)
+            .arg(dir.path())
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["init"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("Detected: python"));
+    let pithignore = fs::read_to_string(dir.path().join(".pithignore")).unwrap();
+    assert!(pithignore.contains("__pycache__/"));
+
+    // The generated pith.toml turns on modelines for Python projects.
+    let output = run(&["codemap"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout).unwrap().contains("manage"));
+
+    assert!(!run(&["init"]).status.success());
+    assert!(run(&["init", "--force"]).status.success());
+}
+
+#[test]
+fn cli_max_tokens_prunes_or_exits_with_status_6() {
+    let dir = tempdir().unwrap();
+    for name in ["alpha", "beta", "gamma"] {
+        let body: String = (0..30)
+            .map(|i| format!("pub fn {name}_{i}(input: &str) -> usize {{ input.len() }}\n"))
+            .collect();
+        write_file(&dir.path().join(format!("src/{name}.rs")), &body);
+    }
+
+    let run = |budget: &str| {
+        Command::new(env!("CARGO_BIN_EXE_pith"))
+            .args(["context", "--select", "**/*.rs", "--max-tokens", budget])
+            .arg(dir.path())
+            .output()
+            .unwrap()
+    };
+
+    let output = run("1500");
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("dropped content of"));
+
+    let output = run("10");
+    assert_eq!(output.status.code(), Some(6));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("over the budget of 10"));
+}
+
+#[test]
+fn cli_verbose_levels_and_pith_log() {
+    let dir = tempdir().unwrap();
+    write_file(&dir.path().join("src/lib.rs"), "pub fn run() {}\n");
+    write_file(&dir.path().join("src/util.rs"), "pub fn help() {}\n");
+    write_file(&dir.path().join("node_modules/dep/index.js"), "export {}\n");
+    write_file(&dir.path().join("notes.txt"), "hello\n");
+
+    let run = |args: &[&str], log: Option<&str>| {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_pith"));
+        command.arg("codemap").arg(dir.path()).args(args);
+        command.env_remove("PITH_LOG");
+        if let Some(log) = log {
+            command.env("PITH_LOG", log);
+        }
+        let output = command.output().unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stderr).unwrap()
+    };
+
+    assert!(run(&[], None).is_empty());
+
+    let stderr = run(&["-v"], None);
+    assert!(stderr.contains("notes.txt: unknown extension"), "{stderr}");
+    assert!(stderr.contains("node_modules: default ignore"), "{stderr}");
+    assert!(!stderr.contains("parsed"), "{stderr}");
+
+    let stderr = run(&["-vv"], None);
+    assert!(stderr.contains("lib.rs (rust) in"), "{stderr}");
+    assert!(stderr.contains("parser cache"), "{stderr}");
+
+    assert!(run(&["-vv"], Some("off")).is_empty());
+    assert!(run(&[], Some("debug")).contains("parsed"));
+}
+
+#[test]
+fn cli_format_flag_and_json_alias() {
+    let dir = tempdir().unwrap();
+    write_file(&dir.path().join("src/lib.rs"), "pub fn run() {}\n");
+
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_pith"))
+            .args(args)
+            .arg(dir.path())
+            .output()
+            .unwrap()
+    };
+    let stdout = |args: &[&str]| {
+        let output = run(args);
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    assert!(stdout(&["codemap", "--format", "md"]).starts_with("# Codemaps\n"));
+    assert!(stdout(&["codemap", "--format", "compact"]).contains("\n  pub fn run ()\n"));
+    assert!(stdout(&["tree", "--format", "xml"]).starts_with("<file_map>\n"));
+    assert!(stdout(&["tokens", "--format", "yaml"]).starts_with("total: "));
+    let strict = stdout(&["codemap", "--format", "strict-xml"]);
+    assert!(strict.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<context>\n"));
+    assert!(strict.contains("<file path=\""));
+    assert!(strict.ends_with("</context>\n"));
+    assert!(stdout(&["tokens", "--format", "strict-xml"]).contains("<token_summary total=\""));
+
+    // --json is kept as an alias for --format json.
+    assert_eq!(
+        stdout(&["codemap", "--json"]),
+        stdout(&["codemap", "--format", "json"])
+    );
+    assert_eq!(
+        stdout(&["tree", "--json"]),
+        stdout(&["tree", "--format", "json"])
+    );
+    assert!(!run(&["codemap", "--json", "--format", "md"])
+        .status
+        .success());
+}
+
+#[test]
+fn cli_tokens_check_gates_on_budget() {
+    let dir = tempdir().unwrap();
+    write_file(&dir.path().join("src/small.rs"), "pub fn a() {}\n");
+    write_file(
+        &dir.path().join("src/large.rs"),
+        &"pub fn b() -> usize { 1 + 2 + 3 }\n".repeat(50),
+    );
+
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_pith"))
+            .arg("tokens")
+            .arg(dir.path())
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["--check", "100000"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("to spare"));
+
+    let output = run(&["--check", "50"]);
+    assert_eq!(output.status.code(), Some(6));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("over by"), "{stdout}");
+    assert!(stdout.contains("  src/large.rs: "), "{stdout}");
+
+    let output = run(&["--check", "50", "--format", "json"]);
+    assert_eq!(output.status.code(), Some(6));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["check"]["passed"], false);
+    assert_eq!(report["check"]["largest"][0]["path"], "src/large.rs");
+}
+
+#[test]
+fn cli_progress_json_emits_ndjson_on_stderr() {
+    let dir = tempdir().unwrap();
+    write_file(&dir.path().join("src/lib.rs"), "pub fn run() {}\n");
+    write_file(&dir.path().join("src/util.rs"), "pub fn help() {}\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pith"))
+        .args(["codemap", "--progress", "json"])
+        .arg(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout).unwrap().contains("fn run"));
+
+    let events: Vec<serde_json::Value> = String::from_utf8(output.stderr)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let phases: Vec<&str> = events
+        .iter()
+        .map(|e| e["phase"].as_str().unwrap())
+        .collect();
+    for phase in ["walk", "extract", "render", "done"] {
+        assert!(phases.contains(&phase), "{phases:?}");
+    }
+    let extract = events.iter().rfind(|e| e["phase"] == "extract").unwrap();
+    assert_eq!(extract["done"], 2);
+    assert_eq!(extract["total"], 2);
+    assert!(events.last().unwrap()["elapsed_ms"].is_u64());
+}